@@ -33,11 +33,16 @@ mod convo;
 mod deep_linking;
 mod device;
 mod invitations;
+mod media_cache;
 mod news;
+mod news_drafts;
+mod outbox;
 mod pins;
 mod profile;
 mod push;
 mod reactions;
+mod reminders;
+mod revisions;
 mod room;
 mod rsvp;
 mod search;
@@ -70,10 +75,10 @@ pub use acter_matrix::{
             RoomServerAclContent, RoomTombstoneContent, RoomTopicContent, SpaceChildContent,
             SpaceParentContent,
         },
-        ActerModel, Tag, TextMessageContent,
+        ActerModel, CalendarEventOccurrence, Tag, TextMessageContent,
     },
 };
-pub use activities::{Activities, Activity, ActivityObject};
+pub use activities::{Activities, Activity, ActivityDigest, ActivityObject};
 pub use attachments::{Attachment, AttachmentDraft, AttachmentsManager};
 pub use auth::{
     destroy_local_data, guest_client, login_new_client, login_with_token, register_with_token,
@@ -87,7 +92,9 @@ pub use auth::{
 };
 pub use backup::BackupManager;
 pub use bookmarks::Bookmarks;
-pub use calendar_events::{CalendarEvent, CalendarEventDraft, CalendarEventUpdateBuilder};
+pub use calendar_events::{
+    CalendarEvent, CalendarEventDraft, CalendarEventUpdateBuilder, IcalImportReport,
+};
 pub use categories::{Categories, CategoriesBuilder};
 pub use client::{Client, ClientStateBuilder, HistoryLoadState, LocalUrlPreview, SyncState};
 pub use comments::{Comment, CommentDraft, CommentUpdateBuilder, CommentsManager};
@@ -103,7 +110,10 @@ pub use core::time::Duration as EfkDuration;
 pub use deep_linking::{new_link_ref_details, ObjRef, RefDetails};
 pub use device::DeviceEvent;
 pub use invitations::{InvitationsManager, ObjectInvitationsManager, RoomInvitation};
+pub use media_cache::MediaCacheStats;
 pub use news::{NewsEntry, NewsEntryDraft, NewsEntryUpdateBuilder, NewsSlide, NewsSlideDraft};
+pub use news_drafts::LocalNewsDraft;
+pub use outbox::OutboxItem;
 pub use pins::{Pin as ActerPin, PinDraft, PinUpdateBuilder};
 pub use profile::UserProfile;
 pub use push::{
@@ -112,17 +122,20 @@ pub use push::{
 };
 pub use reactions::{Reaction, ReactionManager};
 pub use read_receipts::ReadReceiptsManager;
+pub use revisions::{ObjectRevision, RevisionChange, RevisionConflict};
 pub use room::{
     new_join_rule_builder, JoinRuleBuilder, Member, MemberPermission, MembershipStatus, Room,
     RoomPreview, SpaceHierarchyRoomInfo, SpaceRelation, SpaceRelations, UserRoomSettings,
 };
 pub use rsvp::{Rsvp, RsvpDraft, RsvpManager, RsvpStatus};
-pub use search::{PublicSearchResult, PublicSearchResultItem};
+pub use search::{
+    LocalSearchBuilder, LocalSearchResult, PublicSearchResult, PublicSearchResultItem,
+};
 pub use settings::{
     ActerAppSettings, ActerAppSettingsBuilder, ActerUserAppSettings, ActerUserAppSettingsBuilder,
     EventsSettings, NewsSettings, PinsSettings, RoomPowerLevels, SimpleOnOffSetting,
     SimpleOnOffSettingBuilder, SimpleSettingWithTurnOff, SimpleSettingWithTurnOffBuilder,
-    StoriesSettings, TasksSettings,
+    StoriesSettings, StoriesSettingsBuilder, TasksSettings,
 };
 pub use spaces::{
     new_app_permissions_builder, new_space_settings_builder, AppPermissionsBuilder,
@@ -2,7 +2,10 @@ use std::ops::Deref;
 
 pub use acter_matrix::activities::object::ActivityObject;
 use acter_matrix::{
-    activities::Activity as CoreActivity,
+    activities::{
+        digest::{Digest, DigestSettingsBuilder},
+        Activity as CoreActivity,
+    },
     events::news::{FallbackNewsContent, NewsContent},
     models::{
         status::{
@@ -18,7 +21,9 @@ use acter_matrix::{
     referencing::IndexKey,
 };
 use futures::{FutureExt, Stream, StreamExt};
-use matrix_sdk::ruma::{EventId, OwnedEventId, OwnedRoomId, RoomId};
+use matrix_sdk::ruma::{
+    EventId, MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedRoomId, RoomId, UInt,
+};
 use tokio::sync::broadcast::Receiver;
 use tokio_stream::wrappers::BroadcastStream;
 use tracing::error;
@@ -216,7 +221,81 @@ impl Activities {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct ActivityDigest {
+    inner: Digest,
+}
+
+impl ActivityDigest {
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn activities_count(&self) -> u32 {
+        self.inner.activities as u32
+    }
+
+    pub fn tasks_due_count(&self) -> u32 {
+        self.inner.tasks_due.len() as u32
+    }
+
+    pub fn tasks_completed_count(&self) -> u32 {
+        self.inner.tasks_completed.len() as u32
+    }
+
+    pub fn new_events_count(&self) -> u32 {
+        self.inner.new_events.len() as u32
+    }
+
+    pub fn open_invitations_count(&self) -> u32 {
+        self.inner.open_invitations.len() as u32
+    }
+
+    pub fn text(&self) -> String {
+        self.inner.render_text()
+    }
+
+    pub fn markdown(&self) -> String {
+        self.inner.render_markdown()
+    }
+
+    pub fn html(&self) -> String {
+        self.inner.render_html()
+    }
+
+    pub fn json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(&self.inner)?)
+    }
+}
+
 impl Client {
+    /// Summarize what happened across all spaces since `since_ts` (in
+    /// milliseconds), with the open tasks due within `due_within_days`
+    pub async fn activity_digest(
+        &self,
+        since_ts: u64,
+        due_within_days: u32,
+        link_base: Option<String>,
+    ) -> anyhow::Result<ActivityDigest> {
+        let mut builder = DigestSettingsBuilder::default();
+        builder
+            .since(MilliSecondsSinceUnixEpoch(UInt::new_saturating(since_ts)))
+            .due_within_days(due_within_days);
+        if let Some(link_base) = link_base {
+            builder.link_base(link_base);
+        }
+        let settings = builder.build()?;
+        let core = self.core.clone();
+
+        Ok(RUNTIME
+            .spawn(async move {
+                core.activity_digest(settings)
+                    .await
+                    .map(|inner| ActivityDigest { inner })
+            })
+            .await??)
+    }
+
     pub async fn activity(&self, key: String) -> anyhow::Result<Activity> {
         let ev_id = EventId::parse(key)?;
         let client = self.clone();
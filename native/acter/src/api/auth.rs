@@ -117,7 +117,7 @@ pub async fn guest_client(
     let db_passphrase = Uuid::new_v4().to_string();
     let config = platform::new_client_config(
         base_path.clone(),
-        default_homeserver_name,
+        default_homeserver_name.clone(),
         media_cache_base_path,
         Some(db_passphrase.clone()),
         true,
@@ -147,6 +147,8 @@ pub async fn guest_client(
                 },
             };
             client.restore_session(auth_session).await?;
+            // set up before we knew who we are
+            platform::move_media_cache(&default_homeserver_name, response.user_id.as_str());
             let state = ClientStateBuilder::default()
                 .is_guest(true)
                 .db_passphrase(Some(db_passphrase))
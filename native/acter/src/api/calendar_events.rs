@@ -1,3 +1,5 @@
+mod ical;
+
 use acter_matrix::{
     error::Error,
     events::{
@@ -5,13 +7,14 @@ use acter_matrix::{
             self as calendar_events, CalendarEventBuilder, EventLocation, EventLocationInfo,
         },
         rsvp::RsvpStatus,
-        CalendarEventRefPreview, RefDetails as CoreRefDetails, UtcDateTime,
+        CalendarEventRefPreview, RecurrenceRule, RefDetails as CoreRefDetails, UtcDateTime,
     },
-    models::{self, can_redact, ActerModel, AnyActerModel},
+    models::{self, can_redact, ActerModel, AnyActerModel, CalendarEventOccurrence},
     referencing::{IndexKey, SectionIndex},
 };
 use anyhow::{bail, Result};
 use chrono::DateTime;
+use chrono_tz::Tz;
 use futures::{stream::StreamExt, FutureExt};
 use icalendar::Calendar as iCalendar;
 use matrix_sdk::room::Room;
@@ -31,8 +34,15 @@ use tokio::sync::broadcast::Receiver;
 use tokio_stream::{wrappers::BroadcastStream, Stream};
 use tracing::warn;
 
+pub use ical::IcalImportReport;
+
 use super::{
-    client::Client, common::OptionRsvpStatus, deep_linking::RefDetails, spaces::Space, RUNTIME,
+    client::Client,
+    common::OptionRsvpStatus,
+    deep_linking::RefDetails,
+    revisions::{changed_since, ObjectRevision, RevisionConflict},
+    spaces::Space,
+    RUNTIME,
 };
 
 impl Client {
@@ -133,6 +143,44 @@ impl CalendarEvent {
     pub fn sender(&self) -> OwnedUserId {
         self.inner.sender().to_owned()
     }
+
+    /// The creation and all updates applied since, oldest first
+    pub fn revisions(&self) -> Result<Vec<ObjectRevision>> {
+        Ok(self
+            .inner
+            .revisions()?
+            .into_iter()
+            .map(ObjectRevision::from)
+            .collect())
+    }
+
+    /// Revert the event to how it was right after the given revision
+    pub async fn revert_to(&self, revision_id: String) -> Result<OwnedEventId> {
+        let revision = EventId::parse(revision_id)?;
+        let content = self.inner.revert_update(&revision)?;
+        self.client.send_revert(self.room.clone(), content).await
+    }
+
+    /// The revision this event is at, updates made from here are based on it
+    pub fn revision_str(&self) -> String {
+        self.inner.revision().to_string()
+    }
+
+    /// The updates applied since the given revision, e.g. while editing
+    pub fn changed_since(&self, revision_id: String) -> Result<Vec<ObjectRevision>> {
+        let revision = EventId::parse(&revision_id)?;
+        changed_since(self.inner.changed_since(&revision), &revision_id)
+    }
+
+    /// Concurrent updates that changed the same fields and which one was kept
+    pub fn conflicts(&self) -> Vec<RevisionConflict> {
+        self.inner
+            .conflicts()
+            .iter()
+            .cloned()
+            .map(RevisionConflict::from)
+            .collect()
+    }
 }
 
 /// Custom functions
@@ -181,6 +229,8 @@ impl CalendarEvent {
             client: self.client.clone(),
             room: self.room.clone(),
             inner: self.inner.updater(),
+            series: (*self.inner).clone(),
+            exdates: self.inner.exdates.clone(),
         })
     }
 
@@ -273,8 +323,45 @@ impl CalendarEvent {
             .await?
     }
 
+    pub fn rrule(&self) -> Option<String> {
+        self.inner.rrule()
+    }
+
+    pub fn time_zone(&self) -> Option<String> {
+        self.inner.time_zone.as_ref().map(ToString::to_string)
+    }
+
+    /// All occurrences of this event overlapping the given window
+    pub fn occurrences(
+        &self,
+        from_rfc3339: String,
+        to_rfc3339: String,
+    ) -> Result<Vec<CalendarEventOccurrence>> {
+        let from = UtcDateTime::from(DateTime::parse_from_rfc3339(&from_rfc3339)?);
+        let to = UtcDateTime::from(DateTime::parse_from_rfc3339(&to_rfc3339)?);
+        if to < from {
+            bail!("End of the window must not be before its start");
+        }
+        Ok(self.inner.occurrences(from, to))
+    }
+
+    pub async fn responded_by_me_for_occurrence(
+        &self,
+        occurrence_rfc3339: String,
+    ) -> Result<OptionRsvpStatus> {
+        let me = self.clone();
+        RUNTIME
+            .spawn(async move {
+                let manager = me.rsvps().await?;
+                manager
+                    .responded_by_me_for_occurrence(occurrence_rfc3339)
+                    .await
+            })
+            .await?
+    }
+
     pub fn ical_for_sharing(&self, file_name: String) -> Result<bool> {
-        let ical_data: String = (&iCalendar::from([self.inner.as_ical_event()])).try_into()?;
+        let ical_data: String = (&iCalendar::from_iter(self.inner.as_ical_events())).try_into()?;
         std::fs::write(file_name, ical_data)?;
         Ok(true)
     }
@@ -382,6 +469,28 @@ impl CalendarEventDraft {
         Ok(self)
     }
 
+    /// make this a recurring event, following the given RFC 5545 RRULE
+    pub fn rrule(&mut self, rrule: String) -> Result<&mut Self> {
+        self.inner.rrule(Some(rrule.parse::<RecurrenceRule>()?));
+        Ok(self)
+    }
+
+    pub fn unset_rrule(&mut self) -> &mut Self {
+        self.inner.rrule(None);
+        self
+    }
+
+    /// repeat in this IANA time zone, e.g. `Europe/Berlin`, rather than UTC
+    pub fn time_zone(&mut self, time_zone: String) -> Result<&mut Self> {
+        self.inner.time_zone(Some(time_zone.parse::<Tz>()?));
+        Ok(self)
+    }
+
+    pub fn unset_time_zone(&mut self) -> &mut Self {
+        self.inner.time_zone(None);
+        self
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn add_physical_location(
         &mut self,
@@ -435,6 +544,12 @@ impl CalendarEventDraft {
         self
     }
 
+    /// queue to be sent once online, shown as pending right away
+    pub async fn queue(&self) -> Result<OwnedEventId> {
+        let content = self.inner.build()?;
+        self.client.queue_event(self.room.clone(), content).await
+    }
+
     pub async fn send(&self) -> Result<OwnedEventId> {
         let room = self.room.clone();
         let my_id = self.client.user_id()?;
@@ -460,9 +575,19 @@ pub struct CalendarEventUpdateBuilder {
     client: Client,
     room: Room,
     inner: calendar_events::CalendarEventUpdateBuilder,
+    /// the series as it is, to check occurrences against
+    series: calendar_events::CalendarEventEventContent,
+    exdates: Vec<UtcDateTime>,
 }
 
 impl CalendarEventUpdateBuilder {
+    /// the revision this update was made from, defaults to the current one.
+    /// Fields also changed since by others are resolved on arrival.
+    pub fn base_revision(&mut self, revision_id: String) -> Result<&mut Self> {
+        self.inner.base_revision(Some(EventId::parse(revision_id)?));
+        Ok(self)
+    }
+
     pub fn title(&mut self, title: String) -> &mut Self {
         self.inner.title(Some(title));
         self
@@ -546,6 +671,63 @@ impl CalendarEventUpdateBuilder {
         self
     }
 
+    /// make this a recurring event, following the given RFC 5545 RRULE
+    pub fn rrule(&mut self, rrule: String) -> Result<&mut Self> {
+        self.inner
+            .rrule(Some(Some(rrule.parse::<RecurrenceRule>()?)));
+        Ok(self)
+    }
+
+    /// make this a single event again
+    pub fn unset_rrule(&mut self) -> &mut Self {
+        self.inner.rrule(Some(None));
+        self
+    }
+
+    /// repeat in this IANA time zone, e.g. `Europe/Berlin`, rather than UTC
+    pub fn time_zone(&mut self, time_zone: String) -> Result<&mut Self> {
+        self.inner.time_zone(Some(Some(time_zone.parse::<Tz>()?)));
+        Ok(self)
+    }
+
+    /// repeat in UTC again
+    pub fn unset_time_zone(&mut self) -> &mut Self {
+        self.inner.time_zone(Some(None));
+        self
+    }
+
+    pub fn unset_time_zone_update(&mut self) -> &mut Self {
+        self.inner.time_zone(None);
+        self
+    }
+
+    /// remove the occurrence starting at the given time from the series
+    pub fn add_exdate_from_rfc3339(&mut self, exdate: String) -> Result<&mut Self> {
+        let dt = UtcDateTime::from(DateTime::parse_from_rfc3339(&exdate)?);
+        self.ensure_occurrence(dt)?;
+        self.exdates.push(dt);
+        self.inner.exdates(Some(self.exdates.clone()));
+        Ok(self)
+    }
+
+    /// only apply this update to the occurrence originally starting at the given time
+    pub fn occurrence_from_rfc3339(&mut self, occurrence: String) -> Result<&mut Self> {
+        let dt = UtcDateTime::from(DateTime::parse_from_rfc3339(&occurrence)?);
+        self.ensure_occurrence(dt)?;
+        self.inner.recurrence_id(dt);
+        Ok(self)
+    }
+
+    fn ensure_occurrence(&self, start: UtcDateTime) -> Result<()> {
+        if self.exdates.contains(&start) {
+            bail!("The occurrence at {start} has been removed from the series");
+        }
+        if !self.series.has_occurrence_at(start) {
+            bail!("No occurrence of this event starts at {start}");
+        }
+        Ok(())
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn add_physical_location(
         &mut self,
@@ -609,6 +791,12 @@ impl CalendarEventUpdateBuilder {
         self
     }
 
+    /// queue to be sent once online, shown as pending right away
+    pub async fn queue(&self) -> Result<OwnedEventId> {
+        let content = self.inner.build()?;
+        self.client.queue_event(self.room.clone(), content).await
+    }
+
     pub async fn send(&self) -> Result<OwnedEventId> {
         let room = self.room.clone();
         let my_id = self.client.user_id()?;
@@ -0,0 +1,208 @@
+use acter_matrix::{
+    events::{rsvp::RsvpStatus, UtcDateTime},
+    models::parse_ical,
+};
+use anyhow::{bail, Result};
+use chrono::DateTime;
+use icalendar::Calendar as iCalendar;
+use matrix_sdk_base::ruma::{events::MessageLikeEventType, OwnedEventId};
+use std::collections::HashSet;
+
+use crate::RUNTIME;
+
+use super::{
+    super::{client::Client, spaces::Space},
+    CalendarEvent,
+};
+
+/// Build one VCALENDAR of all events overlapping the window, with the
+/// user’s own RSVP as participation status
+async fn ical_feed(
+    name: String,
+    events: Vec<CalendarEvent>,
+    from_rfc3339: String,
+    to_rfc3339: String,
+    skip_declined: bool,
+) -> Result<String> {
+    let from = UtcDateTime::from(DateTime::parse_from_rfc3339(&from_rfc3339)?);
+    let to = UtcDateTime::from(DateTime::parse_from_rfc3339(&to_rfc3339)?);
+    if to < from {
+        bail!("End of the window must not be before its start");
+    }
+
+    let mut calendar = iCalendar::new();
+    calendar.name(&name);
+    for event in events {
+        if event.occurrence_starts(from, to).is_empty() {
+            continue;
+        }
+        let my_id = event.client.user_id()?;
+        let status = event.rsvps().await?.responded_by_me().await?.status();
+        if skip_declined && status == Some(RsvpStatus::No) {
+            continue;
+        }
+        for ical_event in event.inner.as_ical_events_for(&my_id, status.as_ref()) {
+            calendar.push(ical_event);
+        }
+    }
+    Ok((&calendar.done()).try_into()?)
+}
+
+/// What an iCalendar import did or - for a dry run - would do
+#[derive(Clone, Debug, Default)]
+pub struct IcalImportReport {
+    dry_run: bool,
+    new_titles: Vec<String>,
+    new_uids: Vec<String>,
+    existing_uids: Vec<String>,
+    skipped: Vec<String>,
+    warnings: Vec<String>,
+    created_event_ids: Vec<OwnedEventId>,
+}
+
+impl IcalImportReport {
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// titles of the events that are (or would be) created
+    pub fn new_titles(&self) -> Vec<String> {
+        self.new_titles.clone()
+    }
+
+    /// UIDs of the events that are (or would be) created
+    pub fn new_uids(&self) -> Vec<String> {
+        self.new_uids.clone()
+    }
+
+    /// UIDs that have already been imported into this space and were skipped
+    pub fn existing_uids(&self) -> Vec<String> {
+        self.existing_uids.clone()
+    }
+
+    /// VEVENTs that couldn’t be imported, with the reason
+    pub fn skipped(&self) -> Vec<String> {
+        self.skipped.clone()
+    }
+
+    /// parts of imported VEVENTs that couldn’t be taken over
+    pub fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
+
+    pub fn created_event_ids(&self) -> Vec<String> {
+        self.created_event_ids
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+}
+
+impl Space {
+    /// Import the VEVENTs of the iCalendar file at `file_path` as calendar
+    /// events into this space. Events whose UID has been imported before are
+    /// skipped. With `dry_run` nothing is sent, only the report is returned.
+    pub async fn import_ical(&self, file_path: String, dry_run: bool) -> Result<IcalImportReport> {
+        if !self.inner.is_joined() {
+            bail!("Unable to import calendar events into a space you didn’t join");
+        }
+        let data = std::fs::read_to_string(file_path)?;
+        let parsed = parse_ical(&data)?;
+        let known_uids = self
+            .calendar_events()
+            .await?
+            .into_iter()
+            .map(|e| e.ical_uid())
+            .collect::<HashSet<_>>();
+
+        let mut report = IcalImportReport {
+            dry_run,
+            skipped: parsed.skipped,
+            ..Default::default()
+        };
+        let mut to_send = vec![];
+        for mut imported in parsed.events {
+            report.warnings.append(&mut imported.warnings);
+            if let Some(uid) = &imported.uid {
+                if known_uids.contains(uid) || report.new_uids.contains(uid) {
+                    report.existing_uids.push(uid.clone());
+                    continue;
+                }
+                report.new_uids.push(uid.clone());
+            }
+            report.new_titles.push(imported.content.title.clone());
+            to_send.push(imported.content);
+        }
+
+        if dry_run || to_send.is_empty() {
+            return Ok(report);
+        }
+
+        let room = self.inner.room.clone();
+        let my_id = self.client.user_id()?;
+        RUNTIME
+            .spawn(async move {
+                let permitted = room
+                    .can_user_send_message(&my_id, MessageLikeEventType::RoomMessage)
+                    .await?;
+                if !permitted {
+                    bail!("No permissions to send message in this room");
+                }
+                for content in to_send {
+                    let response = room.send(content).await?;
+                    report.created_event_ids.push(response.event_id);
+                }
+                Ok(report)
+            })
+            .await?
+    }
+}
+
+impl Space {
+    /// All calendar events of this space overlapping the given window as one
+    /// iCalendar feed. With `skip_declined` events the user said "no" to are
+    /// left out.
+    pub async fn ical_feed(
+        &self,
+        from_rfc3339: String,
+        to_rfc3339: String,
+        skip_declined: bool,
+    ) -> Result<String> {
+        let name = self
+            .inner
+            .name()
+            .unwrap_or_else(|| self.inner.room_id().to_string());
+        let events = self.calendar_events().await?;
+        RUNTIME
+            .spawn(async move {
+                ical_feed(name, events, from_rfc3339, to_rfc3339, skip_declined).await
+            })
+            .await?
+    }
+}
+
+impl Client {
+    /// All calendar events across all spaces overlapping the given window as
+    /// one iCalendar feed. With `skip_declined` events the user said "no" to
+    /// are left out.
+    pub async fn ical_feed(
+        &self,
+        from_rfc3339: String,
+        to_rfc3339: String,
+        skip_declined: bool,
+    ) -> Result<String> {
+        let events = self.calendar_events().await?;
+        RUNTIME
+            .spawn(async move {
+                ical_feed(
+                    "Acter".to_owned(),
+                    events,
+                    from_rfc3339,
+                    to_rfc3339,
+                    skip_declined,
+                )
+                .await
+            })
+            .await?
+    }
+}
@@ -153,6 +153,8 @@ pub struct SyncState {
     first_synced_rx: Arc<Receiver<bool>>,
     sync_error: Arc<Receiver<SyncError>>,
     history_loading: Mutable<HistoryLoadState>,
+    stories_expiry_task: Mutable<Option<JoinHandle<()>>>,
+    news_schedule_task: Mutable<Option<JoinHandle<()>>>,
 }
 
 impl SyncState {
@@ -161,6 +163,8 @@ impl SyncState {
             first_synced_rx: Arc::new(first_synced_rx),
             sync_error: Arc::new(sync_error),
             history_loading: Default::default(),
+            stories_expiry_task: Default::default(),
+            news_schedule_task: Default::default(),
             first_sync_task: Default::default(),
             handle: Default::default(),
         }
@@ -212,6 +216,12 @@ impl SyncState {
         if let Some(handle) = self.handle.replace(None) {
             handle.abort();
         }
+        if let Some(handle) = self.stories_expiry_task.replace(None) {
+            handle.abort();
+        }
+        if let Some(handle) = self.news_schedule_task.replace(None) {
+            handle.abort();
+        }
     }
 }
 
@@ -665,6 +675,14 @@ impl Client {
                     }
                 }
 
+                // we are online, send what has been queued in the meantime
+                let executor = me.executor().clone();
+                RUNTIME.spawn(async move {
+                    if let Err(error) = executor.flush_outbox().await {
+                        error!(?error, "flushing the outbox failed");
+                    }
+                });
+
                 trace!(token = response.next_batch, "storing sync token");
                 if let Err(error) = me
                     .store()
@@ -683,6 +701,12 @@ impl Client {
             };
         });
         sync_state.handle.set(Some(handle));
+        sync_state.stories_expiry_task.set(Some(
+            RUNTIME.spawn(self.executor().clone().archive_stories_on_expiry()),
+        ));
+        sync_state.news_schedule_task.set(Some(
+            RUNTIME.spawn(self.executor().clone().publish_news_on_schedule()),
+        ));
         sync_state
     }
 
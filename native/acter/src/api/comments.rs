@@ -158,6 +158,12 @@ impl CommentDraft {
         self
     }
 
+    /// queue to be sent once online, shown as pending right away
+    pub async fn queue(&self) -> Result<OwnedEventId> {
+        let content = self.inner.build()?;
+        self.client.queue_event(self.room.clone(), content).await
+    }
+
     pub async fn send(&self) -> Result<OwnedEventId> {
         let room = self.room.clone();
         let my_id = self.client.user_id()?;
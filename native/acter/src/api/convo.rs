@@ -1,34 +1,17 @@
-use acter_matrix::{
-    referencing::{ExecuteReference, RoomParam},
-    statics::default_acter_convo_states,
-    Error,
+pub use acter_matrix::convos::{
+    new_convo_settings_builder, CreateConvoSettings, CreateConvoSettingsBuilder,
 };
+use acter_matrix::referencing::{ExecuteReference, RoomParam};
 use anyhow::{bail, Context, Result};
-use derive_builder::Builder;
 use futures::stream::{Stream, StreamExt};
 use matrix_sdk_base::{
     executor::JoinHandle,
-    ruma::{
-        api::client::room::{create_room, Visibility},
-        assign,
-        events::{
-            room::{
-                avatar::{ImageInfo, InitialRoomAvatarEvent, RoomAvatarEventContent},
-                join_rules::{AllowRule, InitialRoomJoinRulesEvent, RoomJoinRulesEventContent},
-            },
-            space::parent::SpaceParentEventContent,
-            InitialStateEvent,
-        },
-        serde::Raw,
-        MxcUri, OwnedEventId, OwnedRoomAliasId, OwnedRoomId, OwnedUserId, RoomAliasId, RoomId,
-        RoomOrAliasId, ServerName, UserId,
-    },
+    ruma::{OwnedEventId, OwnedRoomAliasId, OwnedRoomId, RoomAliasId, RoomId, RoomOrAliasId},
     ComposerDraft, ComposerDraftType,
 };
 use matrix_sdk_ui::{timeline::RoomExt, Timeline};
 use std::{
     ops::Deref,
-    path::PathBuf,
     sync::{Arc, RwLock},
 };
 use tokio_retry::{strategy::FixedInterval, Retry};
@@ -380,143 +363,11 @@ impl Deref for Convo {
     }
 }
 
-#[derive(Builder, Default, Clone)]
-pub struct CreateConvoSettings {
-    #[builder(setter(into, strip_option), default)]
-    name: Option<String>,
-
-    // #[builder(default = "Visibility::Private")]
-    // visibility: Visibility,
-    //
-    #[builder(default = "Vec::new()")]
-    invites: Vec<OwnedUserId>,
-
-    #[builder(setter(into, strip_option), default)]
-    alias: Option<String>,
-
-    #[builder(setter(into, strip_option), default)]
-    topic: Option<String>,
-
-    #[builder(setter(strip_option), default)]
-    avatar_uri: Option<String>,
-
-    #[builder(setter(strip_option), default)]
-    parent: Option<OwnedRoomId>,
-}
-
-// helper for built-in setters
-impl CreateConvoSettingsBuilder {
-    pub fn set_name(&mut self, value: String) {
-        self.name(value);
-    }
-
-    pub fn set_alias(&mut self, value: String) {
-        self.alias(value);
-    }
-
-    pub fn set_topic(&mut self, value: String) {
-        self.topic(value);
-    }
-
-    pub fn add_invitee(&mut self, value: String) -> Result<()> {
-        let user_id = UserId::parse(value)?;
-        match self.invites.as_mut() {
-            Some(invites) => {
-                if !invites.contains(&user_id) {
-                    invites.push(user_id);
-                }
-            }
-            None => {
-                self.invites = Some(vec![user_id]);
-            }
-        }
-        Ok(())
-    }
-
-    pub fn set_avatar_uri(&mut self, value: String) {
-        self.avatar_uri(value);
-    }
-
-    pub fn set_parent(&mut self, value: String) -> Result<()> {
-        let room_id = RoomId::parse(value)?;
-        self.parent(room_id);
-        Ok(())
-    }
-}
-
-pub fn new_convo_settings_builder() -> CreateConvoSettingsBuilder {
-    CreateConvoSettingsBuilder::default()
-}
-
 impl Client {
     pub async fn create_convo(&self, settings: Box<CreateConvoSettings>) -> Result<OwnedRoomId> {
-        let client = self.core.client().clone();
-
+        let core = self.core.clone();
         RUNTIME
-            .spawn(async move {
-                let mut initial_states = default_acter_convo_states();
-
-                if let Some(avatar_uri) = settings.avatar_uri {
-                    let uri = Box::<MxcUri>::from(avatar_uri.as_str());
-                    let avatar_content = if uri.is_valid() {
-                        // remote uri
-                        assign!(RoomAvatarEventContent::new(), {
-                            url: Some((*uri).to_owned()),
-                        })
-                    } else {
-                        // local uri
-                        let path = PathBuf::from(avatar_uri);
-                        let guess = mime_guess::from_path(path.clone());
-                        let content_type = guess.first().context("don’t know mime type")?;
-                        let buf = std::fs::read(path)?;
-                        let response = client.media().upload(&content_type, buf, None).await?;
-
-                        let info = assign!(ImageInfo::new(), {
-                            blurhash: response.blurhash,
-                            mimetype: Some(content_type.to_string()),
-                        });
-                        assign!(RoomAvatarEventContent::new(), {
-                            url: Some(response.content_uri),
-                            info: Some(Box::new(info)),
-                        })
-                    };
-                    initial_states.push(InitialRoomAvatarEvent::new(avatar_content).to_raw_any());
-                }
-
-                if let Some(parent) = settings.parent {
-                    let Some(Ok(homeserver)) =
-                        client.homeserver().host_str().map(ServerName::parse)
-                    else {
-                        return Err(Error::HomeserverMissesHostname)?;
-                    };
-                    let parent_event = InitialStateEvent::<SpaceParentEventContent> {
-                        content: assign!(SpaceParentEventContent::new(vec![homeserver]), {
-                            canonical: true,
-                        }),
-                        state_key: parent.clone(),
-                    };
-                    initial_states.push(parent_event.to_raw_any());
-                    // if we have a parent, by default we allow access to the subspace.
-                    let join_rule =
-                        InitialRoomJoinRulesEvent::new(RoomJoinRulesEventContent::restricted(
-                            vec![AllowRule::room_membership(parent)],
-                        ));
-                    initial_states.push(join_rule.to_raw_any());
-                };
-
-                let request = assign!(create_room::v3::Request::new(), {
-                    creation_content: Some(Raw::new(&create_room::v3::CreationContent::new())?),
-                    initial_state: initial_states,
-                    is_direct: true,
-                    invite: settings.invites,
-                    room_alias_name: settings.alias,
-                    name: settings.name,
-                    visibility: Visibility::Private,
-                    topic: settings.topic,
-                });
-                let room = client.create_room(request).await?;
-                Ok(room.room_id().to_owned())
-            })
+            .spawn(async move { Ok(core.create_acter_convo(*settings).await?) })
             .await?
     }
 
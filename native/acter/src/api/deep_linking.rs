@@ -2,15 +2,13 @@ use std::ops::Deref;
 use std::sync::Arc;
 
 use crate::{Client, RUNTIME};
+use acter_matrix::deep_linking;
 use acter_matrix::events::{ObjRef as CoreObjRef, RefDetails as CoreRefDetails};
 use acter_matrix::share_link::api;
-use anyhow::{bail, Context, Result};
-use matrix_sdk::ruma::{
-    events::room::message::UrlPreview, OwnedEventId, OwnedRoomId, OwnedServerName,
-};
+use anyhow::Result;
+use matrix_sdk::ruma::events::room::message::UrlPreview;
 use matrix_sdk::Client as SdkClient;
 use ruma::assign;
-use urlencoding::encode;
 
 #[derive(Clone)]
 pub struct ObjRef {
@@ -73,49 +71,14 @@ impl Client {
             new_link_ref_details(title, uri)?,
         ))
     }
-}
-
-fn generate_object_link(
-    room_id: &OwnedRoomId,
-    path: &[(&str, &OwnedEventId)],
-    via: &[OwnedServerName],
-    params: &[(&str, Option<&String>)],
-) -> String {
-    // acter:o/${ROOM_ID}/${PATH}?via=${SERVER_NAME}&via=${SERVER_NAME}
-    let room_id = &room_id.to_string()[1..];
-    format!(
-        "acter:o/{room_id}/{}?{}",
-        path.iter()
-            .map(|(p, o)| format!("{p}/{}", &o.to_string()[1..]))
-            .collect::<Vec<String>>()
-            .join("/"),
-        via.iter()
-            .map(|v| format!("via={}", encode(v.as_str())))
-            .chain(
-                params
-                    .iter()
-                    .filter_map(|(key, v)| v.map(|i| format!("{key}={}", encode(i.as_str()))))
-            )
-            .collect::<Vec<String>>()
-            .join("&")
-    )
-}
 
-fn generate_room_link(room_id: &OwnedRoomId, via: &[OwnedServerName]) -> String {
-    // matrix:roomid/${ROOM_ID}?via=${SERVER_NAME}&via=${SERVER_NAME}
-    let room_id = &room_id.to_string()[1..];
-    format!(
-        "matrix:roomid/{room_id}?{}",
-        via.iter()
-            .map(|v| format!("via={}", encode(v.as_str())))
-            .collect::<Vec<String>>()
-            .join("&")
-    )
-}
-
-fn generate_invite_link(server_name: &str, token: &str, inviter_user_id: &str) -> String {
-    // acter:i/${SERVER_NAME}/${INVITE_TOKEN}?userId=${INVITER}
-    format!("acter:i/{server_name}/{token}?userId={inviter_user_id}")
+    /// the ref details an acter:, matrix: or matrix.to link points to
+    pub fn parse_link(&self, link: String) -> Result<RefDetails> {
+        Ok(RefDetails::new(
+            self.core.client().clone(),
+            deep_linking::parse_link(&link)?,
+        ))
+    }
 }
 
 impl RefDetails {
@@ -127,6 +90,7 @@ impl RefDetails {
         match &self.inner {
             CoreRefDetails::Link { title, uri } => false,
             CoreRefDetails::Room { room_id, .. } => true, // always
+            CoreRefDetails::Event { .. } => true,
             CoreRefDetails::SuperInviteToken { rooms, .. } => !rooms.is_empty(),
             CoreRefDetails::Task { room_id, .. }
             | CoreRefDetails::TaskList { room_id, .. }
@@ -137,165 +101,11 @@ impl RefDetails {
     }
 
     pub fn generate_internal_link(&self, include_preview: bool) -> Result<String> {
-        Ok(match &self.inner {
-            CoreRefDetails::Link { title, uri } => bail!("Link can't be made into internal link"),
-            CoreRefDetails::Room {
-                room_id,
-                is_space,
-                via,
-                preview,
-            } => generate_room_link(room_id, via.as_slice()),
-            CoreRefDetails::SuperInviteToken {
-                token,
-                create_dm,
-                accepted_count,
-                rooms,
-            } => {
-                let my_id = self
-                    .client
-                    .user_id()
-                    .context("You must be logged in to do that")?
-                    .as_str();
-                generate_invite_link("acter.global", token, my_id)
-            }
-            CoreRefDetails::Task {
-                target_id,
-                room_id,
-                via,
-                preview,
-                task_list,
-                action,
-            } => {
-                let Some(room_id) = room_id else {
-                    bail!("Object misses room_id")
-                };
-                let params = if include_preview {
-                    vec![
-                        ("roomDisplayName", preview.room_display_name.as_ref()),
-                        ("title", preview.title.as_ref()),
-                    ]
-                } else {
-                    vec![]
-                };
-
-                generate_object_link(
-                    room_id,
-                    &[("taskList", task_list), ("task", target_id)],
-                    via.as_slice(),
-                    params.as_slice(),
-                )
-            }
-            CoreRefDetails::TaskList {
-                target_id,
-                room_id,
-                via,
-                preview,
-                action,
-            } => {
-                let Some(room_id) = room_id else {
-                    bail!("Object misses room_id")
-                };
-                let params = if include_preview {
-                    vec![
-                        ("roomDisplayName", preview.room_display_name.as_ref()),
-                        ("title", preview.title.as_ref()),
-                    ]
-                } else {
-                    vec![]
-                };
-
-                generate_object_link(
-                    room_id,
-                    &[("taskList", target_id)],
-                    via.as_slice(),
-                    params.as_slice(),
-                )
-            }
-            CoreRefDetails::Pin {
-                target_id,
-                room_id,
-                via,
-                preview,
-                action,
-            } => {
-                let Some(room_id) = room_id else {
-                    bail!("Object misses room_id")
-                };
-                let params = if include_preview {
-                    vec![
-                        ("roomDisplayName", preview.room_display_name.as_ref()),
-                        ("title", preview.title.as_ref()),
-                    ]
-                } else {
-                    vec![]
-                };
-
-                generate_object_link(
-                    room_id,
-                    &[("pin", target_id)],
-                    via.as_slice(),
-                    params.as_slice(),
-                )
-            }
-            CoreRefDetails::News {
-                target_id,
-                room_id,
-                via,
-                preview,
-            } => {
-                let Some(room_id) = room_id else {
-                    bail!("Object misses room_id")
-                };
-                let params = if include_preview {
-                    vec![
-                        ("roomDisplayName", preview.room_display_name.as_ref()),
-                        ("title", preview.title.as_ref()),
-                    ]
-                } else {
-                    vec![]
-                };
-
-                generate_object_link(
-                    room_id,
-                    &[("boost", target_id)],
-                    via.as_slice(),
-                    params.as_slice(),
-                )
-            }
-            CoreRefDetails::CalendarEvent {
-                target_id,
-                room_id,
-                via,
-                preview,
-                action,
-            } => {
-                let Some(room_id) = room_id else {
-                    bail!("Object misses room_id")
-                };
-                let participants = preview.participants.as_ref().map(ToString::to_string);
-                let start_at = preview
-                    .start_at_utc
-                    .as_ref()
-                    .map(|s| s.timestamp().to_string());
-                let params = if include_preview {
-                    vec![
-                        ("roomDisplayName", preview.room_display_name.as_ref()),
-                        ("title", preview.title.as_ref()),
-                        ("participants", participants.as_ref()),
-                        ("startAtUtc", start_at.as_ref()),
-                    ]
-                } else {
-                    vec![]
-                };
-
-                generate_object_link(
-                    room_id,
-                    &[("calendarEvent", target_id)],
-                    via.as_slice(),
-                    params.as_slice(),
-                )
-            }
-        })
+        Ok(deep_linking::generate_internal_link(
+            &self.inner,
+            include_preview,
+            self.client.user_id(),
+        )?)
     }
 
     pub async fn generate_external_link(&self) -> Result<String> {
@@ -0,0 +1,111 @@
+use anyhow::{Context, Result};
+use matrix_sdk_store_file_event_cache::{MediaCache, MediaCacheStats as InnerStats};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::{platform, Client, RUNTIME};
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Debug)]
+pub struct MediaCacheStats {
+    inner: InnerStats,
+}
+
+impl MediaCacheStats {
+    pub fn file_count(&self) -> u64 {
+        self.inner.file_count as u64
+    }
+
+    /// in bytes, including pinned files
+    pub fn total_size(&self) -> u64 {
+        self.inner.total_size
+    }
+
+    /// files kept regardless of the limits, e.g. avatars
+    pub fn pinned_count(&self) -> u64 {
+        self.inner.pinned_count as u64
+    }
+
+    pub fn pinned_size(&self) -> u64 {
+        self.inner.pinned_size
+    }
+
+    pub fn max_cache_size(&self) -> Option<u64> {
+        self.inner.policy.max_cache_size.map(|s| s as u64)
+    }
+
+    pub fn max_file_size(&self) -> Option<u64> {
+        self.inner.policy.max_file_size.map(|s| s as u64)
+    }
+
+    /// days after which files not accessed are removed
+    pub fn expiry_days(&self) -> Option<u32> {
+        self.inner
+            .policy
+            .last_access_expiry
+            .map(|d| (d.as_secs() / DAY_SECS) as u32)
+    }
+
+    /// milliseconds since epoch of the last clean up
+    pub fn last_cleanup_ts(&self) -> Option<u64> {
+        self.inner
+            .last_cleanup
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64)
+    }
+}
+
+impl Client {
+    fn media_cache(&self) -> Result<MediaCache> {
+        let user_id = self.user_id()?;
+        platform::media_cache_for(user_id.as_str())
+            .context("This client doesn’t cache media in files")
+    }
+
+    pub async fn media_cache_stats(&self) -> Result<MediaCacheStats> {
+        let cache = self.media_cache()?;
+        RUNTIME
+            .spawn_blocking(move || {
+                let inner = cache.stats()?;
+                Ok(MediaCacheStats { inner })
+            })
+            .await?
+    }
+
+    /// Limit the media cache, `None` meaning unlimited. Takes effect with the
+    /// next clean up.
+    pub async fn set_media_cache_limits(
+        &self,
+        max_cache_size: Option<u64>,
+        max_file_size: Option<u64>,
+        expiry_days: Option<u32>,
+    ) -> Result<bool> {
+        let cache = self.media_cache()?;
+        let policy = cache
+            .policy()
+            .with_max_cache_size(max_cache_size.map(|s| s as usize))
+            .with_max_file_size(max_file_size.map(|s| s as usize))
+            .with_last_access_expiry(
+                expiry_days.map(|d| Duration::from_secs(u64::from(d) * DAY_SECS)),
+            );
+        RUNTIME
+            .spawn_blocking(move || {
+                cache.set_policy(policy)?;
+                Ok(true)
+            })
+            .await?
+    }
+
+    /// Remove what is beyond the limits now rather than waiting for the
+    /// regular clean up
+    pub async fn clean_up_media_cache(&self) -> Result<MediaCacheStats> {
+        let cache = self.media_cache()?;
+        RUNTIME
+            .spawn_blocking(move || {
+                cache.clean_up()?;
+                let inner = cache.stats()?;
+                Ok(MediaCacheStats { inner })
+            })
+            .await?
+    }
+}
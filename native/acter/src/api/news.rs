@@ -6,6 +6,7 @@ use acter_matrix::{
     },
     models::{self, can_redact, ActerModel, AnyActerModel, ReactionManager},
     referencing::{IndexKey, SectionIndex},
+    store::NewsDraft,
 };
 use anyhow::{bail, Context, Result};
 use futures::stream::StreamExt;
@@ -13,7 +14,7 @@ use matrix_sdk::room::Room;
 use matrix_sdk_base::{
     ruma::{
         events::{room::message::MessageType, MessageLikeEventType},
-        OwnedEventId, OwnedRoomId, OwnedUserId,
+        MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedRoomId, OwnedUserId,
     },
     RoomState,
 };
@@ -32,6 +33,7 @@ use super::{
     client::Client,
     common::ThumbnailSize,
     deep_linking::{ObjRef, RefDetails},
+    news_drafts::publish_time,
     spaces::Space,
     timeline::MsgContent,
     RUNTIME,
@@ -109,6 +111,20 @@ impl Deref for NewsSlide {
 }
 
 impl NewsSlide {
+    pub(super) fn new(
+        client: Client,
+        room: Room,
+        unique_id: String,
+        inner: news::NewsSlide,
+    ) -> Self {
+        NewsSlide {
+            client,
+            room,
+            unique_id,
+            inner,
+        }
+    }
+
     pub fn type_str(&self) -> String {
         self.inner.content().type_str()
     }
@@ -266,7 +282,7 @@ impl NewsSlideDraft {
         self.colorize_builder = *colors;
     }
 
-    async fn build(self, client: &Client, room: &Room) -> Result<news::NewsSlide> {
+    pub(super) async fn build(self, client: &Client, room: &Room) -> Result<news::NewsSlide> {
         let msg = self.content.into_room_msg(room).await?;
         let content = match msg.msgtype {
             MessageType::Text(msg) => NewsContent::Text(msg),
@@ -539,6 +555,34 @@ impl NewsEntryDraft {
             })
             .await?
     }
+
+    /// keep it on this device to continue later, returns the draft id
+    pub async fn save_draft(&self) -> Result<String> {
+        self.save_local(None).await
+    }
+
+    /// publish it at `publish_at` milliseconds since epoch, returns the draft id
+    pub async fn schedule(&self, publish_at: u64) -> Result<String> {
+        self.save_local(Some(publish_time(publish_at)?)).await
+    }
+
+    async fn save_local(&self, publish_at: Option<MilliSecondsSinceUnixEpoch>) -> Result<String> {
+        let client = self.client.clone();
+        let room = self.room.clone();
+        let slides_drafts = self.slides.clone();
+
+        RUNTIME
+            .spawn(async move {
+                let mut slides = vec![];
+                for slide in slides_drafts {
+                    slides.push(slide.build(&client, &room).await?);
+                }
+                let mut draft = NewsDraft::new(room.room_id().to_owned(), slides);
+                draft.set_publish_at(publish_at);
+                client.save_news_draft(room, draft).await
+            })
+            .await?
+    }
 }
 
 #[derive(Clone)]
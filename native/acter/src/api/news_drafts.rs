@@ -0,0 +1,292 @@
+use acter_matrix::store::{NewsDraft, NEWS_DRAFTS_KEY};
+use anyhow::{bail, Context, Result};
+use futures::{Stream, StreamExt};
+use matrix_sdk::room::Room;
+use matrix_sdk_base::ruma::{
+    events::MessageLikeEventType, MilliSecondsSinceUnixEpoch, OwnedEventId, UInt,
+};
+use tokio_stream::wrappers::BroadcastStream;
+use tracing::warn;
+
+use super::{
+    client::Client,
+    news::{NewsSlide, NewsSlideDraft},
+    spaces::Space,
+    RUNTIME,
+};
+
+/// milliseconds since epoch, as given over the FFI
+pub(crate) fn publish_time(millis: u64) -> Result<MilliSecondsSinceUnixEpoch> {
+    UInt::new(millis)
+        .map(MilliSecondsSinceUnixEpoch)
+        .context("Publish time out of range")
+}
+
+async fn ensure_can_post(room: &Room, client: &Client) -> Result<()> {
+    let my_id = client.user_id()?;
+    let permitted = room
+        .can_user_send_message(&my_id, MessageLikeEventType::RoomMessage)
+        .await?;
+    if !permitted {
+        bail!("No permissions to send message in this room");
+    }
+    Ok(())
+}
+
+/// A news entry kept on this device, to be edited and published later or
+/// at its scheduled time
+#[derive(Clone, Debug)]
+pub struct LocalNewsDraft {
+    client: Client,
+    room: Room,
+    inner: NewsDraft,
+}
+
+impl LocalNewsDraft {
+    fn new(client: Client, inner: NewsDraft) -> Result<Self> {
+        let room = client.room_by_id_typed(inner.room_id())?;
+        Ok(LocalNewsDraft {
+            client,
+            room,
+            inner,
+        })
+    }
+
+    pub fn draft_id(&self) -> String {
+        self.inner.draft_id().to_owned()
+    }
+
+    pub fn room_id_str(&self) -> String {
+        self.inner.room_id().to_string()
+    }
+
+    pub fn slides_count(&self) -> u8 {
+        self.inner.slides().len() as u8
+    }
+
+    pub fn get_slide(&self, pos: u8) -> Option<NewsSlide> {
+        let unique_id = format!("{}-{pos}", self.inner.draft_id());
+        self.inner.slides().get(pos as usize).map(|inner| {
+            NewsSlide::new(
+                self.client.clone(),
+                self.room.clone(),
+                unique_id,
+                inner.clone(),
+            )
+        })
+    }
+
+    pub fn slides(&self) -> Vec<NewsSlide> {
+        (0..self.slides_count())
+            .filter_map(|pos| self.get_slide(pos))
+            .collect()
+    }
+
+    pub fn created_at(&self) -> u64 {
+        self.inner.created_at().get().into()
+    }
+
+    pub fn updated_at(&self) -> u64 {
+        self.inner.updated_at().get().into()
+    }
+
+    /// milliseconds since epoch it is going to be published at, if scheduled
+    pub fn publish_at(&self) -> Option<u64> {
+        self.inner.publish_at().map(|t| t.get().into())
+    }
+
+    pub fn is_scheduled(&self) -> bool {
+        self.inner.publish_at().is_some()
+    }
+
+    /// why publishing it at the scheduled time failed, until rescheduled
+    pub fn last_error(&self) -> Option<String> {
+        self.inner.last_error().map(ToOwned::to_owned)
+    }
+
+    /// fresh copy of the stored draft
+    pub async fn refresh(&self) -> Result<LocalNewsDraft> {
+        self.client.news_draft(self.draft_id()).await
+    }
+
+    /// change the stored draft, failing if it has been published or
+    /// discarded meanwhile
+    async fn update(
+        &self,
+        f: impl FnOnce(&mut NewsDraft) -> Result<()> + Send + 'static,
+    ) -> Result<bool> {
+        let executor = self.client.executor().clone();
+        let draft_id = self.draft_id();
+        RUNTIME
+            .spawn(async move {
+                let keys = executor.store().update_news_draft(&draft_id, f).await?;
+                executor.notify(keys);
+                Ok(true)
+            })
+            .await?
+    }
+
+    /// uploads the media of the slide right away
+    pub async fn add_slide(&self, draft: Box<NewsSlideDraft>) -> Result<bool> {
+        let client = self.client.clone();
+        let room = self.room.clone();
+        let slide = RUNTIME
+            .spawn(async move { (*draft).build(&client, &room).await })
+            .await??;
+        self.update(move |d| {
+            d.slides_mut().push(slide);
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn remove_slide(&self, pos: u8) -> Result<bool> {
+        self.update(move |d| {
+            let slides = d.slides_mut();
+            if pos as usize >= slides.len() {
+                bail!("upper bound is exceeded")
+            }
+            slides.remove(pos as usize);
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn swap_slides(&self, from: u8, to: u8) -> Result<bool> {
+        self.update(move |d| {
+            let slides = d.slides_mut();
+            if from as usize >= slides.len() || to as usize >= slides.len() {
+                bail!("upper bound is exceeded")
+            }
+            slides.swap(from as usize, to as usize);
+            Ok(())
+        })
+        .await
+    }
+
+    /// publish it at `publish_at` milliseconds since epoch, also to reschedule
+    pub async fn schedule(&self, publish_at: u64) -> Result<bool> {
+        let publish_at = publish_time(publish_at)?;
+        let room = self.room.clone();
+        let client = self.client.clone();
+        RUNTIME
+            .spawn(async move { ensure_can_post(&room, &client).await })
+            .await??;
+        self.update(move |d| {
+            d.set_publish_at(Some(publish_at));
+            Ok(())
+        })
+        .await
+    }
+
+    /// cancel the scheduled publishing, keeping it as draft
+    pub async fn unschedule(&self) -> Result<bool> {
+        self.update(|d| {
+            d.set_publish_at(None);
+            Ok(())
+        })
+        .await
+    }
+
+    /// publish it now, returns the event id of the pending news entry
+    pub async fn publish(&self) -> Result<OwnedEventId> {
+        let room = self.room.clone();
+        let client = self.client.clone();
+        let executor = self.client.executor().clone();
+        let draft_id = self.draft_id();
+        RUNTIME
+            .spawn(async move {
+                ensure_can_post(&room, &client).await?;
+                Ok(executor.publish_news_draft(&draft_id).await?)
+            })
+            .await?
+    }
+
+    /// throw it away
+    pub async fn discard(&self) -> Result<bool> {
+        let executor = self.client.executor().clone();
+        let draft_id = self.draft_id();
+        RUNTIME
+            .spawn(async move {
+                let keys = executor.store().remove_news_draft(&draft_id).await?;
+                executor.notify(keys);
+                Ok(true)
+            })
+            .await?
+    }
+}
+
+impl Client {
+    /// Keep the draft locally, scheduled or not. Returns its id.
+    pub(crate) async fn save_news_draft(&self, room: Room, draft: NewsDraft) -> Result<String> {
+        if draft.publish_at().is_some() {
+            ensure_can_post(&room, self).await?;
+        }
+        let executor = self.executor().clone();
+        let draft_id = draft.draft_id().to_owned();
+        let keys = executor.store().add_news_draft(draft).await?;
+        executor.notify(keys);
+        Ok(draft_id)
+    }
+
+    /// All news drafts on this device, in the order they were created
+    pub async fn news_drafts(&self) -> Result<Vec<LocalNewsDraft>> {
+        let me = self.clone();
+        RUNTIME
+            .spawn(async move {
+                Ok(me
+                    .store()
+                    .news_drafts()?
+                    .into_iter()
+                    .filter_map(|inner| match LocalNewsDraft::new(me.clone(), inner) {
+                        Ok(draft) => Some(draft),
+                        Err(error) => {
+                            warn!(?error, "Skipping news draft of unknown room");
+                            None
+                        }
+                    })
+                    .collect())
+            })
+            .await?
+    }
+
+    pub async fn news_draft(&self, draft_id: String) -> Result<LocalNewsDraft> {
+        let me = self.clone();
+        RUNTIME
+            .spawn(async move {
+                let inner = me.store().news_draft(&draft_id)?;
+                LocalNewsDraft::new(me, inner)
+            })
+            .await?
+    }
+
+    pub fn subscribe_news_drafts_stream(&self) -> impl Stream<Item = bool> {
+        BroadcastStream::new(self.executor().subscribe(NEWS_DRAFTS_KEY.clone())).map(|_| true)
+    }
+}
+
+impl Space {
+    /// The news drafts of this space on this device
+    pub async fn news_drafts(&self) -> Result<Vec<LocalNewsDraft>> {
+        let room_id = self.inner.room.room_id().to_owned();
+        Ok(self
+            .client
+            .news_drafts()
+            .await?
+            .into_iter()
+            .filter(|d| d.inner.room_id() == &room_id)
+            .collect())
+    }
+
+    /// The news of this space waiting to be published, soonest first
+    pub async fn scheduled_news(&self) -> Result<Vec<LocalNewsDraft>> {
+        let mut scheduled: Vec<_> = self
+            .news_drafts()
+            .await?
+            .into_iter()
+            .filter(|d| d.is_scheduled())
+            .collect();
+        scheduled.sort_by_key(|d| d.inner.publish_at());
+        Ok(scheduled)
+    }
+}
@@ -0,0 +1,139 @@
+use acter_matrix::store::{OutboxEntry, OutboxState, OUTBOX_KEY};
+use anyhow::{bail, Result};
+use futures::{Stream, StreamExt};
+use matrix_sdk::{
+    room::Room,
+    ruma::{
+        events::{MessageLikeEventType, StaticEventContent},
+        EventId, OwnedEventId,
+    },
+};
+use serde::Serialize;
+use tokio_stream::wrappers::BroadcastStream;
+
+use super::{client::Client, RUNTIME};
+
+/// A change queued to be sent once we are online again
+#[derive(Clone, Debug)]
+pub struct OutboxItem {
+    inner: OutboxEntry,
+}
+
+impl OutboxItem {
+    /// the event id of the local pending object
+    pub fn local_id_str(&self) -> String {
+        self.inner.local_id().to_string()
+    }
+
+    /// the event id the server gave it, once sent
+    pub fn event_id_str(&self) -> Option<String> {
+        self.inner.event_id().map(ToString::to_string)
+    }
+
+    pub fn room_id_str(&self) -> String {
+        self.inner.room_id().to_string()
+    }
+
+    pub fn event_type(&self) -> String {
+        self.inner.event_type().to_owned()
+    }
+
+    /// one of `queued`, `sent`, `failed` or `conflict`
+    pub fn state(&self) -> String {
+        self.inner.state().to_string()
+    }
+
+    /// why it failed or conflicts, or the error of the last attempt
+    pub fn error(&self) -> Option<String> {
+        match self.inner.state() {
+            OutboxState::Failed { error } => Some(error.clone()),
+            OutboxState::Conflict { reason } => Some(reason.clone()),
+            _ => self.inner.last_error().map(ToOwned::to_owned),
+        }
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.inner.attempts()
+    }
+
+    pub fn queued_at(&self) -> u64 {
+        self.inner.queued_at().get().into()
+    }
+
+    /// when the next attempt is due, if it failed before
+    pub fn retry_at(&self) -> Option<u64> {
+        self.inner.retry_at().map(|t| t.get().into())
+    }
+}
+
+impl Client {
+    /// Queue the content for the room and apply it locally right away
+    pub(crate) async fn queue_event<C>(&self, room: Room, content: C) -> Result<OwnedEventId>
+    where
+        C: StaticEventContent + Serialize + Send + 'static,
+    {
+        let my_id = self.user_id()?;
+        let executor = self.executor().clone();
+
+        RUNTIME
+            .spawn(async move {
+                let permitted = room
+                    .can_user_send_message(&my_id, MessageLikeEventType::RoomMessage)
+                    .await?;
+                if !permitted {
+                    bail!("No permissions to send message in this room");
+                }
+                let content = serde_json::to_value(content)?;
+                Ok(executor
+                    .enqueue(room.room_id().to_owned(), C::TYPE.to_owned(), content)
+                    .await?)
+            })
+            .await?
+    }
+
+    /// The changes waiting to be sent or confirmed, in order
+    pub fn outbox(&self) -> Result<Vec<OutboxItem>> {
+        Ok(self
+            .store()
+            .outbox_entries()?
+            .into_iter()
+            .map(|inner| OutboxItem { inner })
+            .collect())
+    }
+
+    pub fn subscribe_outbox_stream(&self) -> impl Stream<Item = bool> {
+        BroadcastStream::new(self.executor().subscribe(OUTBOX_KEY.clone())).map(|_| true)
+    }
+
+    /// Send the queued changes that are due now, returns how many were sent
+    pub async fn flush_outbox(&self) -> Result<u32> {
+        let executor = self.executor().clone();
+        RUNTIME
+            .spawn(async move { Ok(executor.flush_outbox().await?) })
+            .await?
+    }
+
+    /// Send a failed or conflicting change again at the next opportunity
+    pub async fn retry_outbox_item(&self, local_id: String) -> Result<bool> {
+        let local_id = EventId::parse(local_id)?;
+        let executor = self.executor().clone();
+        RUNTIME
+            .spawn(async move {
+                executor.retry_outbox_entry(&local_id).await?;
+                Ok(true)
+            })
+            .await?
+    }
+
+    /// Drop a queued change and undo it locally
+    pub async fn discard_outbox_item(&self, local_id: String) -> Result<bool> {
+        let local_id = EventId::parse(local_id)?;
+        let executor = self.executor().clone();
+        RUNTIME
+            .spawn(async move {
+                executor.discard_outbox_entry(&local_id).await?;
+                Ok(true)
+            })
+            .await?
+    }
+}
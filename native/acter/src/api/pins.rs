@@ -27,7 +27,13 @@ use tracing::warn;
 
 use crate::MsgContent;
 
-use super::{client::Client, deep_linking::RefDetails, spaces::Space, RUNTIME};
+use super::{
+    client::Client,
+    deep_linking::RefDetails,
+    revisions::{changed_since, ObjectRevision, RevisionConflict},
+    spaces::Space,
+    RUNTIME,
+};
 
 impl Client {
     pub async fn wait_for_pin(&self, key: String, timeout: Option<u8>) -> Result<Pin> {
@@ -109,6 +115,44 @@ impl Pin {
         self.content.title.clone()
     }
 
+    /// The creation and all updates applied since, oldest first
+    pub fn revisions(&self) -> Result<Vec<ObjectRevision>> {
+        Ok(self
+            .content
+            .revisions()?
+            .into_iter()
+            .map(ObjectRevision::from)
+            .collect())
+    }
+
+    /// Revert the pin to how it was right after the given revision
+    pub async fn revert_to(&self, revision_id: String) -> Result<OwnedEventId> {
+        let revision = EventId::parse(revision_id)?;
+        let content = self.content.revert_update(&revision)?;
+        self.client.send_revert(self.room.clone(), content).await
+    }
+
+    /// The revision this pin is at, updates made from here are based on it
+    pub fn revision_str(&self) -> String {
+        self.content.revision().to_string()
+    }
+
+    /// The updates applied since the given revision, e.g. while editing
+    pub fn changed_since(&self, revision_id: String) -> Result<Vec<ObjectRevision>> {
+        let revision = EventId::parse(&revision_id)?;
+        changed_since(self.content.changed_since(&revision), &revision_id)
+    }
+
+    /// Concurrent updates that changed the same fields and which one was kept
+    pub fn conflicts(&self) -> Vec<RevisionConflict> {
+        self.content
+            .conflicts()
+            .iter()
+            .cloned()
+            .map(RevisionConflict::from)
+            .collect()
+    }
+
     pub fn has_formatted_text(&self) -> bool {
         matches!(
             self.content.content(),
@@ -301,6 +345,12 @@ impl PinDraft {
         self
     }
 
+    /// queue to be sent once online, shown as pending right away
+    pub async fn queue(&self) -> Result<OwnedEventId> {
+        let content = self.content.build()?;
+        self.client.queue_event(self.room.clone(), content).await
+    }
+
     pub async fn send(&self) -> Result<OwnedEventId> {
         let room = self.room.clone();
         let my_id = self.client.user_id()?;
@@ -329,6 +379,14 @@ pub struct PinUpdateBuilder {
 }
 
 impl PinUpdateBuilder {
+    /// the revision this update was made from, defaults to the current one.
+    /// Fields also changed since by others are resolved on arrival.
+    pub fn base_revision(&mut self, revision_id: String) -> Result<&mut Self> {
+        self.content
+            .base_revision(Some(EventId::parse(revision_id)?));
+        Ok(self)
+    }
+
     pub fn title(&mut self, title: String) -> &mut Self {
         self.content.title(Some(title));
         self
@@ -398,6 +456,12 @@ impl PinUpdateBuilder {
         self
     }
 
+    /// queue to be sent once online, shown as pending right away
+    pub async fn queue(&self) -> Result<OwnedEventId> {
+        let content = self.content.build()?;
+        self.client.queue_event(self.room.clone(), content).await
+    }
+
     pub async fn send(&self) -> Result<OwnedEventId> {
         let room = self.room.clone();
         let my_id = self.client.user_id()?;
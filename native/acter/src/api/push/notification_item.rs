@@ -8,6 +8,7 @@ use acter_matrix::{
     },
     models::{ActerModel, AnyActerModel, Attachment},
     push::default_rules,
+    reminders::{Reminder, ReminderChange, ReminderKind},
 };
 use anyhow::{bail, Context, Result};
 use chrono::{NaiveDate, NaiveTime, Utc};
@@ -174,6 +175,10 @@ pub enum NotificationItemInner {
         room_id: OwnedRoomId,
     },
     Activity(Box<Activity>),
+    Reminder {
+        reminder: Box<Reminder>,
+        cancelled: bool,
+    },
 }
 
 impl NotificationItemInner {
@@ -182,6 +187,9 @@ impl NotificationItemInner {
             NotificationItemInner::Fallback { .. } => "fallback".to_owned(),
             NotificationItemInner::Invite { .. } => "invite".to_owned(),
             NotificationItemInner::Activity(a) => a.type_str(),
+            NotificationItemInner::Reminder { reminder, .. } => {
+                format!("{}Reminder", reminder.kind())
+            }
             NotificationItemInner::ChatMessage { is_dm, .. } => {
                 if *is_dm {
                     "dm"
@@ -202,6 +210,7 @@ impl NotificationItemInner {
             NotificationItemInner::Invite { room_id } => "/activities/invites".to_owned(),
             NotificationItemInner::ChatMessage { room_id, .. } => format!("/chat/{room_id}"),
             NotificationItemInner::Activity(a) => a.target_url(),
+            NotificationItemInner::Reminder { reminder, .. } => reminder.object().target_url(),
         }
     }
 
@@ -214,17 +223,21 @@ impl NotificationItemInner {
     }
 
     pub fn parent(&self) -> Option<ActivityObject> {
-        let NotificationItemInner::Activity(a) = &self else {
-            return None;
-        };
-
-        a.object()
+        match &self {
+            NotificationItemInner::Activity(a) => a.object(),
+            NotificationItemInner::Reminder { reminder, .. } => Some(reminder.object().clone()),
+            _ => None,
+        }
     }
     pub fn parent_id_str(&self) -> Option<String> {
-        let NotificationItemInner::Activity(a) = &self else {
+        self.parent().map(|a| a.object_id_str())
+    }
+
+    pub fn reminder(&self) -> Option<&Reminder> {
+        let NotificationItemInner::Reminder { reminder, .. } = &self else {
             return None;
         };
-        a.object().map(|a| a.object_id_str())
+        Some(reminder)
     }
 
     pub fn reaction_key(&self) -> Option<String> {
@@ -235,6 +248,9 @@ impl NotificationItemInner {
     }
 
     pub fn utc_start(&self) -> Option<UtcDateTime> {
+        if let Some(reminder) = self.reminder() {
+            return reminder.object().utc_start();
+        }
         let NotificationItemInner::Activity(a) = &self else {
             return None;
         };
@@ -248,6 +264,9 @@ impl NotificationItemInner {
     }
 
     pub fn utc_end(&self) -> Option<UtcDateTime> {
+        if let Some(reminder) = self.reminder() {
+            return reminder.object().utc_end();
+        }
         let NotificationItemInner::Activity(a) = &self else {
             return None;
         };
@@ -261,6 +280,12 @@ impl NotificationItemInner {
     }
 
     pub fn due_date(&self) -> Option<String> {
+        if let Some(reminder) = self.reminder() {
+            return reminder
+                .object()
+                .due_date()
+                .map(|d| d.format("%Y-%m-%d").to_string());
+        }
         let NotificationItemInner::Activity(a) = &self else {
             return None;
         };
@@ -290,6 +315,9 @@ impl NotificationItemInner {
                 }
                 _ => None,
             },
+            NotificationItemInner::Reminder { reminder, .. } => {
+                Some(MsgContent::from_text(reminder_body(reminder)))
+            }
             NotificationItemInner::Activity(activity) => match activity.content() {
                 ActivityContent::DescriptionChange { content, .. } => {
                     content.new_val.as_ref().map(MsgContent::from)
@@ -362,6 +390,23 @@ impl NotificationItem {
     pub fn mentions_you(&self) -> bool {
         self.mentions_you
     }
+    pub fn reminder_id(&self) -> Option<String> {
+        self.inner.reminder().map(Reminder::id)
+    }
+    pub fn reminder_at(&self) -> Option<u64> {
+        self.inner
+            .reminder()
+            .map(|r| r.remind_at().timestamp_millis() as u64)
+    }
+    pub fn is_reminder_cancelled(&self) -> bool {
+        matches!(
+            self.inner,
+            NotificationItemInner::Reminder {
+                cancelled: true,
+                ..
+            }
+        )
+    }
     pub fn has_image(&self) -> bool {
         self.msg_content.as_ref().and_then(|a| a.source()).is_some()
     }
@@ -418,6 +463,31 @@ impl NotificationItem {
         Ok(builder.build()?)
     }
 
+    pub(crate) async fn for_reminder(client: Client, change: ReminderChange) -> Result<Self> {
+        let (reminder, cancelled) = match change {
+            ReminderChange::Schedule(reminder) => (reminder, false),
+            ReminderChange::Cancel(reminder) => (reminder, true),
+        };
+        let object = reminder.object();
+        let title = format!("{} {}", object.emoji(), object.title().unwrap_or_default());
+
+        let mut builder = NotificationItemBuilder::default();
+        builder
+            .sender(NotificationSender::fallback(client.clone()))
+            .client(client.clone())
+            .title(title)
+            .noisy(Some(true))
+            .thread_id(reminder.object().object_id_str());
+        let room = client.room(reminder.room_id().to_string()).await?;
+        builder.room(NotificationRoom::for_room(client, &room.room).await);
+        Ok(builder
+            .inner(NotificationItemInner::Reminder {
+                reminder: Box::new(reminder),
+                cancelled,
+            })
+            .build()?)
+    }
+
     pub(super) async fn from(
         client: Client,
         inner: SdkNotificationItem,
@@ -482,6 +552,24 @@ impl NotificationItem {
     }
 }
 
+/// e.g. `Due in 1 day(s)` or `Starts in 15 minute(s)`
+fn reminder_body(reminder: &Reminder) -> String {
+    let minutes = reminder.minutes_before();
+    let when = if minutes == 0 {
+        "now".to_owned()
+    } else if minutes % (24 * 60) == 0 {
+        format!("in {} day(s)", minutes / (24 * 60))
+    } else if minutes % 60 == 0 {
+        format!("in {} hour(s)", minutes / 60)
+    } else {
+        format!("in {minutes} minute(s)")
+    };
+    match reminder.kind() {
+        ReminderKind::TaskDue => format!("Due {when}"),
+        ReminderKind::EventStart => format!("Starts {when}"),
+    }
+}
+
 async fn convert_acter_model(client: Client, event: AnyActerEvent) -> Result<Activity> {
     Ok(Activity::for_acter_model(client.store(), AnyActerModel::try_from(event)?).await?)
 }
@@ -575,7 +663,7 @@ impl NotificationItemBuilder {
                         RefDetails::Task { .. } => format!("☑️ {title}"),
                         RefDetails::TaskList { .. } => format!("📋 {title}"),
                         RefDetails::Link { .. } => format!("🔗 {title}"),
-                        RefDetails::Room { .. } => title,
+                        RefDetails::Room { .. } | RefDetails::Event { .. } => title,
                         RefDetails::SuperInviteToken { .. } => title,
                     })
                 } else {
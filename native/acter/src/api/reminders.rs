@@ -0,0 +1,110 @@
+use acter_matrix::{
+    events::settings::{AppReminderSettings, APP_USER_SETTINGS},
+    referencing::IndexKey,
+    reminders::{Reminder, ReminderChange},
+};
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use futures::{
+    future::{self, Either},
+    stream::{select, Stream, StreamExt},
+};
+use tokio_stream::wrappers::BroadcastStream;
+use tracing::error;
+
+use super::{Client, NotificationItem, RUNTIME};
+
+/// Even without any reminder coming up, look again every so often
+const MAX_REMINDER_CHECK_INTERVAL_SECS: i64 = 15 * 60;
+
+impl Client {
+    async fn reminder_settings(&self) -> Result<AppReminderSettings> {
+        Ok(self
+            .account()?
+            .acter_app_settings()
+            .await?
+            .reminders
+            .clone())
+    }
+
+    async fn compute_reminders(&self, ahead: Duration) -> Result<Vec<Reminder>> {
+        let settings = self.reminder_settings().await?;
+        let core = self.core.clone();
+        RUNTIME
+            .spawn(async move {
+                let now = Utc::now();
+                let until = now + ahead;
+                Ok(core.upcoming_reminders(&settings, now, until).await?)
+            })
+            .await?
+    }
+
+    /// The local reminders to show within the next `secs_from_now` seconds,
+    /// soonest first, for the platform to schedule at their `reminder_at`
+    pub async fn upcoming_reminders(&self, secs_from_now: u32) -> Result<Vec<NotificationItem>> {
+        let reminders = self
+            .compute_reminders(Duration::seconds(secs_from_now.into()))
+            .await?;
+        let mut items = Vec::with_capacity(reminders.len());
+        for reminder in reminders {
+            let change = ReminderChange::Schedule(reminder);
+            items.push(NotificationItem::for_reminder(self.clone(), change).await?);
+        }
+        Ok(items)
+    }
+
+    /// All reminders within the next `secs_from_now` seconds and then, whenever
+    /// tasks, events, RSVPs or the reminder settings change or another one
+    /// comes within that time, the new ones and cancellations of those that
+    /// don't apply anymore, by `reminder_id`
+    pub fn reminders_stream(&self, secs_from_now: u32) -> impl Stream<Item = NotificationItem> {
+        let me = self.clone();
+        let history = BroadcastStream::new(self.subscribe(IndexKey::AllHistory)).map(|_| ());
+        let settings = BroadcastStream::new(self.subscribe(APP_USER_SETTINGS.clone())).map(|_| ());
+        let mut changes = select(history, settings);
+        let window = Duration::seconds(secs_from_now.into());
+        let max_wait = Duration::seconds(MAX_REMINDER_CHECK_INTERVAL_SECS);
+        async_stream::stream! {
+            let mut scheduled: Vec<Reminder> = vec![];
+            loop {
+                let mut wait = max_wait;
+                // look further ahead, to know when the next one comes within the window
+                match me.compute_reminders(window + max_wait).await {
+                    Ok(upcoming) => {
+                        let now = Utc::now();
+                        let (current, later): (Vec<_>, Vec<_>) = upcoming
+                            .into_iter()
+                            .partition(|r| r.remind_at() <= now + window);
+                        if let Some(next) = later.iter().map(|r| r.remind_at() - window).min() {
+                            wait = wait.min(next - now);
+                        }
+                        // and once the next one is shown, to let go of it
+                        if let Some(next) = current.iter().map(Reminder::remind_at).filter(|at| *at > now).min() {
+                            wait = wait.min(next - now);
+                        }
+                        // those already shown aren't cancelled, just gone
+                        scheduled.retain(|r| r.remind_at() > now);
+                        for change in ReminderChange::between(&scheduled, &current) {
+                            match NotificationItem::for_reminder(me.clone(), change).await {
+                                Ok(item) => yield item,
+                                Err(error) => error!(?error, "building reminder failed"),
+                            }
+                        }
+                        scheduled = current;
+                    }
+                    Err(error) => error!(?error, "computing reminders failed"),
+                }
+                // on our runtime, as the stream may be polled outside of it
+                let timer = RUNTIME.spawn(tokio::time::sleep(
+                    wait.max(Duration::zero()).to_std().unwrap_or_default(),
+                ));
+                if let Either::Right((changed, timer)) = future::select(timer, changes.next()).await {
+                    timer.abort();
+                    if changed.is_none() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use acter_matrix::models::{FieldChange, Revision, UpdateConflict};
+use anyhow::{bail, Result};
+use matrix_sdk::room::Room;
+use matrix_sdk_base::ruma::{
+    events::{MessageLikeEventContent, MessageLikeEventType},
+    OwnedEventId,
+};
+use serde_json::Value;
+
+use super::{client::Client, RUNTIME};
+
+/// A field changed by a revision
+#[derive(Clone, Debug)]
+pub struct RevisionChange {
+    inner: FieldChange,
+}
+
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Object(o) => match o.get("body") {
+            // formatted text
+            Some(Value::String(body)) => body.clone(),
+            _ => value.to_string(),
+        },
+        _ => value.to_string(),
+    }
+}
+
+impl RevisionChange {
+    /// the name of the field as in the event content
+    pub fn field(&self) -> String {
+        self.inner.field.clone()
+    }
+
+    /// the previous value as JSON, none if it wasn’t set
+    pub fn old_json(&self) -> Option<String> {
+        self.inner.old.as_ref().map(ToString::to_string)
+    }
+
+    /// the new value as JSON, none if it was unset
+    pub fn new_json(&self) -> Option<String> {
+        self.inner.new.as_ref().map(ToString::to_string)
+    }
+
+    /// the previous value for display, using the body of texts
+    pub fn old_text(&self) -> Option<String> {
+        self.inner.old.as_ref().map(as_text)
+    }
+
+    /// the new value for display, using the body of texts
+    pub fn new_text(&self) -> Option<String> {
+        self.inner.new.as_ref().map(as_text)
+    }
+}
+
+/// The creation of an object or an update applied to it
+#[derive(Clone, Debug)]
+pub struct ObjectRevision {
+    inner: Revision,
+}
+
+impl From<Revision> for ObjectRevision {
+    fn from(inner: Revision) -> Self {
+        ObjectRevision { inner }
+    }
+}
+
+impl ObjectRevision {
+    pub fn event_id_str(&self) -> String {
+        self.inner.event_id.to_string()
+    }
+
+    pub fn sender(&self) -> String {
+        self.inner.sender.to_string()
+    }
+
+    pub fn origin_server_ts(&self) -> u64 {
+        self.inner.origin_server_ts.get().into()
+    }
+
+    /// the fields changed, for the creation all fields it was created with
+    pub fn changes(&self) -> Vec<RevisionChange> {
+        self.inner
+            .changes
+            .iter()
+            .cloned()
+            .map(|inner| RevisionChange { inner })
+            .collect()
+    }
+}
+
+/// Two updates made based on the same revision changed the same fields
+#[derive(Clone, Debug)]
+pub struct RevisionConflict {
+    inner: UpdateConflict,
+}
+
+impl From<UpdateConflict> for RevisionConflict {
+    fn from(inner: UpdateConflict) -> Self {
+        RevisionConflict { inner }
+    }
+}
+
+impl RevisionConflict {
+    /// the update that arrived later
+    pub fn event_id_str(&self) -> String {
+        self.inner.event_id.to_string()
+    }
+
+    pub fn sender(&self) -> String {
+        self.inner.sender.to_string()
+    }
+
+    /// the revision both updates were based on
+    pub fn base_revision_str(&self) -> String {
+        self.inner.base.to_string()
+    }
+
+    /// the update it conflicted with
+    pub fn other_event_id_str(&self) -> String {
+        self.inner.other_event_id.to_string()
+    }
+
+    pub fn other_sender(&self) -> String {
+        self.inner.other_sender.to_string()
+    }
+
+    /// the fields both changed
+    pub fn fields(&self) -> Vec<String> {
+        self.inner.fields.clone()
+    }
+
+    /// the update whose values were kept
+    pub fn winner_str(&self) -> String {
+        self.inner.winner.to_string()
+    }
+}
+
+/// The revisions applied since `revision`, an error if it isn’t known
+pub(crate) fn changed_since(
+    revisions: Option<&[Revision]>,
+    revision: &str,
+) -> Result<Vec<ObjectRevision>> {
+    let Some(revisions) = revisions else {
+        bail!("Unknown revision {revision}");
+    };
+    Ok(revisions
+        .iter()
+        .cloned()
+        .map(ObjectRevision::from)
+        .collect())
+}
+
+impl Client {
+    /// Send the update reverting an object to an earlier revision
+    pub(crate) async fn send_revert<C>(&self, room: Room, content: C) -> Result<OwnedEventId>
+    where
+        C: MessageLikeEventContent + Send + 'static,
+    {
+        let my_id = self.user_id()?;
+        RUNTIME
+            .spawn(async move {
+                let permitted = room
+                    .can_user_send_message(&my_id, MessageLikeEventType::RoomMessage)
+                    .await?;
+                if !permitted {
+                    bail!("No permissions to send message in this room");
+                }
+                let response = room.send(content).await?;
+                Ok(response.event_id)
+            })
+            .await?
+    }
+}
@@ -1,10 +1,11 @@
 pub use acter_matrix::events::rsvp::RsvpStatus;
 use acter_matrix::{
-    events::rsvp::RsvpBuilder,
+    events::{rsvp::RsvpBuilder, UtcDateTime},
     models::{self, ActerModel, AnyActerModel},
     referencing::{IndexKey, SectionIndex},
 };
 use anyhow::{bail, Result};
+use chrono::DateTime;
 use core::time::Duration;
 use futures::stream::StreamExt;
 use matrix_sdk::room::Room;
@@ -201,6 +202,13 @@ impl Rsvp {
     pub fn status(&self) -> String {
         self.inner.status.to_string()
     }
+
+    /// the occurrence this response is limited to, if any
+    pub fn occurrence_rfc3339(&self) -> Option<String> {
+        self.inner
+            .occurrence
+            .map(|d| d.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+    }
 }
 
 pub struct RsvpDraft {
@@ -219,6 +227,13 @@ impl RsvpDraft {
         self
     }
 
+    /// only respond for the occurrence of a recurring event originally starting at the given time
+    pub fn occurrence_from_rfc3339(&mut self, occurrence: String) -> Result<&mut Self> {
+        let dt = DateTime::parse_from_rfc3339(&occurrence)?;
+        self.inner.occurrence(UtcDateTime::from(dt));
+        Ok(self)
+    }
+
     pub async fn send(&self) -> Result<OwnedEventId> {
         let room = self.room.clone();
         let my_id = self.client.user_id()?;
@@ -318,6 +333,42 @@ impl RsvpManager {
             .await?
     }
 
+    pub async fn responded_by_me_for_occurrence(
+        &self,
+        occurrence: String,
+    ) -> Result<OptionRsvpStatus> {
+        let manager = self.inner.clone();
+        let my_id = self.client.user_id()?;
+        let occurrence = UtcDateTime::from(DateTime::parse_from_rfc3339(&occurrence)?);
+        RUNTIME
+            .spawn(async move {
+                let entries = manager.rsvp_entries_for_occurrence(occurrence).await?;
+                let status = entries.get(&my_id).map(|x| x.status.clone());
+                Ok(OptionRsvpStatus::new(status))
+            })
+            .await?
+    }
+
+    pub async fn users_at_status_for_occurrence(
+        &self,
+        status: String,
+        occurrence: String,
+    ) -> Result<Vec<OwnedUserId>> {
+        let manager = self.inner.clone();
+        let status = RsvpStatus::from_str(&status)?;
+        let occurrence = UtcDateTime::from(DateTime::parse_from_rfc3339(&occurrence)?);
+        RUNTIME
+            .spawn(async move {
+                let entries = manager.rsvp_entries_for_occurrence(occurrence).await?;
+                Ok(entries
+                    .into_iter()
+                    .filter(|(_, entry)| entry.status == status)
+                    .map(|(user_id, _)| user_id)
+                    .collect())
+            })
+            .await?
+    }
+
     pub async fn count_at_status(&self, status: String) -> Result<u32> {
         let manager = self.inner.clone();
         RUNTIME
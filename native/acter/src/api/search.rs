@@ -1,5 +1,9 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, str::FromStr};
 
+use acter_matrix::{
+    referencing::SectionIndex,
+    store::{SearchHit, SearchQuery},
+};
 use anyhow::Result;
 use matrix_sdk::{room::RoomMember, RoomMemberships};
 use matrix_sdk_base::{
@@ -10,13 +14,14 @@ use matrix_sdk_base::{
         directory::{Filter, PublicRoomJoinRule, PublicRoomsChunk, RoomNetwork, RoomTypeFilter},
         events::room::MediaSource,
         room::RoomType,
-        OwnedMxcUri, OwnedRoomAliasId, OwnedRoomId, OwnedUserId, RoomId, ServerName,
+        MilliSecondsSinceUnixEpoch, OwnedMxcUri, OwnedRoomAliasId, OwnedRoomId, OwnedUserId,
+        RoomId, ServerName, UserId,
     },
     RoomDisplayName, RoomState,
 };
 use ruma::api::client::user_directory::search_users;
 
-use super::{client::Client, profile::PublicProfile, UserProfile, RUNTIME};
+use super::{client::Client, profile::PublicProfile, spaces::Space, UserProfile, RUNTIME};
 
 use crate::{OptionBuffer, ThumbnailSize};
 
@@ -149,8 +154,146 @@ impl PublicSearchResult {
     }
 }
 
+/// A match of the local search over tasks, pins, events, boosts,
+/// stories, comments and attachments
+#[derive(Clone, Debug)]
+pub struct LocalSearchResult {
+    inner: SearchHit,
+}
+
+impl LocalSearchResult {
+    pub fn event_id_str(&self) -> String {
+        self.inner.event_id.to_string()
+    }
+
+    pub fn room_id_str(&self) -> String {
+        self.inner.room_id.to_string()
+    }
+
+    pub fn sender_str(&self) -> String {
+        self.inner.sender.to_string()
+    }
+
+    pub fn origin_server_ts(&self) -> u64 {
+        self.inner.origin_server_ts.get().into()
+    }
+
+    /// the event type of the matching object
+    pub fn model_type(&self) -> String {
+        self.inner.model_type.clone()
+    }
+
+    /// the section this belongs to, if any
+    pub fn section(&self) -> Option<String> {
+        self.inner.section.as_ref().map(ToString::to_string)
+    }
+
+    /// for comments and attachments: the object they are on
+    pub fn parent_id_str(&self) -> Option<String> {
+        self.inner.parent.as_ref().map(ToString::to_string)
+    }
+
+    /// the text that matched best, shortened
+    pub fn snippet(&self) -> String {
+        self.inner.snippet.clone()
+    }
+
+    /// relevance in hundredths, only meaningful relative to other results
+    pub fn score(&self) -> u32 {
+        (self.inner.score * 100.0).round() as u32
+    }
+}
+
+/// Search the locally stored objects, works offline and in encrypted rooms
+#[derive(Clone, Debug)]
+pub struct LocalSearchBuilder {
+    client: Client,
+    query: SearchQuery,
+}
+
+impl LocalSearchBuilder {
+    pub fn text(&mut self, text: String) -> &mut Self {
+        self.query.text = text;
+        self
+    }
+
+    /// limit to the given section, e.g. `tasks`, `pins`, `calendar`,
+    /// `boosts` or `stories`. Can be called multiple times.
+    pub fn section(&mut self, section: String) -> Result<&mut Self> {
+        self.query.sections.push(SectionIndex::from_str(&section)?);
+        Ok(self)
+    }
+
+    /// limit to the given space or chat. Can be called multiple times.
+    pub fn room(&mut self, room_id: String) -> Result<&mut Self> {
+        self.query.room_ids.push(RoomId::parse(room_id)?);
+        Ok(self)
+    }
+
+    /// limit to objects sent by the user. Can be called multiple times.
+    pub fn sender(&mut self, user_id: String) -> Result<&mut Self> {
+        self.query.senders.push(UserId::parse(user_id)?);
+        Ok(self)
+    }
+
+    /// only objects created at or after this timestamp in milliseconds
+    pub fn since(&mut self, timestamp: u64) -> Result<&mut Self> {
+        self.query.since = Some(millis(timestamp)?);
+        Ok(self)
+    }
+
+    /// only objects created at or before this timestamp in milliseconds
+    pub fn until(&mut self, timestamp: u64) -> Result<&mut Self> {
+        self.query.until = Some(millis(timestamp)?);
+        Ok(self)
+    }
+
+    pub fn limit(&mut self, limit: u32) -> &mut Self {
+        self.query.limit = Some(limit as usize);
+        self
+    }
+
+    pub async fn search(&self) -> Result<Vec<LocalSearchResult>> {
+        let store = self.client.store().clone();
+        let query = self.query.clone();
+        RUNTIME
+            .spawn(async move {
+                Ok(store
+                    .search(&query)?
+                    .into_iter()
+                    .map(|inner| LocalSearchResult { inner })
+                    .collect())
+            })
+            .await?
+    }
+}
+
+fn millis(timestamp: u64) -> Result<MilliSecondsSinceUnixEpoch> {
+    Ok(MilliSecondsSinceUnixEpoch(timestamp.try_into()?))
+}
+
+impl Space {
+    /// search the locally stored objects of this space
+    pub fn local_search(&self, text: String) -> LocalSearchBuilder {
+        let mut search = self.client.local_search(text);
+        search.query.room_ids.push(self.room_id());
+        search
+    }
+}
+
 // public API
 impl Client {
+    /// search the locally stored objects of all spaces
+    pub fn local_search(&self, text: String) -> LocalSearchBuilder {
+        LocalSearchBuilder {
+            client: self.clone(),
+            query: SearchQuery {
+                text,
+                ..Default::default()
+            },
+        }
+    }
+
     pub async fn search_public_room(
         &self,
         search_term: Option<String>,
@@ -4,7 +4,8 @@ mod user;
 pub use space::{
     ActerAppSettings, ActerAppSettingsBuilder, ActerAppSettingsContent, EventsSettings,
     NewsSettings, PinsSettings, RoomPowerLevels, SimpleOnOffSetting, SimpleOnOffSettingBuilder,
-    SimpleSettingWithTurnOff, SimpleSettingWithTurnOffBuilder, StoriesSettings, TasksSettings,
+    SimpleSettingWithTurnOff, SimpleSettingWithTurnOffBuilder, StoriesSettings,
+    StoriesSettingsBuilder, TasksSettings,
 };
 
 pub use user::{ActerUserAppSettings, ActerUserAppSettingsBuilder};
@@ -1,7 +1,7 @@
 pub use acter_matrix::events::settings::{
     ActerAppSettingsContent, EventsSettings, NewsSettings, PinsSettings, SimpleOnOffSetting,
     SimpleOnOffSettingBuilder, SimpleSettingWithTurnOff, SimpleSettingWithTurnOffBuilder,
-    StoriesSettings, TasksSettings,
+    StoriesSettings, StoriesSettingsBuilder, TasksSettings,
 };
 use acter_matrix::events::{
     attachments::AttachmentEventContent,
@@ -223,6 +223,9 @@ impl Room {
             "state_default" => {
                 current_power_levels.state_default = Int::from(power_level);
             }
+            "users_default" => {
+                current_power_levels.users_default = Int::from(power_level);
+            }
             _ => {
                 bail!("Power level {name} unknown");
             }
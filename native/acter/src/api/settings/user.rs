@@ -3,9 +3,10 @@ pub use acter_matrix::events::settings::{
 };
 use anyhow::Result;
 use matrix_sdk::Account;
+use matrix_sdk_base::ruma::OwnedServerName;
 use std::ops::Deref;
 
-use crate::RUNTIME;
+use crate::{api::api::FfiBuffer, RUNTIME};
 
 #[derive(Clone)]
 pub struct ActerUserAppSettings {
@@ -47,6 +48,14 @@ impl ActerUserAppSettings {
         self.inner.notifications.auto_subscribe_on_activity
     }
 
+    pub fn task_due_reminders(&self) -> FfiBuffer<u32> {
+        FfiBuffer::new(self.inner.reminders.task_due_offsets())
+    }
+
+    pub fn event_start_reminders(&self) -> FfiBuffer<u32> {
+        FfiBuffer::new(self.inner.reminders.event_start_offsets())
+    }
+
     pub fn update_builder(&self) -> ActerUserAppSettingsBuilder {
         ActerUserAppSettingsBuilder {
             account: self.account.clone(),
@@ -70,6 +79,39 @@ impl ActerUserAppSettingsBuilder {
         Ok(self)
     }
 
+    pub fn add_task_due_reminder(&mut self, minutes: u32) -> &mut Self {
+        self.inner.add_task_due_reminder(minutes);
+        self
+    }
+
+    pub fn remove_task_due_reminder(&mut self, minutes: u32) -> &mut Self {
+        self.inner.remove_task_due_reminder(minutes);
+        self
+    }
+
+    pub fn add_event_start_reminder(&mut self, minutes: u32) -> &mut Self {
+        self.inner.add_event_start_reminder(minutes);
+        self
+    }
+
+    pub fn remove_event_start_reminder(&mut self, minutes: u32) -> &mut Self {
+        self.inner.remove_event_start_reminder(minutes);
+        self
+    }
+
+    pub fn reset_reminders(&mut self) -> &mut Self {
+        self.inner.reset_reminders();
+        self
+    }
+
+    pub(crate) fn super_invite_server(
+        &mut self,
+        server_name: Option<OwnedServerName>,
+    ) -> &mut Self {
+        self.inner.super_invite_server(server_name);
+        self
+    }
+
     pub async fn send(&self) -> Result<bool> {
         let account = self.account.clone();
         let update = self.inner.build()?;
@@ -6,8 +6,12 @@ pub use acter_matrix::spaces::{
     SpaceRelations as CoreSpaceRelations,
 };
 use acter_matrix::{
-    error::Error, events::AnyActerEvent, models::AnyActerModel,
-    statics::default_acter_space_states, store::Store, templates::Engine,
+    error::Error,
+    events::AnyActerEvent,
+    models::AnyActerModel,
+    statics::default_acter_space_states,
+    store::{room_history_key, Store},
+    templates::Engine,
 };
 use anyhow::{bail, Context, Result};
 use futures::stream::StreamExt;
@@ -73,7 +77,7 @@ impl HistoryState {
         HistoryState { seen: last_seen }
     }
     pub(crate) fn storage_key(room_id: &RoomId) -> String {
-        format!("{room_id}::history")
+        room_history_key(room_id)
     }
 
     pub(crate) async fn load(store: &Store, room_id: &RoomId) -> Result<HistoryState> {
@@ -208,6 +212,16 @@ impl Space {
             .await?
     }
 
+    /// A template recreating the task lists, tasks, pins, calendar events,
+    /// news and stories of this space in another space
+    pub async fn export_template(&self) -> Result<String> {
+        let core = self.client.core.clone();
+        let room_id = self.room.room_id().to_owned();
+        RUNTIME
+            .spawn(async move { Ok(core.export_template(&room_id).await?) })
+            .await?
+    }
+
     pub fn subscribe_stream(&self) -> impl Stream<Item = bool> {
         BroadcastStream::new(self.subscribe()).map(|_| true)
     }
@@ -2,12 +2,13 @@ use acter_matrix::{
     events::{
         stories::{self, StoryBuilder, StoryContent, StorySlideBuilder},
         Colorize, ColorizeBuilder, ObjRef as CoreObjRef, ObjRefBuilder,
-        RefDetails as CoreRefDetails, RefPreview,
+        RefDetails as CoreRefDetails, RefPreview, UtcDateTime,
     },
-    models::{self, can_redact, ActerModel, AnyActerModel, ReactionManager},
+    models::{self, can_redact, story_expiry, ActerModel, AnyActerModel, ReactionManager},
     referencing::{IndexKey, SectionIndex},
 };
 use anyhow::{bail, Context, Result};
+use chrono::DateTime;
 use futures::stream::StreamExt;
 use matrix_sdk::room::Room;
 use matrix_sdk_base::{
@@ -65,6 +66,47 @@ impl Client {
             .map(|(inner, room)| Story::new(self.clone(), room, inner))
             .collect())
     }
+
+    /// expired stories the user sent or may moderate, newest first
+    pub async fn archived_stories(&self, count: u32) -> Result<Vec<Story>> {
+        let entries = self
+            .models_of_list_with_room(IndexKey::Section(SectionIndex::StoriesArchive))
+            .await?;
+        visible_archived_stories(self.clone(), entries, count).await
+    }
+}
+
+/// Archived stories are only shown to their author and to those who could
+/// redact them anyways
+async fn visible_archived_stories(
+    client: Client,
+    entries: impl Iterator<Item = (models::Story, Room)> + Send + 'static,
+    count: u32,
+) -> Result<Vec<Story>> {
+    let my_id = client.user_id()?;
+    RUNTIME
+        .spawn(async move {
+            let mut moderated: HashMap<OwnedRoomId, bool> = HashMap::new();
+            let mut stories = vec![];
+            for (inner, room) in entries {
+                if stories.len() >= count as usize {
+                    break;
+                }
+                let visible = if *inner.sender() == *my_id {
+                    true
+                } else {
+                    match moderated.entry(room.room_id().to_owned()) {
+                        Entry::Occupied(o) => *o.get(),
+                        Entry::Vacant(v) => *v.insert(room.can_user_redact_other(&my_id).await?),
+                    }
+                };
+                if visible {
+                    stories.push(Story::new(client.clone(), room, inner));
+                }
+            }
+            Ok(stories)
+        })
+        .await?
 }
 
 impl Space {
@@ -89,6 +131,20 @@ impl Space {
             .map(|(inner, room)| Story::new(self.client.clone(), room, inner))
             .collect())
     }
+
+    /// expired stories of this space the user sent or may moderate, newest first
+    pub async fn archived_stories(&self, count: u32) -> Result<Vec<Story>> {
+        let room = self.room.clone();
+        let room_id = room.room_id().to_owned();
+        let entries = self
+            .client
+            .models_of_list_with_room_under_check(
+                IndexKey::RoomSection(room_id, SectionIndex::StoriesArchive),
+                move |_r| Ok(room.clone()),
+            )
+            .await?;
+        visible_archived_stories(self.client.clone(), entries, count).await
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -386,6 +442,7 @@ impl Story {
             room: self.room.clone(),
             content: self.content.updater(),
             slides: None,
+            expires_at: None,
         })
     }
 
@@ -421,6 +478,24 @@ impl Story {
         self.content.meta.origin_server_ts.get().into()
     }
 
+    /// milliseconds since epoch when this story is archived, if ever
+    pub async fn expires_at(&self) -> Result<Option<u64>> {
+        let room = self.room.clone();
+        let content = self.content.clone();
+        RUNTIME
+            .spawn(async move {
+                let default_expiry = story_expiry(&room).await?;
+                Ok(content
+                    .expires_at(default_expiry)
+                    .map(|at| at.timestamp_millis() as u64))
+            })
+            .await?
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.content.is_archived()
+    }
+
     pub async fn ref_details(&self) -> Result<RefDetails> {
         let room = self.room.clone();
         let client = self.client.deref().clone();
@@ -483,6 +558,18 @@ impl StoryDraft {
         self
     }
 
+    /// archive at this time rather than after the expiry of the space
+    pub fn expires_at_from_rfc3339(&mut self, expires_at: String) -> Result<&mut Self> {
+        let dt = DateTime::parse_from_rfc3339(&expires_at)?;
+        self.content.expires_at(Some(dt.into()));
+        Ok(self)
+    }
+
+    pub fn unset_expires_at(&mut self) -> &mut Self {
+        self.content.expires_at(None);
+        self
+    }
+
     pub async fn send(&mut self) -> Result<OwnedEventId> {
         trace!("starting send");
         let client = self.client.clone();
@@ -522,6 +609,7 @@ pub struct StoryUpdateBuilder {
     room: Room,
     content: stories::StoryUpdateBuilder,
     slides: Option<Vec<StorySlideDraft>>,
+    expires_at: Option<Option<UtcDateTime>>,
 }
 
 impl StoryUpdateBuilder {
@@ -557,12 +645,34 @@ impl StoryUpdateBuilder {
         self
     }
 
+    /// archive at this time rather than after the expiry of the space
+    pub fn expires_at_from_rfc3339(&mut self, expires_at: String) -> Result<&mut Self> {
+        let dt = DateTime::parse_from_rfc3339(&expires_at)?;
+        self.expires_at = Some(Some(dt.into()));
+        Ok(self)
+    }
+
+    /// archive after the expiry of the space again
+    pub fn unset_expires_at(&mut self) -> &mut Self {
+        self.expires_at = Some(None);
+        self
+    }
+
+    pub fn unset_expires_at_update(&mut self) -> &mut Self {
+        self.expires_at = None;
+        self
+    }
+
     pub async fn send(&self) -> Result<OwnedEventId> {
         let client = self.client.clone();
         let room = self.room.clone();
         let my_id = self.client.user_id()?;
         let mut inner = self.content.clone();
-        let drafts = self.slides.clone().context("No slides to send")?;
+        let drafts = self.slides.clone();
+        if drafts.is_none() && self.expires_at.is_none() {
+            bail!("Nothing to update");
+        }
+        inner.expires_at(self.expires_at);
 
         RUNTIME
             .spawn(async move {
@@ -572,12 +682,14 @@ impl StoryUpdateBuilder {
                 if !permitted {
                     bail!("No permissions to send message in this room");
                 }
-                let mut slides = vec![];
-                for draft in drafts {
-                    let slide = draft.build(&client, &room).await?;
-                    slides.push(slide);
+                if let Some(drafts) = drafts {
+                    let mut slides = vec![];
+                    for draft in drafts {
+                        let slide = draft.build(&client, &room).await?;
+                        slides.push(slide);
+                    }
+                    inner.slides(Some(slides));
                 }
-                inner.slides(Some(slides));
                 let content = inner.build()?;
                 let response = room.send(content).await?;
                 Ok(response.event_id)
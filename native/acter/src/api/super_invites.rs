@@ -1,11 +1,14 @@
 // internal API
 
 use acter_matrix::{
+    deep_linking,
     events::{RefDetails as CoreRefDetails, RefPreview},
     super_invites::{api, CreateToken, Token, TokenInfo, UpdateToken},
 };
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use matrix_sdk_base::ruma::{OwnedServerName, ServerName};
 use std::ops::Deref;
+use tracing::trace;
 
 use super::deep_linking::RefDetails;
 
@@ -52,11 +55,16 @@ impl SuperInviteInfo {
 pub struct SuperInviteToken {
     client: Client,
     token: Token,
+    server_name: Option<OwnedServerName>,
 }
 
 impl SuperInviteToken {
-    fn new(client: Client, token: Token) -> SuperInviteToken {
-        SuperInviteToken { client, token }
+    fn new(client: Client, token: Token, server_name: Option<OwnedServerName>) -> SuperInviteToken {
+        SuperInviteToken {
+            client,
+            token,
+            server_name,
+        }
     }
 
     pub fn token(&self) -> String {
@@ -94,6 +102,8 @@ impl SuperInviteToken {
                 create_dm: self.create_dm(),
                 accepted_count: self.accepted_count(),
                 rooms: self.rooms(),
+                server_name: self.server_name.clone(),
+                inviter: None,
             },
         )
     }
@@ -157,6 +167,7 @@ impl SuperInvitesTokenUpdateBuilder {
 impl SuperInvites {
     pub async fn tokens(&self) -> Result<Vec<SuperInviteToken>> {
         let client = self.client.clone();
+        let server_name = Some(self.client.super_invite_server().await?);
         RUNTIME
             .spawn(async move {
                 let req = api::list::Request::new();
@@ -164,7 +175,7 @@ impl SuperInvites {
                 let tokens = resp
                     .tokens
                     .into_iter()
-                    .map(|token| SuperInviteToken::new(client.clone(), token))
+                    .map(|token| SuperInviteToken::new(client.clone(), token, server_name.clone()))
                     .collect::<Vec<SuperInviteToken>>();
                 Ok(tokens)
             })
@@ -186,6 +197,35 @@ impl SuperInvites {
             .await?
     }
 
+    /// The token of a super invite link. Tokens are always redeemed on our
+    /// homeserver: the server the link names is just what the inviter
+    /// configured to show, not necessarily theirs or ours.
+    fn token_from_link(&self, link: &str) -> Result<String> {
+        let CoreRefDetails::SuperInviteToken {
+            token, server_name, ..
+        } = deep_linking::parse_link(link)?
+        else {
+            bail!("Not a super invite link");
+        };
+        trace!(
+            ?server_name,
+            "redeeming super invite link on our homeserver"
+        );
+        Ok(token)
+    }
+
+    /// Redeem the token of a super invite link, see `redeem`
+    pub async fn redeem_link(&self, link: String) -> Result<Vec<String>> {
+        let token = self.token_from_link(&link)?;
+        self.redeem(token).await
+    }
+
+    /// Info about the token of a super invite link, see `info`
+    pub async fn info_for_link(&self, link: String) -> Result<SuperInviteInfo> {
+        let token = self.token_from_link(&link)?;
+        self.info(token).await
+    }
+
     pub async fn info(&self, token: String) -> Result<SuperInviteInfo> {
         let client = self.client.clone();
         RUNTIME
@@ -202,6 +242,7 @@ impl SuperInvites {
         builder: Box<SuperInvitesTokenUpdateBuilder>,
     ) -> Result<SuperInviteToken> {
         let client = self.client.clone();
+        let server_name = Some(self.client.super_invite_server().await?);
         RUNTIME
             .spawn(async move {
                 let token = if builder.has_token() {
@@ -218,7 +259,7 @@ impl SuperInvites {
                     let resp = client.deref().send(req).await?;
                     resp.token
                 };
-                Ok(SuperInviteToken { client, token })
+                Ok(SuperInviteToken::new(client, token, server_name))
             })
             .await?
     }
@@ -241,4 +282,30 @@ impl Client {
             client: self.clone(),
         }
     }
+
+    /// The server super invite links are for: the one configured in the app
+    /// settings or that of our user id
+    pub(crate) async fn super_invite_server(&self) -> Result<OwnedServerName> {
+        let settings = self.account()?.acter_app_settings().await?;
+        if let Some(server_name) = settings.super_invite_server.clone() {
+            return Ok(server_name);
+        }
+        Ok(self.user_id()?.server_name().to_owned())
+    }
+
+    pub async fn super_invite_server_name(&self) -> Result<String> {
+        Ok(self.super_invite_server().await?.to_string())
+    }
+
+    /// Set the server named in super invite links, e.g. when the server name
+    /// people sign up with differs from ours. `None` to go back to ours.
+    /// Kept in the app settings of the user, so it applies once synced.
+    pub async fn set_super_invite_server_name(&self, server_name: Option<String>) -> Result<bool> {
+        let server_name = server_name
+            .map(|s| ServerName::parse(s.as_str()))
+            .transpose()?;
+        let mut builder = self.account()?.acter_app_settings().await?.update_builder();
+        builder.super_invite_server(server_name);
+        builder.send().await
+    }
 }
@@ -1,13 +1,14 @@
 use acter_matrix::{
     events::{
-        tasks::{self, Priority, TaskBuilder, TaskListBuilder},
-        Display, RefDetails as CoreRefDetails, RefPreview, UtcDateTime,
+        tasks::{self, Priority, RecurrenceAnchor, TaskBuilder, TaskListBuilder, TaskRecurrence},
+        Display, Frequency, RecurrenceRule, RecurrenceRuleBuilder, RefDetails as CoreRefDetails,
+        RefPreview, UtcDateTime,
     },
     models::{self, can_redact, ActerModel, AnyActerModel, TaskStats},
-    referencing::{IndexKey, SectionIndex, SpecialListsIndex},
+    referencing::{IndexKey, ObjectListIndex, SectionIndex, SpecialListsIndex},
 };
 use anyhow::{bail, Context, Result};
-use chrono::DateTime;
+use chrono::{DateTime, Utc};
 use futures::stream::StreamExt;
 use matrix_sdk::room::Room;
 use matrix_sdk_base::{
@@ -28,7 +29,13 @@ use tracing::warn;
 
 use crate::MsgContent;
 
-use super::{client::Client, deep_linking::RefDetails, spaces::Space, RUNTIME};
+use super::{
+    client::Client,
+    deep_linking::RefDetails,
+    revisions::{changed_since, ObjectRevision, RevisionConflict},
+    spaces::Space,
+    RUNTIME,
+};
 
 impl Client {
     pub async fn task_list(&self, key: String, timeout: Option<u8>) -> Result<TaskList> {
@@ -212,6 +219,12 @@ impl TaskListDraft {
         self
     }
 
+    /// queue to be sent once online, shown as pending right away
+    pub async fn queue(&self) -> Result<OwnedEventId> {
+        let content = self.content.build()?;
+        self.client.queue_event(self.room.clone(), content).await
+    }
+
     pub async fn send(&self) -> Result<OwnedEventId> {
         let room = self.room.clone();
         let my_id = self.client.user_id()?;
@@ -252,6 +265,23 @@ impl TaskList {
         self.content.name.clone()
     }
 
+    /// The creation and all updates applied since, oldest first
+    pub fn revisions(&self) -> Result<Vec<ObjectRevision>> {
+        Ok(self
+            .content
+            .revisions()?
+            .into_iter()
+            .map(ObjectRevision::from)
+            .collect())
+    }
+
+    /// Revert the task list to how it was right after the given revision
+    pub async fn revert_to(&self, revision_id: String) -> Result<OwnedEventId> {
+        let revision = EventId::parse(revision_id)?;
+        let content = self.content.revert_update(&revision)?;
+        self.client.send_revert(self.room.clone(), content).await
+    }
+
     pub fn description(&self) -> Option<MsgContent> {
         self.content.description.as_ref().map(MsgContent::from)
     }
@@ -494,6 +524,44 @@ impl Task {
         self.content.title().clone()
     }
 
+    /// The creation and all updates applied since, oldest first
+    pub fn revisions(&self) -> Result<Vec<ObjectRevision>> {
+        Ok(self
+            .content
+            .revisions()?
+            .into_iter()
+            .map(ObjectRevision::from)
+            .collect())
+    }
+
+    /// Revert the task to how it was right after the given revision
+    pub async fn revert_to(&self, revision_id: String) -> Result<OwnedEventId> {
+        let revision = EventId::parse(revision_id)?;
+        let content = self.content.revert_update(&revision)?;
+        self.client.send_revert(self.room.clone(), content).await
+    }
+
+    /// The revision this task is at, updates made from here are based on it
+    pub fn revision_str(&self) -> String {
+        self.content.revision().to_string()
+    }
+
+    /// The updates applied since the given revision, e.g. while editing
+    pub fn changed_since(&self, revision_id: String) -> Result<Vec<ObjectRevision>> {
+        let revision = EventId::parse(&revision_id)?;
+        changed_since(self.content.changed_since(&revision), &revision_id)
+    }
+
+    /// Concurrent updates that changed the same fields and which one was kept
+    pub fn conflicts(&self) -> Vec<RevisionConflict> {
+        self.content
+            .conflicts()
+            .iter()
+            .cloned()
+            .map(RevisionConflict::from)
+            .collect()
+    }
+
     pub fn event_id_str(&self) -> String {
         self.content.event_id().to_string()
     }
@@ -557,6 +625,54 @@ impl Task {
         self.content.progress_percent
     }
 
+    /// the progress, derived from the subtasks if there are any
+    pub fn percent(&self) -> Option<u8> {
+        self.content.percent()
+    }
+
+    pub fn parent_task_id_str(&self) -> Option<String> {
+        self.content.parent_task.as_ref().map(ToString::to_string)
+    }
+
+    pub fn blocked_by_str(&self) -> Vec<String> {
+        self.content
+            .blocked_by
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    pub fn is_recurring(&self) -> bool {
+        self.content.is_recurring()
+    }
+
+    pub fn recurrence_rrule(&self) -> Option<String> {
+        self.content.recurrence.as_ref().map(|r| r.rule.to_string())
+    }
+
+    /// `due_date` or `completion`
+    pub fn recurrence_relative_to(&self) -> Option<String> {
+        self.content
+            .recurrence
+            .as_ref()
+            .map(|r| r.relative_to.to_string())
+    }
+
+    pub fn previous_instance_id_str(&self) -> Option<String> {
+        self.content
+            .previous_instance
+            .as_ref()
+            .map(ToString::to_string)
+    }
+
+    pub fn subtasks_count(&self) -> u32 {
+        self.content.subtasks_stats().total_count()
+    }
+
+    pub fn subtasks_done_count(&self) -> u32 {
+        self.content.subtasks_stats().done_count()
+    }
+
     pub fn keywords(&self) -> Vec<String> {
         // don’t use cloned().
         // create string vector to deallocate string item using toDartString().
@@ -709,6 +825,54 @@ impl Task {
         let event_id = self.content.event_id().to_owned();
         crate::ObjectInvitationsManager::new(client, room, event_id).await
     }
+
+    pub async fn subtasks(&self) -> Result<Vec<Task>> {
+        let subtasks_key = self.content.subtasks_key();
+        let client = self.client.clone();
+        let room = self.room.clone();
+        RUNTIME
+            .spawn(async move {
+                let res = client
+                    .store()
+                    .get_list(&subtasks_key)
+                    .await?
+                    .filter_map(|e| match e {
+                        AnyActerModel::Task(content) => Some(Task {
+                            client: client.clone(),
+                            room: room.clone(),
+                            content,
+                        }),
+                        _ => None,
+                    })
+                    .collect();
+                Ok(res)
+            })
+            .await?
+    }
+
+    pub fn subscribe_subtasks_stream(&self) -> impl Stream<Item = bool> {
+        BroadcastStream::new(self.client.subscribe(self.content.subtasks_key())).map(|_| true)
+    }
+
+    /// whether any of the tasks this one is blocked by isn’t done yet
+    pub async fn is_blocked(&self) -> Result<bool> {
+        let blocked_by = self.content.blocked_by();
+        let client = self.client.clone();
+        RUNTIME
+            .spawn(async move {
+                for task_id in blocked_by {
+                    match client.store().get(&task_id).await {
+                        Ok(AnyActerModel::Task(task)) if task.is_done() => {}
+                        Ok(AnyActerModel::Task(_)) => return Ok(true),
+                        _ => {
+                            warn!(?task_id, "blocking task not found");
+                        }
+                    }
+                }
+                Ok(false)
+            })
+            .await?
+    }
 }
 
 #[derive(Clone)]
@@ -840,6 +1004,62 @@ impl TaskDraft {
         self
     }
 
+    pub fn parent_task(&mut self, task_id: String) -> Result<&mut Self> {
+        self.content.parent_task(Some(EventId::parse(task_id)?));
+        Ok(self)
+    }
+
+    pub fn unset_parent_task(&mut self) -> &mut Self {
+        self.content.parent_task(None);
+        self
+    }
+
+    #[allow(clippy::ptr_arg)]
+    pub fn blocked_by(&mut self, task_ids: &mut Vec<String>) -> Result<&mut Self> {
+        let blocked_by = task_ids
+            .iter()
+            .map(EventId::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.content.blocked_by(blocked_by);
+        Ok(self)
+    }
+
+    pub fn unset_blocked_by(&mut self) -> &mut Self {
+        self.content.blocked_by(vec![]);
+        self
+    }
+
+    /// repeat `daily`, `weekly`, `monthly` or `yearly` every `interval` periods,
+    /// counted from the `due_date` or the `completion`
+    pub fn recurrence(
+        &mut self,
+        frequency: String,
+        interval: u32,
+        relative_to: String,
+    ) -> Result<&mut Self> {
+        self.content
+            .recurrence(task_recurrence(frequency, interval, relative_to)?);
+        Ok(self)
+    }
+
+    /// repeat according to a custom RFC 5545 RRULE
+    pub fn recurrence_rrule(&mut self, rrule: String, relative_to: String) -> Result<&mut Self> {
+        self.content
+            .recurrence(task_recurrence_from_rrule(rrule, relative_to)?);
+        Ok(self)
+    }
+
+    pub fn unset_recurrence(&mut self) -> &mut Self {
+        self.content.recurrence(None);
+        self
+    }
+
+    /// queue to be sent once online, shown as pending right away
+    pub async fn queue(&self) -> Result<OwnedEventId> {
+        let content = self.content.build()?;
+        self.client.queue_event(self.room.clone(), content).await
+    }
+
     pub async fn send(&self) -> Result<OwnedEventId> {
         let room = self.room.clone();
         let my_id = self.client.user_id()?;
@@ -868,6 +1088,14 @@ pub struct TaskUpdateBuilder {
 }
 
 impl TaskUpdateBuilder {
+    /// the revision this update was made from, defaults to the current one.
+    /// Fields also changed since by others are resolved on arrival.
+    pub fn base_revision(&mut self, revision_id: String) -> Result<&mut Self> {
+        self.content
+            .base_revision(Some(EventId::parse(revision_id)?));
+        Ok(self)
+    }
+
     pub fn title(&mut self, title: String) -> &mut Self {
         self.content.title(Some(title));
         self
@@ -1048,10 +1276,98 @@ impl TaskUpdateBuilder {
         self
     }
 
+    pub fn parent_task(&mut self, task_id: String) -> Result<&mut Self> {
+        self.content
+            .parent_task(Some(Some(EventId::parse(task_id)?)));
+        Ok(self)
+    }
+
+    pub fn unset_parent_task(&mut self) -> &mut Self {
+        self.content.parent_task(Some(None));
+        self
+    }
+
+    pub fn unset_parent_task_update(&mut self) -> &mut Self {
+        self.content.parent_task(None);
+        self
+    }
+
+    #[allow(clippy::ptr_arg)]
+    pub fn blocked_by(&mut self, task_ids: &mut Vec<String>) -> Result<&mut Self> {
+        let blocked_by = task_ids
+            .iter()
+            .map(EventId::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.content.blocked_by(Some(blocked_by));
+        Ok(self)
+    }
+
+    pub fn unset_blocked_by(&mut self) -> &mut Self {
+        self.content.blocked_by(Some(vec![]));
+        self
+    }
+
+    pub fn unset_blocked_by_update(&mut self) -> &mut Self {
+        self.content.blocked_by(None);
+        self
+    }
+
+    /// repeat `daily`, `weekly`, `monthly` or `yearly` every `interval` periods,
+    /// counted from the `due_date` or the `completion`
+    pub fn recurrence(
+        &mut self,
+        frequency: String,
+        interval: u32,
+        relative_to: String,
+    ) -> Result<&mut Self> {
+        self.content.recurrence(Some(Some(task_recurrence(
+            frequency,
+            interval,
+            relative_to,
+        )?)));
+        Ok(self)
+    }
+
+    /// repeat according to a custom RFC 5545 RRULE
+    pub fn recurrence_rrule(&mut self, rrule: String, relative_to: String) -> Result<&mut Self> {
+        self.content
+            .recurrence(Some(Some(task_recurrence_from_rrule(rrule, relative_to)?)));
+        Ok(self)
+    }
+
+    pub fn unset_recurrence(&mut self) -> &mut Self {
+        self.content.recurrence(Some(None));
+        self
+    }
+
+    pub fn unset_recurrence_update(&mut self) -> &mut Self {
+        self.content.recurrence(None);
+        self
+    }
+
+    /// queue to be sent once online, shown as pending right away
+    pub async fn queue(&self) -> Result<OwnedEventId> {
+        let room = self.room.clone();
+        let content = self.content.build()?;
+        let client = self.client.clone();
+
+        RUNTIME
+            .spawn(async move {
+                let completed = check_task_update(&client, &content).await?;
+                let local_id = client.queue_event(room.clone(), content).await?;
+                if let Some(task) = completed {
+                    respawn_after_update(&client, &room, &task, true).await;
+                }
+                Ok(local_id)
+            })
+            .await?
+    }
+
     pub async fn send(&self) -> Result<OwnedEventId> {
         let room = self.room.clone();
         let my_id = self.client.user_id()?;
         let content = self.content.build()?;
+        let client = self.client.clone();
 
         RUNTIME
             .spawn(async move {
@@ -1061,13 +1377,115 @@ impl TaskUpdateBuilder {
                 if !permitted {
                     bail!("No permissions to send message in this room");
                 }
+                let completed = check_task_update(&client, &content).await?;
                 let response = room.send(content).await?;
+                if let Some(task) = completed {
+                    respawn_after_update(&client, &room, &task, false).await;
+                }
                 Ok(response.event_id)
             })
             .await?
     }
 }
 
+/// Make sure the update doesn’t create cyclic relations. Returns the task if
+/// the update completes it and it is recurring, so it needs respawning.
+async fn check_task_update(
+    client: &Client,
+    content: &tasks::TaskUpdateEventContent,
+) -> Result<Option<models::Task>> {
+    let task_id = content.task.event_id.clone();
+    let AnyActerModel::Task(task) = client.store().get(&task_id).await? else {
+        bail!("{task_id} is not a task");
+    };
+    if content.changes_relations() {
+        let parent_task = match &content.parent_task {
+            Some(parent_task) => parent_task.clone(),
+            None => task.parent_task(),
+        };
+        let blocked_by = match &content.blocked_by {
+            Some(blocked_by) => blocked_by.clone(),
+            None => task.blocked_by(),
+        };
+        models::Task::ensure_no_cycles(
+            client.store(),
+            &task_id,
+            parent_task.as_deref(),
+            &blocked_by,
+        )
+        .await?;
+    }
+    // with subtasks still open the task isn't done, whatever its own progress
+    let completes = !task.is_done()
+        && matches!(content.progress_percent, Some(Some(percent)) if percent >= 100)
+        && task
+            .subtasks_stats()
+            .percent()
+            .map_or(true, |percent| percent >= 100);
+    Ok((completes && task.is_recurring()).then_some(task))
+}
+
+/// Post or queue the next instance of `task`, once its completing update
+/// went out. That already happened, so failing here is only logged.
+async fn respawn_after_update(client: &Client, room: &Room, task: &models::Task, queue: bool) {
+    if let Err(error) = respawn_recurring_task(client, room, task, queue).await {
+        let task_id = task.event_id();
+        warn!(
+            ?error,
+            ?task_id,
+            "posting next instance of recurring task failed"
+        );
+    }
+}
+
+fn task_recurrence(
+    frequency: String,
+    interval: u32,
+    relative_to: String,
+) -> Result<TaskRecurrence> {
+    let rule = RecurrenceRuleBuilder::default()
+        .freq(frequency.to_uppercase().parse::<Frequency>()?)
+        .interval(interval.max(1))
+        .build()?;
+    Ok(TaskRecurrence::new(rule, relative_to.parse()?))
+}
+
+fn task_recurrence_from_rrule(rrule: String, relative_to: String) -> Result<TaskRecurrence> {
+    let rule = rrule.parse::<RecurrenceRule>()?;
+    Ok(TaskRecurrence::new(
+        rule,
+        relative_to.parse::<RecurrenceAnchor>()?,
+    ))
+}
+
+/// Post, or queue, the next instance of the recurring `task` that has just been done
+/// by us, unless that has happened before
+async fn respawn_recurring_task(
+    client: &Client,
+    room: &Room,
+    task: &models::Task,
+    queue: bool,
+) -> Result<()> {
+    let task_id = task.event_id().to_owned();
+    let tasks_key =
+        IndexKey::ObjectList(task.task_list_id.event_id.clone(), ObjectListIndex::Tasks);
+    let already_respawned = client.store().get_list(&tasks_key).await?.any(
+        |m| matches!(m, AnyActerModel::Task(t) if t.previous_instance.as_ref() == Some(&task_id)),
+    );
+    if already_respawned {
+        return Ok(());
+    }
+    let Some(next) = task.next_instance(Utc::now().date_naive()) else {
+        return Ok(()); // the series has ended
+    };
+    if queue {
+        client.queue_event(room.clone(), next).await?;
+    } else {
+        room.send(next).await?;
+    }
+    Ok(())
+}
+
 #[derive(Clone)]
 pub struct TaskListUpdateBuilder {
     client: Client,
@@ -1161,6 +1579,12 @@ impl TaskListUpdateBuilder {
         self
     }
 
+    /// queue to be sent once online, shown as pending right away
+    pub async fn queue(&self) -> Result<OwnedEventId> {
+        let content = self.content.build()?;
+        self.client.queue_event(self.room.clone(), content).await
+    }
+
     pub async fn send(&self) -> Result<OwnedEventId> {
         let room = self.room.clone();
         let my_id = self.client.user_id()?;
@@ -17,4 +17,6 @@ mod desktop;
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 pub use desktop::*;
 
-pub use native::{rotate_log_file, sanitize, would_log, write_log};
+pub use native::{
+    media_cache_for, move_media_cache, rotate_log_file, sanitize, would_log, write_log,
+};
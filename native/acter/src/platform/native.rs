@@ -5,8 +5,10 @@ use log::{log_enabled, Level, LevelFilter, Log, Metadata, Record};
 use matrix_sdk::{Client, ClientBuilder, SqliteEventCacheStore};
 use matrix_sdk_base::{event_cache::store::EventCacheStoreError, store::StoreConfig};
 use matrix_sdk_sqlite::{OpenStoreError, SqliteCryptoStore, SqliteStateStore};
+use matrix_sdk_store_file_event_cache::MediaCache;
 use parse_env_filter::eager::{filters, Filter};
 use std::{
+    collections::HashMap,
     fmt::{Display, Error},
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
@@ -64,7 +66,7 @@ pub async fn new_client_config(
         .spawn(async move {
             let data_path = make_data_path(&db_base_path, &home_dir, reset_if_existing)?;
 
-            let config = match make_store_config(
+            let (config, media_cache) = match make_store_config(
                 &data_path,
                 media_cached_path.clone(),
                 db_passphrase.as_deref(),
@@ -83,6 +85,13 @@ pub async fn new_client_config(
                 }
                 Ok(config) => config,
             };
+            {
+                let mut media_caches = MEDIA_CACHES.lock().expect("media caches lock poisoned");
+                match media_cache {
+                    Some(media_cache) => media_caches.insert(home_dir, media_cache),
+                    None => media_caches.remove(&home_dir),
+                };
+            }
             let builder = Client::builder()
                 .store_config(config)
                 .user_agent(format!("acter-testing/{:}", env!("CARGO_PKG_VERSION")));
@@ -93,6 +102,28 @@ pub async fn new_client_config(
 
 lazy_static! {
     static ref FILE_LOGGER: Mutex<Option<Arc<fern::ImplDispatch>>> = Mutex::new(None);
+    /// the file based media caches by home dir, as the store itself is gone into the client
+    static ref MEDIA_CACHES: Mutex<HashMap<String, MediaCache>> = Mutex::new(HashMap::new());
+}
+
+/// The media cache of the client set up for `home_dir`, if it caches media
+/// in files, which it only does with a database passphrase
+pub fn media_cache_for(home_dir: &str) -> Option<MediaCache> {
+    MEDIA_CACHES
+        .lock()
+        .expect("media caches lock poisoned")
+        .get(home_dir)
+        .cloned()
+}
+
+/// Look up the media cache set up for `home_dir` by `user_id` from now on,
+/// for clients that only know their user once logged in
+pub fn move_media_cache(home_dir: &str, user_id: &str) {
+    let mut media_caches = MEDIA_CACHES.lock().expect("media caches lock poisoned");
+    match media_caches.remove(home_dir) {
+        Some(media_cache) => media_caches.insert(user_id.to_owned(), media_cache),
+        None => media_caches.remove(user_id),
+    };
 }
 
 #[cfg(feature = "tracing")]
@@ -309,7 +340,7 @@ async fn make_store_config(
     path: &Path,
     media_cache_path: PathBuf,
     passphrase: Option<&str>,
-) -> Result<StoreConfig, MakeStoreConfigError> {
+) -> Result<(StoreConfig, Option<MediaCache>), MakeStoreConfigError> {
     // FIXME: this stock holder name probably needs to be decided upon
     //        by the outer part to inform us whether this is the main
     //        process or the background job
@@ -319,9 +350,10 @@ async fn make_store_config(
     let sql_state_store = SqliteStateStore::open(path, passphrase).await?;
     let event_cache_store = SqliteEventCacheStore::open(path, passphrase).await?;
     let Some(passphrase) = passphrase else {
-        return Ok(config
+        let config = config
             .state_store(sql_state_store)
-            .event_cache_store(event_cache_store));
+            .event_cache_store(event_cache_store);
+        return Ok((config, None));
     };
 
     let event_cache_store = matrix_sdk_store_file_event_cache::wrap_with_file_cache_and_limits(
@@ -335,7 +367,9 @@ async fn make_store_config(
         200,
     )
     .await?;
-    Ok(config
+    let media_cache = event_cache_store.inner().media_cache();
+    let config = config
         .state_store(sql_state_store)
-        .event_cache_store(event_cache_store))
+        .event_cache_store(event_cache_store);
+    Ok((config, Some(media_cache)))
 }
@@ -1,15 +1,25 @@
 use anyhow::Result;
 use clap::Subcommand;
 
+mod daemon;
+mod digest;
 mod execute;
+mod export;
+mod export_template;
 mod history;
 mod list;
 mod manage;
+mod objects;
 
+pub use daemon::Daemon;
+pub use digest::DigestOpts;
 pub use execute::ExecuteOpts;
+pub use export::ExportOpts;
+pub use export_template::ExportTemplateOpts;
 pub use history::HistoryOpts;
 pub use list::List;
 pub use manage::Manage;
+pub use objects::Objects;
 
 #[derive(Subcommand, Debug)]
 pub enum Action {
@@ -21,6 +31,16 @@ pub enum Action {
     History(HistoryOpts),
     /// Template Execution
     Execute(ExecuteOpts),
+    /// Export a space as template
+    ExportTemplate(ExportTemplateOpts),
+    /// Export a space or chat as archive
+    Export(ExportOpts),
+    /// Create and change tasks, pins, events, news, comments and RSVPs
+    Objects(Objects),
+    /// Keep running and invoke hooks on new objects
+    Daemon(Daemon),
+    /// Summarize what happened across all spaces
+    Digest(DigestOpts),
 }
 
 impl Action {
@@ -30,6 +50,11 @@ impl Action {
             Action::List(config) => config.run().await?,
             Action::History(config) => config.run().await?,
             Action::Execute(config) => config.run().await?,
+            Action::ExportTemplate(config) => config.run().await?,
+            Action::Export(config) => config.run().await?,
+            Action::Objects(config) => config.run().await?,
+            Action::Daemon(config) => config.run().await?,
+            Action::Digest(config) => config.run().await?,
         };
         Ok(())
     }
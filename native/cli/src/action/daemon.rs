@@ -0,0 +1,536 @@
+use acter_matrix::{
+    executor::Executor,
+    models::{ActerModel, AnyActerModel},
+    referencing::{ExecuteReference, IndexKey, SectionIndex, SpecialListsIndex},
+};
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use futures::stream::StreamExt;
+use matrix_sdk_base::ruma::{OwnedEventId, OwnedRoomId};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+};
+use tokio::{
+    io::AsyncWriteExt,
+    process::Command,
+    sync::{broadcast::error::RecvError, Mutex},
+};
+use tracing::{error, info, warn};
+
+use crate::config::LoginConfig;
+
+/// Where a hook listens, exactly one of `section`, `object`, `room`,
+/// `special` or `history` narrowed down by the others
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct HookConfig {
+    pub name: String,
+
+    /// `boosts`, `calendar`, `pins`, `stories` or `tasks`, in `room` if given
+    pub section: Option<String>,
+    /// all changes to that object or, with `list`, its `comments`,
+    /// `attachments`, `rsvp`, `tasks`, …
+    pub object: Option<OwnedEventId>,
+    pub list: Option<String>,
+    /// all objects of that room, or its history with `history = true`
+    pub room: Option<OwnedRoomId>,
+    /// `my_open_tasks`, `my_done_tasks` or `invited_to`
+    pub special: Option<String>,
+    /// every change, or those of `room`
+    #[serde(default)]
+    pub history: bool,
+
+    /// only run for these event types, e.g. `global.acter.dev.task`
+    #[serde(default)]
+    pub types: Vec<String>,
+    /// also run for everything that was already there when the hook was
+    /// first seen, rather than just what comes in afterwards
+    #[serde(default)]
+    pub backfill: bool,
+
+    /// shell command, run with `sh -c`
+    pub run: Option<String>,
+    /// program and its arguments, run directly
+    pub exec: Option<Vec<String>>,
+}
+
+impl HookConfig {
+    pub fn index_key(&self) -> Result<IndexKey> {
+        let key = match self {
+            HookConfig {
+                section: Some(section),
+                object: None,
+                special: None,
+                history: false,
+                room,
+                ..
+            } => {
+                let section: SectionIndex = section
+                    .parse()
+                    .with_context(|| format!("unknown section `{section}`"))?;
+                match room {
+                    Some(room) => IndexKey::RoomSection(room.clone(), section),
+                    None => IndexKey::Section(section),
+                }
+            }
+            HookConfig {
+                object: Some(object),
+                section: None,
+                room: None,
+                special: None,
+                history: false,
+                list,
+                ..
+            } => match list {
+                Some(list) => IndexKey::ObjectList(
+                    object.clone(),
+                    list.parse()
+                        .with_context(|| format!("unknown list `{list}`"))?,
+                ),
+                None => IndexKey::ObjectHistory(object.clone()),
+            },
+            HookConfig {
+                room: Some(room),
+                section: None,
+                object: None,
+                special: None,
+                history,
+                ..
+            } => {
+                if *history {
+                    IndexKey::RoomHistory(room.clone())
+                } else {
+                    IndexKey::RoomModels(room.clone())
+                }
+            }
+            HookConfig {
+                special: Some(special),
+                section: None,
+                object: None,
+                room: None,
+                history: false,
+                ..
+            } => {
+                let special: SpecialListsIndex = special
+                    .parse()
+                    .with_context(|| format!("unknown special list `{special}`"))?;
+                IndexKey::Special(special)
+            }
+            HookConfig {
+                history: true,
+                section: None,
+                object: None,
+                room: None,
+                special: None,
+                ..
+            } => IndexKey::AllHistory,
+            _ => bail!(
+                "hook `{}` needs exactly one of `section`, `object`, `room`, `special` or `history`",
+                self.name
+            ),
+        };
+        if self.list.is_some() && self.object.is_none() {
+            bail!("hook `{}`: `list` only works with `object`", self.name);
+        }
+        Ok(key)
+    }
+
+    /// Whether the hook runs for models of that type
+    fn wants(&self, model_type: &str) -> bool {
+        self.types.is_empty() || self.types.iter().any(|t| t == model_type)
+    }
+
+    fn command(&self) -> Result<Command> {
+        match (&self.run, &self.exec) {
+            (Some(run), None) => {
+                let mut cmd = Command::new("sh");
+                cmd.arg("-c").arg(run);
+                Ok(cmd)
+            }
+            (None, Some(exec)) => {
+                let Some((program, args)) = exec.split_first() else {
+                    bail!("hook `{}`: `exec` is empty", self.name);
+                };
+                let mut cmd = Command::new(program);
+                cmd.args(args);
+                Ok(cmd)
+            }
+            _ => bail!("hook `{}` needs either `run` or `exec`", self.name),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DaemonConfig {
+    #[serde(default, rename = "hook")]
+    pub hooks: Vec<HookConfig>,
+}
+
+/// The events each hook has handled already, by hook name. Only those still
+/// in the list the hook watches are kept, so it doesn’t grow without bounds;
+/// should one come back to the list, the hook runs for it again.
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct DaemonState {
+    hooks: BTreeMap<String, BTreeSet<OwnedEventId>>,
+}
+
+#[derive(Clone)]
+struct StateFile {
+    path: PathBuf,
+    state: Arc<Mutex<DaemonState>>,
+}
+
+impl StateFile {
+    fn load(path: &Path) -> Result<Self> {
+        let state = if path.exists() {
+            let content = std::fs::read_to_string(path)?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("reading state from {}", path.display()))?
+        } else {
+            DaemonState::default()
+        };
+        Ok(StateFile {
+            path: path.to_owned(),
+            state: Arc::new(Mutex::new(state)),
+        })
+    }
+
+    async fn write(&self, state: &DaemonState) -> Result<()> {
+        // write aside and move over so a crash never leaves a broken file
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(state)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+
+    /// Mark everything as handled if we haven't seen this hook before
+    async fn init_hook(&self, hook: &str, existing: &[OwnedEventId]) -> Result<bool> {
+        let mut state = self.state.lock().await;
+        if state.hooks.contains_key(hook) {
+            return Ok(false);
+        }
+        state
+            .hooks
+            .insert(hook.to_owned(), existing.iter().cloned().collect());
+        self.write(&state).await?;
+        Ok(true)
+    }
+
+    async fn is_handled(&self, hook: &str, event_id: &OwnedEventId) -> bool {
+        let state = self.state.lock().await;
+        state
+            .hooks
+            .get(hook)
+            .map(|handled| handled.contains(event_id))
+            .unwrap_or_default()
+    }
+
+    /// Drop the handled events that aren't in the list anymore
+    async fn forget_missing(&self, hook: &str, present: &BTreeSet<OwnedEventId>) -> Result<()> {
+        let mut state = self.state.lock().await;
+        let Some(handled) = state.hooks.get_mut(hook) else {
+            return Ok(());
+        };
+        let before = handled.len();
+        handled.retain(|event_id| present.contains(event_id));
+        if handled.len() == before {
+            return Ok(());
+        }
+        self.write(&state).await
+    }
+
+    async fn mark_handled(&self, hook: &str, event_id: OwnedEventId) -> Result<()> {
+        let mut state = self.state.lock().await;
+        state
+            .hooks
+            .entry(hook.to_owned())
+            .or_default()
+            .insert(event_id);
+        self.write(&state).await
+    }
+}
+
+/// Keep syncing and run hooks for new objects as they come in
+///
+/// Each hook gets the object as JSON on stdin and its `ACTER_EVENT_ID`,
+/// `ACTER_ROOM_ID`, `ACTER_SENDER` and `ACTER_MODEL_TYPE` in the environment:
+///
+/// ```toml
+/// [[hook]]
+/// name = "new-tasks"
+/// special = "my_open_tasks"
+/// types = ["global.acter.dev.task"]
+/// exec = ["python3", "assign.py"]
+/// ```
+#[derive(Parser, Debug)]
+pub struct Daemon {
+    #[clap(flatten)]
+    pub login: LoginConfig,
+
+    /// TOML file with the `[[hook]]`s to run
+    #[clap(long, short)]
+    pub config: PathBuf,
+
+    /// JSON file keeping track of the handled events across restarts
+    #[clap(long, default_value = "acter-daemon-state.json")]
+    pub state: PathBuf,
+}
+
+impl Daemon {
+    pub async fn run(&self) -> Result<()> {
+        let content = std::fs::read_to_string(&self.config)
+            .with_context(|| format!("reading {}", self.config.display()))?;
+        let config: DaemonConfig = toml::from_str(&content)?;
+        if config.hooks.is_empty() {
+            bail!("no `[[hook]]` found in {}", self.config.display());
+        }
+        let mut hooks = Vec::with_capacity(config.hooks.len());
+        let mut names = BTreeSet::new();
+        for hook in config.hooks {
+            if !names.insert(hook.name.clone()) {
+                bail!("hook `{}` is defined twice", hook.name);
+            }
+            let key = hook.index_key()?;
+            hook.command()?; // fail early on broken hooks
+            hooks.push((hook, key));
+        }
+        let state = StateFile::load(&self.state)?;
+
+        let mut client = self.login.client().await?;
+        info!(" - Syncing -");
+        let sync_state = client.start_sync();
+
+        let mut is_synced = sync_state.first_synced_rx();
+        while is_synced.next().await != Some(true) {} // let’s wait for it to have synced
+        info!(" - First Sync finished - ");
+
+        let executor = client.executor().clone();
+        let mut tasks = Vec::with_capacity(hooks.len());
+        for (hook, key) in hooks {
+            let runner = HookRunner {
+                executor: executor.clone(),
+                state: state.clone(),
+                hook,
+                key,
+            };
+            tasks.push(tokio::spawn(async move {
+                let name = runner.hook.name.clone();
+                if let Err(error) = runner.run().await {
+                    error!(hook = name, ?error, "hook stopped");
+                }
+            }));
+        }
+        info!("Running {} hooks, stop with Ctrl-C", tasks.len());
+
+        tokio::signal::ctrl_c().await?;
+        info!("Shutting down");
+        for task in tasks {
+            task.abort();
+        }
+        sync_state.cancel();
+        Ok(())
+    }
+}
+
+struct HookRunner {
+    executor: Executor,
+    state: StateFile,
+    hook: HookConfig,
+    key: IndexKey,
+}
+
+impl HookRunner {
+    async fn run(&self) -> Result<()> {
+        // subscribe before the first pass, so we don't miss what comes in meanwhile
+        let mut updates = self
+            .executor
+            .subscribe(ExecuteReference::Index(self.key.clone()));
+
+        let existing = self.models().await?;
+        let skip: Vec<OwnedEventId> = if self.hook.backfill {
+            vec![]
+        } else {
+            existing.iter().map(|m| m.event_id().to_owned()).collect()
+        };
+        if self.state.init_hook(&self.hook.name, &skip).await? && !skip.is_empty() {
+            info!(
+                hook = self.hook.name,
+                "skipping {} existing objects",
+                skip.len()
+            );
+        }
+        self.handle(existing).await?;
+
+        loop {
+            match updates.recv().await {
+                Ok(()) => {}
+                Err(RecvError::Lagged(_)) => {} // we look at the whole list anyways
+                Err(RecvError::Closed) => return Ok(()),
+            }
+            let models = self.models().await?;
+            self.handle(models).await?;
+        }
+    }
+
+    async fn models(&self) -> Result<Vec<AnyActerModel>> {
+        Ok(self.executor.store().get_list(&self.key).await?.collect())
+    }
+
+    async fn handle(&self, models: Vec<AnyActerModel>) -> Result<()> {
+        let present = models.iter().map(|m| m.event_id().to_owned()).collect();
+        self.state.forget_missing(&self.hook.name, &present).await?;
+        for model in models {
+            let event_id = model.event_id().to_owned();
+            if self.state.is_handled(&self.hook.name, &event_id).await {
+                continue;
+            }
+            if !self.hook.wants(model.model_type()) {
+                self.state.mark_handled(&self.hook.name, event_id).await?;
+                continue;
+            }
+            // failed runs aren't recorded, so they are retried on the next change
+            match self.invoke(&model).await {
+                Ok(()) => self.state.mark_handled(&self.hook.name, event_id).await?,
+                Err(error) => warn!(hook = self.hook.name, %event_id, ?error, "hook failed"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the hook with the model as JSON on stdin
+    async fn invoke(&self, model: &AnyActerModel) -> Result<()> {
+        let meta = model.event_meta();
+        info!(hook = self.hook.name, event_id = %meta.event_id, "running hook");
+        let mut child = self
+            .hook
+            .command()?
+            .env("ACTER_HOOK", &self.hook.name)
+            .env("ACTER_EVENT_ID", meta.event_id.as_str())
+            .env("ACTER_ROOM_ID", meta.room_id.as_str())
+            .env("ACTER_SENDER", meta.sender.as_str())
+            .env("ACTER_MODEL_TYPE", model.model_type())
+            .stdin(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&serde_json::to_vec(model)?).await?;
+            // dropping closes it, so the hook sees the end of input
+        }
+        let status = child.wait().await?;
+        if !status.success() {
+            bail!("exited with {status}");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acter_matrix::referencing::ObjectListIndex;
+    use matrix_sdk_base::ruma::{owned_event_id, owned_room_id};
+
+    fn hook(config: &str) -> HookConfig {
+        toml::from_str(&format!("name = \"test\"\nrun = \"true\"\n{config}")).expect("hook parses")
+    }
+
+    #[test]
+    fn index_keys() -> Result<()> {
+        let room = owned_room_id!("!space:example.org");
+        let object = owned_event_id!("$object");
+        assert_eq!(
+            hook(r#"section = "tasks""#).index_key()?,
+            IndexKey::Section(SectionIndex::Tasks)
+        );
+        assert_eq!(
+            hook(&format!("section = \"news\"\nroom = \"{room}\"")).index_key()?,
+            IndexKey::RoomSection(room.clone(), SectionIndex::Boosts)
+        );
+        assert_eq!(
+            hook(&format!("object = \"{object}\"")).index_key()?,
+            IndexKey::ObjectHistory(object.clone())
+        );
+        assert_eq!(
+            hook(&format!("object = \"{object}\"\nlist = \"comments\"")).index_key()?,
+            IndexKey::ObjectList(object, ObjectListIndex::Comments)
+        );
+        assert_eq!(
+            hook(&format!("room = \"{room}\"")).index_key()?,
+            IndexKey::RoomModels(room.clone())
+        );
+        assert_eq!(
+            hook(&format!("room = \"{room}\"\nhistory = true")).index_key()?,
+            IndexKey::RoomHistory(room)
+        );
+        assert_eq!(
+            hook(r#"special = "my_open_tasks""#).index_key()?,
+            IndexKey::Special(SpecialListsIndex::MyOpenTasks)
+        );
+        assert_eq!(hook("history = true").index_key()?, IndexKey::AllHistory);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_index_keys() {
+        for config in [
+            "",
+            r#"section = "unknown""#,
+            r#"special = "unknown""#,
+            "section = \"tasks\"\nhistory = true",
+            "section = \"tasks\"\nspecial = \"my_open_tasks\"",
+            "object = \"$object\"\nlist = \"unknown\"",
+            "object = \"$object\"\nroom = \"!space:example.org\"",
+            "section = \"tasks\"\nlist = \"comments\"",
+        ] {
+            assert!(hook(config).index_key().is_err(), "{config:?} accepted");
+        }
+    }
+
+    #[test]
+    fn type_filtering() {
+        let all = hook(r#"special = "my_open_tasks""#);
+        assert!(all.wants("global.acter.dev.task"));
+        assert!(all.wants("global.acter.dev.comment"));
+
+        let tasks = hook(
+            "special = \"my_open_tasks\"\n\
+             types = [\"global.acter.dev.task\", \"global.acter.dev.tasklist\"]",
+        );
+        assert!(tasks.wants("global.acter.dev.task"));
+        assert!(tasks.wants("global.acter.dev.tasklist"));
+        assert!(!tasks.wants("global.acter.dev.comment"));
+    }
+
+    #[tokio::test]
+    async fn state_forgets_what_left_the_list() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("acter-daemon-state-{}.json", std::process::id()));
+        let state = StateFile::load(&path)?;
+        let (a, b, c) = (
+            owned_event_id!("$a"),
+            owned_event_id!("$b"),
+            owned_event_id!("$c"),
+        );
+        assert!(state.init_hook("test", &[a.clone(), b.clone()]).await?);
+        assert!(
+            !state.init_hook("test", &[]).await?,
+            "only initialized once"
+        );
+        state.mark_handled("test", c.clone()).await?;
+
+        state
+            .forget_missing("test", &[b.clone(), c.clone()].into_iter().collect())
+            .await?;
+        assert!(!state.is_handled("test", &a).await);
+        assert!(state.is_handled("test", &b).await);
+
+        let reloaded = StateFile::load(&path)?;
+        assert!(!reloaded.is_handled("test", &a).await);
+        assert!(reloaded.is_handled("test", &c).await);
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use anyhow::Result;
+use clap::{Parser, ValueEnum};
+use futures::stream::StreamExt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::config::LoginConfig;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Markdown,
+    Html,
+    Json,
+}
+
+/// Summarize what happened across all spaces
+#[derive(Parser, Debug)]
+pub struct DigestOpts {
+    #[clap(flatten)]
+    pub login: LoginConfig,
+
+    /// Days to look back, e.g. 1 for a daily or 7 for a weekly digest
+    #[clap(long, default_value_t = 7)]
+    pub days: u32,
+
+    #[clap(long, short, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Turn the objects into links to the app, e.g. `https://app.acter.global/#`
+    #[clap(long)]
+    pub link_base: Option<String>,
+}
+
+impl DigestOpts {
+    pub async fn run(&self) -> Result<()> {
+        let mut client = self.login.client().await?;
+        info!(" - Syncing -");
+        let sync_state = client.start_sync();
+
+        let mut is_synced = sync_state.first_synced_rx();
+        while is_synced.next().await != Some(true) {} // let’s wait for it to have synced
+        info!(" - First Sync finished - ");
+
+        let since = SystemTime::now() - Duration::from_secs(u64::from(self.days) * 24 * 60 * 60);
+        let since_ts = since.duration_since(UNIX_EPOCH)?.as_millis() as u64;
+        let digest = client
+            .activity_digest(since_ts, self.days.max(1), self.link_base.clone())
+            .await?;
+        let output = match self.format {
+            Format::Text => digest.text(),
+            Format::Markdown => digest.markdown(),
+            Format::Html => digest.html(),
+            Format::Json => digest.json()?,
+        };
+        println!("{output}");
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use acter_matrix::templates::ExecutionRecord;
 use anyhow::{bail, Result};
 use clap::Parser;
 use futures::{pin_mut, stream::StreamExt};
@@ -11,28 +12,60 @@ pub struct ExecuteOpts {
     #[clap(flatten)]
     pub login: LoginConfig,
 
+    /// `key=value` for the template inputs: room ids for spaces, `main` or
+    /// the logged in user id for users, RFC 3339 or `YYYY-MM-DD` for dates,
+    /// one of the options for choices and whole numbers for numbers
     #[clap(short, long = "input-value")]
     pub inputs: Vec<String>,
 
     #[clap(long)]
     pub ignore_sync: bool,
 
+    /// Only check the templates and report all problems found, without
+    /// creating anything
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// Execute without checking the templates first
+    #[clap(long, conflicts_with = "dry_run")]
+    pub skip_validation: bool,
+
+    /// Keep track of what was created in this file. If it exists already,
+    /// the execution is resumed, skipping what has been created before
+    #[clap(long)]
+    pub record: Option<PathBuf>,
+
+    /// Undo what the `--record` says was created: redact the objects and
+    /// leave the spaces
+    #[clap(long, requires = "record", conflicts_with = "dry_run")]
+    pub rollback: bool,
+
     #[clap()]
     pub templates: Vec<PathBuf>,
 }
 
 impl ExecuteOpts {
     pub async fn run(&self) -> Result<()> {
-        let mapped_inputs = self
-            .inputs
-            .iter()
-            .filter_map(|v| v.split_once('='))
-            .collect::<HashMap<&str, &str>>();
+        let mut mapped_inputs = HashMap::new();
+        for input in self.inputs.iter() {
+            let Some((key, value)) = input.split_once('=') else {
+                bail!("input value `{input}` must be given as key=value");
+            };
+            mapped_inputs.insert(key, value);
+        }
+        if self.record.is_some() && self.templates.len() > 1 {
+            bail!("A record can only be kept for one template at a time");
+        }
+        if let (true, Some(path)) = (self.rollback, &self.record) {
+            if !path.exists() {
+                bail!("No record at {path:?} to roll back");
+            }
+        }
         let mut user = self.login.client().await?;
 
         let sync_state = user.start_sync();
 
-        if !self.ignore_sync {
+        if !self.ignore_sync && !self.dry_run {
             let mut is_synced = sync_state.first_synced_rx();
             while is_synced.next().await != Some(true) {} // let’s wait for it to have synced
         }
@@ -45,15 +78,30 @@ impl ExecuteOpts {
                 tmpl_engine
                     .requested_inputs()
                     .iter()
-                    .map(|(key, input)| (key.clone(), (input.is_required(), input.is_space())))
+                    .map(|(key, input)| {
+                        (
+                            key.clone(),
+                            (
+                                input.is_required() && !input.has_default_value(),
+                                input.is_user(),
+                            ),
+                        )
+                    })
                     .collect::<Vec<_>>()
             };
-            for (key, (is_required, is_space)) in input_values {
+            for (key, (is_required, is_user)) in input_values {
                 if let Some(res) = mapped_inputs.get(key.as_str()).cloned() {
-                    if !is_space {
-                        bail!("{key} : non-space input values not yet supported");
+                    if is_user {
+                        // we only have the one client we are logged in with
+                        if key == "main" || res == "main" || res == user.user_id()?.as_str() {
+                            if key != "main" {
+                                tmpl_engine.alias_user(key.clone(), "main")?;
+                            }
+                            continue;
+                        }
+                        bail!("{key} : only the logged in user or `main` can be given as user");
                     }
-                    tmpl_engine.add_ref(key.clone(), "space".to_owned(), res.to_owned())?;
+                    tmpl_engine.add_input(key.clone(), res)?;
                 } else if is_required {
                     if key != "main" {
                         bail!("Missing required input value {key} for {tmpl_path:?}");
@@ -64,11 +112,51 @@ impl ExecuteOpts {
                 }
             }
 
-            let exec_stream = tmpl_engine.execute()?;
+            if self.dry_run {
+                tmpl_engine.validate()?;
+                println!("{tmpl_path:?} is valid");
+                continue;
+            }
+
+            let previous = match &self.record {
+                Some(path) if path.exists() => {
+                    serde_json::from_slice::<ExecutionRecord>(&std::fs::read(path)?)?
+                }
+                _ => ExecutionRecord::default(),
+            };
+
+            if self.rollback {
+                tmpl_engine.rollback(&previous).await?;
+                if let Some(path) = &self.record {
+                    std::fs::remove_file(path)?;
+                }
+                info!("Rolled back {} objects of {tmpl_path:?}", previous.len());
+                continue;
+            }
+
+            if !self.skip_validation {
+                tmpl_engine.validate()?;
+            }
+            if !previous.is_empty() {
+                info!("Resuming after {} objects created before", previous.len());
+            }
+
+            let exec_stream = tmpl_engine.resume(previous)?;
             pin_mut!(exec_stream);
+            let mut result = Ok(());
             while let Some(i) = exec_stream.next().await {
-                i?
+                if let Err(e) = i {
+                    result = Err(e);
+                    break;
+                }
+            }
+            if let Some(path) = &self.record {
+                std::fs::write(path, serde_json::to_vec_pretty(&exec_stream.record())?)?;
+                if result.is_err() {
+                    info!("Run again with the same record to resume or pass --rollback to undo");
+                }
             }
+            result?;
         }
         Ok(())
     }
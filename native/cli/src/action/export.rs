@@ -0,0 +1,836 @@
+use acter_matrix::{
+    activities::digest::markdown_escape,
+    events::{
+        attachments::{AttachmentContent, FallbackAttachmentContent},
+        UtcDateTime,
+    },
+    models::{ActerModel, AnyActerModel},
+    referencing::IndexKey,
+};
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use futures::stream::StreamExt;
+use matrix_sdk::{
+    media::{MediaFormat, MediaRequestParameters},
+    room::{Messages, MessagesOptions, Room},
+    Client as SdkClient,
+};
+use matrix_sdk_base::{
+    ruma::{
+        events::{
+            room::{message::MessageType, MediaSource},
+            AnySyncMessageLikeEvent, AnySyncTimelineEvent, SyncMessageLikeEvent,
+        },
+        EventId, OwnedRoomId, RoomId,
+    },
+    RoomState,
+};
+use serde_json::{json, Map, Value as JsonValue};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tracing::{info, trace, warn};
+
+use crate::config::{LoginConfig, ENV_ROOM};
+
+/// The directory within the archive the media is downloaded to
+static MEDIA_DIR: &str = "media";
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Only the `archive.json`
+    #[default]
+    Json,
+    /// An `index.md` besides the `archive.json`
+    Markdown,
+    /// An `index.html` besides the `archive.json`
+    Html,
+}
+
+/// Export a space or chat as archive: its objects with their comments,
+/// attachments and RSVPs, the decrypted chat history and the media
+#[derive(Parser, Debug)]
+pub struct ExportOpts {
+    #[clap(flatten)]
+    pub login: LoginConfig,
+
+    /// The directory to write the archive to, created if missing
+    #[clap(short, long, default_value = "acter-export")]
+    pub output: PathBuf,
+
+    /// What to render besides the `archive.json`
+    #[clap(short, long, value_enum, default_value_t)]
+    pub format: Format,
+
+    /// Also export the chats of the space you have joined
+    #[clap(long)]
+    pub include_chats: bool,
+
+    /// Don’t download the media of attachments and messages
+    #[clap(long)]
+    pub skip_media: bool,
+
+    /// The space or chat to export
+    #[clap(env = ENV_ROOM)]
+    pub room: OwnedRoomId,
+}
+
+/// Where an object goes in the archive: its type and, if it belongs to
+/// another object, the list of that parent it is added to and the parent
+fn object_kind(model: &AnyActerModel) -> Option<(&'static str, Option<(&'static str, String)>)> {
+    // comments, attachments and RSVPs don’t report what they belong to via
+    // `belongs_to`, their managers do
+    let child_of = |list, parent: &EventId| Some((list, parent.to_string()));
+    Some(match model {
+        AnyActerModel::TaskList(_) => ("task-list", None),
+        AnyActerModel::Task(t) => ("task", child_of("tasks", &t.task_list_id.event_id)),
+        AnyActerModel::Pin(_) => ("pin", None),
+        AnyActerModel::CalendarEvent(_) => ("calendar-event", None),
+        AnyActerModel::NewsEntry(_) => ("news-entry", None),
+        AnyActerModel::Story(_) => ("story", None),
+        AnyActerModel::Comment(c) => ("comment", child_of("comments", &c.on.event_id)),
+        AnyActerModel::Attachment(a) => ("attachment", child_of("attachments", &a.on.event_id)),
+        AnyActerModel::Rsvp(r) => ("rsvp", child_of("rsvps", &r.to.event_id)),
+        _ => return None,
+    })
+}
+
+/// The media to download for the attachment, with its file name
+fn media_of(content: &AttachmentContent) -> Option<(&MediaSource, &str)> {
+    let (source, filename, body) = match content {
+        AttachmentContent::Image(c)
+        | AttachmentContent::Fallback(FallbackAttachmentContent::Image(c)) => {
+            (&c.source, &c.filename, &c.body)
+        }
+        AttachmentContent::Video(c)
+        | AttachmentContent::Fallback(FallbackAttachmentContent::Video(c)) => {
+            (&c.source, &c.filename, &c.body)
+        }
+        AttachmentContent::Audio(c)
+        | AttachmentContent::Fallback(FallbackAttachmentContent::Audio(c)) => {
+            (&c.source, &c.filename, &c.body)
+        }
+        AttachmentContent::File(c)
+        | AttachmentContent::Fallback(FallbackAttachmentContent::File(c)) => {
+            (&c.source, &c.filename, &c.body)
+        }
+        _ => return None,
+    };
+    Some((source, filename.as_deref().unwrap_or(body)))
+}
+
+/// The text to show for an exported object or message
+fn summary(entry: &Map<String, JsonValue>) -> String {
+    let text = |v: Option<&JsonValue>| v.and_then(JsonValue::as_str).map(ToOwned::to_owned);
+    text(entry.get("title"))
+        .or_else(|| text(entry.get("name")))
+        .or_else(|| {
+            let content = entry.get("content")?;
+            text(content.get("name"))
+                .or_else(|| text(content.get("body")))
+                .or_else(|| text(content.get("link")))
+        })
+        .or_else(|| text(entry.get("slides")?.get(0)?.get("body")))
+        .or_else(|| text(entry.get("status")?.get("type")))
+        .or_else(|| text(entry.get("type")))
+        .unwrap_or_default()
+}
+
+fn timestamp(entry: &Map<String, JsonValue>) -> String {
+    entry
+        .get("origin_server_ts")
+        .and_then(JsonValue::as_i64)
+        .and_then(UtcDateTime::from_timestamp_millis)
+        .map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default()
+}
+
+/// The parts of the rendered index
+#[derive(Debug, PartialEq, Eq)]
+enum Block {
+    Heading(u8, String),
+    Paragraph(String),
+    /// A list item at the given depth, optionally linking somewhere
+    Item(usize, String, Option<String>),
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_markdown(blocks: &[Block]) -> String {
+    let mut out = String::new();
+    for block in blocks {
+        let _ = match block {
+            Block::Heading(level, text) => writeln!(
+                out,
+                "\n{} {}\n",
+                "#".repeat(*level as usize),
+                markdown_escape(text)
+            ),
+            Block::Paragraph(text) => writeln!(out, "{}\n", markdown_escape(text)),
+            Block::Item(depth, text, None) => {
+                writeln!(out, "{}- {}", "  ".repeat(*depth), markdown_escape(text))
+            }
+            Block::Item(depth, text, Some(link)) => writeln!(
+                out,
+                "{}- [{}]({link})",
+                "  ".repeat(*depth),
+                markdown_escape(text)
+            ),
+        };
+    }
+    out
+}
+
+fn render_html(title: &str, blocks: &[Block]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n",
+        escape_html(title)
+    );
+    let mut depth = 0;
+    for block in blocks {
+        let target = match block {
+            Block::Item(d, ..) => d + 1,
+            _ => 0,
+        };
+        while depth < target {
+            out.push_str("<ul>\n");
+            depth += 1;
+        }
+        while depth > target {
+            out.push_str("</ul>\n");
+            depth -= 1;
+        }
+        let _ = match block {
+            Block::Heading(level, text) => {
+                writeln!(out, "<h{level}>{}</h{level}>", escape_html(text))
+            }
+            Block::Paragraph(text) => writeln!(out, "<p>{}</p>", escape_html(text)),
+            Block::Item(_, text, None) => writeln!(out, "<li>{}</li>", escape_html(text)),
+            Block::Item(_, text, Some(link)) => writeln!(
+                out,
+                "<li><a href=\"{}\">{}</a></li>",
+                escape_html(link),
+                escape_html(text)
+            ),
+        };
+    }
+    while depth > 0 {
+        out.push_str("</ul>\n");
+        depth -= 1;
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Index blocks for an object and what was added to it
+fn object_blocks(entry: &Map<String, JsonValue>, depth: usize, blocks: &mut Vec<Block>) {
+    let mut line = summary(entry);
+    if let Some(status) = entry.get("status").and_then(JsonValue::as_str) {
+        line = format!("[{status}] {line}");
+    }
+    if let Some(assignees) = entry.get("assignees").and_then(JsonValue::as_array) {
+        let names: Vec<&str> = assignees.iter().filter_map(JsonValue::as_str).collect();
+        if !names.is_empty() {
+            line = format!("{line} (assigned to {})", names.join(", "));
+        }
+    }
+    let sender = entry
+        .get("sender")
+        .and_then(JsonValue::as_str)
+        .unwrap_or_default();
+    let line = format!("{line} — {sender}, {}", timestamp(entry));
+    let link = entry
+        .get("media_file")
+        .and_then(JsonValue::as_str)
+        .map(ToOwned::to_owned);
+    blocks.push(Block::Item(depth, line, link));
+    for list in ["tasks", "attachments", "comments", "rsvps"] {
+        for child in entry
+            .get(list)
+            .and_then(JsonValue::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(JsonValue::as_object)
+        {
+            object_blocks(child, depth + 1, blocks);
+        }
+    }
+}
+
+fn index_blocks(archive: &JsonValue) -> Vec<Block> {
+    let mut blocks = vec![
+        Block::Heading(1, "Acter export".to_owned()),
+        Block::Paragraph(format!(
+            "Exported by {} at {}",
+            archive["exported_by"].as_str().unwrap_or_default(),
+            archive["exported_at"].as_str().unwrap_or_default(),
+        )),
+    ];
+    for room in archive["rooms"].as_array().into_iter().flatten() {
+        let room_id = room["room_id"].as_str().unwrap_or_default();
+        blocks.push(Block::Heading(
+            2,
+            room["name"].as_str().unwrap_or(room_id).to_owned(),
+        ));
+        if let Some(topic) = room["topic"].as_str() {
+            blocks.push(Block::Paragraph(topic.to_owned()));
+        }
+        blocks.push(Block::Paragraph(room_id.to_owned()));
+
+        let objects: Vec<_> = room["objects"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(JsonValue::as_object)
+            .collect();
+        for (obj_type, heading) in [
+            ("task-list", "Task lists"),
+            ("task", "Tasks"),
+            ("pin", "Pins"),
+            ("calendar-event", "Events"),
+            ("news-entry", "News"),
+            ("story", "Stories"),
+            ("comment", "Comments"),
+            ("attachment", "Attachments"),
+            ("rsvp", "RSVPs"),
+        ] {
+            let of_type: Vec<_> = objects
+                .iter()
+                .filter(|o| o.get("type").and_then(JsonValue::as_str) == Some(obj_type))
+                .collect();
+            if of_type.is_empty() {
+                continue;
+            }
+            blocks.push(Block::Heading(3, heading.to_owned()));
+            for object in of_type {
+                object_blocks(object, 0, &mut blocks);
+            }
+        }
+
+        let messages: Vec<_> = room["messages"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(JsonValue::as_object)
+            .collect();
+        if messages.is_empty() {
+            continue;
+        }
+        blocks.push(Block::Heading(3, "Chat history".to_owned()));
+        for message in messages {
+            let Some(body) = message
+                .get("content")
+                .and_then(|c| c.get("body"))
+                .and_then(JsonValue::as_str)
+            else {
+                continue;
+            };
+            let sender = message
+                .get("sender")
+                .and_then(JsonValue::as_str)
+                .unwrap_or_default();
+            blocks.push(Block::Item(
+                0,
+                format!("{sender} ({}): {body}", timestamp(message)),
+                message
+                    .get("media_file")
+                    .and_then(JsonValue::as_str)
+                    .map(ToOwned::to_owned),
+            ));
+        }
+    }
+    blocks
+}
+
+/// Collects the archive, downloading media as it goes
+struct Exporter<'a> {
+    client: &'a SdkClient,
+    output: &'a Path,
+    skip_media: bool,
+}
+
+impl Exporter<'_> {
+    /// Download the media to the archive, returning its path within it
+    async fn download(&self, id: &str, source: &MediaSource, filename: &str) -> Option<String> {
+        if self.skip_media {
+            return None;
+        }
+        let request = MediaRequestParameters {
+            source: source.clone(),
+            format: MediaFormat::File,
+        };
+        let content = match self.client.media().get_media_content(&request, true).await {
+            Ok(content) => content,
+            Err(error) => {
+                warn!(id, ?error, "downloading media failed");
+                return None;
+            }
+        };
+        let safe = |s: &str| {
+            s.chars()
+                .map(|c| {
+                    if c.is_alphanumeric() || c == '.' || c == '-' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect::<String>()
+        };
+        let path = format!("{MEDIA_DIR}/{}-{}", safe(id), safe(filename));
+        if let Err(error) = std::fs::write(self.output.join(&path), content) {
+            warn!(id, ?error, "writing media failed");
+            return None;
+        }
+        Some(path)
+    }
+
+    /// The fields of the model, with those of its content at the top
+    async fn object(
+        &self,
+        obj_type: &str,
+        model: &AnyActerModel,
+    ) -> Result<Map<String, JsonValue>> {
+        let JsonValue::Object(variant) = serde_json::to_value(model)? else {
+            bail!("{} couldn’t be exported", model.event_id());
+        };
+        // externally tagged: { "Task": { "inner": .., "meta": .. } }
+        let Some((_, JsonValue::Object(mut fields))) = variant.into_iter().next() else {
+            bail!("{} couldn’t be exported", model.event_id());
+        };
+        let meta = model.event_meta();
+        let mut entry = Map::new();
+        entry.insert("type".to_owned(), obj_type.into());
+        entry.insert("event_id".to_owned(), meta.event_id.to_string().into());
+        entry.insert("sender".to_owned(), meta.sender.to_string().into());
+        entry.insert(
+            "origin_server_ts".to_owned(),
+            u64::from(meta.origin_server_ts.get()).into(),
+        );
+        if let Some(JsonValue::Object(inner)) = fields.remove("inner") {
+            entry.extend(inner);
+        }
+        fields.remove("meta");
+        entry.extend(fields);
+
+        match model {
+            AnyActerModel::Task(task) => {
+                let status = if task.is_done() { "done" } else { "open" };
+                entry.insert("status".to_owned(), status.into());
+            }
+            AnyActerModel::Attachment(attachment) => {
+                if let Some((source, filename)) = media_of(&attachment.content) {
+                    if let Some(path) = self
+                        .download(meta.event_id.as_str(), source, filename)
+                        .await
+                    {
+                        entry.insert("media_file".to_owned(), path.into());
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(entry)
+    }
+
+    /// All objects of the room, with comments, attachments, RSVPs and
+    /// tasks added to what they belong to
+    async fn objects(&self, store_models: Vec<AnyActerModel>) -> Result<Vec<JsonValue>> {
+        let mut models: Vec<_> = store_models
+            .into_iter()
+            .filter_map(|m| Some((object_kind(&m)?, m)))
+            .collect();
+        models.sort_by_key(|(_, m)| m.event_meta().origin_server_ts);
+
+        let mut entries = BTreeMap::new();
+        let mut order = Vec::new();
+        for ((obj_type, parent), model) in models.into_iter() {
+            let id = model.event_id().to_string();
+            entries.insert(id.clone(), self.object(obj_type, &model).await?);
+            order.push((id, parent));
+        }
+
+        // latest first, so children are complete when moved to their parent
+        for (id, parent) in order.iter().rev() {
+            let Some((list, parent)) = parent else {
+                continue;
+            };
+            if !entries.contains_key(parent) {
+                continue; // not exported, keep it top level
+            }
+            let Some(entry) = entries.remove(id) else {
+                continue;
+            };
+            let parent = entries.get_mut(parent).expect("checked above");
+            match parent
+                .entry(list.to_owned())
+                .or_insert_with(|| JsonValue::Array(vec![]))
+            {
+                JsonValue::Array(items) => items.insert(0, JsonValue::Object(entry)),
+                _ => unreachable!("lists are arrays"),
+            }
+        }
+        Ok(order
+            .into_iter()
+            .filter_map(|(id, _)| entries.remove(&id))
+            .map(JsonValue::Object)
+            .collect())
+    }
+
+    /// The whole, decrypted history of the room
+    async fn messages(&self, room: &Room) -> Result<Vec<JsonValue>> {
+        let mut messages = Vec::new();
+        let mut msg_options = MessagesOptions::forward().from(None);
+        msg_options.limit = 100u32.into();
+
+        loop {
+            let Messages { end, chunk, .. } = room.messages(msg_options).await?;
+
+            for msg in chunk {
+                let raw = msg.kind.raw();
+                let mut entry: Map<String, JsonValue> = raw.deserialize_as()?;
+                entry.remove("unsigned");
+                if let Ok(AnySyncTimelineEvent::MessageLike(
+                    AnySyncMessageLikeEvent::RoomMessage(SyncMessageLikeEvent::Original(event)),
+                )) = raw.deserialize()
+                {
+                    let media = match event.content.msgtype {
+                        msgtype @ (MessageType::Image(_)
+                        | MessageType::Video(_)
+                        | MessageType::Audio(_)
+                        | MessageType::File(_)) => AttachmentContent::try_from(msgtype).ok(),
+                        _ => None,
+                    };
+                    if let Some((source, filename)) = media.as_ref().and_then(media_of) {
+                        if let Some(path) = self
+                            .download(event.event_id.as_str(), source, filename)
+                            .await
+                        {
+                            entry.insert("media_file".to_owned(), path.into());
+                        }
+                    }
+                }
+                messages.push(JsonValue::Object(entry));
+            }
+
+            if end.is_some() {
+                msg_options = MessagesOptions::forward().from(end.as_deref());
+            } else {
+                trace!(room_id = ?room.room_id(), "Done loading");
+                break;
+            }
+        }
+        Ok(messages)
+    }
+
+    async fn room(&self, room: &Room, models: Vec<AnyActerModel>) -> Result<JsonValue> {
+        info!(room_id = ?room.room_id(), "exporting");
+        let name = room.cached_display_name().map(|n| n.to_string());
+        Ok(json!({
+            "room_id": room.room_id().to_string(),
+            "name": name,
+            "topic": room.topic(),
+            "is_space": room.is_space(),
+            "objects": self.objects(models).await?,
+            "messages": self.messages(room).await?,
+        }))
+    }
+}
+
+impl ExportOpts {
+    pub async fn run(&self) -> Result<()> {
+        let mut client = self.login.client().await?;
+
+        info!(" - Syncing -");
+        let sync_state = client.start_sync();
+
+        let mut is_synced = sync_state.first_synced_rx();
+        while is_synced.next().await != Some(true) {} // let’s wait for it to have synced
+        info!(" - Waiting for history to have synced - ");
+        sync_state.await_has_synced_history().await?;
+
+        let mut rooms: Vec<Room> = vec![client.room_by_id_typed(&self.room)?];
+        if self.include_chats {
+            let space = client.space(self.room.to_string()).await?;
+            for child in space.space_relations().await?.children() {
+                match client.get_room(&child.room_id()) {
+                    Some(room) if !room.is_space() && matches!(room.state(), RoomState::Joined) => {
+                        rooms.push(room)
+                    }
+                    _ => info!(room_id = ?child.room_id(), "skipping child not joined"),
+                }
+            }
+        }
+
+        std::fs::create_dir_all(self.output.join(MEDIA_DIR))?;
+        let exporter = Exporter {
+            client: &client,
+            output: &self.output,
+            skip_media: self.skip_media,
+        };
+        let mut exported = Vec::new();
+        for room in rooms.iter() {
+            let models = room_models(&client, room.room_id()).await?;
+            exported.push(exporter.room(room, models).await?);
+        }
+
+        let archive = json!({
+            "version": "0.1",
+            "exported_at": UtcDateTime::from(SystemTime::now()).to_rfc3339(),
+            "exported_by": client.user_id()?.to_string(),
+            "rooms": exported,
+        });
+        let archive_path = self.output.join("archive.json");
+        std::fs::write(&archive_path, serde_json::to_string_pretty(&archive)?)?;
+        info!("Archive written to {archive_path:?}");
+
+        let index = match self.format {
+            Format::Json => return Ok(()),
+            Format::Markdown => ("index.md", render_markdown(&index_blocks(&archive))),
+            Format::Html => (
+                "index.html",
+                render_html("Acter export", &index_blocks(&archive)),
+            ),
+        };
+        let index_path = self.output.join(index.0);
+        std::fs::write(&index_path, index.1)?;
+        info!("Index written to {index_path:?}");
+        Ok(())
+    }
+}
+
+/// All models of the room in the store
+async fn room_models(client: &acter::api::Client, room_id: &RoomId) -> Result<Vec<AnyActerModel>> {
+    Ok(client
+        .store()
+        .get_list(&IndexKey::RoomModels(room_id.to_owned()))
+        .await
+        .context("reading the objects of the room")?
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(event_id: &str, ts: u64) -> JsonValue {
+        json!({
+            "event_id": event_id,
+            "sender": "@sari:example.org",
+            "origin_server_ts": ts,
+            "room_id": "!space:example.org",
+        })
+    }
+
+    fn belongs_to(event_id: &str) -> JsonValue {
+        json!({ "rel_type": "global.acter.dev.belongs_to", "event_id": event_id })
+    }
+
+    fn model(value: JsonValue) -> AnyActerModel {
+        serde_json::from_value(value).expect("model parses")
+    }
+
+    fn entry(value: JsonValue) -> Map<String, JsonValue> {
+        let JsonValue::Object(entry) = value else {
+            panic!("not an object");
+        };
+        entry
+    }
+
+    #[test]
+    fn summary_picks_the_most_telling_field() {
+        assert_eq!(
+            summary(&entry(json!({ "title": "Plant", "name": "x" }))),
+            "Plant"
+        );
+        assert_eq!(summary(&entry(json!({ "name": "Groceries" }))), "Groceries");
+        assert_eq!(
+            summary(&entry(json!({ "content": { "body": "nice!" } }))),
+            "nice!"
+        );
+        assert_eq!(
+            summary(&entry(
+                json!({ "slides": [{ "body": "first" }, { "body": "second" }] })
+            )),
+            "first"
+        );
+        assert_eq!(
+            summary(&entry(
+                json!({ "status": { "type": "yes" }, "type": "rsvp" })
+            )),
+            "yes"
+        );
+        assert_eq!(summary(&entry(json!({ "type": "story" }))), "story");
+        assert_eq!(summary(&entry(json!({}))), "");
+    }
+
+    #[test]
+    fn markdown_is_escaped_and_nested() {
+        let blocks = [
+            Block::Heading(2, "Garden *club*".to_owned()),
+            Block::Paragraph("<b>weekly</b> #1".to_owned()),
+            Block::Item(0, "[open] Water_plants".to_owned(), None),
+            Block::Item(
+                1,
+                "photo [1]".to_owned(),
+                Some("media/photo.jpg".to_owned()),
+            ),
+        ];
+        assert_eq!(
+            render_markdown(&blocks),
+            "\n## Garden \\*club\\*\n\n\
+             \\<b\\>weekly\\</b\\> \\#1\n\n\
+             - \\[open\\] Water\\_plants\n\
+             \x20 - [photo \\[1\\]](media/photo.jpg)\n"
+        );
+    }
+
+    #[test]
+    fn html_is_escaped_and_lists_are_closed() {
+        let blocks = [
+            Block::Heading(2, "Garden <club>".to_owned()),
+            Block::Item(0, "task".to_owned(), None),
+            Block::Item(1, "comment".to_owned(), None),
+            Block::Item(2, "reply".to_owned(), Some("media/a\"b.jpg".to_owned())),
+            Block::Item(0, "other task".to_owned(), None),
+            Block::Paragraph("done & dusted".to_owned()),
+        ];
+        let html = render_html("Export <1>", &blocks);
+        assert!(html.contains("<title>Export &lt;1&gt;</title>"));
+        assert!(html.contains("<h2>Garden &lt;club&gt;</h2>"));
+        assert!(html.contains(
+            "<ul>\n<li>task</li>\n<ul>\n<li>comment</li>\n<ul>\n\
+             <li><a href=\"media/a&quot;b.jpg\">reply</a></li>\n\
+             </ul>\n</ul>\n<li>other task</li>\n</ul>\n<p>done &amp; dusted</p>"
+        ));
+        assert_eq!(html.matches("<ul>").count(), html.matches("</ul>").count());
+        assert!(html.ends_with("</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn index_groups_objects_and_lists_messages() {
+        let archive = json!({
+            "exported_by": "@sari:example.org",
+            "exported_at": "2026-10-18T10:00:00+00:00",
+            "rooms": [{
+                "room_id": "!space:example.org",
+                "name": "Garden",
+                "topic": "All about plants",
+                "objects": [
+                    {
+                        "type": "task",
+                        "title": "Water plants",
+                        "status": "done",
+                        "assignees": ["@kim:example.org"],
+                        "sender": "@sari:example.org",
+                        "origin_server_ts": 0,
+                        "comments": [{
+                            "type": "comment",
+                            "content": { "body": "did it" },
+                            "sender": "@kim:example.org",
+                            "origin_server_ts": 60_000,
+                        }],
+                    },
+                    { "type": "pin", "title": "Rules", "sender": "@sari:example.org" },
+                ],
+                "messages": [
+                    {
+                        "sender": "@kim:example.org",
+                        "origin_server_ts": 120_000,
+                        "content": { "body": "hello" },
+                    },
+                    { "sender": "@kim:example.org", "content": {} },
+                ],
+            }],
+        });
+        assert_eq!(
+            index_blocks(&archive),
+            [
+                Block::Heading(1, "Acter export".to_owned()),
+                Block::Paragraph(
+                    "Exported by @sari:example.org at 2026-10-18T10:00:00+00:00".to_owned()
+                ),
+                Block::Heading(2, "Garden".to_owned()),
+                Block::Paragraph("All about plants".to_owned()),
+                Block::Paragraph("!space:example.org".to_owned()),
+                Block::Heading(3, "Tasks".to_owned()),
+                Block::Item(
+                    0,
+                    "[done] Water plants (assigned to @kim:example.org) — \
+                     @sari:example.org, 1970-01-01 00:00 UTC"
+                        .to_owned(),
+                    None
+                ),
+                Block::Item(
+                    1,
+                    "did it — @kim:example.org, 1970-01-01 00:01 UTC".to_owned(),
+                    None
+                ),
+                Block::Heading(3, "Pins".to_owned()),
+                Block::Item(0, "Rules — @sari:example.org, ".to_owned(), None),
+                Block::Heading(3, "Chat history".to_owned()),
+                Block::Item(
+                    0,
+                    "@kim:example.org (1970-01-01 00:02 UTC): hello".to_owned(),
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn objects_are_added_to_what_they_belong_to() -> Result<()> {
+        let client = SdkClient::builder()
+            .homeserver_url("http://localhost:8008")
+            .build()
+            .await?;
+        let exporter = Exporter {
+            client: &client,
+            output: Path::new("."),
+            skip_media: true,
+        };
+        let comment = |id: &str, ts: u64, on: &str| {
+            model(json!({ "Comment": {
+                "inner": { "m.relates_to": belongs_to(on), "content": { "body": id } },
+                "meta": meta(id, ts),
+            }}))
+        };
+        let models = vec![
+            comment("$second", 3, "$pin"),
+            model(json!({ "Pin": {
+                "inner": { "title": "Rules" },
+                "meta": meta("$pin", 1),
+            }})),
+            comment("$first", 2, "$pin"),
+            comment("$orphan", 4, "$unknown"),
+            model(json!({ "Task": {
+                "inner": { "title": "Water plants", "m.relates_to": belongs_to("$list") },
+                "meta": meta("$task", 5),
+            }})),
+        ];
+
+        let objects = exporter.objects(models).await?;
+        let ids: Vec<_> = objects.iter().map(|o| o["event_id"].clone()).collect();
+        // not exported parents keep them top level
+        assert_eq!(ids, [json!("$pin"), json!("$orphan"), json!("$task")]);
+        assert_eq!(objects[0]["type"], "pin");
+        assert_eq!(objects[0]["title"], "Rules");
+        let comments: Vec<_> = objects[0]["comments"]
+            .as_array()
+            .expect("comments added")
+            .iter()
+            .map(|c| c["event_id"].clone())
+            .collect();
+        assert_eq!(comments, [json!("$first"), json!("$second")]);
+        assert_eq!(objects[2]["status"], "open");
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use anyhow::Result;
+use clap::Parser;
+use futures::stream::StreamExt;
+use matrix_sdk_base::ruma::OwnedRoomId;
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::config::{LoginConfig, ENV_ROOM};
+
+/// Export the objects of a space as template to recreate them elsewhere
+#[derive(Parser, Debug)]
+pub struct ExportTemplateOpts {
+    #[clap(flatten)]
+    pub login: LoginConfig,
+
+    /// Write the template to this file rather than stdout
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// The space to export
+    #[clap(env = ENV_ROOM)]
+    pub room: OwnedRoomId,
+}
+
+impl ExportTemplateOpts {
+    pub async fn run(&self) -> Result<()> {
+        let mut client = self.login.client().await?;
+
+        info!(" - Syncing -");
+        let sync_state = client.start_sync();
+
+        let mut is_synced = sync_state.first_synced_rx();
+        while is_synced.next().await != Some(true) {} // let’s wait for it to have synced
+        info!(" - Waiting for history to have synced - ");
+        sync_state.await_has_synced_history().await?;
+
+        let space = client.space(self.room.to_string()).await?;
+        let template = space.export_template().await?;
+        match &self.output {
+            Some(path) => {
+                std::fs::write(path, template)?;
+                info!("Template written to {path:?}");
+            }
+            None => println!("{template}"),
+        }
+        Ok(())
+    }
+}
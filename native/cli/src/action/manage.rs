@@ -1,18 +1,118 @@
+use acter::api::{new_join_rule_builder, Space, SyncState};
 use acter_matrix::spaces::CreateSpaceSettingsBuilder;
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{bail, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use dialoguer::{theme::ColorfulTheme, Confirm};
 use futures::stream::StreamExt;
-use matrix_sdk_base::ruma::{OwnedRoomId, RoomId};
+use matrix_sdk_base::ruma::{OwnedRoomId, OwnedUserId, RoomId};
+use std::path::PathBuf;
 use tracing::{info, warn};
 
 use crate::config::LoginConfig;
 
+mod permissions;
+
+use permissions::{
+    feature_event_type, Plan, PolicyKind, PolicyRule, SpacePermissions, REGULAR_LEVELS,
+};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Toml,
+    Json,
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum Action {
     /// List rooms
     CreateOnboardingSpace,
     /// Mark the space as an acter space
     MarkAsActerSpace { room_id: OwnedRoomId },
+    /// Show the permissions and features of the space, in the format `apply` takes
+    ShowPermissions {
+        room_id: OwnedRoomId,
+        #[clap(long, value_enum, default_value_t = Format::Toml)]
+        format: Format,
+    },
+    /// Change the space to match the permissions and features described in
+    /// the TOML (or `.json`) file, after showing what would change
+    Apply {
+        room_id: OwnedRoomId,
+        file: PathBuf,
+        /// Only show what would change
+        #[clap(long)]
+        dry_run: bool,
+        /// Don’t ask before applying
+        #[clap(long, short)]
+        yes: bool,
+    },
+    /// Set a power level, e.g. `ban`, `state-default` or that of a feature like `news`
+    SetPowerLevel {
+        room_id: OwnedRoomId,
+        name: String,
+        /// Leave out to remove the level of a feature
+        level: Option<i32>,
+    },
+    /// Set the power level of a member
+    SetUserPowerLevel {
+        room_id: OwnedRoomId,
+        user_id: OwnedUserId,
+        level: i32,
+    },
+    /// Switch features of the space on or off
+    SetFeatures {
+        room_id: OwnedRoomId,
+        /// `news`, `pins`, `calendar-events`, `tasks` or `stories`
+        #[clap(long)]
+        on: Vec<String>,
+        #[clap(long)]
+        off: Vec<String>,
+    },
+    /// Set who may join: `private`, `public`, `invite`, `knock`,
+    /// `restricted` or `knock_restricted`
+    SetJoinRule {
+        room_id: OwnedRoomId,
+        rule: String,
+        /// Members of this room may join, for the restricted rules
+        #[clap(long = "allow-room")]
+        rooms: Vec<OwnedRoomId>,
+    },
+    /// Set which servers may take part
+    SetServerAcl {
+        room_id: OwnedRoomId,
+        #[clap(long)]
+        allow: Vec<String>,
+        #[clap(long)]
+        deny: Vec<String>,
+        #[clap(long)]
+        allow_ip_literals: bool,
+    },
+    /// Ban rooms, servers or users matching the glob
+    SetPolicyRule {
+        room_id: OwnedRoomId,
+        #[clap(value_enum)]
+        kind: PolicyKindArg,
+        entity: String,
+        #[clap(long)]
+        reason: String,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PolicyKindArg {
+    Room,
+    Server,
+    User,
+}
+
+impl From<PolicyKindArg> for PolicyKind {
+    fn from(value: PolicyKindArg) -> Self {
+        match value {
+            PolicyKindArg::Room => PolicyKind::Room,
+            PolicyKindArg::Server => PolicyKind::Server,
+            PolicyKindArg::User => PolicyKind::User,
+        }
+    }
 }
 
 /// Posting a news item to a given room
@@ -30,9 +130,181 @@ impl Manage {
         match self.action {
             Action::CreateOnboardingSpace => self.run_create_onboarding_space().await,
             Action::MarkAsActerSpace { ref room_id } => self.run_marking_space(room_id).await,
+            Action::ShowPermissions {
+                ref room_id,
+                format,
+            } => self.run_show_permissions(room_id, format).await,
+            Action::Apply {
+                ref room_id,
+                ref file,
+                dry_run,
+                yes,
+            } => {
+                let wanted = SpacePermissions::from_file(file)?;
+                self.run_apply(room_id, &wanted, dry_run, yes).await
+            }
+            Action::SetPowerLevel {
+                ref room_id,
+                ref name,
+                level,
+            } => self.run_set_power_level(room_id, name, level).await,
+            Action::SetUserPowerLevel {
+                ref room_id,
+                ref user_id,
+                level,
+            } => {
+                let (_sync, space) = self.synced_space(room_id).await?;
+                space.update_power_level(user_id.to_string(), level).await?;
+                info!("Power level of {user_id} set to {level}");
+                Ok(())
+            }
+            Action::SetFeatures {
+                ref room_id,
+                ref on,
+                ref off,
+            } => {
+                let features = on
+                    .iter()
+                    .map(|f| (f.clone(), true))
+                    .chain(off.iter().map(|f| (f.clone(), false)))
+                    .collect();
+                let wanted = SpacePermissions {
+                    features,
+                    ..Default::default()
+                };
+                self.run_apply(room_id, &wanted, false, true).await
+            }
+            Action::SetJoinRule {
+                ref room_id,
+                ref rule,
+                ref rooms,
+            } => {
+                let (_sync, space) = self.synced_space(room_id).await?;
+                let mut builder = new_join_rule_builder();
+                builder.join_rule(rule.clone());
+                for room in rooms {
+                    builder.add_room(room.to_string());
+                }
+                space.set_join_rule(Box::new(builder)).await?;
+                info!("Join rule set to {rule}");
+                Ok(())
+            }
+            Action::SetServerAcl {
+                ref room_id,
+                ref allow,
+                ref deny,
+                allow_ip_literals,
+            } => {
+                let (_sync, space) = self.synced_space(room_id).await?;
+                let event_id = space
+                    .set_server_acl(
+                        allow_ip_literals,
+                        serde_json::to_string(allow)?,
+                        serde_json::to_string(deny)?,
+                    )
+                    .await?;
+                info!("Server ACL set: {event_id}");
+                Ok(())
+            }
+            Action::SetPolicyRule {
+                ref room_id,
+                kind,
+                ref entity,
+                ref reason,
+            } => {
+                let wanted = SpacePermissions {
+                    policy_rules: vec![PolicyRule {
+                        kind: kind.into(),
+                        entity: entity.clone(),
+                        reason: reason.clone(),
+                    }],
+                    ..Default::default()
+                };
+                self.run_apply(room_id, &wanted, false, true).await
+            }
         }
     }
 
+    async fn synced_space(&self, room_id: &RoomId) -> Result<(SyncState, Space)> {
+        let mut client = self.login.client().await?;
+        info!(" - Syncing -");
+        let sync_state = client.start_sync();
+
+        let mut is_synced = sync_state.first_synced_rx();
+        while is_synced.next().await != Some(true) {} // let’s wait for it to have synced
+        info!(" - First Sync finished - ");
+
+        let space = client.space(room_id.to_string()).await?;
+        Ok((sync_state, space))
+    }
+
+    async fn run_show_permissions(&self, room_id: &RoomId, format: Format) -> Result<()> {
+        let (_sync, space) = self.synced_space(room_id).await?;
+        let current = SpacePermissions::current(&space).await?;
+        match format {
+            Format::Toml => print!("{}", toml::to_string_pretty(&current)?),
+            Format::Json => println!("{}", serde_json::to_string_pretty(&current)?),
+        }
+        Ok(())
+    }
+
+    async fn run_apply(
+        &self,
+        room_id: &RoomId,
+        wanted: &SpacePermissions,
+        dry_run: bool,
+        yes: bool,
+    ) -> Result<()> {
+        let (_sync, space) = self.synced_space(room_id).await?;
+        let plan = Plan::new(&space, wanted).await?;
+        if plan.is_empty() {
+            println!("{room_id} is up to date");
+            return Ok(());
+        }
+        println!("Changes to {room_id}:");
+        for change in &plan.changes {
+            println!("  {change}");
+        }
+        if dry_run {
+            return Ok(());
+        }
+        if !yes
+            && !Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Apply these changes?")
+                .interact()?
+        {
+            return Ok(());
+        }
+        plan.apply(&space).await?;
+        info!("Changes applied");
+        Ok(())
+    }
+
+    async fn run_set_power_level(
+        &self,
+        room_id: &RoomId,
+        name: &str,
+        level: Option<i32>,
+    ) -> Result<()> {
+        let (_sync, space) = self.synced_space(room_id).await?;
+        if REGULAR_LEVELS.contains(&name) {
+            let Some(level) = level else {
+                bail!("`{name}` can’t be removed, only changed");
+            };
+            space
+                .update_regular_power_levels(name.replace('-', "_"), level)
+                .await?;
+        } else if let Some(event_type) = feature_event_type(name) {
+            space
+                .update_feature_power_levels(event_type.to_owned(), level)
+                .await?;
+        } else {
+            bail!("unknown power level `{name}`");
+        }
+        info!("Power level {name} set to {level:?}");
+        Ok(())
+    }
+
     async fn run_marking_space(&self, room_id: &RoomId) -> Result<()> {
         let mut client = self.login.client().await?;
         info!(" - Syncing -");
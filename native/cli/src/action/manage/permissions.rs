@@ -0,0 +1,675 @@
+use acter::api::{new_join_rule_builder, SimpleOnOffSetting, SimpleSettingWithTurnOff, Space};
+use acter_matrix::events::{
+    attachments::AttachmentEventContent,
+    calendar::CalendarEventEventContent,
+    comments::CommentEventContent,
+    news::NewsEntryEventContent,
+    pins::PinEventContent,
+    rsvp::RsvpEventContent,
+    stories::StoryEventContent,
+    tasks::{TaskEventContent, TaskListEventContent},
+};
+use anyhow::{bail, Context, Result};
+use matrix_sdk_base::ruma::{
+    events::{
+        policy::rule::{
+            room::PolicyRuleRoomEventContent, server::PolicyRuleServerEventContent,
+            user::PolicyRuleUserEventContent, Recommendation,
+        },
+        room::{
+            power_levels::{RoomPowerLevels, RoomPowerLevelsEventContent},
+            server_acl::RoomServerAclEventContent,
+        },
+        StaticEventContent, TimelineEventType,
+    },
+    Int, OwnedUserId,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Display, time::Duration};
+
+/// The features of a space that can be switched on and off
+pub const FEATURES: [&str; 5] = ["news", "pins", "calendar-events", "tasks", "stories"];
+
+/// The power levels every room has, next to those of the features
+pub const REGULAR_LEVELS: [&str; 7] = [
+    "ban",
+    "kick",
+    "invite",
+    "redact",
+    "events-default",
+    "state-default",
+    "users-default",
+];
+
+/// The power levels of what can be posted in a space
+pub const FEATURE_LEVELS: [&str; 9] = [
+    "news",
+    "stories",
+    "calendar-events",
+    "rsvp",
+    "pins",
+    "task-lists",
+    "tasks",
+    "comments",
+    "attachments",
+];
+
+/// The event type whose power level the feature `name` is about
+pub fn feature_event_type(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "news" => <NewsEntryEventContent as StaticEventContent>::TYPE,
+        "stories" => <StoryEventContent as StaticEventContent>::TYPE,
+        "calendar-events" => <CalendarEventEventContent as StaticEventContent>::TYPE,
+        "rsvp" => <RsvpEventContent as StaticEventContent>::TYPE,
+        "pins" => <PinEventContent as StaticEventContent>::TYPE,
+        "task-lists" => <TaskListEventContent as StaticEventContent>::TYPE,
+        "tasks" => <TaskEventContent as StaticEventContent>::TYPE,
+        "comments" => <CommentEventContent as StaticEventContent>::TYPE,
+        "attachments" => <AttachmentEventContent as StaticEventContent>::TYPE,
+        _ => return None,
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyKind {
+    Room,
+    Server,
+    User,
+}
+
+/// Ban `entity`, a glob of rooms, servers or users
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PolicyRule {
+    pub kind: PolicyKind,
+    pub entity: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JoinRuleSpec {
+    /// `private`, `public`, `invite`, `knock`, `restricted` or `knock_restricted`
+    pub rule: String,
+    /// whose members may join, for the restricted ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rooms: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ServerAcl {
+    #[serde(default)]
+    pub allow_ip_literals: bool,
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+/// What permissions and features a space should have. Everything left out
+/// stays as it is.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SpacePermissions {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub features: BTreeMap<String, bool>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub power_levels: BTreeMap<String, i64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub users: BTreeMap<OwnedUserId, i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub join_rule: Option<JoinRuleSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_acl: Option<ServerAcl>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policy_rules: Vec<PolicyRule>,
+}
+
+impl SpacePermissions {
+    /// Parse from TOML, or from JSON if the file name says so
+    pub fn from_file(path: &std::path::Path) -> Result<Self> {
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let spec: SpacePermissions = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&content)?
+        } else {
+            toml::from_str(&content)?
+        };
+        spec.check()?;
+        Ok(spec)
+    }
+
+    fn check(&self) -> Result<()> {
+        for name in self.features.keys() {
+            if !FEATURES.contains(&name.as_str()) {
+                bail!("unknown feature `{name}`, expected one of {FEATURES:?}");
+            }
+        }
+        for name in self.power_levels.keys() {
+            if !REGULAR_LEVELS.contains(&name.as_str()) && feature_event_type(name).is_none() {
+                bail!("unknown power level `{name}`");
+            }
+        }
+        Ok(())
+    }
+
+    /// How the space is set up right now
+    pub async fn current(space: &Space) -> Result<Self> {
+        let settings = space.app_settings().await?;
+        let features = BTreeMap::from([
+            ("news".to_owned(), settings.news().active()),
+            ("pins".to_owned(), settings.pins().active()),
+            ("calendar-events".to_owned(), settings.events().active()),
+            ("tasks".to_owned(), settings.tasks().active()),
+            ("stories".to_owned(), settings.stories().active()),
+        ]);
+
+        let levels = power_levels(space).await?;
+        let power_levels = REGULAR_LEVELS
+            .iter()
+            .chain(FEATURE_LEVELS.iter())
+            .filter_map(|name| level_of(&levels, name).map(|l| (name.to_string(), l)))
+            .collect();
+        let users = levels
+            .users
+            .iter()
+            .map(|(user, level)| (user.clone(), i64::from(*level)))
+            .collect();
+
+        Ok(SpacePermissions {
+            features,
+            power_levels,
+            users,
+            join_rule: Some(JoinRuleSpec {
+                rule: space.join_rule_str(),
+                rooms: space.restricted_room_ids_str(),
+            }),
+            server_acl: server_acl(space).await?,
+            policy_rules: Vec::new(), // there is no way to list them
+        })
+    }
+}
+
+async fn power_levels(space: &Space) -> Result<RoomPowerLevels> {
+    let raw = space
+        .get_state_event_static::<RoomPowerLevelsEventContent>()
+        .await?
+        .context("Power levels not set up")?;
+    Ok(raw.deserialize()?.power_levels())
+}
+
+async fn server_acl(space: &Space) -> Result<Option<ServerAcl>> {
+    let Some(raw) = space
+        .get_state_event_static::<RoomServerAclEventContent>()
+        .await?
+    else {
+        return Ok(None);
+    };
+    Ok(raw
+        .deserialize()?
+        .original_content()
+        .map(|content| ServerAcl {
+            allow_ip_literals: content.allow_ip_literals,
+            allow: content.allow.clone(),
+            deny: content.deny.clone(),
+        }))
+}
+
+/// The reason the entity is currently banned for, if it is
+async fn policy_reason(space: &Space, rule: &PolicyRule) -> Result<Option<String>> {
+    let state_key = format!("rule:{}", rule.entity);
+    let content = match rule.kind {
+        PolicyKind::Room => space
+            .get_state_event_static_for_key::<PolicyRuleRoomEventContent, _>(&state_key)
+            .await?
+            .map(|raw| raw.deserialize())
+            .transpose()?
+            .and_then(|ev| ev.original_content().map(|c| c.0.clone())),
+        PolicyKind::Server => space
+            .get_state_event_static_for_key::<PolicyRuleServerEventContent, _>(&state_key)
+            .await?
+            .map(|raw| raw.deserialize())
+            .transpose()?
+            .and_then(|ev| ev.original_content().map(|c| c.0.clone())),
+        PolicyKind::User => space
+            .get_state_event_static_for_key::<PolicyRuleUserEventContent, _>(&state_key)
+            .await?
+            .map(|raw| raw.deserialize())
+            .transpose()?
+            .and_then(|ev| ev.original_content().map(|c| c.0.clone())),
+    };
+    Ok(content
+        .filter(|c| c.recommendation == Recommendation::Ban)
+        .map(|c| c.reason))
+}
+
+fn level_of(levels: &RoomPowerLevels, name: &str) -> Option<i64> {
+    let level = match name {
+        "ban" => levels.ban,
+        "kick" => levels.kick,
+        "invite" => levels.invite,
+        "redact" => levels.redact,
+        "events-default" => levels.events_default,
+        "state-default" => levels.state_default,
+        "users-default" => levels.users_default,
+        feature => *levels
+            .events
+            .get(&TimelineEventType::from(feature_event_type(feature)?))?,
+    };
+    Some(level.into())
+}
+
+fn set_level(levels: &mut RoomPowerLevels, name: &str, level: i64) -> Result<()> {
+    let level = Int::new(level).context("power level out of range")?;
+    match name {
+        "ban" => levels.ban = level,
+        "kick" => levels.kick = level,
+        "invite" => levels.invite = level,
+        "redact" => levels.redact = level,
+        "events-default" => levels.events_default = level,
+        "state-default" => levels.state_default = level,
+        "users-default" => levels.users_default = level,
+        feature => {
+            let Some(event_type) = feature_event_type(feature) else {
+                bail!("unknown power level `{feature}`");
+            };
+            levels.events.insert(event_type.into(), level);
+        }
+    }
+    Ok(())
+}
+
+/// A single power level to change, through the helpers of the space
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LevelChange {
+    /// one of the [`REGULAR_LEVELS`] or [`FEATURE_LEVELS`]
+    Named(String, i32),
+    User(OwnedUserId, i32),
+}
+
+impl LevelChange {
+    fn is_in(&self, levels: &RoomPowerLevels) -> bool {
+        match self {
+            LevelChange::Named(name, level) => level_of(levels, name) == Some(i64::from(*level)),
+            LevelChange::User(user_id, level) => {
+                levels.users.get(user_id).map(|l| i64::from(*l)) == Some(i64::from(*level))
+            }
+        }
+    }
+
+    async fn apply(&self, space: &Space) -> Result<()> {
+        match self {
+            LevelChange::Named(name, level) if REGULAR_LEVELS.contains(&name.as_str()) => {
+                space
+                    .update_regular_power_levels(name.replace('-', "_"), *level)
+                    .await?;
+            }
+            LevelChange::Named(name, level) => {
+                let event_type = feature_event_type(name).context("checked on planning")?;
+                space
+                    .update_feature_power_levels(event_type.to_owned(), Some(*level))
+                    .await?;
+            }
+            LevelChange::User(user_id, level) => {
+                space
+                    .update_power_level(user_id.to_string(), *level)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The changes of the features from `current` to `wanted`, described in
+/// `changes`
+fn feature_changes(
+    current: &BTreeMap<String, bool>,
+    wanted: &BTreeMap<String, bool>,
+    changes: &mut Vec<String>,
+) -> BTreeMap<String, bool> {
+    let mut features = BTreeMap::new();
+    for (name, active) in wanted {
+        let was = current.get(name).copied();
+        if was != Some(*active) {
+            changes.push(format!("feature {name}: {} → {active}", show(was)));
+            features.insert(name.clone(), *active);
+        }
+    }
+    features
+}
+
+/// The changes of the power levels from `levels` to `wanted`, described in
+/// `changes`. `levels` ends up as they will be.
+fn level_changes(
+    levels: &mut RoomPowerLevels,
+    wanted: &SpacePermissions,
+    changes: &mut Vec<String>,
+) -> Result<Vec<LevelChange>> {
+    let as_i32 = |level: i64| i32::try_from(level).context("power level out of range");
+    let mut level_changes = Vec::new();
+    for (name, level) in &wanted.power_levels {
+        let was = level_of(levels, name);
+        if was != Some(*level) {
+            changes.push(format!("power level {name}: {} → {level}", show(was)));
+            set_level(levels, name, *level)?;
+            level_changes.push(LevelChange::Named(name.clone(), as_i32(*level)?));
+        }
+    }
+    for (user_id, level) in &wanted.users {
+        let was = levels.users.get(user_id).map(|l| i64::from(*l));
+        if was != Some(*level) {
+            changes.push(format!("user {user_id}: {} → {level}", show(was)));
+            let level = as_i32(*level)?;
+            levels.users.insert(user_id.clone(), Int::from(level));
+            level_changes.push(LevelChange::User(user_id.clone(), level));
+        }
+    }
+    Ok(level_changes)
+}
+
+/// How often and how many times to look whether a sent power level change
+/// made it into the local state
+const LEVEL_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+const LEVEL_CHECKS: usize = 60;
+
+fn show<T: Display>(value: Option<T>) -> String {
+    value
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "-".to_owned())
+}
+
+/// The changes needed to get a space to the wanted permissions
+pub struct Plan {
+    pub changes: Vec<String>,
+    features: BTreeMap<String, bool>,
+    power_levels: Vec<LevelChange>,
+    join_rule: Option<JoinRuleSpec>,
+    server_acl: Option<ServerAcl>,
+    policy_rules: Vec<PolicyRule>,
+}
+
+impl Plan {
+    pub async fn new(space: &Space, wanted: &SpacePermissions) -> Result<Self> {
+        wanted.check()?;
+        let current = SpacePermissions::current(space).await?;
+        let mut changes = Vec::new();
+
+        let features = feature_changes(&current.features, &wanted.features, &mut changes);
+        let mut levels = power_levels(space).await?;
+        let power_levels = level_changes(&mut levels, wanted, &mut changes)?;
+
+        let join_rule = wanted
+            .join_rule
+            .as_ref()
+            .filter(|rule| current.join_rule.as_ref() != Some(*rule))
+            .cloned();
+        if let Some(rule) = &join_rule {
+            let was = current.join_rule.as_ref().map(|r| r.rule.as_str());
+            changes.push(format!("join rule: {} → {}", show(was), rule.rule));
+            if !rule.rooms.is_empty() {
+                changes.push(format!("join rule rooms: {}", rule.rooms.join(", ")));
+            }
+        }
+
+        let server_acl = wanted
+            .server_acl
+            .as_ref()
+            .filter(|acl| current.server_acl.as_ref() != Some(*acl))
+            .cloned();
+        if let Some(acl) = &server_acl {
+            changes.push(format!(
+                "server acl: allow [{}], deny [{}], ip literals {}",
+                acl.allow.join(", "),
+                acl.deny.join(", "),
+                acl.allow_ip_literals
+            ));
+        }
+
+        let mut policy_rules = Vec::new();
+        for rule in &wanted.policy_rules {
+            let was = policy_reason(space, rule).await?;
+            if was.as_ref() != Some(&rule.reason) {
+                changes.push(format!(
+                    "ban {:?} {}: {} → {}",
+                    rule.kind,
+                    rule.entity,
+                    show(was),
+                    rule.reason
+                ));
+                policy_rules.push(rule.clone());
+            }
+        }
+
+        Ok(Plan {
+            changes,
+            features,
+            power_levels,
+            join_rule,
+            server_acl,
+            policy_rules,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub async fn apply(self, space: &Space) -> Result<()> {
+        if !self.features.is_empty() {
+            let settings = space.app_settings().await?;
+            let mut builder = settings.update_builder();
+            for (name, active) in self.features {
+                let turn_off = if active {
+                    SimpleSettingWithTurnOff::on()
+                } else {
+                    SimpleSettingWithTurnOff::off()
+                }
+                .map(Box::new);
+                let on_off = if active {
+                    SimpleOnOffSetting::on()
+                } else {
+                    SimpleOnOffSetting::off()
+                }
+                .map(Box::new);
+                match name.as_str() {
+                    "news" => builder.news(turn_off),
+                    "pins" => builder.pins(turn_off),
+                    "calendar-events" => builder.events(turn_off),
+                    "tasks" => builder.tasks(on_off),
+                    // keeps the configured expiry
+                    "stories" => builder.stories(Some(Box::new(
+                        settings.stories().updater().active(active).build()?,
+                    ))),
+                    _ => unreachable!("features are checked on parsing"),
+                }
+            }
+            space.update_app_settings(Box::new(builder)).await?;
+        }
+
+        for change in self.power_levels {
+            change.apply(space).await?;
+            // the helpers change what is in the local state, which only
+            // updates after the next sync
+            let mut checks = 0;
+            while !change.is_in(&power_levels(space).await?) {
+                checks += 1;
+                if checks > LEVEL_CHECKS {
+                    bail!("{change:?} didn’t sync back in time");
+                }
+                tokio::time::sleep(LEVEL_CHECK_INTERVAL).await;
+            }
+        }
+
+        if let Some(JoinRuleSpec { rule, rooms }) = self.join_rule {
+            let mut builder = new_join_rule_builder();
+            builder.join_rule(rule);
+            for room in rooms {
+                builder.add_room(room);
+            }
+            space.set_join_rule(Box::new(builder)).await?;
+        }
+
+        if let Some(acl) = self.server_acl {
+            space
+                .set_server_acl(
+                    acl.allow_ip_literals,
+                    serde_json::to_string(&acl.allow)?,
+                    serde_json::to_string(&acl.deny)?,
+                )
+                .await?;
+        }
+
+        for PolicyRule {
+            kind,
+            entity,
+            reason,
+        } in self.policy_rules
+        {
+            match kind {
+                PolicyKind::Room => space.set_policy_rule_room(entity, reason).await?,
+                PolicyKind::Server => space.set_policy_rule_server(entity, reason).await?,
+                PolicyKind::User => space.set_policy_rule_user(entity, reason).await?,
+            };
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix_sdk_base::ruma::owned_user_id;
+
+    fn levels() -> RoomPowerLevels {
+        RoomPowerLevels::from(RoomPowerLevelsEventContent::new())
+    }
+
+    #[test]
+    fn parses_and_checks_permissions() -> Result<()> {
+        let spec: SpacePermissions = toml::from_str(
+            r#"
+            [features]
+            news = false
+            tasks = true
+
+            [power-levels]
+            kick = 75
+            comments = 10
+
+            [users]
+            "@sari:example.org" = 100
+
+            [join-rule]
+            rule = "restricted"
+            rooms = ["!parent:example.org"]
+
+            [server-acl]
+            deny = ["evil.example.org"]
+
+            [[policy-rules]]
+            kind = "user"
+            entity = "@spam*:example.org"
+            reason = "spam"
+            "#,
+        )?;
+        spec.check()?;
+        assert_eq!(spec.features.get("news"), Some(&false));
+        assert_eq!(spec.power_levels.get("comments"), Some(&10));
+        assert_eq!(
+            spec.users.get(&owned_user_id!("@sari:example.org")),
+            Some(&100)
+        );
+        assert_eq!(
+            spec.join_rule.as_ref().map(|r| r.rule.as_str()),
+            Some("restricted")
+        );
+        let acl = spec.server_acl.as_ref().expect("acl parsed");
+        assert!(!acl.allow_ip_literals);
+        assert!(acl.allow.is_empty());
+        assert_eq!(spec.policy_rules[0].kind, PolicyKind::User);
+
+        // the same back from JSON, as written by `show`
+        let json: SpacePermissions = serde_json::from_str(&serde_json::to_string(&spec)?)?;
+        assert_eq!(json.power_levels, spec.power_levels);
+
+        let unknown_feature: SpacePermissions = toml::from_str("[features]\nchat = true")?;
+        assert!(unknown_feature.check().is_err());
+        let unknown_level: SpacePermissions = toml::from_str("[power-levels]\nchat = 50")?;
+        assert!(unknown_level.check().is_err());
+        assert!(toml::from_str::<SpacePermissions>("colour = \"red\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn levels_by_name() -> Result<()> {
+        let mut levels = levels();
+        assert_eq!(level_of(&levels, "kick"), Some(50));
+        assert_eq!(level_of(&levels, "users-default"), Some(0));
+        assert_eq!(level_of(&levels, "tasks"), None, "not set explicitly");
+        assert_eq!(level_of(&levels, "unknown"), None);
+
+        set_level(&mut levels, "kick", 75)?;
+        set_level(&mut levels, "events-default", 10)?;
+        set_level(&mut levels, "tasks", 20)?;
+        assert_eq!(level_of(&levels, "kick"), Some(75));
+        assert_eq!(level_of(&levels, "events-default"), Some(10));
+        assert_eq!(level_of(&levels, "tasks"), Some(20));
+        assert_eq!(
+            levels
+                .events
+                .get(&TimelineEventType::from(TaskEventContent::TYPE)),
+            Some(&Int::from(20))
+        );
+
+        assert!(set_level(&mut levels, "unknown", 10).is_err());
+        assert!(set_level(&mut levels, "ban", i64::MAX).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn plans_only_what_differs() -> Result<()> {
+        let mut changes = Vec::new();
+        let current = BTreeMap::from([("news".to_owned(), true), ("pins".to_owned(), false)]);
+        let wanted = BTreeMap::from([("news".to_owned(), true), ("pins".to_owned(), true)]);
+        let features = feature_changes(&current, &wanted, &mut changes);
+        assert_eq!(features, BTreeMap::from([("pins".to_owned(), true)]));
+        assert_eq!(changes, ["feature pins: false → true"]);
+
+        let sari = owned_user_id!("@sari:example.org");
+        let kim = owned_user_id!("@kim:example.org");
+        let mut levels = levels();
+        levels.users.insert(sari.clone(), Int::from(100));
+        let wanted = SpacePermissions {
+            power_levels: BTreeMap::from([("kick".to_owned(), 50), ("pins".to_owned(), 25)]),
+            users: BTreeMap::from([(sari.clone(), 100), (kim.clone(), 50)]),
+            ..Default::default()
+        };
+        let mut changes = Vec::new();
+        let planned = level_changes(&mut levels, &wanted, &mut changes)?;
+        assert_eq!(
+            planned,
+            [
+                LevelChange::Named("pins".to_owned(), 25),
+                LevelChange::User(kim.clone(), 50),
+            ]
+        );
+        assert_eq!(
+            changes,
+            ["power level pins: - → 25", "user @kim:example.org: - → 50"]
+        );
+        assert!(planned.iter().all(|change| change.is_in(&levels)));
+        assert!(!LevelChange::User(sari, 50).is_in(&levels));
+
+        // nothing left once they are applied
+        let mut changes = Vec::new();
+        assert!(level_changes(&mut levels, &wanted, &mut changes)?.is_empty());
+        assert!(changes.is_empty());
+
+        let too_high = SpacePermissions {
+            users: BTreeMap::from([(kim, i64::from(i32::MAX) + 1)]),
+            ..Default::default()
+        };
+        assert!(level_changes(&mut levels, &too_high, &mut Vec::new()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,393 @@
+use acter::api::{Client, CommentsManager};
+use acter_matrix::models::AnyActerModel;
+use anyhow::{bail, Result};
+use chrono::{Datelike, NaiveDate};
+use clap::{Parser, Subcommand, ValueEnum};
+use futures::stream::StreamExt;
+use matrix_sdk_base::ruma::{OwnedEventId, OwnedRoomId};
+use serde_json::json;
+use tracing::info;
+
+use crate::config::{LoginConfig, ENV_ROOM};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RsvpStatus {
+    Yes,
+    No,
+    Maybe,
+}
+
+impl RsvpStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RsvpStatus::Yes => "yes",
+            RsvpStatus::No => "no",
+            RsvpStatus::Maybe => "maybe",
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Action {
+    /// Create a task list in the space
+    CreateTaskList {
+        #[clap(env = ENV_ROOM)]
+        room: OwnedRoomId,
+        #[clap(long)]
+        name: String,
+        #[clap(long)]
+        description: Option<String>,
+    },
+    /// Change the name or description of a task list
+    UpdateTaskList {
+        task_list_id: OwnedEventId,
+        #[clap(long)]
+        name: Option<String>,
+        #[clap(long)]
+        description: Option<String>,
+    },
+    /// Create a task in the task list
+    CreateTask {
+        task_list_id: OwnedEventId,
+        #[clap(long)]
+        title: String,
+        #[clap(long)]
+        description: Option<String>,
+        /// as `YYYY-MM-DD`
+        #[clap(long)]
+        due: Option<String>,
+        /// Assign the task to yourself
+        #[clap(long)]
+        assign_self: bool,
+    },
+    /// Change the title, description, due date or progress of a task
+    UpdateTask {
+        task_id: OwnedEventId,
+        #[clap(long)]
+        title: Option<String>,
+        #[clap(long)]
+        description: Option<String>,
+        /// as `YYYY-MM-DD`
+        #[clap(long)]
+        due: Option<String>,
+        /// in percent, 100 closes the task
+        #[clap(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+        progress: Option<u8>,
+    },
+    /// Mark the task as done
+    CloseTask { task_id: OwnedEventId },
+    /// Mark the task as not done
+    ReopenTask { task_id: OwnedEventId },
+    /// Create a pin in the space
+    CreatePin {
+        #[clap(env = ENV_ROOM)]
+        room: OwnedRoomId,
+        #[clap(long)]
+        title: String,
+        #[clap(long)]
+        url: Option<String>,
+        /// in markdown
+        #[clap(long)]
+        content: Option<String>,
+    },
+    /// Create a calendar event in the space
+    CreateEvent {
+        #[clap(env = ENV_ROOM)]
+        room: OwnedRoomId,
+        #[clap(long)]
+        title: String,
+        /// as RFC 3339
+        #[clap(long)]
+        start: String,
+        /// as RFC 3339
+        #[clap(long)]
+        end: String,
+        #[clap(long)]
+        description: Option<String>,
+    },
+    /// Post a news entry with a text slide to the space
+    PostNews {
+        #[clap(env = ENV_ROOM)]
+        room: OwnedRoomId,
+        /// in markdown
+        #[clap(long)]
+        text: String,
+    },
+    /// Comment on a task list, task, pin, calendar event, news entry or story
+    Comment {
+        object_id: OwnedEventId,
+        #[clap(long)]
+        text: String,
+    },
+    /// Respond to a calendar event
+    Rsvp {
+        event_id: OwnedEventId,
+        #[clap(long, value_enum)]
+        status: RsvpStatus,
+        /// Only for this occurrence of a recurring event, as RFC 3339
+        #[clap(long)]
+        occurrence: Option<String>,
+    },
+}
+
+/// Create and change objects non-interactively, printing the result as JSON
+#[derive(Parser, Debug)]
+pub struct Objects {
+    #[clap(flatten)]
+    pub login: LoginConfig,
+
+    /// Seconds to wait for referenced objects to be synced
+    #[clap(long, default_value_t = 30)]
+    pub timeout: u8,
+
+    #[clap(subcommand)]
+    pub action: Action,
+}
+
+/// Split a `YYYY-MM-DD` date into its parts
+fn parse_date(date: &str) -> Result<(i32, u32, u32)> {
+    let Ok(date) = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d") else {
+        bail!("`{date}` is not a valid YYYY-MM-DD date");
+    };
+    Ok((date.year(), date.month(), date.day()))
+}
+
+impl Objects {
+    pub async fn run(&self) -> Result<()> {
+        let mut client = self.login.client().await?;
+        info!(" - Syncing -");
+        let sync_state = client.start_sync();
+
+        let mut is_synced = sync_state.first_synced_rx();
+        while is_synced.next().await != Some(true) {} // let’s wait for it to have synced
+        info!(" - First Sync finished - ");
+
+        let timeout = Some(self.timeout);
+        let output = match &self.action {
+            Action::CreateTaskList {
+                room,
+                name,
+                description,
+            } => {
+                let space = client.space(room.to_string()).await?;
+                let mut draft = space.task_list_draft()?;
+                draft.name(name.clone());
+                if let Some(description) = description {
+                    draft.description_text(description.clone());
+                }
+                let event_id = draft.send().await?;
+                json!({ "type": "task-list", "event_id": event_id, "room_id": room })
+            }
+            Action::UpdateTaskList {
+                task_list_id,
+                name,
+                description,
+            } => {
+                let task_list = client.task_list(task_list_id.to_string(), timeout).await?;
+                let mut update = task_list.update_builder()?;
+                if let Some(name) = name {
+                    update.name(name.clone());
+                }
+                if let Some(description) = description {
+                    update.description_text(description.clone());
+                }
+                let event_id = update.send().await?;
+                json!({ "type": "task-list-update", "event_id": event_id, "task_list_id": task_list_id })
+            }
+            Action::CreateTask {
+                task_list_id,
+                title,
+                description,
+                due,
+                assign_self,
+            } => {
+                let task_list = client.task_list(task_list_id.to_string(), timeout).await?;
+                let mut draft = task_list.task_builder()?;
+                draft.title(title.clone());
+                if let Some(description) = description {
+                    draft.description_text(description.clone());
+                }
+                if let Some(due) = due {
+                    let (year, month, day) = parse_date(due)?;
+                    draft.due_date(year, month, day);
+                }
+                let event_id = draft.send().await?;
+                if *assign_self {
+                    let task = client.wait_for_task(event_id.to_string(), timeout).await?;
+                    task.assign_self().await?;
+                }
+                json!({ "type": "task", "event_id": event_id, "task_list_id": task_list_id })
+            }
+            Action::UpdateTask {
+                task_id,
+                title,
+                description,
+                due,
+                progress,
+            } => {
+                let task = client.wait_for_task(task_id.to_string(), timeout).await?;
+                let mut update = task.update_builder()?;
+                if let Some(title) = title {
+                    update.title(title.clone());
+                }
+                if let Some(description) = description {
+                    update.description_text(description.clone());
+                }
+                if let Some(due) = due {
+                    let (year, month, day) = parse_date(due)?;
+                    update.due_date(year, month, day);
+                }
+                if let Some(progress) = progress {
+                    update.progress_percent(*progress);
+                }
+                let event_id = update.send().await?;
+                json!({ "type": "task-update", "event_id": event_id, "task_id": task_id })
+            }
+            Action::CloseTask { task_id } | Action::ReopenTask { task_id } => {
+                let task = client.wait_for_task(task_id.to_string(), timeout).await?;
+                let mut update = task.update_builder()?;
+                let done = matches!(self.action, Action::CloseTask { .. });
+                if done {
+                    update.mark_done();
+                } else {
+                    update.mark_undone();
+                }
+                let event_id = update.send().await?;
+                json!({ "type": "task-update", "event_id": event_id, "task_id": task_id, "done": done })
+            }
+            Action::CreatePin {
+                room,
+                title,
+                url,
+                content,
+            } => {
+                let space = client.space(room.to_string()).await?;
+                let mut draft = space.pin_draft()?;
+                draft.title(title.clone());
+                if let Some(url) = url {
+                    draft.url(url.clone());
+                }
+                if let Some(content) = content {
+                    draft.content_markdown(content.clone());
+                }
+                let event_id = draft.send().await?;
+                json!({ "type": "pin", "event_id": event_id, "room_id": room })
+            }
+            Action::CreateEvent {
+                room,
+                title,
+                start,
+                end,
+                description,
+            } => {
+                let space = client.space(room.to_string()).await?;
+                let mut draft = space.calendar_event_draft()?;
+                draft
+                    .title(title.clone())
+                    .utc_start_from_rfc3339(start.clone())?
+                    .utc_end_from_rfc3339(end.clone())?;
+                if let Some(description) = description {
+                    draft.description_text(description.clone());
+                }
+                let event_id = draft.send().await?;
+                json!({ "type": "calendar-event", "event_id": event_id, "room_id": room })
+            }
+            Action::PostNews { room, text } => {
+                let space = client.space(room.to_string()).await?;
+                let slide = client.text_markdown_draft(text.clone());
+                let event_id = space
+                    .news_draft()?
+                    .add_slide(Box::new(slide.into()))
+                    .send()
+                    .await?;
+                json!({ "type": "news-entry", "event_id": event_id, "room_id": room })
+            }
+            Action::Comment { object_id, text } => {
+                let manager = self.comments_of(&client, object_id).await?;
+                let event_id = manager
+                    .comment_draft()?
+                    .content_text(text.clone())
+                    .send()
+                    .await?;
+                json!({ "type": "comment", "event_id": event_id, "on": object_id })
+            }
+            Action::Rsvp {
+                event_id: calendar_event_id,
+                status,
+                occurrence,
+            } => {
+                let calendar_event = client
+                    .wait_for_calendar_event(calendar_event_id.to_string(), timeout)
+                    .await?;
+                let mut draft = calendar_event.rsvps().await?.rsvp_draft()?;
+                draft.status(status.as_str().to_owned());
+                if let Some(occurrence) = occurrence {
+                    draft.occurrence_from_rfc3339(occurrence.clone())?;
+                }
+                let event_id = draft.send().await?;
+                json!({
+                    "type": "rsvp",
+                    "event_id": event_id,
+                    "to": calendar_event_id,
+                    "status": status.as_str(),
+                    "occurrence": occurrence,
+                })
+            }
+        };
+        println!("{output}");
+        Ok(())
+    }
+
+    /// The comments of whatever object `object_id` is
+    async fn comments_of(
+        &self,
+        client: &Client,
+        object_id: &OwnedEventId,
+    ) -> Result<CommentsManager> {
+        let key = object_id.to_string();
+        let timeout = Some(self.timeout);
+        match client.wait_for(key.clone(), timeout).await? {
+            AnyActerModel::TaskList(_) => client.task_list(key, timeout).await?.comments().await,
+            AnyActerModel::Task(_) => client.wait_for_task(key, timeout).await?.comments().await,
+            AnyActerModel::Pin(_) => client.wait_for_pin(key, timeout).await?.comments().await,
+            AnyActerModel::CalendarEvent(_) => {
+                client
+                    .wait_for_calendar_event(key, timeout)
+                    .await?
+                    .comments()
+                    .await
+            }
+            AnyActerModel::NewsEntry(_) => {
+                client.wait_for_news(key, timeout).await?.comments().await
+            }
+            AnyActerModel::Story(_) => client.wait_for_story(key, timeout).await?.comments().await,
+            other => bail!(
+                "{key} is a {}, which can’t be commented on",
+                other.model_type()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_are_validated() -> Result<()> {
+        assert_eq!(parse_date("2026-10-18")?, (2026, 10, 18));
+        assert_eq!(parse_date(" 2024-02-29\n")?, (2024, 2, 29));
+        for invalid in [
+            "2025-02-29",
+            "2026-13-01",
+            "2026-10-32",
+            "2026-10",
+            "18.10.2026",
+            "2026-10-18T10:00",
+            "",
+        ] {
+            assert!(parse_date(invalid).is_err(), "{invalid:?} accepted");
+        }
+        Ok(())
+    }
+}
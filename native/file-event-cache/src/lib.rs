@@ -26,12 +26,14 @@ use tracing::instrument;
 
 #[cfg(feature = "queued")]
 mod queued;
+mod retention;
 
 #[cfg(feature = "queued")]
 pub use queued::QueuedEventCacheStore;
+pub use retention::{MediaCache, MediaCacheStats};
 
 pub struct FileEventCacheStore<T> {
-    cache_dir: PathBuf,
+    media: MediaCache,
     store_cipher: StoreCipher,
     inner: T,
 }
@@ -39,7 +41,7 @@ pub struct FileEventCacheStore<T> {
 impl<T> Debug for FileEventCacheStore<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("FileEventCacheStore")
-            .field("cache_dir", &self.cache_dir)
+            .field("cache_dir", self.media.cache_dir())
             .finish()
     }
 }
@@ -51,12 +53,17 @@ impl<T> FileEventCacheStore<T> {
         inner: T,
     ) -> FileEventCacheStore<T> {
         FileEventCacheStore {
-            cache_dir,
+            media: MediaCache::load(cache_dir),
             store_cipher,
             inner,
         }
     }
 
+    /// The media files on disk and their retention
+    pub fn media_cache(&self) -> MediaCache {
+        self.media.clone()
+    }
+
     fn encode_value(&self, value: Vec<u8>) -> Result<Vec<u8>, EventCacheStoreError> {
         let encoded = self
             .store_cipher
@@ -75,6 +82,15 @@ impl<T> FileEventCacheStore<T> {
     fn encode_key(&self, key: impl AsRef<[u8]>) -> String {
         Base64UrlUnpadded::encode_string(&self.store_cipher.hash_key("ext_media", key.as_ref()))
     }
+
+    fn read_media(&self, base_filename: &str) -> Result<Option<Vec<u8>>, EventCacheStoreError> {
+        let Ok(data) = fs::read(self.media.path(base_filename)) else {
+            return Ok(None);
+        };
+        let content = self.decode_value(&data)?;
+        self.media.touch(base_filename);
+        Ok(Some(content))
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -157,15 +173,23 @@ where
         content: Vec<u8>,
         ignore_policy: IgnoreMediaRetentionPolicy,
     ) -> Result<(), Self::Error> {
-        if ignore_policy.is_yes() {
-            return Ok(());
-        }
-        let base_filename = self.encode_key(request.source.unique_key());
+        let pinned = ignore_policy.is_yes();
         let data = self
             .encode_value(content)
             .map_err(|e| EventCacheStoreError::Backend(Box::new(e)))?;
-        fs::write(self.cache_dir.join(base_filename), data)
+        // measured as it ends up on disk, like the clean up does
+        if !pinned && self.media.is_too_large(data.len()) {
+            // not worth keeping around, it would be evicted right away
+            return Ok(());
+        }
+        let base_filename = self.encode_key(request.source.unique_key());
+        fs::write(self.media.path(&base_filename), data)
             .map_err(|e| EventCacheStoreError::Backend(Box::new(e)))?;
+        self.media.touch(&base_filename);
+        self.media.set_pinned(&base_filename, pinned)?;
+        if self.media.needs_clean_up() {
+            self.media.clean_up()?;
+        }
         Ok(())
     }
 
@@ -175,10 +199,7 @@ where
         request: &MediaRequestParameters,
     ) -> Result<Option<Vec<u8>>, Self::Error> {
         let base_filename = self.encode_key(request.source.unique_key());
-        fs::read(self.cache_dir.join(base_filename))
-            .ok()
-            .map(|data| self.decode_value(&data))
-            .transpose()
+        self.read_media(&base_filename)
     }
 
     async fn get_media_content_for_uri(
@@ -186,10 +207,7 @@ where
         uri: &MxcUri,
     ) -> Result<Option<Vec<u8>>, Self::Error> {
         let base_filename = self.encode_key(uri);
-        fs::read(self.cache_dir.join(base_filename))
-            .ok()
-            .map(|data| self.decode_value(&data))
-            .transpose()
+        self.read_media(&base_filename)
     }
 
     #[instrument(skip_all)]
@@ -198,17 +216,17 @@ where
         request: &MediaRequestParameters,
     ) -> Result<(), Self::Error> {
         let base_filename = self.encode_key(request.source.unique_key());
-        fs::remove_file(self.cache_dir.join(base_filename))
+        fs::remove_file(self.media.path(&base_filename))
             .map_err(|e| EventCacheStoreError::Backend(Box::new(e)))?;
-        Ok(())
+        self.media.forget(&base_filename)
     }
 
     #[instrument(skip_all)]
     async fn remove_media_content_for_uri(&self, uri: &MxcUri) -> Result<(), Self::Error> {
         let base_filename = self.encode_key(uri);
-        fs::remove_file(self.cache_dir.join(base_filename))
+        fs::remove_file(self.media.path(&base_filename))
             .map_err(|e| EventCacheStoreError::Backend(Box::new(e)))?;
-        Ok(())
+        self.media.forget(&base_filename)
     }
 
     #[instrument(skip_all)]
@@ -219,33 +237,28 @@ where
     ) -> Result<(), Self::Error> {
         let from_filename = self.encode_key(from.source.unique_key());
         let to_filename = self.encode_key(to.source.unique_key());
-        fs::rename(from_filename, to_filename)
-            .map_err(|e| EventCacheStoreError::Backend(Box::new(e)))?;
-        Ok(())
+        self.media.rename(&from_filename, &to_filename)
     }
 
     fn media_retention_policy(&self) -> MediaRetentionPolicy {
-        self.inner.media_retention_policy()
+        self.media.policy()
     }
 
     async fn set_media_retention_policy(
         &self,
         policy: MediaRetentionPolicy,
     ) -> Result<(), Self::Error> {
-        self.inner
-            .set_media_retention_policy(policy)
-            .await
-            .map_err(Into::into)
+        self.media.set_policy(policy)
     }
+
     async fn set_ignore_media_retention_policy(
         &self,
         request: &MediaRequestParameters,
         ignore_policy: IgnoreMediaRetentionPolicy,
     ) -> Result<(), Self::Error> {
-        self.inner
-            .set_ignore_media_retention_policy(request, ignore_policy)
-            .await
-            .map_err(Into::into)
+        let base_filename = self.encode_key(request.source.unique_key());
+        self.media
+            .set_pinned(&base_filename, ignore_policy.is_yes())
     }
 
     async fn clear_all_rooms_chunks(&self) -> Result<(), Self::Error> {
@@ -253,7 +266,7 @@ where
     }
 
     async fn clean_up_media_cache(&self) -> Result<(), Self::Error> {
-        Ok(())
+        self.media.clean_up()
     }
 
     async fn filter_duplicated_events(
@@ -495,4 +508,140 @@ mod tests {
 
         Ok(())
     }
+
+    async fn store_with_policy(
+        cache_dir: &std::path::Path,
+        policy: MediaRetentionPolicy,
+    ) -> Result<FileEventCacheStore<SqliteEventCacheStore>> {
+        let cache = SqliteEventCacheStore::open(cache_dir, None).await?;
+        let fmc = FileEventCacheStore::with_store_cipher(
+            cache_dir.to_path_buf(),
+            StoreCipher::new()?,
+            cache,
+        );
+        fmc.set_media_retention_policy(policy).await?;
+        Ok(fmc)
+    }
+
+    fn set_last_access(fmc: &FileEventCacheStore<SqliteEventCacheStore>, id: &str, ago: Duration) {
+        let name = fmc.encode_key(fake_mr(id).source.unique_key());
+        fmc.media_cache()
+            .set_last_access(&name, std::time::SystemTime::now() - ago);
+    }
+
+    #[async_test]
+    async fn test_skips_too_large_unless_pinned() -> Result<()> {
+        let cache_dir = tempfile::tempdir()?;
+        let policy = MediaRetentionPolicy::default()
+            .with_max_file_size(Some(1024))
+            .with_cleanup_frequency(None);
+        let fmc = store_with_policy(cache_dir.path(), policy).await?;
+        let large = vec![7u8; 2048];
+
+        fmc.add_media_content(
+            &fake_mr("large"),
+            large.clone(),
+            IgnoreMediaRetentionPolicy::No,
+        )
+        .await?;
+        assert_eq!(fmc.get_media_content(&fake_mr("large")).await?, None);
+
+        // the encrypted file on disk is what counts
+        fmc.add_media_content(
+            &fake_mr("borderline"),
+            vec![7u8; 1024],
+            IgnoreMediaRetentionPolicy::No,
+        )
+        .await?;
+        assert_eq!(fmc.get_media_content(&fake_mr("borderline")).await?, None);
+
+        fmc.add_media_content(
+            &fake_mr("pinned"),
+            large.clone(),
+            IgnoreMediaRetentionPolicy::Yes,
+        )
+        .await?;
+        assert_eq!(
+            fmc.get_media_content(&fake_mr("pinned")).await?,
+            Some(large)
+        );
+
+        let stats = fmc.media_cache().stats()?;
+        assert_eq!(stats.file_count, 1);
+        assert_eq!(stats.pinned_count, 1);
+        assert_eq!(stats.pinned_size, stats.total_size);
+        Ok(())
+    }
+
+    #[async_test]
+    async fn test_clean_up_expired_and_least_recently_used() -> Result<()> {
+        let cache_dir = tempfile::tempdir()?;
+        let day = Duration::from_secs(24 * 60 * 60);
+        let policy = MediaRetentionPolicy::default()
+            .with_max_cache_size(Some(2500))
+            .with_last_access_expiry(Some(7 * day))
+            .with_cleanup_frequency(None);
+        let fmc = store_with_policy(cache_dir.path(), policy).await?;
+
+        for id in ["expired", "oldest", "older", "recent"] {
+            fmc.add_media_content(
+                &fake_mr(id),
+                vec![1u8; 1000],
+                IgnoreMediaRetentionPolicy::No,
+            )
+            .await?;
+        }
+        fmc.add_media_content(
+            &fake_mr("pinned"),
+            vec![1u8; 1000],
+            IgnoreMediaRetentionPolicy::Yes,
+        )
+        .await?;
+        set_last_access(&fmc, "expired", 8 * day);
+        set_last_access(&fmc, "oldest", 3 * day);
+        set_last_access(&fmc, "older", 2 * day);
+        set_last_access(&fmc, "pinned", 30 * day);
+        // reading counts as access
+        fmc.get_media_content(&fake_mr("recent")).await?;
+
+        fmc.clean_up_media_cache().await?;
+
+        assert_eq!(fmc.get_media_content(&fake_mr("expired")).await?, None);
+        assert_eq!(fmc.get_media_content(&fake_mr("oldest")).await?, None);
+        assert!(fmc.get_media_content(&fake_mr("older")).await?.is_some());
+        assert!(fmc.get_media_content(&fake_mr("recent")).await?.is_some());
+        assert!(fmc.get_media_content(&fake_mr("pinned")).await?.is_some());
+
+        let stats = fmc.media_cache().stats()?;
+        assert_eq!(stats.file_count, 3);
+        assert_eq!(stats.pinned_count, 1);
+        assert!(stats.last_cleanup.is_some());
+        // the sqlite files in the same directory are left alone
+        assert!(cache_dir.path().read_dir()?.count() > 3);
+        Ok(())
+    }
+
+    #[async_test]
+    async fn test_policy_survives_restart() -> Result<()> {
+        let cache_dir = tempfile::tempdir()?;
+        let policy = MediaRetentionPolicy::default().with_max_file_size(Some(1234));
+        {
+            let fmc = store_with_policy(cache_dir.path(), policy).await?;
+            fmc.add_media_content(
+                &fake_mr("pinned"),
+                vec![1u8; 10],
+                IgnoreMediaRetentionPolicy::Yes,
+            )
+            .await?;
+        }
+        let cache = SqliteEventCacheStore::open(cache_dir.path(), None).await?;
+        let fmc = FileEventCacheStore::with_store_cipher(
+            cache_dir.path().to_path_buf(),
+            StoreCipher::new()?,
+            cache,
+        );
+        assert_eq!(fmc.media_retention_policy(), policy);
+        assert_eq!(fmc.media_cache().stats()?.pinned_count, 1);
+        Ok(())
+    }
 }
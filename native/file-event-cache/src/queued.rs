@@ -37,6 +37,10 @@ where
             queue: Arc::new(Semaphore::new(queue_size)),
         }
     }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
 }
 
 #[async_trait]
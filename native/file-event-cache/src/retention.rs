@@ -0,0 +1,288 @@
+use matrix_sdk_base::event_cache::store::{media::MediaRetentionPolicy, EventCacheStoreError};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use tracing::{instrument, warn};
+
+/// Kept next to the media files, holding the policy and the pinned files
+const STATE_FILE: &str = "retention-state";
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct RetentionState {
+    #[serde(default)]
+    policy: MediaRetentionPolicy,
+    #[serde(default)]
+    last_cleanup: Option<SystemTime>,
+    /// files stored with `IgnoreMediaRetentionPolicy::Yes`
+    #[serde(default)]
+    pinned: BTreeSet<String>,
+    /// when files were last stored or read. Only written out along with
+    /// the rest of the state, files we don't know about count as last
+    /// accessed when they were written.
+    #[serde(default)]
+    last_access: BTreeMap<String, SystemTime>,
+}
+
+/// How much the media cache takes up on disk
+#[derive(Debug, Clone, Default)]
+pub struct MediaCacheStats {
+    pub policy: MediaRetentionPolicy,
+    pub file_count: usize,
+    pub total_size: u64,
+    /// of those, ignoring the retention policy
+    pub pinned_count: usize,
+    pub pinned_size: u64,
+    pub last_cleanup: Option<SystemTime>,
+}
+
+struct CachedFile {
+    name: String,
+    size: u64,
+    last_access: SystemTime,
+}
+
+/// The media files of a [`FileEventCacheStore`](crate::FileEventCacheStore)
+/// and their retention. Cheap to clone and shared with the store, so it can
+/// be inspected from outside after the store has been handed to the client.
+#[derive(Clone, Debug)]
+pub struct MediaCache {
+    cache_dir: PathBuf,
+    state: Arc<Mutex<RetentionState>>,
+}
+
+/// Our files are named by the unpadded base64 of a 32 byte hash. Anything
+/// else, like a database sharing the directory, is none of our business.
+fn is_media_file(name: &str) -> bool {
+    name.len() == 43
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+fn backend(error: impl std::error::Error + Send + Sync + 'static) -> EventCacheStoreError {
+    EventCacheStoreError::Backend(Box::new(error))
+}
+
+impl MediaCache {
+    pub(crate) fn load(cache_dir: PathBuf) -> Self {
+        let state = match fs::read(cache_dir.join(STATE_FILE)) {
+            Ok(data) => rmp_serde::from_slice(&data).unwrap_or_else(|error| {
+                warn!(?error, "unreadable media retention state, using defaults");
+                RetentionState::default()
+            }),
+            Err(_) => RetentionState::default(),
+        };
+        MediaCache {
+            cache_dir,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    pub fn cache_dir(&self) -> &PathBuf {
+        &self.cache_dir
+    }
+
+    pub(crate) fn path(&self, name: &str) -> PathBuf {
+        self.cache_dir.join(name)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, RetentionState> {
+        // nothing in here can leave the state half-updated, so poisoning is fine to ignore
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn save(&self, state: &RetentionState) -> Result<(), EventCacheStoreError> {
+        // write aside and move over so a crash never leaves a broken file
+        let data = rmp_serde::to_vec_named(state).map_err(backend)?;
+        let tmp = self.cache_dir.join(format!("{STATE_FILE}.tmp"));
+        fs::write(&tmp, data).map_err(backend)?;
+        fs::rename(&tmp, self.cache_dir.join(STATE_FILE)).map_err(backend)
+    }
+
+    pub fn policy(&self) -> MediaRetentionPolicy {
+        self.lock().policy
+    }
+
+    pub fn set_policy(&self, policy: MediaRetentionPolicy) -> Result<(), EventCacheStoreError> {
+        let mut state = self.lock();
+        state.policy = policy;
+        self.save(&state)
+    }
+
+    pub(crate) fn set_pinned(&self, name: &str, pinned: bool) -> Result<(), EventCacheStoreError> {
+        let mut state = self.lock();
+        let changed = if pinned {
+            state.pinned.insert(name.to_owned())
+        } else {
+            state.pinned.remove(name)
+        };
+        if changed {
+            self.save(&state)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn rename(&self, from: &str, to: &str) -> Result<(), EventCacheStoreError> {
+        fs::rename(self.path(from), self.path(to)).map_err(backend)?;
+        let mut state = self.lock();
+        if let Some(last_access) = state.last_access.remove(from) {
+            state.last_access.insert(to.to_owned(), last_access);
+        }
+        if state.pinned.remove(from) {
+            state.pinned.insert(to.to_owned());
+            self.save(&state)?;
+        }
+        Ok(())
+    }
+
+    /// Whether content of that size is too big to be cached at all
+    pub(crate) fn is_too_large(&self, size: usize) -> bool {
+        let policy = self.policy();
+        policy.max_file_size.is_some_and(|max| size > max)
+            || policy.max_cache_size.is_some_and(|max| size > max)
+    }
+
+    /// Mark the file as just used, so it is evicted last
+    pub(crate) fn touch(&self, name: &str) {
+        self.set_last_access(name, SystemTime::now());
+    }
+
+    pub(crate) fn set_last_access(&self, name: &str, at: SystemTime) {
+        self.lock().last_access.insert(name.to_owned(), at);
+    }
+
+    /// The file was removed, forget everything about it
+    pub(crate) fn forget(&self, name: &str) -> Result<(), EventCacheStoreError> {
+        let mut state = self.lock();
+        state.last_access.remove(name);
+        if state.pinned.remove(name) {
+            self.save(&state)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn needs_clean_up(&self) -> bool {
+        let state = self.lock();
+        let Some(frequency) = state.policy.cleanup_frequency else {
+            return false;
+        };
+        state.last_cleanup.map_or(true, |last| {
+            SystemTime::now()
+                .duration_since(last)
+                .is_ok_and(|since| since >= frequency)
+        })
+    }
+
+    fn files(&self, last_access: &BTreeMap<String, SystemTime>) -> io::Result<Vec<CachedFile>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.cache_dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !metadata.is_file() || !is_media_file(&name) {
+                continue;
+            }
+            let last_access = match last_access.get(&name) {
+                Some(at) => *at,
+                None => metadata.modified()?,
+            };
+            files.push(CachedFile {
+                name,
+                size: metadata.len(),
+                last_access,
+            });
+        }
+        Ok(files)
+    }
+
+    pub fn stats(&self) -> io::Result<MediaCacheStats> {
+        let state = self.lock();
+        let files = self.files(&state.last_access)?;
+        let pinned: Vec<_> = files
+            .iter()
+            .filter(|f| state.pinned.contains(&f.name))
+            .collect();
+        Ok(MediaCacheStats {
+            policy: state.policy,
+            file_count: files.len(),
+            total_size: files.iter().map(|f| f.size).sum(),
+            pinned_count: pinned.len(),
+            pinned_size: pinned.iter().map(|f| f.size).sum(),
+            last_cleanup: state.last_cleanup,
+        })
+    }
+
+    /// Remove expired and too large files, then the least recently used ones
+    /// until the cache fits its maximum size. Pinned files are left alone and
+    /// don't count towards that size.
+    #[instrument(skip(self))]
+    pub fn clean_up(&self) -> Result<(), EventCacheStoreError> {
+        let now = SystemTime::now();
+        let (policy, pinned, last_access) = {
+            let state = self.lock();
+            (
+                state.policy,
+                state.pinned.clone(),
+                state.last_access.clone(),
+            )
+        };
+        let mut files: Vec<_> = self
+            .files(&last_access)
+            .map_err(backend)?
+            .into_iter()
+            .filter(|f| !pinned.contains(&f.name))
+            .collect();
+
+        let remove = |file: &CachedFile| {
+            if let Err(error) = fs::remove_file(self.path(&file.name)) {
+                warn!(?error, name = file.name, "couldn’t remove cached media");
+            }
+        };
+
+        files.retain(|file| {
+            let expired = policy.last_access_expiry.is_some_and(|expiry| {
+                now.duration_since(file.last_access)
+                    .is_ok_and(|age| age > expiry)
+            });
+            let too_large = policy
+                .max_file_size
+                .is_some_and(|max| file.size > max as u64);
+            if expired || too_large {
+                remove(file);
+                false
+            } else {
+                true
+            }
+        });
+
+        if let Some(max) = policy.max_cache_size {
+            let mut total: u64 = files.iter().map(|f| f.size).sum();
+            files.sort_by_key(|f| f.last_access);
+            for file in files {
+                if total <= max as u64 {
+                    break;
+                }
+                remove(&file);
+                total -= file.size;
+            }
+        }
+
+        let mut state = self.lock();
+        state.last_cleanup = Some(now);
+        // forget about files that are gone
+        let existing: BTreeSet<String> = self
+            .files(&state.last_access)
+            .map_err(backend)?
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+        state.pinned.retain(|name| existing.contains(name));
+        state.last_access.retain(|name, _| existing.contains(name));
+        self.save(&state)
+    }
+}
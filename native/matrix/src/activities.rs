@@ -19,6 +19,7 @@ use crate::{
     store::Store,
 };
 
+pub mod digest;
 pub mod object;
 pub mod status;
 
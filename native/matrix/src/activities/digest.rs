@@ -0,0 +1,591 @@
+use chrono::{DateTime, Duration, Utc};
+use derive_builder::Builder;
+use matrix_sdk_base::ruma::{
+    MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedRoomId, OwnedUserId, UInt,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Write};
+
+use super::{object::ActivityObject, Activity, ActivityContent};
+use crate::{
+    client::CoreClient,
+    models::{ActerModel, AnyActerModel, InvitationsManager, MyInvitesManager},
+    referencing::{IndexKey, SpecialListsIndex},
+    Result,
+};
+
+/// What to summarize in a [`Digest`]
+#[derive(Builder, Clone, Debug)]
+pub struct DigestSettings {
+    /// Start of the time window
+    since: MilliSecondsSinceUnixEpoch,
+
+    /// End of the time window, now if not given
+    #[builder(setter(strip_option), default)]
+    until: Option<MilliSecondsSinceUnixEpoch>,
+
+    /// Open tasks due up to this many days after the end of the window
+    #[builder(default = "7")]
+    due_within_days: u32,
+
+    /// How many of the most commented objects to list
+    #[builder(default = "5")]
+    most_commented: usize,
+
+    /// Only look at these rooms, all if empty
+    #[builder(default)]
+    rooms: Vec<OwnedRoomId>,
+
+    /// Prefix for the `target_url`s to link to in markdown and HTML
+    #[builder(setter(into, strip_option), default)]
+    link_base: Option<String>,
+}
+
+impl DigestSettings {
+    /// The window of the last `days` days, e.g. 1 for daily or 7 for weekly
+    pub fn for_last_days(days: u32) -> Self {
+        let since = Utc::now() - Duration::days(days.into());
+        DigestSettings {
+            since: to_ts(since),
+            until: None,
+            due_within_days: days.max(1),
+            most_commented: 5,
+            rooms: Vec::new(),
+            link_base: None,
+        }
+    }
+}
+
+fn to_ts(date: DateTime<Utc>) -> MilliSecondsSinceUnixEpoch {
+    MilliSecondsSinceUnixEpoch(UInt::new_saturating(date.timestamp_millis().max(0) as u64))
+}
+
+fn from_ts(ts: MilliSecondsSinceUnixEpoch) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(u64::from(ts.get()) as i64).unwrap_or_default()
+}
+
+/// One line of a digest, pointing to an object
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DigestEntry {
+    pub object_id: OwnedEventId,
+    pub object_type: String,
+    pub room_id: OwnedRoomId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room_name: Option<String>,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub target_url: String,
+}
+
+impl DigestEntry {
+    fn for_object(object: &ActivityObject, room_id: OwnedRoomId) -> Self {
+        DigestEntry {
+            object_id: object.object_id_str().parse().expect("ids stay valid"),
+            object_type: object.type_str(),
+            room_id,
+            room_name: None,
+            title: object.title().unwrap_or_else(|| object.type_str()),
+            detail: None,
+            target_url: object.target_url(),
+        }
+    }
+
+    fn with_detail(mut self, detail: String) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+}
+
+/// Summary of what happened for a user across their spaces over some time
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Digest {
+    pub user_id: OwnedUserId,
+    pub since: MilliSecondsSinceUnixEpoch,
+    pub until: MilliSecondsSinceUnixEpoch,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_base: Option<String>,
+    /// How many changes there were in total
+    pub activities: usize,
+    pub tasks_due: Vec<DigestEntry>,
+    pub tasks_completed: Vec<DigestEntry>,
+    pub new_events: Vec<DigestEntry>,
+    pub open_invitations: Vec<DigestEntry>,
+    pub most_commented: Vec<DigestEntry>,
+}
+
+impl Digest {
+    pub fn is_empty(&self) -> bool {
+        self.sections()
+            .iter()
+            .all(|(_, entries)| entries.is_empty())
+    }
+
+    pub fn sections(&self) -> [(&'static str, &Vec<DigestEntry>); 5] {
+        [
+            ("Tasks due soon", &self.tasks_due),
+            ("Tasks completed", &self.tasks_completed),
+            ("New events", &self.new_events),
+            ("Waiting for your answer", &self.open_invitations),
+            ("Most discussed", &self.most_commented),
+        ]
+    }
+
+    fn headline(&self) -> String {
+        format!(
+            "Your Acter digest for {} – {}: {} changes",
+            from_ts(self.since).format("%Y-%m-%d"),
+            from_ts(self.until).format("%Y-%m-%d"),
+            self.activities
+        )
+    }
+
+    fn link(&self, entry: &DigestEntry) -> Option<String> {
+        self.link_base
+            .as_ref()
+            .map(|base| format!("{}{}", base.trim_end_matches('/'), entry.target_url))
+    }
+
+    fn line(entry: &DigestEntry) -> String {
+        let mut line = String::new();
+        if let Some(room) = &entry.room_name {
+            let _ = write!(line, " ({room})");
+        }
+        if let Some(detail) = &entry.detail {
+            let _ = write!(line, " – {detail}");
+        }
+        line
+    }
+
+    pub fn render_text(&self) -> String {
+        let mut out = self.headline();
+        out.push('\n');
+        if self.is_empty() {
+            out.push_str("\nNothing new.\n");
+        }
+        for (title, entries) in self.sections() {
+            if entries.is_empty() {
+                continue;
+            }
+            let _ = write!(out, "\n{title}\n");
+            for entry in entries {
+                let _ = writeln!(out, "  - {}{}", entry.title, Self::line(entry));
+            }
+        }
+        out
+    }
+
+    pub fn render_markdown(&self) -> String {
+        let mut out = format!("# {}\n", self.headline());
+        if self.is_empty() {
+            out.push_str("\nNothing new.\n");
+        }
+        for (title, entries) in self.sections() {
+            if entries.is_empty() {
+                continue;
+            }
+            let _ = write!(out, "\n## {title}\n\n");
+            for entry in entries {
+                let name = markdown_escape(&entry.title);
+                let name = match self.link(entry) {
+                    Some(url) => format!("[{name}](<{url}>)"),
+                    None => name,
+                };
+                let _ = writeln!(out, "- {name}{}", markdown_escape(&Self::line(entry)));
+            }
+        }
+        out
+    }
+
+    pub fn render_html(&self) -> String {
+        let mut out = format!("<h1>{}</h1>\n", html_escape(&self.headline()));
+        if self.is_empty() {
+            out.push_str("<p>Nothing new.</p>\n");
+        }
+        for (title, entries) in self.sections() {
+            if entries.is_empty() {
+                continue;
+            }
+            let _ = writeln!(out, "<h2>{}</h2>\n<ul>", html_escape(title));
+            for entry in entries {
+                let name = html_escape(&entry.title);
+                let name = match self.link(entry) {
+                    Some(url) => format!("<a href=\"{}\">{name}</a>", html_escape(&url)),
+                    None => name,
+                };
+                let _ = writeln!(out, "<li>{name}{}</li>", html_escape(&Self::line(entry)));
+            }
+            out.push_str("</ul>\n");
+        }
+        out
+    }
+}
+
+/// Escape what Markdown would otherwise format in plain text
+pub fn markdown_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|'
+        ) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Aggregates the activities of the window, oldest first
+struct Collector {
+    activities: usize,
+    completed: Vec<(OwnedEventId, DigestEntry)>,
+    new_events: Vec<DigestEntry>,
+    comments: HashMap<OwnedEventId, (usize, MilliSecondsSinceUnixEpoch, DigestEntry)>,
+}
+
+impl Collector {
+    fn new() -> Self {
+        Collector {
+            activities: 0,
+            completed: Vec::new(),
+            new_events: Vec::new(),
+            comments: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, activity: &Activity) {
+        self.activities += 1;
+        let meta = activity.event_meta();
+        let room_id = meta.room_id.clone();
+        match activity.content() {
+            ActivityContent::TaskProgress { object, done } => {
+                let entry = DigestEntry::for_object(object, room_id);
+                // only the latest state counts
+                self.completed.retain(|(id, _)| id != &entry.object_id);
+                if *done {
+                    let entry = entry.with_detail(format!("by {}", meta.sender));
+                    self.completed.push((entry.object_id.clone(), entry));
+                }
+            }
+            ActivityContent::Creation {
+                object: object @ ActivityObject::CalendarEvent { utc_start, .. },
+            } => {
+                let detail = format!("starts {}", utc_start.format("%Y-%m-%d %H:%M UTC"));
+                self.new_events
+                    .push(DigestEntry::for_object(object, room_id).with_detail(detail));
+            }
+            ActivityContent::Comment { object, .. } => {
+                let entry = DigestEntry::for_object(object, room_id);
+                let (count, last, _) = self
+                    .comments
+                    .entry(entry.object_id.clone())
+                    .or_insert_with(|| (0, meta.origin_server_ts, entry));
+                *count += 1;
+                *last = meta.origin_server_ts;
+            }
+            _ => {}
+        }
+    }
+
+    fn most_commented(&self, limit: usize) -> Vec<DigestEntry> {
+        let mut commented: Vec<_> = self.comments.values().collect();
+        commented.sort_by(|(a, a_last, _), (b, b_last, _)| b.cmp(a).then(b_last.cmp(a_last)));
+        commented
+            .into_iter()
+            .take(limit)
+            .map(|(count, _, entry)| {
+                let noun = if *count == 1 { "comment" } else { "comments" };
+                entry.clone().with_detail(format!("{count} {noun}"))
+            })
+            .collect()
+    }
+}
+
+impl CoreClient {
+    /// Summarize the activities of the user's spaces
+    pub async fn activity_digest(&self, settings: DigestSettings) -> Result<Digest> {
+        let store = self.store();
+        let user_id = store.user_id().to_owned();
+        let until = settings
+            .until
+            .unwrap_or_else(MilliSecondsSinceUnixEpoch::now);
+        let in_rooms =
+            |room_id: &OwnedRoomId| settings.rooms.is_empty() || settings.rooms.contains(room_id);
+
+        let mut models: Vec<AnyActerModel> = store
+            .get_list(&IndexKey::AllHistory)
+            .await?
+            .filter(|m| {
+                let meta = m.event_meta();
+                meta.origin_server_ts >= settings.since
+                    && meta.origin_server_ts < until
+                    && in_rooms(&meta.room_id)
+            })
+            .collect();
+        models.sort_by_key(|m| m.event_meta().origin_server_ts);
+
+        let mut collector = Collector::new();
+        for model in models {
+            if let Ok(activity) = Activity::for_acter_model(store, model).await {
+                collector.add(&activity);
+            }
+        }
+
+        let due_before =
+            from_ts(until).date_naive() + Duration::days(settings.due_within_days.into());
+        let mut tasks_due: Vec<_> = store
+            .get_list(&IndexKey::Special(SpecialListsIndex::MyOpenTasks))
+            .await?
+            .filter_map(|m| match m {
+                AnyActerModel::Task(task) if !task.is_done() && in_rooms(&task.meta.room_id) => {
+                    let due = task.inner.due_date.filter(|d| *d <= due_before)?;
+                    let object =
+                        ActivityObject::try_from(&AnyActerModel::Task(task.clone())).ok()?;
+                    Some((
+                        due,
+                        DigestEntry::for_object(&object, task.meta.room_id.clone()),
+                    ))
+                }
+                _ => None,
+            })
+            .collect();
+        tasks_due.sort_by_key(|(due, _)| *due);
+        let today = from_ts(until).date_naive();
+        let tasks_due = tasks_due
+            .into_iter()
+            .map(|(due, entry)| {
+                let overdue = if due < today { ", overdue" } else { "" };
+                entry.with_detail(format!("due {}{overdue}", due.format("%Y-%m-%d")))
+            })
+            .collect();
+
+        let mut open_invitations = Vec::new();
+        for object_id in MyInvitesManager::load(store).await.invited_to() {
+            let invitations = InvitationsManager::from_store_and_event_id(store, object_id).await;
+            if !invitations.invited().contains(&user_id) {
+                continue;
+            }
+            let Ok(model) = store.get(object_id).await else {
+                continue;
+            };
+            let room_id = model.event_meta().room_id.clone();
+            if !in_rooms(&room_id) {
+                continue;
+            }
+            if let Ok(object) = ActivityObject::try_from(&model) {
+                open_invitations.push(DigestEntry::for_object(&object, room_id));
+            }
+        }
+
+        let mut digest = Digest {
+            user_id,
+            since: settings.since,
+            until,
+            link_base: settings.link_base,
+            activities: collector.activities,
+            tasks_due,
+            most_commented: collector.most_commented(settings.most_commented),
+            tasks_completed: collector.completed.into_iter().map(|(_, e)| e).collect(),
+            new_events: collector.new_events,
+            open_invitations,
+        };
+
+        let client = self.client();
+        for entries in [
+            &mut digest.tasks_due,
+            &mut digest.tasks_completed,
+            &mut digest.new_events,
+            &mut digest.open_invitations,
+            &mut digest.most_commented,
+        ] {
+            for entry in entries.iter_mut() {
+                entry.room_name = client.get_room(&entry.room_id).and_then(|r| r.name());
+            }
+        }
+        Ok(digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::rsvp::RsvpStatus, models::EventMeta};
+    use matrix_sdk_base::ruma::{
+        events::room::message::TextMessageEventContent, owned_event_id, owned_room_id,
+        owned_user_id,
+    };
+
+    fn meta(id: &str, ts: u64) -> EventMeta {
+        EventMeta {
+            event_id: format!("${id}").parse().unwrap(),
+            sender: owned_user_id!("@sari:example.org"),
+            origin_server_ts: MilliSecondsSinceUnixEpoch(UInt::new(ts).unwrap()),
+            room_id: owned_room_id!("!space:example.org"),
+            redacted: None,
+        }
+    }
+
+    fn task(id: &str) -> ActivityObject {
+        ActivityObject::Task {
+            tl_id: owned_event_id!("$list"),
+            object_id: format!("${id}").parse().unwrap(),
+            title: format!("Task {id}"),
+            due_date: None,
+        }
+    }
+
+    fn pin(id: &str) -> ActivityObject {
+        ActivityObject::Pin {
+            object_id: format!("${id}").parse().unwrap(),
+            title: format!("Pin {id}"),
+            description: None,
+        }
+    }
+
+    fn comment(id: &str, ts: u64, object: ActivityObject) -> Activity {
+        Activity::new(
+            meta(id, ts),
+            ActivityContent::Comment {
+                object,
+                content: TextMessageEventContent::plain("hi"),
+            },
+        )
+    }
+
+    #[test]
+    fn collects_completed_events_and_discussions() {
+        let mut collector = Collector::new();
+        let start = DateTime::parse_from_rfc3339("2026-10-20T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let activities = [
+            Activity::new(
+                meta("done-1", 1),
+                ActivityContent::TaskProgress {
+                    object: task("a"),
+                    done: true,
+                },
+            ),
+            Activity::new(
+                meta("done-2", 2),
+                ActivityContent::TaskProgress {
+                    object: task("b"),
+                    done: true,
+                },
+            ),
+            // reopened again, so not completed anymore
+            Activity::new(
+                meta("reopen-2", 3),
+                ActivityContent::TaskProgress {
+                    object: task("b"),
+                    done: false,
+                },
+            ),
+            Activity::new(
+                meta("event", 4),
+                ActivityContent::Creation {
+                    object: ActivityObject::CalendarEvent {
+                        object_id: owned_event_id!("$event"),
+                        title: "Assembly".to_owned(),
+                        description: None,
+                        utc_start: start,
+                        utc_end: start + Duration::hours(2),
+                    },
+                },
+            ),
+            Activity::new(
+                meta("rsvp", 5),
+                ActivityContent::Rsvp {
+                    object: pin("x"),
+                    rsvp: RsvpStatus::Yes,
+                },
+            ),
+            comment("c1", 6, pin("p")),
+            comment("c2", 7, task("a")),
+            comment("c3", 8, pin("p")),
+        ];
+        for activity in &activities {
+            collector.add(activity);
+        }
+
+        assert_eq!(collector.activities, 8);
+        let completed: Vec<_> = collector
+            .completed
+            .iter()
+            .map(|(id, _)| id.as_str())
+            .collect();
+        assert_eq!(completed, ["$a"]);
+        assert_eq!(collector.new_events.len(), 1);
+        assert_eq!(collector.new_events[0].title, "Assembly");
+        assert_eq!(
+            collector.new_events[0].detail.as_deref(),
+            Some("starts 2026-10-20 10:00 UTC")
+        );
+
+        let discussed = collector.most_commented(5);
+        assert_eq!(discussed.len(), 2);
+        assert_eq!(discussed[0].title, "Pin p");
+        assert_eq!(discussed[0].detail.as_deref(), Some("2 comments"));
+        assert_eq!(discussed[1].title, "Task a");
+        assert_eq!(discussed[1].detail.as_deref(), Some("1 comment"));
+        assert_eq!(collector.most_commented(1).len(), 1);
+    }
+
+    #[test]
+    fn renders_all_formats() {
+        let entry = DigestEntry {
+            object_id: owned_event_id!("$task"),
+            object_type: "task".to_owned(),
+            room_id: owned_room_id!("!space:example.org"),
+            room_name: Some("Garden <club>".to_owned()),
+            title: "Water *all* plants".to_owned(),
+            detail: Some("due 2026-10-21".to_owned()),
+            target_url: "/tasks/$list/$task".to_owned(),
+        };
+        let mut digest = Digest {
+            user_id: owned_user_id!("@sari:example.org"),
+            since: MilliSecondsSinceUnixEpoch(UInt::new(1_792_281_600_000).unwrap()),
+            until: MilliSecondsSinceUnixEpoch(UInt::new(1_792_886_400_000).unwrap()),
+            link_base: None,
+            activities: 3,
+            tasks_due: vec![entry],
+            tasks_completed: vec![],
+            new_events: vec![],
+            open_invitations: vec![],
+            most_commented: vec![],
+        };
+
+        let text = digest.render_text();
+        assert!(text.starts_with("Your Acter digest for 2026-10-18 – 2026-10-25: 3 changes\n"));
+        assert!(text.contains(
+            "\nTasks due soon\n  - Water *all* plants (Garden <club>) – due 2026-10-21\n"
+        ));
+        assert!(!text.contains("Tasks completed"));
+
+        let markdown = digest.render_markdown();
+        assert!(markdown.contains("## Tasks due soon\n\n- Water \\*all\\* plants"));
+
+        digest.link_base = Some("https://app.example.org/#/".to_owned());
+        let markdown = digest.render_markdown();
+        assert!(markdown
+            .contains("- [Water \\*all\\* plants](<https://app.example.org/#/tasks/$list/$task>)"));
+
+        let html = digest.render_html();
+        assert!(html.contains("<h2>Tasks due soon</h2>"));
+        assert!(html.contains(
+            "<li><a href=\"https://app.example.org/#/tasks/$list/$task\">Water *all* plants</a> (Garden &lt;club&gt;) – due 2026-10-21</li>"
+        ));
+
+        digest.tasks_due.clear();
+        assert!(digest.is_empty());
+        assert!(digest.render_text().contains("Nothing new."));
+    }
+}
@@ -0,0 +1,630 @@
+//! Internal `acter:` links to objects, rooms and super invites: generating
+//! them from [`RefDetails`] and parsing them, as well as `matrix:` and
+//! `matrix.to` room and event links, back into those.
+
+use chrono::DateTime;
+use matrix_sdk_base::ruma::{OwnedEventId, OwnedRoomId, OwnedServerName, OwnedUserId, UserId};
+use url::{form_urlencoded, Url};
+use urlencoding::{decode, encode};
+
+use crate::{
+    events::{CalendarEventRefPreview, RefDetails, RefPreview},
+    Error, Result,
+};
+
+fn parse_error(msg: impl Into<String>) -> Error {
+    Error::FailedToParse {
+        model_type: "Link".to_owned(),
+        msg: msg.into(),
+    }
+}
+
+pub fn generate_object_link(
+    room_id: &OwnedRoomId,
+    path: &[(&str, &OwnedEventId)],
+    via: &[OwnedServerName],
+    params: &[(&str, Option<&String>)],
+) -> String {
+    // acter:o/${ROOM_ID}/${PATH}?via=${SERVER_NAME}&via=${SERVER_NAME}
+    let room_id = &room_id.to_string()[1..];
+    format!(
+        "acter:o/{room_id}/{}?{}",
+        path.iter()
+            .map(|(p, o)| format!("{p}/{}", &o.to_string()[1..]))
+            .collect::<Vec<String>>()
+            .join("/"),
+        via.iter()
+            .map(|v| format!("via={}", encode(v.as_str())))
+            .chain(
+                params
+                    .iter()
+                    .filter_map(|(key, v)| v.map(|i| format!("{key}={}", encode(i.as_str()))))
+            )
+            .collect::<Vec<String>>()
+            .join("&")
+    )
+}
+
+pub fn generate_room_link(room_id: &OwnedRoomId, via: &[OwnedServerName]) -> String {
+    // matrix:roomid/${ROOM_ID}?via=${SERVER_NAME}&via=${SERVER_NAME}
+    let room_id = &room_id.to_string()[1..];
+    format!(
+        "matrix:roomid/{room_id}?{}",
+        via.iter()
+            .map(|v| format!("via={}", encode(v.as_str())))
+            .collect::<Vec<String>>()
+            .join("&")
+    )
+}
+
+pub fn generate_event_link(
+    room_id: &OwnedRoomId,
+    event_id: &OwnedEventId,
+    via: &[OwnedServerName],
+) -> String {
+    // matrix:roomid/${ROOM_ID}/e/${EVENT_ID}?via=${SERVER_NAME}&via=${SERVER_NAME}
+    let room_id = &room_id.to_string()[1..];
+    let event_id = &event_id.to_string()[1..];
+    format!(
+        "matrix:roomid/{room_id}/e/{event_id}?{}",
+        via.iter()
+            .map(|v| format!("via={}", encode(v.as_str())))
+            .collect::<Vec<String>>()
+            .join("&")
+    )
+}
+
+pub fn generate_invite_link(server_name: &str, token: &str, inviter_user_id: &str) -> String {
+    // acter:i/${SERVER_NAME}/${INVITE_TOKEN}?userId=${INVITER}
+    format!("acter:i/{server_name}/{token}?userId={inviter_user_id}")
+}
+
+fn preview_params(preview: &RefPreview, include_preview: bool) -> Vec<(&str, Option<&String>)> {
+    if include_preview {
+        vec![
+            ("roomDisplayName", preview.room_display_name.as_ref()),
+            ("title", preview.title.as_ref()),
+        ]
+    } else {
+        vec![]
+    }
+}
+
+/// The internal `acter:` link for these details. Super invites need an
+/// inviter, the one they were shared by or else `inviter`, whose server they
+/// are for unless they name one. Plain links can't be made into one.
+pub fn generate_internal_link(
+    details: &RefDetails,
+    include_preview: bool,
+    inviter: Option<&UserId>,
+) -> Result<String> {
+    let missing_room = || Error::MissingField("room_id".to_owned());
+    Ok(match details {
+        RefDetails::Link { .. } => {
+            return Err(Error::Custom(
+                "Link can't be made into internal link".to_owned(),
+            ))
+        }
+        RefDetails::Room { room_id, via, .. } => generate_room_link(room_id, via.as_slice()),
+        RefDetails::Event {
+            target_id,
+            room_id,
+            via,
+        } => generate_event_link(room_id, target_id, via.as_slice()),
+        RefDetails::SuperInviteToken {
+            token,
+            server_name,
+            inviter: shared_by,
+            ..
+        } => {
+            let inviter = shared_by
+                .as_deref()
+                .or(inviter)
+                .ok_or(Error::ClientNotLoggedIn)?;
+            let server_name = server_name.as_deref().unwrap_or(inviter.server_name());
+            generate_invite_link(server_name.as_str(), token, inviter.as_str())
+        }
+        RefDetails::Task {
+            target_id,
+            room_id,
+            via,
+            preview,
+            task_list,
+            ..
+        } => generate_object_link(
+            room_id.as_ref().ok_or_else(missing_room)?,
+            &[("taskList", task_list), ("task", target_id)],
+            via.as_slice(),
+            preview_params(preview, include_preview).as_slice(),
+        ),
+        RefDetails::TaskList {
+            target_id,
+            room_id,
+            via,
+            preview,
+            ..
+        } => generate_object_link(
+            room_id.as_ref().ok_or_else(missing_room)?,
+            &[("taskList", target_id)],
+            via.as_slice(),
+            preview_params(preview, include_preview).as_slice(),
+        ),
+        RefDetails::Pin {
+            target_id,
+            room_id,
+            via,
+            preview,
+            ..
+        } => generate_object_link(
+            room_id.as_ref().ok_or_else(missing_room)?,
+            &[("pin", target_id)],
+            via.as_slice(),
+            preview_params(preview, include_preview).as_slice(),
+        ),
+        RefDetails::News {
+            target_id,
+            room_id,
+            via,
+            preview,
+        } => generate_object_link(
+            room_id.as_ref().ok_or_else(missing_room)?,
+            &[("boost", target_id)],
+            via.as_slice(),
+            preview_params(preview, include_preview).as_slice(),
+        ),
+        RefDetails::CalendarEvent {
+            target_id,
+            room_id,
+            via,
+            preview,
+            ..
+        } => {
+            let participants = preview.participants.as_ref().map(ToString::to_string);
+            let start_at = preview
+                .start_at_utc
+                .as_ref()
+                .map(|s| s.timestamp().to_string());
+            let params = if include_preview {
+                vec![
+                    ("roomDisplayName", preview.room_display_name.as_ref()),
+                    ("title", preview.title.as_ref()),
+                    ("participants", participants.as_ref()),
+                    ("startAtUtc", start_at.as_ref()),
+                ]
+            } else {
+                vec![]
+            };
+            generate_object_link(
+                room_id.as_ref().ok_or_else(missing_room)?,
+                &[("calendarEvent", target_id)],
+                via.as_slice(),
+                params.as_slice(),
+            )
+        }
+    })
+}
+
+/// The query parameters of a link we care about
+#[derive(Default)]
+struct LinkParams {
+    via: Vec<OwnedServerName>,
+    title: Option<String>,
+    room_display_name: Option<String>,
+    participants: Option<u32>,
+    start_at_utc: Option<i64>,
+    user_id: Option<OwnedUserId>,
+}
+
+impl LinkParams {
+    fn parse(query: &str) -> Result<Self> {
+        let mut params = LinkParams::default();
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "via" => params.via.push(value.as_ref().try_into()?),
+                "title" => params.title = Some(value.into_owned()),
+                "roomDisplayName" => params.room_display_name = Some(value.into_owned()),
+                "participants" => params.participants = value.parse().ok(),
+                "startAtUtc" => params.start_at_utc = value.parse().ok(),
+                "userId" => params.user_id = value.as_ref().try_into().ok(),
+                _ => {} // unknown ones are fine, newer clients might add some
+            }
+        }
+        Ok(params)
+    }
+
+    fn preview(&self) -> RefPreview {
+        RefPreview {
+            title: self.title.clone(),
+            room_display_name: self.room_display_name.clone(),
+        }
+    }
+
+    fn calendar_event_preview(&self) -> CalendarEventRefPreview {
+        CalendarEventRefPreview {
+            title: self.title.clone(),
+            room_display_name: self.room_display_name.clone(),
+            participants: self.participants,
+            start_at_utc: self
+                .start_at_utc
+                .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+        }
+    }
+}
+
+fn segments(path: &str) -> Result<Vec<String>> {
+    path.split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            decode(s)
+                .map(|s| s.into_owned())
+                .map_err(|e| parse_error(e.to_string()))
+        })
+        .collect()
+}
+
+/// Ids in our links come without their sigil
+fn with_sigil(sigil: char, id: &str) -> String {
+    if id.starts_with(sigil) {
+        id.to_owned()
+    } else {
+        format!("{sigil}{id}")
+    }
+}
+
+fn room_id(id: &str) -> Result<OwnedRoomId> {
+    Ok(with_sigil('!', id).try_into()?)
+}
+
+fn event_id(id: &str) -> Result<OwnedEventId> {
+    Ok(with_sigil('$', id).try_into()?)
+}
+
+fn room_details(room_id: OwnedRoomId, params: LinkParams) -> RefDetails {
+    RefDetails::Room {
+        room_id,
+        // we can't tell from the link, the room itself will
+        is_space: false,
+        preview: params.preview(),
+        via: params.via,
+    }
+}
+
+fn parse_object_path(room: &str, path: &[String], params: LinkParams) -> Result<RefDetails> {
+    let room_id = Some(room_id(room)?);
+    let path: Vec<&str> = path.iter().map(String::as_str).collect();
+    Ok(match path.as_slice() {
+        ["taskList", task_list, "task", task] => RefDetails::Task {
+            target_id: event_id(task)?,
+            task_list: event_id(task_list)?,
+            room_id,
+            preview: params.preview(),
+            via: params.via,
+            action: Default::default(),
+        },
+        ["taskList", task_list] => RefDetails::TaskList {
+            target_id: event_id(task_list)?,
+            room_id,
+            preview: params.preview(),
+            via: params.via,
+            action: Default::default(),
+        },
+        ["pin", pin] => RefDetails::Pin {
+            target_id: event_id(pin)?,
+            room_id,
+            preview: params.preview(),
+            via: params.via,
+            action: Default::default(),
+        },
+        ["boost", news] => RefDetails::News {
+            target_id: event_id(news)?,
+            room_id,
+            preview: params.preview(),
+            via: params.via,
+        },
+        ["calendarEvent", event] => RefDetails::CalendarEvent {
+            target_id: event_id(event)?,
+            room_id,
+            preview: params.calendar_event_preview(),
+            via: params.via,
+            action: Default::default(),
+        },
+        _ => {
+            return Err(parse_error(format!(
+                "unknown object path {}",
+                path.join("/")
+            )))
+        }
+    })
+}
+
+/// `o/…`, `i/…` or `r/…`, as in `acter:` links
+fn parse_acter_path(path: &str, query: &str) -> Result<RefDetails> {
+    let params = LinkParams::parse(query)?;
+    let segments = segments(path)?;
+    match segments.as_slice() {
+        [kind, room, rest @ ..] if kind == "o" => parse_object_path(room, rest, params),
+        [kind, server_name, token] if kind == "i" => Ok(RefDetails::SuperInviteToken {
+            token: token.clone(),
+            create_dm: false,
+            accepted_count: 0,
+            rooms: vec![],
+            server_name: Some(server_name.as_str().try_into()?),
+            inviter: params.user_id,
+        }),
+        [kind, room] if kind == "r" => Ok(room_details(room_id(room)?, params)),
+        _ => Err(parse_error(format!("unsupported acter link {path}"))),
+    }
+}
+
+fn event_details(room_id: OwnedRoomId, event: &str, params: LinkParams) -> Result<RefDetails> {
+    Ok(RefDetails::Event {
+        target_id: event_id(event)?,
+        room_id,
+        via: params.via,
+    })
+}
+
+/// `roomid/…` or `r/…` of `matrix:` links, optionally followed by `e/…`
+fn parse_matrix_path(path: &str, query: &str) -> Result<RefDetails> {
+    let params = LinkParams::parse(query)?;
+    let segments = segments(path)?;
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    match segments.as_slice() {
+        ["roomid", room] => Ok(room_details(room_id(room)?, params)),
+        ["roomid", room, "e", event] => event_details(room_id(room)?, event, params),
+        ["r", alias] | ["r", alias, "e", _] => Err(Error::RoomAliasLink(with_sigil('#', alias))),
+        _ => Err(parse_error(format!(
+            "only links to rooms and their events are supported, not {path}"
+        ))),
+    }
+}
+
+/// The part after `#/` of `matrix.to` links
+fn parse_matrix_to(fragment: &str) -> Result<RefDetails> {
+    let (path, query) = fragment.split_once('?').unwrap_or((fragment, ""));
+    let params = LinkParams::parse(query)?;
+    match segments(path)?.as_slice() {
+        [room] if room.starts_with('!') => Ok(room_details(room_id(room)?, params)),
+        [room, event] if room.starts_with('!') && event.starts_with('$') => {
+            event_details(room_id(room)?, event, params)
+        }
+        [alias] | [alias, _] if alias.starts_with('#') => Err(Error::RoomAliasLink(alias.clone())),
+        _ => Err(parse_error(format!(
+            "only links to rooms and their events are supported, not {path}"
+        ))),
+    }
+}
+
+/// Turn an `acter:o/…`, `acter:i/…` or `acter:r/…` link or a
+/// `matrix:roomid/…` or `https://matrix.to/#/!…` room or event link back
+/// into the details it refers to. Links to rooms by alias fail with
+/// [`Error::RoomAliasLink`], the alias has to be resolved first. External
+/// share links are made up by the server, so they aren't understood here.
+///
+/// Links don't tell whether a room is a space nor the embed action, those
+/// come back as `false` and the default respectively.
+pub fn parse_link(link: &str) -> Result<RefDetails> {
+    let link = link.trim();
+    if let Some(rest) = link.strip_prefix("acter:") {
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        return parse_acter_path(path, query);
+    }
+    if let Some(rest) = link.strip_prefix("matrix:") {
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        return parse_matrix_path(path, query);
+    }
+
+    let url = Url::parse(link).map_err(|e| parse_error(e.to_string()))?;
+    if !matches!(url.scheme(), "https" | "http") {
+        return Err(parse_error(format!("unsupported scheme {}", url.scheme())));
+    }
+    if url.host_str() != Some("matrix.to") {
+        return Err(parse_error(format!(
+            "unsupported link to {}",
+            url.host_str().unwrap_or_default()
+        )));
+    }
+    let Some(fragment) = url.fragment().filter(|f| !f.is_empty()) else {
+        return Err(parse_error("link doesn't point to anything"));
+    };
+    parse_matrix_to(fragment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix_sdk_base::ruma::{
+        owned_event_id, owned_room_id, owned_server_name, owned_user_id, user_id,
+    };
+
+    fn round_trip(details: RefDetails) {
+        let link = generate_internal_link(&details, true, Some(user_id!("@me:example.org")))
+            .expect("can generate link");
+        let parsed = parse_link(&link).expect("can parse link");
+        assert_eq!(parsed, details, "{link}");
+    }
+
+    fn preview() -> RefPreview {
+        RefPreview {
+            title: Some("Fix the bike & ride it".to_owned()),
+            room_display_name: Some("Bike Club #1".to_owned()),
+        }
+    }
+
+    #[test]
+    fn objects_round_trip() {
+        let room_id = Some(owned_room_id!("!room:example.org"));
+        let via = vec![
+            owned_server_name!("example.org"),
+            owned_server_name!("other.example.org:8448"),
+        ];
+        round_trip(RefDetails::Task {
+            target_id: owned_event_id!("$task"),
+            task_list: owned_event_id!("$list"),
+            room_id: room_id.clone(),
+            via: via.clone(),
+            preview: preview(),
+            action: Default::default(),
+        });
+        round_trip(RefDetails::TaskList {
+            target_id: owned_event_id!("$list"),
+            room_id: room_id.clone(),
+            via: vec![],
+            preview: RefPreview::default(),
+            action: Default::default(),
+        });
+        round_trip(RefDetails::Pin {
+            target_id: owned_event_id!("$pin"),
+            room_id: room_id.clone(),
+            via: via.clone(),
+            preview: preview(),
+            action: Default::default(),
+        });
+        round_trip(RefDetails::News {
+            target_id: owned_event_id!("$news"),
+            room_id: room_id.clone(),
+            via: via.clone(),
+            preview: preview(),
+        });
+        round_trip(RefDetails::CalendarEvent {
+            target_id: owned_event_id!("$event"),
+            room_id,
+            via,
+            preview: CalendarEventRefPreview {
+                title: Some("Onboarding".to_owned()),
+                room_display_name: None,
+                participants: Some(3),
+                start_at_utc: DateTime::from_timestamp(1_790_000_000, 0),
+            },
+            action: Default::default(),
+        });
+    }
+
+    #[test]
+    fn room_and_invite_round_trip() {
+        round_trip(RefDetails::Room {
+            room_id: owned_room_id!("!room:example.org"),
+            is_space: false,
+            via: vec![owned_server_name!("example.org")],
+            preview: RefPreview::default(),
+        });
+        round_trip(RefDetails::SuperInviteToken {
+            token: "BIKECLUB".to_owned(),
+            create_dm: false,
+            accepted_count: 0,
+            rooms: vec![],
+            server_name: Some(owned_server_name!("invites.example.org")),
+            // not the one generating the link
+            inviter: Some(owned_user_id!("@friend:example.org")),
+        });
+        round_trip(RefDetails::Event {
+            target_id: owned_event_id!("$event"),
+            room_id: owned_room_id!("!room:example.org"),
+            via: vec![owned_server_name!("example.org")],
+        });
+    }
+
+    #[test]
+    fn invite_defaults_to_inviters_server() {
+        let details = RefDetails::SuperInviteToken {
+            token: "BIKECLUB".to_owned(),
+            create_dm: false,
+            accepted_count: 0,
+            rooms: vec![],
+            server_name: None,
+            inviter: None,
+        };
+        let link =
+            generate_internal_link(&details, false, Some(user_id!("@me:example.org"))).unwrap();
+        assert_eq!(link, "acter:i/example.org/BIKECLUB?userId=@me:example.org");
+        assert!(generate_internal_link(&details, false, None).is_err());
+    }
+
+    #[test]
+    fn without_preview() {
+        let details = RefDetails::Pin {
+            target_id: owned_event_id!("$pin"),
+            room_id: Some(owned_room_id!("!room:example.org")),
+            via: vec![],
+            preview: preview(),
+            action: Default::default(),
+        };
+        let link = generate_internal_link(&details, false, None).unwrap();
+        assert_eq!(link, "acter:o/room:example.org/pin/pin?");
+        assert_eq!(parse_link(&link).unwrap().title(), None);
+    }
+
+    #[test]
+    fn room_links() {
+        let expected = RefDetails::Room {
+            room_id: owned_room_id!("!room:example.org"),
+            is_space: false,
+            via: vec![owned_server_name!("example.org")],
+            preview: RefPreview::default(),
+        };
+        for link in [
+            "acter:r/room:example.org?via=example.org",
+            "matrix:roomid/room:example.org?via=example.org",
+            "https://matrix.to/#/!room:example.org?via=example.org",
+            "https://matrix.to/#/%21room%3Aexample.org?via=example.org",
+        ] {
+            assert_eq!(parse_link(link).unwrap(), expected, "{link}");
+        }
+    }
+
+    #[test]
+    fn invite_links() {
+        let invite = parse_link("acter:i/acter.global/BIKECLUB?userId=@me:example.org").unwrap();
+        assert_eq!(invite.type_str(), "super-invite");
+        assert_eq!(invite.title().as_deref(), Some("BIKECLUB"));
+        assert_eq!(invite.server_name_str().as_deref(), Some("acter.global"));
+        assert_eq!(invite.inviter_str().as_deref(), Some("@me:example.org"));
+    }
+
+    #[test]
+    fn event_links() {
+        let expected = RefDetails::Event {
+            target_id: owned_event_id!("$event"),
+            room_id: owned_room_id!("!room:example.org"),
+            via: vec![owned_server_name!("example.org")],
+        };
+        for link in [
+            "matrix:roomid/room:example.org/e/event?via=example.org",
+            "https://matrix.to/#/!room:example.org/$event?via=example.org",
+            "https://matrix.to/#/%21room%3Aexample.org/%24event?via=example.org",
+        ] {
+            assert_eq!(parse_link(link).unwrap(), expected, "{link}");
+        }
+    }
+
+    #[test]
+    fn alias_links() {
+        for link in [
+            "matrix:r/alias:example.org",
+            "matrix:r/alias:example.org/e/event",
+            "https://matrix.to/#/#alias:example.org",
+            "https://matrix.to/#/%23alias%3Aexample.org/$event",
+        ] {
+            assert!(
+                matches!(parse_link(link), Err(Error::RoomAliasLink(alias)) if alias == "#alias:example.org"),
+                "{link}"
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_links() {
+        for link in [
+            "https://example.org/",
+            "https://example.org/#o/room:example.org/pin/pin",
+            "mailto:me@example.org",
+            "acter:o/room:example.org/unknown/event",
+            "acter:o/room:example.org/task/event",
+            "matrix:r/alias:example.org",
+            "https://matrix.to/#/#alias:example.org",
+            "https://matrix.to/#/@me:example.org",
+        ] {
+            assert!(parse_link(link).is_err(), "{link}");
+        }
+    }
+}
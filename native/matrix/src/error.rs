@@ -60,6 +60,12 @@ pub enum Error {
     #[error("{0:?} field is missing")]
     MissingField(String),
 
+    #[error("Relating to {0} would create a cycle")]
+    RelationCycle(String),
+
+    #[error("Links to rooms by alias aren’t supported, resolve {0} first")]
+    RoomAliasLink(String),
+
     #[error("{0}")]
     Custom(String),
 }
@@ -1,6 +1,10 @@
+use chrono_tz::Tz;
 use derive_builder::Builder;
 use derive_getters::Getters;
-use matrix_sdk_base::ruma::events::{macros::EventContent, room::message::TextMessageEventContent};
+use matrix_sdk_base::ruma::{
+    events::{macros::EventContent, room::message::TextMessageEventContent},
+    OwnedEventId,
+};
 use serde::{Deserialize, Serialize};
 use tracing::trace;
 
@@ -10,7 +14,7 @@ use crate::{models::TextMessageContent, util::deserialize_some, Result};
 /// modeled after [JMAP Calendar Events](https://jmap.io/spec-calendars.html#calendar-events), extensions to
 /// [ietf rfc8984](https://www.rfc-editor.org/rfc/rfc8984.html#name-event).
 ///
-use super::{Display, Icon, Update, UtcDateTime};
+use super::{Display, Icon, RecurrenceRule, Update, UtcDateTime};
 
 /// Event Location
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -177,7 +181,8 @@ impl EventLocationInfo {
 ///
 /// modeled after [JMAP Calendar Events](https://jmap.io/spec-calendars.html#calendar-events)
 /// see also the [IETF CalendarEvent](https://www.rfc-editor.org/rfc/rfc8984.html#name-event)
-/// but all timezones have been dumbed down to UTC-only.
+/// but all timezones have been dumbed down to UTC-only, apart from the one
+/// a series repeats in.
 #[derive(Clone, Debug, Deserialize, Serialize, EventContent, Builder, Getters)]
 #[ruma_event(type = "global.acter.dev.calendar_event", kind = MessageLike)]
 #[builder(name = "CalendarEventBuilder", derive(Debug))]
@@ -221,6 +226,62 @@ pub struct CalendarEventEventContent {
     #[builder(setter(into), default)]
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub categories: Vec<String>,
+
+    /// Does this event repeat? Following the RFC 5545 RRULE with `utc_start`
+    /// as the start of the series
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rrule: Option<RecurrenceRule>,
+
+    /// The time zone the series repeats in: its occurrences start at the
+    /// local time of day of `utc_start` there, also across daylight saving
+    /// time changes. UTC if not set.
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<Tz>,
+
+    /// Start times of occurrences that have been removed from the series
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exdates: Vec<UtcDateTime>,
+
+    /// The `UID` of the iCalendar event this has been imported from
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ical_uid: Option<String>,
+}
+
+impl CalendarEventEventContent {
+    pub fn is_recurring(&self) -> bool {
+        self.rrule.is_some()
+    }
+
+    /// The start times of all occurrences overlapping the given window,
+    /// excluding removed ones
+    pub fn occurrence_starts(&self, from: UtcDateTime, to: UtcDateTime) -> Vec<UtcDateTime> {
+        let duration = self.utc_end - self.utc_start;
+        let Some(rrule) = &self.rrule else {
+            if self.utc_start <= to && self.utc_end >= from {
+                return vec![self.utc_start];
+            }
+            return vec![];
+        };
+        rrule
+            .occurrences_from(
+                self.utc_start,
+                self.time_zone.unwrap_or(Tz::UTC),
+                from - duration,
+            )
+            .skip_while(|start| *start + duration < from)
+            .take_while(|start| *start <= to)
+            .filter(|start| !self.exdates.contains(start))
+            .collect()
+    }
+
+    /// Whether an occurrence, that hasn't been removed, starts at `start`
+    pub fn has_occurrence_at(&self, start: UtcDateTime) -> bool {
+        self.occurrence_starts(start, start).contains(&start)
+    }
 }
 
 impl CalendarEventBuilder {
@@ -356,6 +417,45 @@ pub struct CalendarEventUpdateEventContent {
         deserialize_with = "deserialize_some"
     )]
     pub display: Option<Option<Display>>,
+
+    /// Change or remove the recurrence of this event
+    #[builder(default)]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_some"
+    )]
+    pub rrule: Option<Option<RecurrenceRule>>,
+
+    /// Change or remove the time zone the series repeats in
+    #[builder(setter(into), default)]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_some"
+    )]
+    pub time_zone: Option<Option<Tz>>,
+
+    /// Replace the removed occurrences of the series
+    #[builder(default)]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_some"
+    )]
+    pub exdates: Option<Vec<UtcDateTime>>,
+
+    /// If set, this update only applies to the single occurrence of the series
+    /// originally starting at this time (RFC 5545 `RECURRENCE-ID`)
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence_id: Option<UtcDateTime>,
+
+    /// The revision of the event this update is based on, to detect
+    /// concurrent changes by others
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_revision: Option<OwnedEventId>,
 }
 
 impl CalendarEventUpdateEventContent {
@@ -406,6 +506,21 @@ impl CalendarEventUpdateEventContent {
             updated = true;
         }
 
+        if let Some(time_zone) = &self.time_zone {
+            calendar_event.time_zone = *time_zone;
+            updated = true;
+        }
+
+        if let Some(rrule) = &self.rrule {
+            calendar_event.rrule.clone_from(rrule);
+            updated = true;
+        }
+
+        if let Some(exdates) = &self.exdates {
+            calendar_event.exdates.clone_from(exdates);
+            updated = true;
+        }
+
         trace!(update = ?self, ?updated, ?calendar_event, "CalendarEvent updated");
 
         Ok(updated)
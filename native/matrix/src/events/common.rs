@@ -7,6 +7,7 @@ mod color;
 mod display;
 mod labels;
 mod object_reference;
+mod recurrence;
 mod rendering;
 
 pub use categories::{
@@ -18,6 +19,10 @@ pub use object_reference::{
     CalendarEventAction, CalendarEventRefPreview, ObjRef, ObjRefBuilder, RefDetails, RefPreview,
     TaskAction, TaskListAction,
 };
+pub use recurrence::{
+    parse_ical_datetime, Frequency, Occurrences, RecurrenceRule, RecurrenceRuleBuilder, WeekdayNum,
+    ICAL_UTC_FORMAT,
+};
 pub use rendering::{ActerIcon, BrandLogo, Colorize, ColorizeBuilder, Icon, Position};
 
 pub use display::{Display, DisplayBuilder};
@@ -1,5 +1,5 @@
 use matrix_sdk_base::{
-    ruma::{OwnedEventId, OwnedRoomId, OwnedServerName},
+    ruma::{OwnedEventId, OwnedRoomId, OwnedServerName, OwnedUserId},
     RoomDisplayName,
 };
 use serde::{Deserialize, Serialize};
@@ -304,12 +304,30 @@ pub enum RefDetails {
         #[serde(default, skip_serializing_if = "RefPreview::is_none")]
         preview: RefPreview,
     },
+    /// An event of a room, as `matrix:` and `matrix.to` links point to,
+    /// whatever kind of event that is
+    Event {
+        /// the target event id
+        target_id: OwnedEventId,
+
+        /// the room the event is in
+        room_id: OwnedRoomId,
+
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        via: Vec<OwnedServerName>,
+    },
     SuperInviteToken {
         token: String,
         create_dm: bool,
         accepted_count: u32,
         #[serde(default, skip_serializing_if = "Vec::is_empty")]
         rooms: Vec<String>,
+        /// the server that issued the token and it has to be redeemed on
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        server_name: Option<OwnedServerName>,
+        /// who shared the invite, as given in its link
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        inviter: Option<OwnedUserId>,
     },
 }
 
@@ -325,6 +343,7 @@ impl RefDetails {
             RefDetails::SuperInviteToken { .. } => "super-invite".to_owned(),
             RefDetails::Pin { .. } => "pin".to_owned(),
             RefDetails::News { .. } => "news".to_owned(),
+            RefDetails::Event { .. } => "event".to_owned(),
         }
     }
 
@@ -336,6 +355,7 @@ impl RefDetails {
             RefDetails::SuperInviteToken { .. } => "super-invite".to_owned(),
             RefDetails::Pin { .. } => "pin".to_owned(),
             RefDetails::News { .. } => "news".to_owned(),
+            RefDetails::Event { .. } => "event".to_owned(),
             RefDetails::Task { action, .. } => action.to_string(),
             RefDetails::TaskList { action, .. } => action.to_string(),
             RefDetails::CalendarEvent { action, .. } => action.to_string(),
@@ -351,14 +371,17 @@ impl RefDetails {
             | RefDetails::TaskList { target_id, .. }
             | RefDetails::Pin { target_id, .. }
             | RefDetails::News { target_id, .. }
-            | RefDetails::CalendarEvent { target_id, .. } => Some(target_id.to_string()),
+            | RefDetails::CalendarEvent { target_id, .. }
+            | RefDetails::Event { target_id, .. } => Some(target_id.to_string()),
         }
     }
 
     pub fn room_id_str(&self) -> Option<String> {
         match self {
             RefDetails::Link { .. } | RefDetails::SuperInviteToken { .. } => None,
-            RefDetails::Room { room_id, .. } => Some(room_id.to_string()),
+            RefDetails::Room { room_id, .. } | RefDetails::Event { room_id, .. } => {
+                Some(room_id.to_string())
+            }
             RefDetails::Task { room_id, .. }
             | RefDetails::TaskList { room_id, .. }
             | RefDetails::Pin { room_id, .. }
@@ -373,6 +396,7 @@ impl RefDetails {
         match self {
             RefDetails::Link { .. } | RefDetails::SuperInviteToken { .. } => vec![],
             RefDetails::Room { via, .. }
+            | RefDetails::Event { via, .. }
             | RefDetails::Task { via, .. }
             | RefDetails::TaskList { via, .. }
             | RefDetails::Pin { via, .. }
@@ -383,6 +407,24 @@ impl RefDetails {
         }
     }
 
+    pub fn server_name_str(&self) -> Option<String> {
+        match self {
+            RefDetails::SuperInviteToken { server_name, .. } => {
+                server_name.as_ref().map(ToString::to_string)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn inviter_str(&self) -> Option<String> {
+        match self {
+            RefDetails::SuperInviteToken { inviter, .. } => {
+                inviter.as_ref().map(ToString::to_string)
+            }
+            _ => None,
+        }
+    }
+
     pub fn task_list_id_str(&self) -> Option<String> {
         match self {
             RefDetails::Task { task_list, .. } => Some(task_list.to_string()),
@@ -400,7 +442,7 @@ impl RefDetails {
             | RefDetails::News { preview, .. }
             | RefDetails::Task { preview, .. }
             | RefDetails::TaskList { preview, .. } => preview.title.clone(),
-            // _ => None,
+            RefDetails::Event { .. } => None,
         }
     }
 
@@ -0,0 +1,762 @@
+use chrono::{
+    Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use derive_builder::Builder;
+use std::{collections::VecDeque, fmt, str::FromStr};
+
+use super::UtcDateTime;
+use crate::Error;
+
+/// Upper bound of consecutive periods without an occurrence we walk through
+/// when expanding a rule, so that rules which never match again (e.g.
+/// `BYMONTHDAY=31;BYMONTH=2`) terminate
+const MAX_EMPTY_PERIODS: u32 = 10_000;
+
+/// Format used by RFC 5545 for UTC date-times
+pub const ICAL_UTC_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        })
+    }
+}
+
+impl FromStr for Frequency {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "DAILY" => Ok(Frequency::Daily),
+            "WEEKLY" => Ok(Frequency::Weekly),
+            "MONTHLY" => Ok(Frequency::Monthly),
+            "YEARLY" => Ok(Frequency::Yearly),
+            _ => Err(parse_error(format!("unsupported frequency {s}"))),
+        }
+    }
+}
+
+/// A weekday with an optional ordinal, e.g. `2TU` for the second tuesday
+/// or `-1FR` for the last friday of the period
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WeekdayNum {
+    pub ordinal: Option<i8>,
+    pub weekday: Weekday,
+}
+
+impl From<Weekday> for WeekdayNum {
+    fn from(weekday: Weekday) -> Self {
+        WeekdayNum {
+            ordinal: None,
+            weekday,
+        }
+    }
+}
+
+impl fmt::Display for WeekdayNum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ordinal) = self.ordinal {
+            write!(f, "{ordinal}")?;
+        }
+        f.write_str(match self.weekday {
+            Weekday::Mon => "MO",
+            Weekday::Tue => "TU",
+            Weekday::Wed => "WE",
+            Weekday::Thu => "TH",
+            Weekday::Fri => "FR",
+            Weekday::Sat => "SA",
+            Weekday::Sun => "SU",
+        })
+    }
+}
+
+impl FromStr for WeekdayNum {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() < 2 || !s.is_char_boundary(s.len() - 2) {
+            return Err(parse_error(format!("invalid weekday {s}")));
+        }
+        let (ordinal, day) = s.split_at(s.len() - 2);
+        let weekday = match day {
+            "MO" => Weekday::Mon,
+            "TU" => Weekday::Tue,
+            "WE" => Weekday::Wed,
+            "TH" => Weekday::Thu,
+            "FR" => Weekday::Fri,
+            "SA" => Weekday::Sat,
+            "SU" => Weekday::Sun,
+            _ => return Err(parse_error(format!("invalid weekday {s}"))),
+        };
+        let ordinal = if ordinal.is_empty() {
+            None
+        } else {
+            let ordinal = ordinal
+                .trim_start_matches('+')
+                .parse::<i8>()
+                .map_err(|e| parse_error(format!("invalid weekday ordinal {s}: {e}")))?;
+            if ordinal == 0 || !(-5..=5).contains(&ordinal) {
+                return Err(parse_error(format!("weekday ordinal out of range {s}")));
+            }
+            Some(ordinal)
+        };
+        Ok(WeekdayNum { ordinal, weekday })
+    }
+}
+
+/// Recurrence Rule
+///
+/// A subset of the [RFC 5545 RRULE](https://www.rfc-editor.org/rfc/rfc5545#section-3.3.10)
+/// supporting daily, weekly, monthly and yearly rules. Serializes to and from
+/// its textual representation, e.g. `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH`.
+#[derive(Clone, Debug, PartialEq, Eq, Builder)]
+#[builder(name = "RecurrenceRuleBuilder", derive(Debug))]
+pub struct RecurrenceRule {
+    pub freq: Frequency,
+
+    /// every how many periods this recurs, at least 1
+    #[builder(default = "1")]
+    pub interval: u32,
+
+    /// stop after this many occurrences
+    #[builder(setter(into), default)]
+    pub count: Option<u32>,
+
+    /// stop after this point in time (inclusive)
+    #[builder(setter(into), default)]
+    pub until: Option<UtcDateTime>,
+
+    #[builder(default)]
+    pub by_day: Vec<WeekdayNum>,
+
+    /// days of the month, negative values count from the end of the month
+    #[builder(default)]
+    pub by_month_day: Vec<i8>,
+
+    #[builder(default)]
+    pub by_month: Vec<u8>,
+}
+
+fn parse_error(msg: String) -> Error {
+    Error::FailedToParse {
+        model_type: "rrule".to_owned(),
+        msg,
+    }
+}
+
+fn parse_list<T: FromStr>(key: &str, value: &str) -> Result<Vec<T>, Error>
+where
+    T::Err: fmt::Display,
+{
+    value
+        .split(',')
+        .map(|v| {
+            v.parse::<T>()
+                .map_err(|e| parse_error(format!("invalid {key} value {v}: {e}")))
+        })
+        .collect()
+}
+
+fn join_list<T: fmt::Display>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parse an RFC 5545 date or date-time, floating times are taken as UTC
+pub fn parse_ical_datetime(value: &str) -> Option<UtcDateTime> {
+    if let Ok(dt) = NaiveDateTime::parse_from_str(value, ICAL_UTC_FORMAT) {
+        return Some(dt.and_utc());
+    }
+    if let Ok(dt) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Some(dt.and_utc());
+    }
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(23, 59, 59))
+        .map(|dt| dt.and_utc())
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix("RRULE:").unwrap_or(s);
+        let mut builder = RecurrenceRuleBuilder::default();
+        let mut has_freq = false;
+        for part in s.split(';').filter(|p| !p.is_empty()) {
+            let Some((key, value)) = part.split_once('=') else {
+                return Err(parse_error(format!("invalid rule part {part}")));
+            };
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    builder.freq(value.to_ascii_uppercase().parse()?);
+                    has_freq = true;
+                }
+                "INTERVAL" => {
+                    let interval = value
+                        .parse::<u32>()
+                        .map_err(|e| parse_error(format!("invalid INTERVAL {value}: {e}")))?;
+                    builder.interval(interval.max(1));
+                }
+                "COUNT" => {
+                    let count = value
+                        .parse::<u32>()
+                        .map_err(|e| parse_error(format!("invalid COUNT {value}: {e}")))?;
+                    builder.count(count);
+                }
+                "UNTIL" => {
+                    let until = parse_ical_datetime(value)
+                        .ok_or_else(|| parse_error(format!("invalid UNTIL {value}")))?;
+                    builder.until(until);
+                }
+                "BYDAY" => {
+                    builder.by_day(parse_list(key, &value.to_ascii_uppercase())?);
+                }
+                "BYMONTHDAY" => {
+                    let days: Vec<i8> = parse_list(key, value)?;
+                    if days.iter().any(|d| *d == 0 || !(-31..=31).contains(d)) {
+                        return Err(parse_error(format!("BYMONTHDAY out of range {value}")));
+                    }
+                    builder.by_month_day(days);
+                }
+                "BYMONTH" => {
+                    let months: Vec<u8> = parse_list(key, value)?;
+                    if months.iter().any(|m| !(1..=12).contains(m)) {
+                        return Err(parse_error(format!("BYMONTH out of range {value}")));
+                    }
+                    builder.by_month(months);
+                }
+                // we always expand with monday as week start
+                "WKST" => {}
+                other => {
+                    return Err(parse_error(format!("unsupported rule part {other}")));
+                }
+            }
+        }
+        if !has_freq {
+            return Err(parse_error("FREQ is required".to_owned()));
+        }
+        if builder.count.flatten().is_some() && builder.until.flatten().is_some() {
+            return Err(parse_error(
+                "COUNT and UNTIL are mutually exclusive".to_owned(),
+            ));
+        }
+        builder.build().map_err(|e| parse_error(e.to_string()))
+    }
+}
+
+impl fmt::Display for RecurrenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={}", self.freq)?;
+        if self.interval > 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={count}")?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format(ICAL_UTC_FORMAT))?;
+        }
+        if !self.by_day.is_empty() {
+            write!(f, ";BYDAY={}", join_list(&self.by_day))?;
+        }
+        if !self.by_month_day.is_empty() {
+            write!(f, ";BYMONTHDAY={}", join_list(&self.by_month_day))?;
+        }
+        if !self.by_month.is_empty() {
+            write!(f, ";BYMONTH={}", join_list(&self.by_month))?;
+        }
+        Ok(())
+    }
+}
+
+impl serde::Serialize for RecurrenceRule {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for RecurrenceRule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).expect("first of the month always exists")
+}
+
+fn days_in_month(month_start: NaiveDate) -> u32 {
+    month_start
+        .checked_add_months(Months::new(1))
+        .map(|next| next.signed_duration_since(month_start).num_days() as u32)
+        .unwrap_or(31)
+}
+
+impl RecurrenceRule {
+    /// Iterate over all occurrence start times of a series starting at
+    /// `dtstart`, in chronological order. The series start itself is the
+    /// first occurrence.
+    pub fn occurrences(&self, dtstart: UtcDateTime) -> Occurrences<'_> {
+        self.occurrences_in(dtstart, Tz::UTC)
+    }
+
+    /// Like [`Self::occurrences`], repeating in the time zone `tz`: every
+    /// occurrence starts at the local time of day of `dtstart` there, also
+    /// across daylight saving time changes.
+    pub fn occurrences_in(&self, dtstart: UtcDateTime, tz: Tz) -> Occurrences<'_> {
+        let local = dtstart.with_timezone(&tz).naive_local();
+        Occurrences {
+            rule: self,
+            tz,
+            start_date: local.date(),
+            time: local.time(),
+            period: 0,
+            empty_periods: 0,
+            emitted: 0,
+            pending: VecDeque::from([local.date()]),
+            done: false,
+        }
+    }
+
+    /// Like [`Self::occurrences_in`], but skipping the periods that end
+    /// before `from` where that doesn’t change the result, i.e. unless the
+    /// series is limited by `COUNT`. Occurrences before `from` may still
+    /// be yielded.
+    pub fn occurrences_from(
+        &self,
+        dtstart: UtcDateTime,
+        tz: Tz,
+        from: UtcDateTime,
+    ) -> Occurrences<'_> {
+        let mut occurrences = self.occurrences_in(dtstart, tz);
+        if self.count.is_some() {
+            return occurrences;
+        }
+        let start = occurrences.start_date;
+        // a day of margin for the offset of the time zone
+        let from = from.with_timezone(&tz).date_naive() - Duration::days(1);
+        let periods = match self.freq {
+            Frequency::Daily => (from - start).num_days(),
+            Frequency::Weekly => {
+                let week_start =
+                    |d: NaiveDate| d - Duration::days(d.weekday().num_days_from_monday().into());
+                (week_start(from) - week_start(start)).num_weeks()
+            }
+            Frequency::Monthly => {
+                i64::from(from.year() - start.year()) * 12 + i64::from(from.month())
+                    - i64::from(start.month())
+            }
+            Frequency::Yearly => i64::from(from.year() - start.year()),
+        } / i64::from(self.interval.max(1));
+        if let Ok(period) = u32::try_from(periods) {
+            if period > 0 {
+                occurrences.period = period;
+                // the series start is long gone
+                occurrences.pending.clear();
+            }
+        }
+        occurrences
+    }
+
+    /// The first occurrence of a series starting at `dtstart` that starts
+    /// strictly after `after`
+    pub fn next_after(&self, dtstart: UtcDateTime, after: UtcDateTime) -> Option<UtcDateTime> {
+        self.occurrences(dtstart).find(|o| *o > after)
+    }
+
+    fn matches_filters(&self, date: NaiveDate) -> bool {
+        if !self.by_month.is_empty() && !self.by_month.contains(&(date.month() as u8)) {
+            return false;
+        }
+        if self.freq != Frequency::Daily {
+            // the other filters are expansions for the larger periods
+            return true;
+        }
+        if !self.by_month_day.is_empty() {
+            let days = days_in_month(first_of_month(date)) as i8;
+            let day = date.day() as i8;
+            if !self
+                .by_month_day
+                .iter()
+                .any(|d| *d == day || *d == day - days - 1)
+            {
+                return false;
+            }
+        }
+        self.by_day.is_empty() || self.by_day.iter().any(|d| d.weekday == date.weekday())
+    }
+
+    fn month_candidates(&self, month_start: NaiveDate, start: NaiveDate) -> Vec<NaiveDate> {
+        let days = days_in_month(month_start);
+        if !self.by_month_day.is_empty() {
+            return self
+                .by_month_day
+                .iter()
+                .filter_map(|d| {
+                    let day = if *d > 0 {
+                        *d as u32
+                    } else {
+                        (days as i32 + *d as i32 + 1).try_into().ok()?
+                    };
+                    month_start.with_day(day)
+                })
+                .filter(|d| {
+                    self.by_day.is_empty() || self.by_day.iter().any(|w| w.weekday == d.weekday())
+                })
+                .collect();
+        }
+        if !self.by_day.is_empty() {
+            return self
+                .by_day
+                .iter()
+                .flat_map(|wd| {
+                    let offset = (7 + wd.weekday.num_days_from_monday()
+                        - month_start.weekday().num_days_from_monday())
+                        % 7;
+                    let all: Vec<NaiveDate> = (offset..days)
+                        .step_by(7)
+                        .filter_map(|d| month_start.checked_add_days(Days::new(d.into())))
+                        .collect();
+                    match wd.ordinal {
+                        None => all,
+                        Some(n) if n > 0 => all.get(n as usize - 1).copied().into_iter().collect(),
+                        Some(n) => all
+                            .len()
+                            .checked_sub(n.unsigned_abs() as usize)
+                            .and_then(|idx| all.get(idx).copied())
+                            .into_iter()
+                            .collect(),
+                    }
+                })
+                .collect();
+        }
+        // invalid dates (e.g. the 31st in short months) are skipped as per RFC 5545
+        month_start.with_day(start.day()).into_iter().collect()
+    }
+
+    fn period_candidates(&self, start: NaiveDate, period: u32) -> Option<Vec<NaiveDate>> {
+        let step = period.checked_mul(self.interval.max(1))?;
+        let dates = match self.freq {
+            Frequency::Daily => vec![start.checked_add_days(Days::new(step.into()))?],
+            Frequency::Weekly => {
+                let week_start = start
+                    .checked_sub_days(Days::new(start.weekday().num_days_from_monday().into()))?
+                    .checked_add_days(Days::new(u64::from(step) * 7))?;
+                if self.by_day.is_empty() {
+                    vec![week_start.checked_add_days(Days::new(
+                        start.weekday().num_days_from_monday().into(),
+                    ))?]
+                } else {
+                    self.by_day
+                        .iter()
+                        .filter_map(|d| {
+                            week_start.checked_add_days(Days::new(
+                                d.weekday.num_days_from_monday().into(),
+                            ))
+                        })
+                        .collect()
+                }
+            }
+            Frequency::Monthly => {
+                let month_start = first_of_month(start).checked_add_months(Months::new(step))?;
+                self.month_candidates(month_start, start)
+            }
+            Frequency::Yearly => {
+                let year = start.year().checked_add(i32::try_from(step).ok()?)?;
+                let months = if self.by_month.is_empty() {
+                    vec![start.month()]
+                } else {
+                    self.by_month.iter().map(|m| u32::from(*m)).collect()
+                };
+                months
+                    .into_iter()
+                    .filter_map(|m| NaiveDate::from_ymd_opt(year, m, 1))
+                    .flat_map(|month_start| self.month_candidates(month_start, start))
+                    .collect()
+            }
+        };
+        Some(
+            dates
+                .into_iter()
+                .filter(|d| self.matches_filters(*d))
+                .collect(),
+        )
+    }
+}
+
+/// Iterator over the occurrences of a [`RecurrenceRule`]
+pub struct Occurrences<'a> {
+    rule: &'a RecurrenceRule,
+    tz: Tz,
+    /// the local date and time of day of the series start in `tz`
+    start_date: NaiveDate,
+    time: NaiveTime,
+    period: u32,
+    empty_periods: u32,
+    emitted: u32,
+    pending: VecDeque<NaiveDate>,
+    done: bool,
+}
+
+impl Occurrences<'_> {
+    /// The start of the occurrence on that local date
+    fn start_on(&self, date: NaiveDate) -> UtcDateTime {
+        let local = date.and_time(self.time);
+        self.tz
+            .from_local_datetime(&local)
+            .earliest()
+            // skipped by a daylight saving time change, RFC 5545 moves it
+            // by the length of the gap
+            .or_else(|| {
+                self.tz
+                    .from_local_datetime(&(local + Duration::hours(1)))
+                    .earliest()
+            })
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|| local.and_utc())
+    }
+}
+
+impl Iterator for Occurrences<'_> {
+    type Item = UtcDateTime;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            if let Some(date) = self.pending.pop_front() {
+                let occurrence = self.start_on(date);
+                let past_until = self.rule.until.is_some_and(|until| occurrence > until);
+                let past_count = self.rule.count.is_some_and(|c| self.emitted >= c);
+                if past_until || past_count {
+                    self.done = true;
+                    return None;
+                }
+                self.emitted += 1;
+                return Some(occurrence);
+            }
+            if self.empty_periods >= MAX_EMPTY_PERIODS {
+                self.done = true;
+                return None;
+            }
+            match self.rule.period_candidates(self.start_date, self.period) {
+                Some(mut dates) => {
+                    // the series start has been emitted already
+                    dates.retain(|d| d > &self.start_date);
+                    dates.sort();
+                    dates.dedup();
+                    if dates.is_empty() {
+                        self.empty_periods += 1;
+                    } else {
+                        self.empty_periods = 0;
+                    }
+                    self.pending.extend(dates);
+                }
+                None => self.done = true,
+            }
+            match self.period.checked_add(1) {
+                Some(period) => self.period = period,
+                None => self.done = true,
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn utc(y: i32, m: u32, d: u32, h: u32) -> UtcDateTime {
+        Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn roundtrip() -> Result<(), Error> {
+        for raw in [
+            "FREQ=WEEKLY;BYDAY=MO,TH",
+            "FREQ=MONTHLY;INTERVAL=2;COUNT=5;BYDAY=-1FR",
+            "FREQ=YEARLY;UNTIL=20301231T000000Z;BYMONTHDAY=1,-1;BYMONTH=1,6",
+        ] {
+            let rule = raw.parse::<RecurrenceRule>()?;
+            assert_eq!(rule.to_string(), raw);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parses_in_json() -> Result<(), serde_json::Error> {
+        let rule: RecurrenceRule = serde_json::from_str(r#""RRULE:FREQ=DAILY;COUNT=3""#)?;
+        assert_eq!(rule.freq, Frequency::Daily);
+        assert_eq!(rule.count, Some(3));
+        assert_eq!(serde_json::to_string(&rule)?, r#""FREQ=DAILY;COUNT=3""#);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unsupported() {
+        assert!("FREQ=HOURLY".parse::<RecurrenceRule>().is_err());
+        assert!("INTERVAL=2".parse::<RecurrenceRule>().is_err());
+        assert!("FREQ=DAILY;BYSETPOS=1".parse::<RecurrenceRule>().is_err());
+        assert!("FREQ=DAILY;COUNT=2;UNTIL=20300101"
+            .parse::<RecurrenceRule>()
+            .is_err());
+    }
+
+    #[test]
+    fn weekly_by_day() -> Result<(), Error> {
+        // 2024-01-03 is a wednesday
+        let rule = "FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4".parse::<RecurrenceRule>()?;
+        let all: Vec<_> = rule.occurrences(utc(2024, 1, 3, 9)).collect();
+        assert_eq!(
+            all,
+            vec![
+                utc(2024, 1, 3, 9),
+                utc(2024, 1, 8, 9),
+                utc(2024, 1, 10, 9),
+                utc(2024, 1, 15, 9)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn monthly_last_friday() -> Result<(), Error> {
+        let rule = "FREQ=MONTHLY;BYDAY=-1FR".parse::<RecurrenceRule>()?;
+        let all: Vec<_> = rule.occurrences(utc(2024, 1, 26, 12)).take(3).collect();
+        assert_eq!(
+            all,
+            vec![
+                utc(2024, 1, 26, 12),
+                utc(2024, 2, 23, 12),
+                utc(2024, 3, 29, 12)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn monthly_skips_invalid_days() -> Result<(), Error> {
+        let rule = "FREQ=MONTHLY;COUNT=3".parse::<RecurrenceRule>()?;
+        let all: Vec<_> = rule.occurrences(utc(2024, 1, 31, 8)).collect();
+        assert_eq!(
+            all,
+            vec![
+                utc(2024, 1, 31, 8),
+                utc(2024, 3, 31, 8),
+                utc(2024, 5, 31, 8)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn daily_until_and_next_after() -> Result<(), Error> {
+        let rule = "FREQ=DAILY;INTERVAL=2;UNTIL=20240107T090000Z".parse::<RecurrenceRule>()?;
+        let start = utc(2024, 1, 1, 9);
+        assert_eq!(rule.occurrences(start).count(), 4);
+        assert_eq!(
+            rule.next_after(start, utc(2024, 1, 2, 0)),
+            Some(utc(2024, 1, 3, 9))
+        );
+        assert_eq!(rule.next_after(start, utc(2024, 1, 7, 9)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn impossible_rule_terminates() -> Result<(), Error> {
+        let rule = "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=30".parse::<RecurrenceRule>()?;
+        assert_eq!(rule.occurrences(utc(2024, 1, 1, 0)).count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_local_time_across_dst() -> Result<(), Error> {
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        let rule = "FREQ=WEEKLY;COUNT=3".parse::<RecurrenceRule>()?;
+        // 09:00 in Berlin, the clocks go forward on 2024-03-31
+        let all: Vec<_> = rule.occurrences_in(utc(2024, 3, 24, 8), berlin).collect();
+        assert_eq!(
+            all,
+            vec![utc(2024, 3, 24, 8), utc(2024, 3, 31, 7), utc(2024, 4, 7, 7)]
+        );
+        // in UTC it stays at 08:00 UTC
+        assert_eq!(
+            rule.occurrences(utc(2024, 3, 24, 8)).nth(1),
+            Some(utc(2024, 3, 31, 8))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn skipped_local_times_move_forward() -> Result<(), Error> {
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        let rule = "FREQ=DAILY;COUNT=3".parse::<RecurrenceRule>()?;
+        // 02:30 doesn't exist in Berlin on 2024-03-31
+        let start = Utc.with_ymd_and_hms(2024, 3, 30, 1, 30, 0).unwrap();
+        let all: Vec<_> = rule.occurrences_in(start, berlin).collect();
+        // 02:30 CET, then 03:30 CEST for the skipped hour, then 02:30 CEST
+        assert_eq!(all[1], Utc.with_ymd_and_hms(2024, 3, 31, 1, 30, 0).unwrap());
+        assert_eq!(all[2], Utc.with_ymd_and_hms(2024, 4, 1, 0, 30, 0).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn open_ended_rules_keep_going() -> Result<(), Error> {
+        let rule = "FREQ=DAILY".parse::<RecurrenceRule>()?;
+        let start = utc(2024, 1, 1, 9);
+        let far = utc(2100, 6, 1, 0);
+        assert_eq!(rule.next_after(start, far), Some(utc(2100, 6, 1, 9)));
+        Ok(())
+    }
+
+    #[test]
+    fn occurrences_from_skips_ahead() -> Result<(), Error> {
+        let start = utc(2024, 1, 1, 9);
+        let from = utc(2030, 3, 10, 0);
+        for raw in [
+            "FREQ=DAILY;INTERVAL=3",
+            "FREQ=WEEKLY;BYDAY=MO,FR",
+            "FREQ=MONTHLY;INTERVAL=2;BYDAY=-1FR",
+            "FREQ=YEARLY;BYMONTH=3,9",
+        ] {
+            let rule = raw.parse::<RecurrenceRule>()?;
+            let skipped: Vec<_> = rule
+                .occurrences_from(start, Tz::UTC, from)
+                .skip_while(|o| *o < from)
+                .take(5)
+                .collect();
+            let walked: Vec<_> = rule
+                .occurrences(start)
+                .skip_while(|o| *o < from)
+                .take(5)
+                .collect();
+            assert_eq!(skipped, walked, "{raw}");
+        }
+        Ok(())
+    }
+}
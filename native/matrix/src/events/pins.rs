@@ -1,6 +1,9 @@
 use derive_builder::Builder;
 use derive_getters::Getters;
-use matrix_sdk_base::ruma::events::{macros::EventContent, room::message::TextMessageEventContent};
+use matrix_sdk_base::ruma::{
+    events::{macros::EventContent, room::message::TextMessageEventContent},
+    OwnedEventId,
+};
 use serde::{Deserialize, Serialize};
 use tracing::trace;
 
@@ -75,6 +78,12 @@ pub struct PinUpdateEventContent {
         deserialize_with = "deserialize_some"
     )]
     pub display: Option<Option<Display>>,
+
+    /// The revision of the pin this update is based on, to detect
+    /// concurrent changes by others
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_revision: Option<OwnedEventId>,
 }
 
 impl PinUpdateEventContent {
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use strum::{Display, ParseError};
 
-use super::BelongsTo;
+use super::{BelongsTo, UtcDateTime};
 
 /// RSVP status
 // previously accepted only PascalCase
@@ -46,4 +46,10 @@ pub struct RsvpEventContent {
 
     /// The status responded by this user
     pub status: RsvpStatus,
+
+    /// For recurring events: the original start time of the single occurrence
+    /// this response is for. Applies to the whole series if not given.
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub occurrence: Option<UtcDateTime>,
 }
@@ -15,10 +15,12 @@ pub use space::{
     ActerAppSettings, ActerAppSettingsContent, ActerAppSettingsContentBuilder,
     ActerAppSettingsContentBuilderError, EventsSettings, NewsSettings, PinsSettings,
     SimpleOnOffSetting, SimpleOnOffSettingBuilder, SimpleSettingWithTurnOff,
-    SimpleSettingWithTurnOffBuilder, StoriesSettings, TasksSettings,
+    SimpleSettingWithTurnOffBuilder, StoriesSettings, StoriesSettingsBuilder, TasksSettings,
+    DEFAULT_STORY_EXPIRY_SECS,
 };
 pub use user::{
-    ActerUserAppSettingsContent, ActerUserAppSettingsContentBuilder, AppChatSettings, AutoDownload,
+    ActerUserAppSettingsContent, ActerUserAppSettingsContentBuilder, AppChatSettings,
+    AppReminderSettings, AutoDownload, DEFAULT_EVENT_START_REMINDERS, DEFAULT_TASK_DUE_REMINDERS,
 };
 
 use crate::referencing::ExecuteReference;
@@ -1,3 +1,4 @@
+use chrono::Duration;
 use derive_builder::Builder;
 use matrix_sdk_base::ruma::events::{macros::EventContent, EmptyStateKey};
 use serde::{Deserialize, Serialize};
@@ -55,8 +56,58 @@ impl SimpleOnOffSetting {
     }
 }
 
+/// Stories expire after a day unless the space says otherwise
+pub const DEFAULT_STORY_EXPIRY_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Debug, Deserialize, Serialize, Builder, Default)]
+pub struct StoriesSettings {
+    // default: off
+    #[serde(default)]
+    active: bool,
+    /// seconds after which stories are archived, `0` for never
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expiry_secs: Option<u64>,
+}
+
+impl StoriesSettings {
+    pub fn off() -> Option<Self> {
+        // no need, we are off by default
+        None
+    }
+
+    pub fn on() -> Option<Self> {
+        Some(StoriesSettings {
+            active: true,
+            expiry_secs: None,
+        })
+    }
+
+    pub fn active(&self) -> bool {
+        self.active
+    }
+
+    pub fn expiry_secs(&self) -> u64 {
+        self.expiry_secs.unwrap_or(DEFAULT_STORY_EXPIRY_SECS)
+    }
+
+    /// How long stories are shown before being archived, `None` for forever
+    pub fn expiry(&self) -> Option<Duration> {
+        match self.expiry_secs() {
+            0 => None,
+            secs => Some(Duration::seconds(secs.try_into().unwrap_or(i64::MAX))),
+        }
+    }
+
+    pub fn updater(&self) -> StoriesSettingsBuilder {
+        StoriesSettingsBuilder::default()
+            .active(self.active)
+            .expiry_secs(self.expiry_secs)
+            .to_owned()
+    }
+}
+
 pub type TasksSettings = SimpleOnOffSetting;
-pub type StoriesSettings = SimpleOnOffSetting;
 pub type NewsSettings = SimpleSettingWithTurnOff;
 pub type PinsSettings = SimpleSettingWithTurnOff;
 pub type EventsSettings = SimpleSettingWithTurnOff;
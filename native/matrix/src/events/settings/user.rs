@@ -1,5 +1,5 @@
 use derive_builder::Builder;
-use matrix_sdk_base::ruma::events::macros::EventContent;
+use matrix_sdk_base::ruma::{events::macros::EventContent, OwnedServerName};
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use strum::{Display, EnumString, ParseError};
@@ -38,6 +38,39 @@ impl Default for AppNotificationSettings {
     }
 }
 
+/// Minutes before a task is due to remind the user, unless configured
+pub const DEFAULT_TASK_DUE_REMINDERS: [u32; 1] = [24 * 60];
+/// Minutes before an event starts to remind the user, unless configured
+pub const DEFAULT_EVENT_START_REMINDERS: [u32; 1] = [15];
+
+/// When to remind the user locally, in minutes before the task is due or
+/// the event starts. Unset means the defaults, empty means no reminders.
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+pub struct AppReminderSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_due: Option<Vec<u32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_start: Option<Vec<u32>>,
+}
+
+impl AppReminderSettings {
+    fn is_empty(&self) -> bool {
+        self.task_due.is_none() && self.event_start.is_none()
+    }
+
+    pub fn task_due_offsets(&self) -> Vec<u32> {
+        self.task_due
+            .clone()
+            .unwrap_or_else(|| DEFAULT_TASK_DUE_REMINDERS.to_vec())
+    }
+
+    pub fn event_start_offsets(&self) -> Vec<u32> {
+        self.event_start
+            .clone()
+            .unwrap_or_else(|| DEFAULT_EVENT_START_REMINDERS.to_vec())
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, EventContent, Builder, Default)]
 #[ruma_event(type = "global.acter.user_app_settings", kind = GlobalAccountData)]
 pub struct ActerUserAppSettingsContent {
@@ -45,6 +78,13 @@ pub struct ActerUserAppSettingsContent {
     pub chat: AppChatSettings,
     #[serde(default, skip_serializing_if = "AppNotificationSettings::is_empty")]
     pub notifications: AppNotificationSettings,
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "AppReminderSettings::is_empty")]
+    pub reminders: AppReminderSettings,
+    /// the server named in super invite links, the one of the user id if unset
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub super_invite_server: Option<OwnedServerName>,
 }
 
 impl ActerUserAppSettingsContent {
@@ -52,6 +92,8 @@ impl ActerUserAppSettingsContent {
         ActerUserAppSettingsContentBuilder::default()
             .chat(self.chat.clone())
             .notifications(self.notifications.clone())
+            .reminders(self.reminders.clone())
+            .super_invite_server(self.super_invite_server.clone())
             .to_owned()
     }
 }
@@ -93,4 +135,60 @@ impl ActerUserAppSettingsContentBuilder {
 
         Ok(self)
     }
+
+    /// Set the minutes before a task is due to remind at, empty for none
+    pub fn task_due_reminders(&mut self, minutes: Vec<u32>) -> &mut Self {
+        self.reminders.get_or_insert_with(Default::default).task_due = Some(minutes);
+        self
+    }
+
+    pub fn add_task_due_reminder(&mut self, minutes: u32) -> &mut Self {
+        let reminders = self.reminders.get_or_insert_with(Default::default);
+        reminders.task_due = Some(with_offset(reminders.task_due_offsets(), minutes));
+        self
+    }
+
+    pub fn remove_task_due_reminder(&mut self, minutes: u32) -> &mut Self {
+        let reminders = self.reminders.get_or_insert_with(Default::default);
+        let mut offsets = reminders.task_due_offsets();
+        offsets.retain(|m| *m != minutes);
+        reminders.task_due = Some(offsets);
+        self
+    }
+
+    /// Set the minutes before an event starts to remind at, empty for none
+    pub fn event_start_reminders(&mut self, minutes: Vec<u32>) -> &mut Self {
+        self.reminders
+            .get_or_insert_with(Default::default)
+            .event_start = Some(minutes);
+        self
+    }
+
+    pub fn add_event_start_reminder(&mut self, minutes: u32) -> &mut Self {
+        let reminders = self.reminders.get_or_insert_with(Default::default);
+        reminders.event_start = Some(with_offset(reminders.event_start_offsets(), minutes));
+        self
+    }
+
+    pub fn remove_event_start_reminder(&mut self, minutes: u32) -> &mut Self {
+        let reminders = self.reminders.get_or_insert_with(Default::default);
+        let mut offsets = reminders.event_start_offsets();
+        offsets.retain(|m| *m != minutes);
+        reminders.event_start = Some(offsets);
+        self
+    }
+
+    /// Go back to the default reminders
+    pub fn reset_reminders(&mut self) -> &mut Self {
+        self.reminders = Some(AppReminderSettings::default());
+        self
+    }
+}
+
+fn with_offset(mut offsets: Vec<u32>, minutes: u32) -> Vec<u32> {
+    if !offsets.contains(&minutes) {
+        offsets.push(minutes);
+        offsets.sort_unstable();
+    }
+    offsets
 }
@@ -9,7 +9,7 @@ use matrix_sdk_base::ruma::events::{
 };
 use serde::{Deserialize, Serialize};
 
-use super::{Colorize, ObjRef, Update};
+use super::{Colorize, ObjRef, Update, UtcDateTime};
 use crate::{util::deserialize_some, Result};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -132,6 +132,12 @@ pub struct StoryEventContent {
     /// A Story entry may have one or more slides of Story
     /// which are scrolled through horizontally
     pub slides: Vec<StorySlide>,
+
+    /// When to archive the story, if not after the expiry the space
+    /// configured in its [`StoriesSettings`](super::settings::StoriesSettings)
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<UtcDateTime>,
 }
 
 /// The payload for our Story update event.
@@ -152,6 +158,16 @@ pub struct StoryUpdateEventContent {
         deserialize_with = "deserialize_some"
     )]
     pub slides: Option<Vec<StorySlide>>,
+
+    /// When to archive the story, `Some(None)` to fall back to the
+    /// expiry of the space again
+    #[builder(setter(into), default)]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_some"
+    )]
+    pub expires_at: Option<Option<UtcDateTime>>,
 }
 
 impl StoryUpdateEventContent {
@@ -161,6 +177,10 @@ impl StoryUpdateEventContent {
             task.slides.clone_from(slides);
             updated = true;
         }
+        if let Some(expires_at) = &self.expires_at {
+            task.expires_at = *expires_at;
+            updated = true;
+        }
         Ok(updated)
     }
 }
@@ -2,16 +2,20 @@ use chrono_tz::Tz;
 use core::result::Result as CoreResult;
 use derive_builder::Builder;
 use derive_getters::Getters;
-use matrix_sdk_base::ruma::events::{macros::EventContent, room::message::TextMessageEventContent};
+use matrix_sdk_base::ruma::{
+    events::{macros::EventContent, room::message::TextMessageEventContent},
+    OwnedEventId,
+};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
+use strum::{Display as StrumDisplay, EnumString};
 use tracing::trace;
 
 /// ToDo Lists and Task Items management
 /// modeled after [JMAP Tasks](https://jmap.io/spec-tasks.html), extensions to
 /// [ietf rfc8984](https://www.rfc-editor.org/rfc/rfc8984.html#name-task).
 ///
-use super::{BelongsTo, Date, Display, Update, UtcDateTime};
+use super::{BelongsTo, Date, Display, RecurrenceRule, Update, UtcDateTime};
 use crate::{util::deserialize_some, Result as ActerResult};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -45,6 +49,60 @@ impl Priority {
     }
 }
 
+/// What the next instance of a recurring task is scheduled from
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, StrumDisplay, EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum RecurrenceAnchor {
+    /// the due date of the completed instance, keeping a fixed schedule
+    #[default]
+    DueDate,
+    /// the day the instance was completed on
+    Completion,
+}
+
+/// A task that is posted again once it has been done
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TaskRecurrence {
+    /// RFC 5545 RRULE the due dates follow. A `COUNT` is the number of
+    /// instances left, including this one.
+    pub rule: RecurrenceRule,
+
+    #[serde(default)]
+    pub relative_to: RecurrenceAnchor,
+}
+
+impl TaskRecurrence {
+    pub fn new(rule: RecurrenceRule, relative_to: RecurrenceAnchor) -> Self {
+        TaskRecurrence { rule, relative_to }
+    }
+
+    /// The due date of the next instance and the recurrence it carries, or
+    /// `None` if the series ends with this instance
+    pub fn next(&self, due_date: Option<Date>, completed_on: Date) -> Option<(Date, Self)> {
+        if self.rule.count.is_some_and(|c| c <= 1) {
+            return None;
+        }
+        let anchor = match (self.relative_to, due_date) {
+            (RecurrenceAnchor::DueDate, Some(due_date)) => due_date,
+            _ => completed_on,
+        };
+        let dtstart = anchor.and_time(Default::default()).and_utc();
+        let next = self.rule.next_after(dtstart, dtstart)?.date_naive();
+        let mut rule = self.rule.clone();
+        rule.count = rule.count.map(|c| c - 1);
+        Some((
+            next,
+            TaskRecurrence {
+                rule,
+                relative_to: self.relative_to,
+            },
+        ))
+    }
+}
+
 /// The TaskList Event
 ///
 /// modeled after [JMAP TaskList](https://jmap.io/spec-tasks.html#tasklists)
@@ -273,6 +331,26 @@ pub struct TaskEventContent {
     #[builder(setter(into), default)]
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub categories: Vec<String>,
+
+    /// The task this is a subtask of
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_task: Option<OwnedEventId>,
+
+    /// Tasks that need to be done before this one can be worked on
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_by: Vec<OwnedEventId>,
+
+    /// Post the next instance of this task once it is done
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<TaskRecurrence>,
+
+    /// The instance of the recurring task this one has been posted after
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_instance: Option<OwnedEventId>,
 }
 
 impl TaskBuilder {
@@ -282,6 +360,11 @@ impl TaskBuilder {
                 return Err("Progress percent can’t be higher than 100".to_owned());
             }
         }
+        if let (Some(Some(parent)), Some(blocked_by)) = (&self.parent_task, &self.blocked_by) {
+            if blocked_by.contains(parent) {
+                return Err("A task can’t be blocked by its parent task".to_owned());
+            }
+        }
         Ok(())
     }
 }
@@ -397,9 +480,47 @@ pub struct TaskUpdateEventContent {
         deserialize_with = "deserialize_some"
     )]
     pub categories: Option<Vec<String>>,
+
+    /// The task this is a subtask of
+    #[builder(default)]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_some"
+    )]
+    pub parent_task: Option<Option<OwnedEventId>>,
+
+    /// Tasks that need to be done before this one can be worked on
+    #[builder(default)]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_some"
+    )]
+    pub blocked_by: Option<Vec<OwnedEventId>>,
+
+    /// Post the next instance of this task once it is done
+    #[builder(default)]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_some"
+    )]
+    pub recurrence: Option<Option<TaskRecurrence>>,
+
+    /// The revision of the task this update is based on, to detect
+    /// concurrent changes by others
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_revision: Option<OwnedEventId>,
 }
 
 impl TaskUpdateEventContent {
+    /// Whether this update changes the parent or the blockers of the task
+    pub fn changes_relations(&self) -> bool {
+        self.parent_task.is_some() || self.blocked_by.is_some()
+    }
+
     pub fn apply(&self, task: &mut TaskEventContent) -> ActerResult<bool> {
         let mut updated = false;
         if let Some(title) = &self.title {
@@ -446,6 +567,18 @@ impl TaskUpdateEventContent {
             task.categories.clone_from(categories);
             updated = true;
         }
+        if let Some(parent_task) = &self.parent_task {
+            task.parent_task.clone_from(parent_task);
+            updated = true;
+        }
+        if let Some(blocked_by) = &self.blocked_by {
+            task.blocked_by.clone_from(blocked_by);
+            updated = true;
+        }
+        if let Some(recurrence) = &self.recurrence {
+            task.recurrence.clone_from(recurrence);
+            updated = true;
+        }
 
         trace!(update = ?self, ?updated, ?task, "Task updated");
 
@@ -472,3 +605,70 @@ pub struct TaskSelfUnassignEventContent {
     #[serde(rename = "m.relates_to")]
     pub task: BelongsTo,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> Date {
+        Date::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn next_instance_from_due_date() -> ActerResult<()> {
+        let recurrence = TaskRecurrence::new(
+            "FREQ=WEEKLY;BYDAY=MO,TH".parse()?,
+            RecurrenceAnchor::DueDate,
+        );
+        // completed late, the schedule stays fixed
+        let (next, _) = recurrence
+            .next(Some(date(2024, 1, 8)), date(2024, 1, 10))
+            .unwrap();
+        assert_eq!(next, date(2024, 1, 11));
+
+        // without a due date we fall back to the completion date
+        let (next, _) = recurrence.next(None, date(2024, 1, 12)).unwrap();
+        assert_eq!(next, date(2024, 1, 15));
+        Ok(())
+    }
+
+    #[test]
+    fn next_instance_from_completion() -> ActerResult<()> {
+        let recurrence = TaskRecurrence::new(
+            "FREQ=DAILY;INTERVAL=3".parse()?,
+            RecurrenceAnchor::Completion,
+        );
+        let (next, _) = recurrence
+            .next(Some(date(2024, 1, 8)), date(2024, 1, 10))
+            .unwrap();
+        assert_eq!(next, date(2024, 1, 13));
+        Ok(())
+    }
+
+    #[test]
+    fn recurrence_count_runs_out() -> ActerResult<()> {
+        let recurrence =
+            TaskRecurrence::new("FREQ=MONTHLY;COUNT=2".parse()?, RecurrenceAnchor::DueDate);
+        let (next, following) = recurrence
+            .next(Some(date(2024, 1, 15)), date(2024, 1, 15))
+            .unwrap();
+        assert_eq!(next, date(2024, 2, 15));
+        assert_eq!(following.rule.count, Some(1));
+        assert!(following.next(Some(next), next).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn recurrence_serialization() -> ActerResult<()> {
+        let recurrence = TaskRecurrence::new("FREQ=WEEKLY".parse()?, RecurrenceAnchor::Completion);
+        let json = serde_json::to_value(&recurrence)?;
+        assert_eq!(
+            json,
+            serde_json::json!({ "rule": "FREQ=WEEKLY", "relative_to": "completion" })
+        );
+        let parsed: TaskRecurrence =
+            serde_json::from_value(serde_json::json!({ "rule": "FREQ=DAILY" }))?;
+        assert_eq!(parsed.relative_to, RecurrenceAnchor::DueDate);
+        Ok(())
+    }
+}
@@ -11,14 +11,19 @@ use tracing::{error, info, trace, trace_span, warn};
 use crate::{
     models::{ActerModel, AnyActerModel, EventMeta, RedactedActerModel},
     referencing::ExecuteReference,
-    store::Store,
+    store::{is_local_id, Store},
     Error, Result,
 };
 
+mod news_drafts;
+mod outbox;
+mod stories;
+
 #[derive(Clone, Debug)]
 pub struct Executor {
     store: Store,
     notifiers: Arc<HashMap<ExecuteReference, Sender<()>>>,
+    flushing_outbox: Arc<tokio::sync::Mutex<()>>,
 }
 
 impl Executor {
@@ -26,6 +31,7 @@ impl Executor {
         Ok(Executor {
             store,
             notifiers: Default::default(),
+            flushing_outbox: Default::default(),
         })
     }
 
@@ -96,6 +102,10 @@ impl Executor {
     }
 
     pub async fn handle(&self, model: AnyActerModel) -> Result<()> {
+        if !is_local_id(model.event_id()) && self.store.with_outbox(|o| !o.entries.is_empty())? {
+            // pending models might need to be rebased or reconciled
+            return self.handle_with_outbox(model).await;
+        }
         let event_id = model.event_id().to_string();
         trace!(?event_id, ?model, "handle");
         match model.execute(&self.store).await {
@@ -147,7 +157,7 @@ impl Executor {
             }
             Err(error) => return Err(error),
         }
-        Ok(())
+        self.outbox_target_removed(&event_id).await
     }
 
     pub async fn live_redact(&self, event: OriginalRoomRedactionEvent) -> Result<()> {
@@ -172,11 +182,11 @@ impl Executor {
             }
             Err(Error::ModelNotFound(_)) => {
                 info!(?event_id, "live redaction: not found");
-                self.notify(vec![ExecuteReference::Model(event_id)]);
+                self.notify(vec![ExecuteReference::Model(event_id.clone())]);
             }
             Err(error) => return Err(error),
         }
-        Ok(())
+        self.outbox_target_removed(&event_id).await
     }
 }
 
@@ -185,9 +195,10 @@ mod tests {
 
     use super::*;
     use crate::{
-        events::{comments::CommentEventContent, BelongsTo},
-        models::{Comment, TestModelBuilder},
-        referencing::{IndexKey, ObjectListIndex},
+        events::{comments::CommentEventContent, AnyActerEvent, BelongsTo},
+        models::{Comment, Task, TaskList, TestModelBuilder},
+        referencing::{IndexKey, ObjectListIndex, SectionIndex},
+        store::{OutboxState, SearchQuery, OUTBOX_KEY},
     };
     use matrix_sdk::Client;
     use matrix_sdk_base::{
@@ -410,4 +421,773 @@ mod tests {
 
         Ok(())
     }
+
+    fn task_event(event_id: &str, content: serde_json::Value) -> AnyActerModel {
+        let event: AnyActerEvent = from_value(json!({
+            "type": content["type"],
+            "room_id": "!tasks:example.org",
+            "sender": "@test:example.org",
+            "origin_server_ts": 1672407531453_u64,
+            "event_id": event_id,
+            "content": content["content"],
+        }))
+        .unwrap();
+        AnyActerModel::try_from(event).unwrap()
+    }
+
+    fn task(event_id: &str, parent: Option<&str>, percent: Option<u8>) -> AnyActerModel {
+        task_event(
+            event_id,
+            json!({
+                "type": "global.acter.dev.task",
+                "content": {
+                    "title": event_id,
+                    "m.relates_to": {
+                        "rel_type": "global.acter.dev.belongs_to",
+                        "event_id": "$list",
+                    },
+                    "parent_task": parent,
+                    "progress_percent": percent,
+                },
+            }),
+        )
+    }
+
+    fn task_update(event_id: &str, task_id: &str, update: serde_json::Value) -> AnyActerModel {
+        let mut content = update;
+        content["m.relates_to"] = json!({
+            "rel_type": "global.acter.dev.update",
+            "event_id": task_id,
+        });
+        task_event(
+            event_id,
+            json!({
+                "type": "global.acter.dev.task.update",
+                "content": content,
+            }),
+        )
+    }
+
+    async fn get_task(executor: &Executor, event_id: &str) -> Result<Task> {
+        let AnyActerModel::Task(task) = executor
+            .store()
+            .get(&OwnedEventId::try_from(event_id)?)
+            .await?
+        else {
+            panic!("{event_id} is not a task");
+        };
+        Ok(task)
+    }
+
+    async fn executor_with_task_list() -> Result<Executor> {
+        let executor = fresh_executor().await?;
+        executor
+            .handle(task_event(
+                "$list",
+                json!({
+                    "type": "global.acter.dev.tasklist",
+                    "content": { "name": "list" },
+                }),
+            ))
+            .await?;
+        Ok(executor)
+    }
+
+    #[tokio::test]
+    async fn subtasks_roll_up_progress() -> Result<()> {
+        let _ = env_logger::try_init();
+        let executor = executor_with_task_list().await?;
+        executor.handle(task("$parent", None, None)).await?;
+        let parent_sub = executor.subscribe(IndexKey::ObjectList(
+            OwnedEventId::try_from("$parent")?,
+            ObjectListIndex::Subtasks,
+        ));
+
+        executor
+            .handle(task("$child1", Some("$parent"), Some(100)))
+            .await?;
+        executor
+            .handle(task("$child2", Some("$parent"), None))
+            .await?;
+        // a grandchild arriving before its parent
+        executor
+            .handle(task("$grandchild", Some("$child3"), Some(50)))
+            .await?;
+        executor
+            .handle(task("$child3", Some("$parent"), None))
+            .await?;
+        assert!(!parent_sub.is_empty());
+
+        assert_eq!(get_task(&executor, "$child3").await?.percent(), Some(50));
+        let parent = get_task(&executor, "$parent").await?;
+        assert_eq!(parent.subtasks_stats().total_count(), 3);
+        assert_eq!(parent.subtasks_stats().done_count(), 1);
+        assert_eq!(parent.percent(), Some(50)); // (100 + 0 + 50) / 3
+        assert_eq!(parent.own_percent(), None);
+
+        // progress on the grandchild travels all the way up
+        executor
+            .handle(task_update(
+                "$update1",
+                "$grandchild",
+                json!({ "progress_percent": 100 }),
+            ))
+            .await?;
+        let parent = get_task(&executor, "$parent").await?;
+        assert_eq!(parent.subtasks_stats().done_count(), 2);
+        assert_eq!(parent.percent(), Some(66));
+
+        // moving a subtask away from the parent
+        executor
+            .handle(task_update(
+                "$update2",
+                "$child2",
+                json!({ "parent_task": null }),
+            ))
+            .await?;
+        let parent = get_task(&executor, "$parent").await?;
+        assert_eq!(parent.subtasks_stats().total_count(), 2);
+        assert_eq!(parent.percent(), Some(100));
+        assert!(parent.is_done());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn redacted_subtasks_leave_their_parent() -> Result<()> {
+        let _ = env_logger::try_init();
+        let executor = executor_with_task_list().await?;
+        executor.handle(task("$parent", None, Some(100))).await?;
+        executor
+            .handle(task("$done", Some("$parent"), Some(100)))
+            .await?;
+        executor
+            .handle(task("$open", Some("$parent"), None))
+            .await?;
+
+        // its own progress doesn't count while a subtask is open
+        let parent = get_task(&executor, "$parent").await?;
+        assert_eq!(parent.own_percent(), Some(100));
+        assert_eq!(parent.percent(), Some(50));
+        assert!(!parent.is_done());
+
+        let open = get_task(&executor, "$open").await?;
+        let redaction: UnsignedRoomRedactionEvent = from_value(json!({
+            "event_id" : "$open:redacted",
+            "sender": "@test:example.org",
+            "origin_server_ts": 1672407531454_u64,
+            "content": { "redacts" : "$open", },
+        }))?;
+        executor
+            .redact(
+                "global.acter.dev.task".to_owned(),
+                open.event_meta().clone(),
+                redaction,
+            )
+            .await?;
+
+        let parent = get_task(&executor, "$parent").await?;
+        assert_eq!(parent.subtasks_stats().total_count(), 1);
+        assert_eq!(parent.percent(), Some(100));
+        assert!(parent.is_done());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn task_relation_cycles_are_ignored() -> Result<()> {
+        let _ = env_logger::try_init();
+        let executor = executor_with_task_list().await?;
+        executor.handle(task("$a", None, None)).await?;
+        executor.handle(task("$b", Some("$a"), None)).await?;
+        executor.handle(task("$c", Some("$b"), None)).await?;
+        let store = executor.store();
+        let a = OwnedEventId::try_from("$a")?;
+        let c = OwnedEventId::try_from("$c")?;
+
+        assert!(matches!(
+            Task::ensure_no_cycles(store, &a, Some(&c), &[]).await,
+            Err(Error::RelationCycle(_))
+        ));
+        assert!(Task::ensure_no_cycles(store, &c, Some(&a), &[])
+            .await
+            .is_ok());
+
+        // making $a a subtask of its own grandchild is ignored
+        executor
+            .handle(task_update(
+                "$update1",
+                "$a",
+                json!({ "parent_task": "$c", "title": "still a" }),
+            ))
+            .await?;
+        let a_task = get_task(&executor, "$a").await?;
+        assert_eq!(a_task.parent_task(), None);
+        assert_eq!(a_task.title(), "still a");
+
+        // blocking chains
+        executor
+            .handle(task_update(
+                "$update2",
+                "$b",
+                json!({ "blocked_by": ["$c"] }),
+            ))
+            .await?;
+        assert_eq!(
+            get_task(&executor, "$b").await?.blocked_by(),
+            vec![c.clone()]
+        );
+        executor
+            .handle(task_update(
+                "$update3",
+                "$c",
+                json!({ "blocked_by": ["$b"] }),
+            ))
+            .await?;
+        assert!(get_task(&executor, "$c").await?.blocked_by().is_empty());
+        Ok(())
+    }
+
+    async fn get_task_list(executor: &Executor, event_id: &str) -> Result<TaskList> {
+        let AnyActerModel::TaskList(list) = executor
+            .store()
+            .get(&OwnedEventId::try_from(event_id)?)
+            .await?
+        else {
+            panic!("{event_id} is not a task list");
+        };
+        Ok(list)
+    }
+
+    /// pretend the server accepted the queued change
+    fn mark_sent(executor: &Executor, local_id: &OwnedEventId, event_id: &str) -> Result<()> {
+        let event_id = OwnedEventId::try_from(event_id)?;
+        executor.store().with_outbox(|o| {
+            o.resolved.insert(local_id.clone(), event_id.clone());
+            o.entry_mut(local_id)
+                .unwrap()
+                .set_state(OutboxState::Sent { event_id });
+        })
+    }
+
+    #[tokio::test]
+    async fn outbox_pending_task_reconciles() -> Result<()> {
+        let _ = env_logger::try_init();
+        let executor = executor_with_task_list().await?;
+        let local_id = executor
+            .enqueue(
+                OwnedRoomId::try_from("!tasks:example.org")?,
+                "global.acter.dev.task".to_owned(),
+                json!({
+                    "title": "offline task",
+                    "m.relates_to": {
+                        "rel_type": "global.acter.dev.belongs_to",
+                        "event_id": "$list",
+                    },
+                }),
+            )
+            .await?;
+        assert!(executor.store().is_pending(&local_id));
+        assert_eq!(
+            get_task(&executor, local_id.as_str()).await?.title(),
+            "offline task"
+        );
+        assert_eq!(
+            *get_task_list(&executor, "$list")
+                .await?
+                .stats()
+                .tasks_count(),
+            1
+        );
+
+        // someone else adds a task meanwhile
+        executor.handle(task("$other", None, None)).await?;
+        assert_eq!(
+            *get_task_list(&executor, "$list")
+                .await?
+                .stats()
+                .tasks_count(),
+            2
+        );
+
+        // ours comes back from the server
+        mark_sent(&executor, &local_id, "$real")?;
+        executor
+            .handle(task_event(
+                "$real",
+                json!({
+                    "type": "global.acter.dev.task",
+                    "content": {
+                        "title": "offline task",
+                        "m.relates_to": {
+                            "rel_type": "global.acter.dev.belongs_to",
+                            "event_id": "$list",
+                        },
+                    },
+                }),
+            ))
+            .await?;
+        assert!(executor.store().get(&local_id).await.is_err());
+        assert_eq!(get_task(&executor, "$real").await?.title(), "offline task");
+        // counted once, not twice
+        assert_eq!(
+            *get_task_list(&executor, "$list")
+                .await?
+                .stats()
+                .tasks_count(),
+            2
+        );
+        assert!(executor.store().outbox_entries()?.is_empty());
+        Ok(())
+    }
+
+    fn offline_task() -> serde_json::Value {
+        json!({
+            "title": "offline task",
+            "m.relates_to": {
+                "rel_type": "global.acter.dev.belongs_to",
+                "event_id": "$list",
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn outbox_drops_sent_changes_never_coming_back() -> Result<()> {
+        use matrix_sdk::ruma::MilliSecondsSinceUnixEpoch;
+        use std::time::{Duration, SystemTime};
+
+        let _ = env_logger::try_init();
+        let executor = executor_with_task_list().await?;
+        let local_id = executor
+            .enqueue(
+                OwnedRoomId::try_from("!tasks:example.org")?,
+                "global.acter.dev.task".to_owned(),
+                offline_task(),
+            )
+            .await?;
+        mark_sent(&executor, &local_id, "$real")?;
+
+        executor
+            .drop_unreconciled(MilliSecondsSinceUnixEpoch::now())
+            .await?;
+        assert!(
+            executor.store().get(&local_id).await.is_ok(),
+            "still waiting"
+        );
+
+        let next_week = SystemTime::now() + Duration::from_secs(7 * 24 * 60 * 60);
+        executor
+            .drop_unreconciled(MilliSecondsSinceUnixEpoch::from_system_time(next_week).unwrap())
+            .await?;
+        assert!(executor.store().get(&local_id).await.is_err());
+        assert!(executor.store().outbox_entries()?.is_empty());
+        assert_eq!(
+            *get_task_list(&executor, "$list")
+                .await?
+                .stats()
+                .tasks_count(),
+            0
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pending_models_go_with_an_unreadable_outbox() -> Result<()> {
+        let _ = env_logger::try_init();
+        let executor = executor_with_task_list().await?;
+        let local_id = executor
+            .enqueue(
+                OwnedRoomId::try_from("!tasks:example.org")?,
+                "global.acter.dev.task".to_owned(),
+                offline_task(),
+            )
+            .await?;
+        executor
+            .store()
+            .set_raw(&OUTBOX_KEY.as_storage_key(), &"not an outbox")
+            .await?;
+
+        let store = Store::new_with_auth(
+            executor.store().client.clone(),
+            user_id!("@test:example.org").to_owned(),
+        )
+        .await?;
+        assert!(store.outbox_entries()?.is_empty());
+        assert!(store.get(&local_id).await.is_err());
+        assert!(store.get(&OwnedEventId::try_from("$list")?).await.is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn outbox_conflicting_update() -> Result<()> {
+        let _ = env_logger::try_init();
+        let executor = executor_with_task_list().await?;
+        executor.handle(task("$task", None, None)).await?;
+        let outbox_sub = executor.subscribe(OUTBOX_KEY.clone());
+        let local_id = executor
+            .enqueue(
+                OwnedRoomId::try_from("!tasks:example.org")?,
+                "global.acter.dev.task.update".to_owned(),
+                json!({
+                    "title": "mine",
+                    "m.relates_to": {
+                        "rel_type": "global.acter.dev.update",
+                        "event_id": "$task",
+                    },
+                }),
+            )
+            .await?;
+        assert!(!outbox_sub.is_empty());
+        assert_eq!(get_task(&executor, "$task").await?.title(), "mine");
+
+        // another user changes the task before ours was sent
+        let theirs: AnyActerEvent = from_value(json!({
+            "type": "global.acter.dev.task.update",
+            "room_id": "!tasks:example.org",
+            "sender": "@someone:example.org",
+            "origin_server_ts": 1672407531453_u64,
+            "event_id": "$theirs",
+            "content": {
+                "title": "theirs",
+                "progress_percent": 50,
+                "m.relates_to": {
+                    "rel_type": "global.acter.dev.update",
+                    "event_id": "$task",
+                },
+            },
+        }))?;
+        executor
+            .handle(AnyActerModel::try_from(theirs).unwrap())
+            .await?;
+
+        let entries = executor.store().outbox_entries()?;
+        assert!(matches!(entries[0].state(), OutboxState::Conflict { .. }));
+        // our pending change is still shown, on top of theirs
+        let task = get_task(&executor, "$task").await?;
+        assert_eq!(task.title(), "mine");
+        assert_eq!(task.percent(), Some(50));
+
+        // dropping ours reveals theirs
+        executor.discard_outbox_entry(&local_id).await?;
+        let task = get_task(&executor, "$task").await?;
+        assert_eq!(task.title(), "theirs");
+        assert_eq!(task.percent(), Some(50));
+        assert!(executor.store().outbox_entries()?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_follows_updates_and_redactions() -> Result<()> {
+        let _ = env_logger::try_init();
+        let executor = executor_with_task_list().await?;
+        executor.handle(task("$garden", None, None)).await?;
+        executor.handle(task("$kitchen", None, None)).await?;
+
+        let search = |text: &str| -> Result<Vec<String>> {
+            Ok(executor
+                .store()
+                .search(&SearchQuery {
+                    text: text.to_owned(),
+                    sections: vec![SectionIndex::Tasks],
+                    ..Default::default()
+                })?
+                .into_iter()
+                .map(|h| h.event_id.to_string())
+                .collect())
+        };
+        assert_eq!(search("garden")?, vec!["$garden"]);
+
+        executor
+            .handle(task_update(
+                "$rename",
+                "$kitchen",
+                json!({ "title": "water the garden plants" }),
+            ))
+            .await?;
+        let found = search("gard")?;
+        assert_eq!(found.len(), 2);
+        assert!(search("kitchen")?.is_empty());
+
+        let task = get_task(&executor, "$garden").await?;
+        let redaction: UnsignedRoomRedactionEvent = from_value(json!({
+            "event_id" : "$redaction",
+            "sender": "@test:example.org",
+            "origin_server_ts": 123456,
+            "content": { "redacts" : "$garden", },
+        }))?;
+        executor
+            .redact(
+                "global.acter.dev.task".to_owned(),
+                task.event_meta().clone(),
+                redaction,
+            )
+            .await?;
+        assert_eq!(search("garden")?, vec!["$kitchen"]);
+        Ok(())
+    }
+
+    fn story(event_id: &str, origin_server_ts: u64, expires_at: Option<&str>) -> AnyActerModel {
+        let event: AnyActerEvent = from_value(json!({
+            "type": "global.acter.dev.story",
+            "room_id": "!stories:example.org",
+            "sender": "@test:example.org",
+            "origin_server_ts": origin_server_ts,
+            "event_id": event_id,
+            "content": { "slides": [], "expires_at": expires_at },
+        }))
+        .unwrap();
+        AnyActerModel::try_from(event).unwrap()
+    }
+
+    #[tokio::test]
+    async fn expired_stories_are_archived() -> Result<()> {
+        let _ = env_logger::try_init();
+        let executor = fresh_executor().await?;
+        let now = chrono::DateTime::parse_from_rfc3339("2026-10-18T12:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let an_hour_ago = (now - chrono::Duration::hours(1)).timestamp_millis() as u64;
+        let two_days_ago = (now - chrono::Duration::days(2)).timestamp_millis() as u64;
+
+        // past the default of a day
+        executor.handle(story("$old", two_days_ago, None)).await?;
+        executor.handle(story("$fresh", an_hour_ago, None)).await?;
+        // the sender asked for longer
+        executor
+            .handle(story("$pinned", two_days_ago, Some("2026-10-18T15:00:00Z")))
+            .await?;
+
+        let mut archive_sub = executor.subscribe(IndexKey::Section(SectionIndex::StoriesArchive));
+        let next = executor.archive_expired_stories(now).await?;
+        assert_eq!(next, Some(now + chrono::Duration::hours(3)));
+        assert!(archive_sub.try_recv().is_ok(), "archive wasn't notified");
+
+        let listed = |section| {
+            let store = executor.store().clone();
+            async move {
+                Ok::<_, Error>(
+                    store
+                        .get_list(&IndexKey::Section(section))
+                        .await?
+                        .map(|m| m.event_id().to_string())
+                        .collect::<Vec<_>>(),
+                )
+            }
+        };
+        let mut current = listed(SectionIndex::Stories).await?;
+        current.sort();
+        assert_eq!(current, ["$fresh", "$pinned"]);
+        assert_eq!(listed(SectionIndex::StoriesArchive).await?, ["$old"]);
+        let AnyActerModel::Story(old) = executor.store().get(&event_id!("$old").to_owned()).await?
+        else {
+            panic!("not a story");
+        };
+        assert!(old.is_archived());
+
+        // a day later, everything is gone
+        let next = executor
+            .archive_expired_stories(now + chrono::Duration::days(1))
+            .await?;
+        assert_eq!(next, None);
+        assert!(listed(SectionIndex::Stories).await?.is_empty());
+        assert_eq!(listed(SectionIndex::StoriesArchive).await?.len(), 3);
+
+        // pushing the expiry out brings it back
+        let update: AnyActerEvent = from_value(json!({
+            "type": "global.acter.dev.story.update",
+            "room_id": "!stories:example.org",
+            "sender": "@test:example.org",
+            "origin_server_ts": an_hour_ago,
+            "event_id": "$extend",
+            "content": {
+                "m.relates_to": {
+                    "rel_type": "global.acter.dev.update",
+                    "event_id": "$old",
+                },
+                "expires_at": "2026-10-20T12:00:00Z",
+            },
+        }))
+        .unwrap();
+        executor
+            .handle(AnyActerModel::try_from(update).unwrap())
+            .await?;
+        let next = executor
+            .archive_expired_stories(now + chrono::Duration::days(1))
+            .await?;
+        assert_eq!(next, Some(now + chrono::Duration::days(2)));
+        assert_eq!(listed(SectionIndex::Stories).await?, ["$old"]);
+        assert_eq!(listed(SectionIndex::StoriesArchive).await?.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scheduled_news_are_published() -> Result<()> {
+        use crate::store::{NewsDraft, NEWS_DRAFTS_KEY};
+        use matrix_sdk::ruma::{owned_room_id, MilliSecondsSinceUnixEpoch, UInt};
+
+        let _ = env_logger::try_init();
+        let executor = fresh_executor().await?;
+        let at = |millis: u32| MilliSecondsSinceUnixEpoch(UInt::from(millis));
+        let room_id = owned_room_id!("!news:example.org");
+
+        let draft = NewsDraft::new(room_id.clone(), vec![]);
+        let mut due = NewsDraft::new(room_id.clone(), vec![]);
+        due.set_publish_at(Some(at(1000)));
+        let mut later = NewsDraft::new(room_id, vec![]);
+        later.set_publish_at(Some(at(5000)));
+        for d in [&draft, &due, &later] {
+            executor.store().add_news_draft(d.clone()).await?;
+        }
+
+        let mut sub = executor.subscribe(NEWS_DRAFTS_KEY.clone());
+        // we don't know that room, so it can't be published
+        assert_eq!(executor.publish_due_news(at(2000)).await?, Some(at(5000)));
+        assert!(sub.try_recv().is_ok(), "drafts weren't notified");
+        let failed = executor.store().news_draft(due.draft_id())?;
+        assert_eq!(
+            failed.last_error(),
+            Some("Room !news:example.org not found")
+        );
+        assert!(executor.store().outbox_entries()?.is_empty());
+
+        // publishing right away queues it as news entry
+        let local_id = executor.publish_news_draft(draft.draft_id()).await?;
+        let entries = executor.store().outbox_entries()?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].local_id(), &*local_id);
+        assert_eq!(entries[0].event_type(), "global.acter.dev.news");
+        let remaining: Vec<_> = executor
+            .store()
+            .news_drafts()?
+            .into_iter()
+            .map(|d| d.draft_id().to_owned())
+            .collect();
+        assert_eq!(remaining, [due.draft_id(), later.draft_id()]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn news_drafts_change_only_while_there() -> Result<()> {
+        use crate::{store::NewsDraft, Error};
+        use matrix_sdk::ruma::{owned_room_id, MilliSecondsSinceUnixEpoch, UInt};
+
+        let executor = fresh_executor().await?;
+        let store = executor.store();
+        let room_id = owned_room_id!("!news:example.org");
+        let first = NewsDraft::new(room_id.clone(), vec![]);
+        let second = NewsDraft::new(room_id, vec![]);
+        let (first_id, second_id) = (first.draft_id(), second.draft_id());
+        store.add_news_draft(first.clone()).await?;
+        store.add_news_draft(second.clone()).await?;
+        assert!(
+            store.add_news_draft(first.clone()).await.is_err(),
+            "added twice"
+        );
+        let ids = || -> Result<Vec<String>> {
+            Ok(store
+                .news_drafts()?
+                .into_iter()
+                .map(|d| d.draft_id().to_owned())
+                .collect())
+        };
+
+        // failing to publish puts it back where it was
+        let failed = store
+            .take_news_draft(first_id, |draft, _| async move {
+                assert!(draft.slides().is_empty());
+                // nobody else gets to it meanwhile
+                assert_eq!(ids()?, [second_id]);
+                Err::<(), _>(Error::Custom("offline".to_owned()))
+            })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(ids()?, [first_id, second_id]);
+
+        // failing changes leave it as it was
+        let at = MilliSecondsSinceUnixEpoch(UInt::from(1000u32));
+        let failed = store
+            .update_news_draft(first_id, |draft| {
+                draft.set_publish_at(Some(at));
+                Err(Error::Custom("invalid".to_owned()))
+            })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(store.news_draft(first_id)?.publish_at(), None);
+        store
+            .update_news_draft(first_id, |draft| {
+                draft.set_publish_at(Some(at));
+                Ok::<_, Error>(())
+            })
+            .await?;
+        assert_eq!(store.news_draft(first_id)?.publish_at(), Some(at));
+
+        // once published, it can't be changed nor published again
+        store
+            .take_news_draft(first_id, |_, _| async { Ok(()) })
+            .await?;
+        let changed = store
+            .update_news_draft(first_id, |_| Ok::<_, Error>(()))
+            .await;
+        assert!(matches!(changed, Err(Error::ModelNotFound(_))));
+        let again = store
+            .take_news_draft(first_id, |_, _| async { Ok(()) })
+            .await;
+        assert!(matches!(again, Err(Error::ModelNotFound(_))));
+        assert_eq!(ids()?, [second_id]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn interrupted_news_publishing_is_reconciled() -> Result<()> {
+        use crate::{
+            events::news::NewsEntryEventContent,
+            store::{NewsDraft, Store},
+        };
+        use matrix_sdk::ruma::{
+            events::StaticEventContent, owned_room_id, MilliSecondsSinceUnixEpoch,
+        };
+
+        let executor = &fresh_executor().await?;
+        let store = executor.store();
+        // what we find when starting again while `publish` runs
+        let restart = || {
+            Store::new_with_auth(
+                store.client.clone(),
+                user_id!("@test:example.org").to_owned(),
+            )
+        };
+
+        // going down before it was queued: it might have been posted or not
+        let mut lost = NewsDraft::new(owned_room_id!("!news:example.org"), vec![]);
+        lost.set_publish_at(Some(MilliSecondsSinceUnixEpoch::now()));
+        store.add_news_draft(lost.clone()).await?;
+        let (restarted, _) = store
+            .take_news_draft(lost.draft_id(), |_, _| restart())
+            .await?;
+        let lost = restarted.news_draft(lost.draft_id())?;
+        assert!(lost.last_error().is_some());
+        assert!(!lost.is_due(MilliSecondsSinceUnixEpoch::now()));
+
+        // going down once it was queued: the outbox publishes it
+        let queued = NewsDraft::new(owned_room_id!("!news:example.org"), vec![]);
+        store.add_news_draft(queued.clone()).await?;
+        let (restarted, _) = store
+            .take_news_draft(queued.draft_id(), |draft, txn_id| async move {
+                let content = serde_json::to_value(draft.content())?;
+                executor
+                    .enqueue_as(
+                        txn_id,
+                        draft.room_id().clone(),
+                        NewsEntryEventContent::TYPE.to_owned(),
+                        content,
+                    )
+                    .await?;
+                restart().await
+            })
+            .await?;
+        assert!(matches!(
+            restarted.news_draft(queued.draft_id()),
+            Err(Error::ModelNotFound(_))
+        ));
+        assert_eq!(restarted.outbox_entries()?.len(), 1);
+        Ok(())
+    }
 }
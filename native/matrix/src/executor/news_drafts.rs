@@ -0,0 +1,119 @@
+use matrix_sdk::ruma::{events::StaticEventContent, MilliSecondsSinceUnixEpoch, OwnedEventId};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{error, info, trace, warn};
+
+use super::Executor;
+use crate::{events::news::NewsEntryEventContent, store::NEWS_DRAFTS_KEY, Error, Result};
+
+/// Even without anything scheduled, look again every so often
+const MAX_NEWS_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+impl Executor {
+    /// Queue the draft as news entry and drop it, returns the event id of
+    /// the pending news entry
+    pub async fn publish_news_draft(&self, draft_id: &str) -> Result<OwnedEventId> {
+        let (local_id, keys) = self
+            .store
+            .take_news_draft(draft_id, |draft, txn_id| async move {
+                let content = serde_json::to_value(draft.content())?;
+                self.enqueue_as(
+                    txn_id,
+                    draft.room_id().clone(),
+                    NewsEntryEventContent::TYPE.to_owned(),
+                    content,
+                )
+                .await
+            })
+            .await?;
+        self.notify(keys);
+        Ok(local_id)
+    }
+
+    /// Keep why publishing the draft failed, unless it is gone by now
+    async fn fail_news_draft(&self, draft_id: &str, error: String) -> Result<()> {
+        match self
+            .store
+            .update_news_draft(draft_id, |draft| {
+                draft.fail(error);
+                Ok::<_, Error>(())
+            })
+            .await
+        {
+            Ok(keys) => self.notify(keys),
+            Err(Error::ModelNotFound(_)) => trace!(draft_id, "failed news draft is gone"),
+            Err(error) => return Err(error),
+        }
+        Ok(())
+    }
+
+    /// Publish the scheduled drafts that are due by `now`, returning when
+    /// the next one is
+    pub async fn publish_due_news(
+        &self,
+        now: MilliSecondsSinceUnixEpoch,
+    ) -> Result<Option<MilliSecondsSinceUnixEpoch>> {
+        let mut published = 0;
+        let mut next: Option<MilliSecondsSinceUnixEpoch> = None;
+        for draft in self.store.news_drafts()? {
+            if !draft.is_due(now) {
+                if draft.last_error().is_none() {
+                    if let Some(at) = draft.publish_at() {
+                        next = Some(next.map_or(at, |n| n.min(at)));
+                    }
+                }
+                continue;
+            }
+            let draft_id = draft.draft_id().to_owned();
+            if self.store.client.get_room(draft.room_id()).is_none() {
+                warn!(draft_id, room_id = ?draft.room_id(), "room of scheduled news not found");
+                let error = format!("Room {} not found", draft.room_id());
+                self.fail_news_draft(&draft_id, error).await?;
+                continue;
+            }
+            match self.publish_news_draft(&draft_id).await {
+                Ok(local_id) => {
+                    info!(draft_id, ?local_id, "scheduled news queued");
+                    published += 1;
+                }
+                Err(error) => {
+                    warn!(draft_id, ?error, "publishing scheduled news failed");
+                    self.fail_news_draft(&draft_id, error.to_string()).await?;
+                }
+            }
+        }
+        if published > 0 {
+            self.flush_outbox().await?;
+        }
+        Ok(next)
+    }
+
+    /// Keep publishing scheduled news as they become due, for as long as the
+    /// client lives. Meant to be spawned once sync has started.
+    pub async fn publish_news_on_schedule(self) {
+        let mut drafts_changed = self.subscribe(NEWS_DRAFTS_KEY.clone());
+        loop {
+            let now = MilliSecondsSinceUnixEpoch::now();
+            let wait = match self.publish_due_news(now).await {
+                Ok(Some(next)) => {
+                    let millis = next.get().saturating_sub(now.get());
+                    Duration::from_millis(u64::from(millis)).min(MAX_NEWS_CHECK_INTERVAL)
+                }
+                Ok(None) => MAX_NEWS_CHECK_INTERVAL,
+                Err(error) => {
+                    error!(?error, "publishing scheduled news failed");
+                    MAX_NEWS_CHECK_INTERVAL
+                }
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                changed = drafts_changed.recv() => {
+                    if let Err(RecvError::Closed) = changed {
+                        // resubscribing reopens the channel
+                        drafts_changed = self.subscribe(NEWS_DRAFTS_KEY.clone());
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use matrix_sdk::{
+    ruma::{
+        EventId, MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedRoomId, OwnedTransactionId,
+        TransactionId,
+    },
+    RumaApiError,
+};
+use serde_json::json;
+use std::collections::{BTreeSet, HashSet};
+use tracing::{info, trace, warn};
+
+use super::Executor;
+use crate::{
+    events::AnyActerEvent,
+    models::{ActerModel, AnyActerModel},
+    store::{local_id_for, OutboxEntry, OutboxState, OUTBOX_KEY},
+    Error, Result,
+};
+
+/// Whether this model changes another one rather than creating something new
+fn is_update(model: &AnyActerModel) -> bool {
+    matches!(
+        model,
+        AnyActerModel::CalendarEventUpdate(_)
+            | AnyActerModel::TaskListUpdate(_)
+            | AnyActerModel::TaskUpdate(_)
+            | AnyActerModel::PinUpdate(_)
+            | AnyActerModel::NewsEntryUpdate(_)
+            | AnyActerModel::StoryUpdate(_)
+            | AnyActerModel::CommentUpdate(_)
+            | AnyActerModel::AttachmentUpdate(_)
+    )
+}
+
+/// The model an update is targeting
+fn update_target(model: &AnyActerModel) -> Option<OwnedEventId> {
+    if !is_update(model) {
+        return None;
+    }
+    model.belongs_to()?.into_iter().next()
+}
+
+impl Executor {
+    /// Queue an acter event to be sent to the room and apply it locally right away.
+    ///
+    /// Returns the event id of the local pending model. It is replaced by the
+    /// real model once the event has been sent and came back via sync.
+    pub async fn enqueue(
+        &self,
+        room_id: OwnedRoomId,
+        event_type: String,
+        content: serde_json::Value,
+    ) -> Result<OwnedEventId> {
+        self.enqueue_as(TransactionId::new(), room_id, event_type, content)
+            .await
+    }
+
+    /// [`Self::enqueue`] with the transaction id to send it with, for callers
+    /// that need to know the local id up front
+    pub(crate) async fn enqueue_as(
+        &self,
+        txn_id: OwnedTransactionId,
+        room_id: OwnedRoomId,
+        event_type: String,
+        content: serde_json::Value,
+    ) -> Result<OwnedEventId> {
+        let local_id = local_id_for(&txn_id)?;
+        let model = self.pending_model(&local_id, &room_id, &event_type, &content)?;
+        let target = update_target(&model);
+        let ancestors = self.ancestors_of(&model).await;
+        let touches = ancestors.iter().map(|m| m.event_id().to_owned()).collect();
+        let entry = OutboxEntry::new(
+            local_id.clone(),
+            txn_id,
+            room_id,
+            event_type,
+            content,
+            target,
+            touches,
+        );
+        trace!(?local_id, "queueing");
+        self.store.with_outbox(move |outbox| {
+            for ancestor in ancestors {
+                // keep the oldest, that is what the server knows about
+                outbox
+                    .bases
+                    .entry(ancestor.event_id().to_owned())
+                    .or_insert(ancestor);
+            }
+            outbox.entries.push(entry);
+        })?;
+
+        if let Err(error) = self.handle(model).await {
+            self.store.with_outbox(|outbox| {
+                outbox.take_entry(&local_id);
+                outbox.prune();
+            })?;
+            return Err(error);
+        }
+        self.store.persist_outbox().await?;
+        self.notify(vec![OUTBOX_KEY.clone()]);
+        Ok(local_id)
+    }
+
+    /// Send all queued changes that are due, in order.
+    ///
+    /// Changes failing for transient reasons are retried with backoff; later
+    /// changes to the same room wait for them. Returns how many were sent.
+    pub async fn flush_outbox(&self) -> Result<u32> {
+        let Ok(_guard) = self.flushing_outbox.try_lock() else {
+            trace!("outbox is already being flushed");
+            return Ok(0);
+        };
+        let now = MilliSecondsSinceUnixEpoch::now();
+        self.drop_unreconciled(now).await?;
+        let entries = self.store.outbox_entries()?;
+        let mut blocked_rooms = HashSet::new();
+        let mut sent = 0u32;
+
+        for entry in entries {
+            let room_id = entry.room_id().clone();
+            if blocked_rooms.contains(&room_id) {
+                continue;
+            }
+            if !entry.is_due(now) {
+                if matches!(entry.state(), OutboxState::Queued) {
+                    // waiting for a retry, keep the order
+                    blocked_rooms.insert(room_id);
+                }
+                continue;
+            }
+            let local_id = entry.local_id().to_owned();
+            let Some(content) = self
+                .store
+                .with_outbox(|o| o.resolve_local_ids(entry.content()))?
+            else {
+                trace!(?local_id, "refers to a model not sent yet, waiting");
+                blocked_rooms.insert(room_id);
+                continue;
+            };
+            let Some(room) = self.store.client.get_room(&room_id) else {
+                warn!(?local_id, ?room_id, "room of queued change not found");
+                self.store.with_outbox(|o| {
+                    if let Some(e) = o.entry_mut(&local_id) {
+                        e.fail(format!("Room {room_id} not found"));
+                    }
+                })?;
+                self.store.persist_outbox().await?;
+                self.notify(vec![OUTBOX_KEY.clone()]);
+                continue;
+            };
+
+            match room
+                .send_raw(entry.event_type(), content)
+                .with_transaction_id(entry.txn_id().clone())
+                .await
+            {
+                Ok(response) => {
+                    let event_id = response.event_id;
+                    info!(?local_id, ?event_id, "queued change sent");
+                    self.store.with_outbox(|o| {
+                        o.resolved.insert(local_id.clone(), event_id.clone());
+                        if let Some(e) = o.entry_mut(&local_id) {
+                            e.set_state(OutboxState::Sent {
+                                event_id: event_id.clone(),
+                            });
+                        }
+                    })?;
+                    sent += 1;
+                    self.store.persist_outbox().await?;
+                    self.notify(vec![OUTBOX_KEY.clone()]);
+                    if self.store.get(&event_id).await.is_ok() {
+                        // it came back via sync before the response did
+                        self.reconcile_sent(&event_id).await?;
+                    }
+                }
+                Err(error) => {
+                    blocked_rooms.insert(room_id);
+                    let permanent = matches!(
+                        error.as_ruma_api_error(),
+                        Some(RumaApiError::ClientApi(e))
+                            if e.status_code.is_client_error() && e.status_code.as_u16() != 429
+                    );
+                    warn!(?local_id, ?error, permanent, "sending queued change failed");
+                    self.store.with_outbox(|o| {
+                        if let Some(e) = o.entry_mut(&local_id) {
+                            if permanent {
+                                e.fail(error.to_string());
+                            } else {
+                                e.backoff(error.to_string());
+                            }
+                        }
+                    })?;
+                    self.store.persist_outbox().await?;
+                    self.notify(vec![OUTBOX_KEY.clone()]);
+                }
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Queue a failed or conflicting change to be sent again
+    pub async fn retry_outbox_entry(&self, local_id: &EventId) -> Result<()> {
+        let found = self.store.with_outbox(|o| match o.entry_mut(local_id) {
+            Some(entry) if entry.event_id().is_none() => {
+                entry.requeue();
+                true
+            }
+            _ => false,
+        })?;
+        if !found {
+            return Err(Error::ModelNotFound(local_id.to_string()));
+        }
+        self.store.persist_outbox().await?;
+        self.notify(vec![OUTBOX_KEY.clone()]);
+        Ok(())
+    }
+
+    /// Drop a queued change and undo its pending model
+    pub async fn discard_outbox_entry(&self, local_id: &EventId) -> Result<()> {
+        let Some(entry) = self.store.with_outbox(|o| o.take_entry(local_id))? else {
+            return Err(Error::ModelNotFound(local_id.to_string()));
+        };
+        info!(?local_id, "discarding queued change");
+        self.rebase(Some(entry), None, Default::default(), true)
+            .await
+    }
+
+    /// handle a model from the server while there are queued changes
+    pub(super) async fn handle_with_outbox(&self, model: AnyActerModel) -> Result<()> {
+        let affected = self
+            .ancestors_of(&model)
+            .await
+            .iter()
+            .map(|m| m.event_id().to_owned())
+            .collect();
+        let my_id = self.store.user_id().to_owned();
+        let (reconciled, conflicts) = self.store.with_outbox(|outbox| {
+            let reconciled = outbox.take_sent(model.event_id());
+            let mut conflicts = false;
+            let sender = &model.event_meta().sender;
+            let Some(target) = update_target(&model).filter(|_| sender != &my_id) else {
+                return (reconciled, conflicts);
+            };
+            for entry in outbox.entries.iter_mut().filter(|e| {
+                matches!(e.state(), OutboxState::Queued | OutboxState::Failed { .. })
+                    && e.target() == Some(&*target)
+                    && e.event_type() == model.model_type()
+            }) {
+                warn!(local_id=?entry.local_id(), ?target, ?sender, "queued change conflicts");
+                entry.set_state(OutboxState::Conflict {
+                    reason: format!("{sender} changed it meanwhile"),
+                });
+                conflicts = true;
+            }
+            (reconciled, conflicts)
+        })?;
+        let outbox_changed = reconciled.is_some() || conflicts;
+        self.rebase(reconciled, Some(model), affected, outbox_changed)
+            .await
+    }
+
+    /// the real event of a sent change is already known, drop the pending model
+    async fn reconcile_sent(&self, event_id: &EventId) -> Result<()> {
+        let Some(entry) = self.store.with_outbox(|o| o.take_sent(event_id))? else {
+            return Ok(());
+        };
+        // the bases already contain the real model, it was rebased when it came in
+        self.rebase(Some(entry), None, Default::default(), true)
+            .await
+    }
+
+    /// Drop sent changes whose event didn't come back via sync for too long,
+    /// along with their pending model. If it shows up later, it is handled
+    /// like any other event.
+    pub(super) async fn drop_unreconciled(&self, now: MilliSecondsSinceUnixEpoch) -> Result<()> {
+        let stale: Vec<OwnedEventId> = self.store.with_outbox(|o| {
+            o.entries
+                .iter()
+                .filter(|e| e.is_unreconciled(now))
+                .filter_map(|e| e.event_id().map(ToOwned::to_owned))
+                .collect()
+        })?;
+        for event_id in stale {
+            warn!(?event_id, "sent change never came back, dropping it");
+            self.reconcile_sent(&event_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Mark queued updates of a removed model as conflicting
+    pub(super) async fn outbox_target_removed(&self, event_id: &EventId) -> Result<()> {
+        let changed = self.store.with_outbox(|o| {
+            let mut changed = false;
+            for entry in o
+                .entries
+                .iter_mut()
+                .filter(|e| e.target() == Some(event_id) && e.event_id().is_none())
+            {
+                entry.set_state(OutboxState::Conflict {
+                    reason: "It has been removed meanwhile".to_owned(),
+                });
+                changed = true;
+            }
+            changed
+        })?;
+        if changed {
+            self.store.persist_outbox().await?;
+            self.notify(vec![OUTBOX_KEY.clone()]);
+        }
+        Ok(())
+    }
+
+    /// Restore the models touched by pending models to what the server knows,
+    /// drop the pending model of `reconciled`, apply `incoming` and re-apply
+    /// the remaining pending models on top.
+    async fn rebase(
+        &self,
+        reconciled: Option<OutboxEntry>,
+        incoming: Option<AnyActerModel>,
+        mut affected: BTreeSet<OwnedEventId>,
+        outbox_changed: bool,
+    ) -> Result<()> {
+        let mut keys = vec![];
+        if outbox_changed {
+            keys.push(OUTBOX_KEY.clone());
+        }
+        if let Some(entry) = &reconciled {
+            trace!(local_id=?entry.local_id(), "dropping pending model");
+            affected.extend(entry.touches().iter().cloned());
+            keys.extend(
+                self.store
+                    .remove_model(&entry.local_id().to_owned())
+                    .await?,
+            );
+        }
+
+        let bases: Vec<AnyActerModel> = self.store.with_outbox(|o| {
+            affected
+                .iter()
+                .filter_map(|id| o.bases.get(id).cloned())
+                .collect()
+        })?;
+        // only models with pending changes need rebasing
+        let affected: Vec<OwnedEventId> = bases.iter().map(|m| m.event_id().to_owned()).collect();
+        if !bases.is_empty() {
+            keys.extend(self.store.save_many(bases).await?);
+        }
+
+        let result = match incoming {
+            Some(model) => model.execute(&self.store).await,
+            None => Ok(vec![]),
+        };
+
+        let new_bases = self.store.get_many(affected.clone()).await;
+        let pending: Vec<OwnedEventId> = self.store.with_outbox(|o| {
+            for base in new_bases.into_iter().flatten() {
+                o.bases.insert(base.event_id().to_owned(), base);
+            }
+            o.prune();
+            o.entries
+                .iter()
+                .filter(|e| e.touches().iter().any(|t| affected.contains(t)))
+                .map(|e| e.local_id().to_owned())
+                .collect()
+        })?;
+        for local_id in pending {
+            let Ok(model) = self.store.get(&local_id).await else {
+                continue;
+            };
+            match model.execute(&self.store).await {
+                Ok(k) => keys.extend(k),
+                Err(error) => warn!(?local_id, ?error, "re-applying pending model failed"),
+            }
+        }
+        if outbox_changed || !affected.is_empty() {
+            self.store.persist_outbox().await?;
+        }
+
+        let result = result.map(|k| keys.extend(k));
+        self.notify(keys);
+        result
+    }
+
+    /// all models executing this one transitions, following `belongs_to`
+    async fn ancestors_of(&self, model: &AnyActerModel) -> Vec<AnyActerModel> {
+        let mut found = vec![];
+        let mut seen = HashSet::new();
+        let mut todo = model.belongs_to().unwrap_or_default();
+        while let Some(event_id) = todo.pop() {
+            if !seen.insert(event_id.clone()) {
+                continue;
+            }
+            let Ok(parent) = self.store.get(&event_id).await else {
+                continue;
+            };
+            todo.extend(parent.belongs_to().unwrap_or_default());
+            found.push(parent);
+        }
+        found
+    }
+
+    fn pending_model(
+        &self,
+        local_id: &EventId,
+        room_id: &OwnedRoomId,
+        event_type: &str,
+        content: &serde_json::Value,
+    ) -> Result<AnyActerModel> {
+        let event: AnyActerEvent = serde_json::from_value(json!({
+            "type": event_type,
+            "room_id": room_id,
+            "sender": self.store.user_id(),
+            "origin_server_ts": MilliSecondsSinceUnixEpoch::now(),
+            "event_id": local_id,
+            "content": content,
+        }))?;
+        AnyActerModel::try_from(event).map_err(|error| Error::FailedToParse {
+            model_type: event_type.to_owned(),
+            msg: error.to_string(),
+        })
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::{Duration, Utc};
+use matrix_sdk_base::ruma::OwnedRoomId;
+use std::collections::HashMap;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{error, trace, warn};
+
+use super::Executor;
+use crate::{
+    events::{settings::StoriesSettings, UtcDateTime},
+    models::{story_expiry, AnyActerModel},
+    referencing::{IndexKey, SectionIndex},
+    Result,
+};
+
+/// Even without anything expiring, look again every so often in case
+/// the space shortened or lengthened its expiry in the meantime
+const MAX_STORY_CHECK_INTERVAL_SECS: i64 = 15 * 60;
+
+impl Executor {
+    /// How long stories in that room are shown, falling back to the
+    /// default if we can't tell
+    async fn story_expiry_for(&self, room_id: &OwnedRoomId) -> Option<Duration> {
+        let default_expiry = StoriesSettings::default().expiry();
+        let Some(room) = self.store.client.get_room(room_id) else {
+            return default_expiry;
+        };
+        story_expiry(&room).await.unwrap_or_else(|error| {
+            warn!(?room_id, ?error, "reading story expiry failed");
+            default_expiry
+        })
+    }
+
+    /// Move the stories that expired by `now` to the
+    /// [`SectionIndex::StoriesArchive`] and back the archived ones whose
+    /// expiry was pushed out since, returning when the next one expires
+    pub async fn archive_expired_stories(&self, now: UtcDateTime) -> Result<Option<UtcDateTime>> {
+        let mut expiries: HashMap<OwnedRoomId, Option<Duration>> = HashMap::new();
+        let mut changed = Vec::new();
+        let mut next_expiry: Option<UtcDateTime> = None;
+
+        let current = self
+            .store
+            .get_list(&IndexKey::Section(SectionIndex::Stories))
+            .await?;
+        let archived = self
+            .store
+            .get_list(&IndexKey::Section(SectionIndex::StoriesArchive))
+            .await?;
+        for model in current.chain(archived) {
+            let AnyActerModel::Story(mut story) = model else {
+                continue;
+            };
+            let room_id = story.meta.room_id.clone();
+            let default_expiry = match expiries.get(&room_id) {
+                Some(expiry) => *expiry,
+                None => {
+                    let expiry = self.story_expiry_for(&room_id).await;
+                    expiries.insert(room_id, expiry);
+                    expiry
+                }
+            };
+            let expires_at = story.expires_at(default_expiry);
+            let expired = expires_at.is_some_and(|expires_at| expires_at <= now);
+            if let Some(expires_at) = expires_at.filter(|_| !expired) {
+                if next_expiry.map_or(true, |next| expires_at < next) {
+                    next_expiry = Some(expires_at);
+                }
+            }
+            if expired == story.is_archived() {
+                continue;
+            }
+            if expired {
+                story.archive();
+            } else {
+                story.unarchive();
+            }
+            changed.push(AnyActerModel::Story(story));
+        }
+
+        if !changed.is_empty() {
+            trace!(
+                count = changed.len(),
+                "moving stories in or out of the archive"
+            );
+            let keys = self.store.save_many(changed).await?;
+            self.notify(keys);
+        }
+        Ok(next_expiry)
+    }
+
+    /// Keep archiving stories as they expire, for as long as the client
+    /// lives. Meant to be spawned once sync has started.
+    pub async fn archive_stories_on_expiry(self) {
+        let mut stories_changed = self.subscribe(IndexKey::Section(SectionIndex::Stories));
+        // an update may push an archived story's expiry out again
+        let mut archive_changed = self.subscribe(IndexKey::Section(SectionIndex::StoriesArchive));
+        loop {
+            let now = Utc::now();
+            let max_wait = Duration::seconds(MAX_STORY_CHECK_INTERVAL_SECS);
+            let wait = match self.archive_expired_stories(now).await {
+                Ok(Some(next)) => (next - now).clamp(Duration::zero(), max_wait),
+                Ok(None) => max_wait,
+                Err(error) => {
+                    error!(?error, "archiving expired stories failed");
+                    max_wait
+                }
+            };
+            let wait = wait.to_std().unwrap_or_default();
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                changed = stories_changed.recv() => {
+                    if let Err(RecvError::Closed) = changed {
+                        // resubscribing reopens the channel
+                        stories_changed = self.subscribe(IndexKey::Section(SectionIndex::Stories));
+                    }
+                }
+                changed = archive_changed.recv() => {
+                    if let Err(RecvError::Closed) = changed {
+                        archive_changed = self.subscribe(IndexKey::Section(SectionIndex::StoriesArchive));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -8,12 +8,15 @@ pub use matrix_sdk::ruma;
 
 pub mod activities;
 pub mod client;
+pub mod convos;
+pub mod deep_linking;
 pub mod error;
 pub mod events;
 pub mod executor;
 pub mod models;
 pub mod push;
 pub mod referencing;
+pub mod reminders;
 pub mod share_link;
 pub mod spaces;
 pub mod statics;
@@ -6,6 +6,7 @@ mod comments;
 mod common;
 mod conversion;
 mod execution;
+mod history;
 mod invites;
 mod meta;
 mod news;
@@ -23,12 +24,16 @@ mod test;
 
 pub use any::{ActerModel, AnyActerModel};
 pub use attachments::{Attachment, AttachmentUpdate, AttachmentsManager, AttachmentsStats};
-pub use calendar::{CalendarEvent, CalendarEventUpdate};
+pub use calendar::{
+    ical_partstat, parse_ical, CalendarEvent, CalendarEventOccurrence, CalendarEventUpdate,
+    ImportedCalendarEvent, ParsedCalendar,
+};
 pub use capabilities::Capability;
 pub use comments::{Comment, CommentUpdate, CommentsManager, CommentsStats};
 pub use common::*;
 pub use core::fmt::Debug;
 pub(crate) use execution::default_model_execute;
+pub use history::{EditHistory, FieldChange, Revision, UpdateConflict};
 pub use invites::{InvitationsManager, InviteStats, MyInvitesManager};
 pub use meta::{can_redact, EventMeta};
 pub use news::{NewsEntry, NewsEntryUpdate};
@@ -38,10 +43,11 @@ pub use read_receipts::{ReadReceipt, ReadReceiptStats, ReadReceiptsManager};
 pub use redaction::RedactedActerModel;
 pub use rsvp::{Rsvp, RsvpManager, RsvpStats};
 pub use status::{ActerSupportedRoomStatusEvents, RoomStatus};
-pub use stories::{Story, StoryUpdate};
+pub use stories::{story_expiry, Story, StoryUpdate};
 pub use tag::Tag;
 pub use tasks::{
-    Task, TaskList, TaskListUpdate, TaskSelfAssign, TaskSelfUnassign, TaskStats, TaskUpdate,
+    SubtasksStats, Task, TaskList, TaskListUpdate, TaskSelfAssign, TaskSelfUnassign, TaskStats,
+    TaskUpdate,
 };
 
 #[cfg(any(test, feature = "testing"))]
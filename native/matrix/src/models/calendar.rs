@@ -1,3 +1,5 @@
 mod event;
+mod ical;
 
-pub use event::{CalendarEvent, CalendarEventUpdate};
+pub use event::{ical_partstat, CalendarEvent, CalendarEventOccurrence, CalendarEventUpdate};
+pub use ical::{parse_ical, ImportedCalendarEvent, ParsedCalendar};
@@ -1,28 +1,96 @@
-use icalendar::{Component, Event as iCalEvent, EventLike};
+use derive_getters::Getters;
+use icalendar::{CalendarDateTime, Component, Event as iCalEvent, EventLike, Property};
 use matrix_sdk::ruma::OwnedEventId;
-use matrix_sdk_base::ruma::{events::OriginalMessageLikeEvent, RoomId, UserId};
+use matrix_sdk_base::ruma::{events::OriginalMessageLikeEvent, EventId, RoomId, UserId};
 use serde::{Deserialize, Serialize};
 use std::ops::Deref;
+use tracing::warn;
 
 use super::super::{
-    default_model_execute, ActerModel, AnyActerModel, Capability, EventMeta, Store,
-    TextMessageContent,
+    default_model_execute, ActerModel, AnyActerModel, Capability, EditHistory, EventMeta, Revision,
+    Store, TextMessageContent, UpdateConflict,
 };
 use crate::{
     events::{
         calendar::{
             CalendarEventEventContent, CalendarEventUpdateBuilder, CalendarEventUpdateEventContent,
+            EventLocation, EventLocationInfo,
         },
-        UtcDateTime,
+        rsvp::RsvpStatus,
+        UtcDateTime, ICAL_UTC_FORMAT,
     },
     referencing::{ExecuteReference, IndexKey, SectionIndex},
-    Result,
+    Error, Result,
 };
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CalendarEvent {
     pub(crate) inner: CalendarEventEventContent,
     meta: EventMeta,
+
+    /// updates targeting single occurrences of the series, in order of arrival
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    overrides: Vec<CalendarEventUpdateEventContent>,
+
+    /// how many updates have been applied to this event
+    #[serde(default)]
+    update_count: u32,
+
+    #[serde(default, skip_serializing_if = "EditHistory::is_empty")]
+    history: EditHistory,
+}
+
+/// A single occurrence of a (possibly recurring) calendar event
+#[derive(Clone, Debug, Deserialize, Serialize, Getters)]
+pub struct CalendarEventOccurrence {
+    /// the original start time of this occurrence within the series
+    recurrence_id: UtcDateTime,
+    /// the content as it applies to this occurrence
+    inner: CalendarEventEventContent,
+    /// whether any update specific to this occurrence has been applied
+    overridden: bool,
+}
+
+impl Deref for CalendarEventOccurrence {
+    type Target = CalendarEventEventContent;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl CalendarEventOccurrence {
+    pub fn title(&self) -> String {
+        self.inner.title.clone()
+    }
+
+    pub fn description(&self) -> Option<TextMessageContent> {
+        self.inner.description.clone().map(TextMessageContent::from)
+    }
+
+    pub fn utc_start(&self) -> UtcDateTime {
+        self.inner.utc_start
+    }
+
+    pub fn utc_end(&self) -> UtcDateTime {
+        self.inner.utc_end
+    }
+
+    pub fn recurrence_id_rfc3339(&self) -> String {
+        self.recurrence_id
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+    }
+
+    pub fn is_overridden(&self) -> bool {
+        self.overridden
+    }
+
+    pub fn locations(&self) -> Vec<EventLocationInfo> {
+        self.inner
+            .locations
+            .iter()
+            .map(EventLocationInfo::new)
+            .collect()
+    }
 }
 impl Deref for CalendarEvent {
     type Target = CalendarEventEventContent;
@@ -51,9 +119,36 @@ impl CalendarEvent {
     pub fn updater(&self) -> CalendarEventUpdateBuilder {
         CalendarEventUpdateBuilder::default()
             .calendar_event(self.meta.event_id.clone())
+            .base_revision(self.revision())
             .to_owned()
     }
 
+    /// The revision this event is at, to base updates on
+    pub fn revision(&self) -> OwnedEventId {
+        self.history.latest_revision(&self.meta.event_id)
+    }
+
+    /// The updates applied since `revision`, `None` if it isn’t known
+    pub fn changed_since(&self, revision: &EventId) -> Option<&[Revision]> {
+        self.history.changed_since(&self.meta.event_id, revision)
+    }
+
+    /// Concurrent updates that changed the same fields and who won
+    pub fn conflicts(&self) -> &[UpdateConflict] {
+        self.history.conflicts()
+    }
+
+    /// The creation and all updates applied since, oldest first
+    pub fn revisions(&self) -> Result<Vec<Revision>> {
+        self.history.revisions(&self.meta, &self.inner)
+    }
+
+    /// An update bringing this event back to how it was after `revision`
+    pub fn revert_update(&self, revision: &EventId) -> Result<CalendarEventUpdateEventContent> {
+        self.history
+            .revert_update(&self.meta.event_id, &self.inner, revision)
+    }
+
     pub fn utc_end(&self) -> UtcDateTime {
         self.inner.utc_end
     }
@@ -66,23 +161,189 @@ impl CalendarEvent {
         self.inner.show_without_time
     }
 
-    pub fn as_ical_event(&self) -> iCalEvent {
+    pub fn is_recurring(&self) -> bool {
+        self.inner.is_recurring()
+    }
+
+    pub fn rrule(&self) -> Option<String> {
+        self.inner.rrule.as_ref().map(ToString::to_string)
+    }
+
+    /// All occurrences of this event overlapping the given window, with
+    /// the occurrence specific updates applied
+    pub fn occurrences(&self, from: UtcDateTime, to: UtcDateTime) -> Vec<CalendarEventOccurrence> {
+        let duration = self.inner.utc_end - self.inner.utc_start;
+        self.inner
+            .occurrence_starts(from, to)
+            .into_iter()
+            .map(|recurrence_id| self.occurrence_at(recurrence_id, duration))
+            .collect()
+    }
+
+    fn occurrence_at(
+        &self,
+        recurrence_id: UtcDateTime,
+        duration: chrono::Duration,
+    ) -> CalendarEventOccurrence {
+        let mut inner = self.inner.clone();
+        inner.utc_start = recurrence_id;
+        inner.utc_end = recurrence_id + duration;
+        let mut overridden = false;
+        for update in self
+            .overrides
+            .iter()
+            .filter(|u| u.recurrence_id == Some(recurrence_id))
+        {
+            match update.apply(&mut inner) {
+                Ok(updated) => overridden |= updated,
+                Err(error) => warn!(?error, ?recurrence_id, "applying override failed"),
+            }
+        }
+        CalendarEventOccurrence {
+            recurrence_id,
+            inner,
+            overridden,
+        }
+    }
+
+    /// The iCalendar `UID` of this event: the one it has been imported with
+    /// or one derived from its event id and the server of its room
+    pub fn ical_uid(&self) -> String {
+        self.inner.ical_uid.clone().unwrap_or_else(|| {
+            let server_name = self
+                .meta
+                .room_id
+                .server_name()
+                .unwrap_or_else(|| self.meta.sender.server_name());
+            format!(
+                "{}@{server_name}",
+                self.meta.event_id.as_str().trim_start_matches('$')
+            )
+        })
+    }
+
+    /// The number of updates applied to this event, used as iCalendar `SEQUENCE`
+    pub fn sequence(&self) -> u32 {
+        self.update_count
+    }
+
+    fn build_ical_event(content: &CalendarEventEventContent, uid: &str) -> iCalEvent {
         let mut cal_e_builder = iCalEvent::new();
 
         cal_e_builder
-            .summary(&self.inner.title)
-            .starts(self.inner.utc_start)
-            .ends(self.inner.utc_end)
+            .uid(uid)
+            .summary(&content.title)
             .class(icalendar::Class::Private);
-        if let Some(msg) = &self.inner.description {
+        match content.time_zone {
+            // so the series is expanded in that time zone, too
+            Some(tz) => {
+                let local = |at: UtcDateTime| CalendarDateTime::WithTimezone {
+                    date_time: at.with_timezone(&tz).naive_local(),
+                    tzid: tz.name().to_owned(),
+                };
+                cal_e_builder
+                    .starts(local(content.utc_start))
+                    .ends(local(content.utc_end));
+            }
+            None => {
+                cal_e_builder
+                    .starts(content.utc_start)
+                    .ends(content.utc_end);
+            }
+        }
+        if let Some(msg) = &content.description {
             if let Some(formatted) = &msg.formatted {
-                return cal_e_builder.description(&formatted.body).done();
+                cal_e_builder.description(&formatted.body);
             } else {
-                return cal_e_builder.description(&msg.body).done();
+                cal_e_builder.description(&msg.body);
+            }
+        }
+        for location in &content.locations {
+            match location {
+                EventLocation::Physical {
+                    name: Some(name), ..
+                } => {
+                    cal_e_builder.location(name);
+                }
+                EventLocation::Virtual { uri, .. } => {
+                    cal_e_builder.add_property("URL", uri);
+                }
+                _ => {}
             }
         }
+        if !content.categories.is_empty() {
+            cal_e_builder.add_property("CATEGORIES", &content.categories.join(","));
+        }
         cal_e_builder.done()
     }
+
+    pub fn as_ical_event(&self) -> iCalEvent {
+        let mut event = Self::build_ical_event(&self.inner, &self.ical_uid());
+        event.add_property("SEQUENCE", &self.update_count.to_string());
+        if let Some(rrule) = &self.inner.rrule {
+            event.add_property("RRULE", &rrule.to_string());
+        }
+        for exdate in &self.inner.exdates {
+            event.add_multi_property("EXDATE", &exdate.format(ICAL_UTC_FORMAT).to_string());
+        }
+        event.done()
+    }
+
+    /// The series event followed by one event for each overridden occurrence,
+    /// linked to the series by `RECURRENCE-ID`
+    pub fn as_ical_events(&self) -> Vec<iCalEvent> {
+        let uid = self.ical_uid();
+        let duration = self.inner.utc_end - self.inner.utc_start;
+        let mut recurrence_ids: Vec<UtcDateTime> = self
+            .overrides
+            .iter()
+            .filter_map(|u| u.recurrence_id)
+            .collect();
+        recurrence_ids.sort();
+        recurrence_ids.dedup();
+
+        let mut events = vec![self.as_ical_event()];
+        for recurrence_id in recurrence_ids {
+            if self.inner.exdates.contains(&recurrence_id) {
+                continue;
+            }
+            let occurrence = self.occurrence_at(recurrence_id, duration);
+            let mut event = Self::build_ical_event(&occurrence.inner, &uid);
+            event.add_property("SEQUENCE", &self.update_count.to_string());
+            event.add_property(
+                "RECURRENCE-ID",
+                &recurrence_id.format(ICAL_UTC_FORMAT).to_string(),
+            );
+            events.push(event.done());
+        }
+        events
+    }
+
+    /// Like [`Self::as_ical_events`] with `user_id` added as `ATTENDEE`, their
+    /// `PARTSTAT` taken from the given RSVP status
+    pub fn as_ical_events_for(
+        &self,
+        user_id: &UserId,
+        status: Option<&RsvpStatus>,
+    ) -> Vec<iCalEvent> {
+        let attendee = Property::new("ATTENDEE", user_id.matrix_to_uri().to_string())
+            .add_parameter("PARTSTAT", ical_partstat(status))
+            .done();
+        self.as_ical_events()
+            .into_iter()
+            .map(|mut event| event.append_property(attendee.clone()).done())
+            .collect()
+    }
+}
+
+/// The iCalendar participation status matching the RSVP status
+pub fn ical_partstat(status: Option<&RsvpStatus>) -> &'static str {
+    match status {
+        Some(RsvpStatus::Yes) => "ACCEPTED",
+        Some(RsvpStatus::Maybe) => "TENTATIVE",
+        Some(RsvpStatus::No) => "DECLINED",
+        None => "NEEDS-ACTION",
+    }
 }
 
 impl ActerModel for CalendarEvent {
@@ -125,7 +386,31 @@ impl ActerModel for CalendarEvent {
         // FIXME: redacting a CalendarEventUpdate would mean reverting to the previous
         //        state. That is currently not that easy...
 
-        update.apply(&mut self.inner)
+        if let Some(recurrence_id) = update.inner.recurrence_id {
+            if !self.inner.has_occurrence_at(recurrence_id) {
+                return Err(Error::Custom(format!(
+                    "No occurrence of {} starts at {recurrence_id}",
+                    self.meta.event_id
+                )));
+            }
+            // only affects that one occurrence, applied when expanding
+            self.overrides.push(update.inner.clone());
+            self.update_count += 1;
+            return Ok(true);
+        }
+
+        let updated = self.history.apply_update(
+            &self.meta.event_id,
+            update.event_meta(),
+            update.base_revision.as_deref(),
+            &update.inner,
+            &mut self.inner,
+            CalendarEventUpdateEventContent::apply,
+        )?;
+        if updated {
+            self.update_count += 1;
+        }
+        Ok(updated)
     }
 }
 
@@ -148,6 +433,9 @@ impl From<OriginalMessageLikeEvent<CalendarEventEventContent>> for CalendarEvent
                 origin_server_ts,
                 redacted: None,
             },
+            overrides: Default::default(),
+            update_count: 0,
+            history: Default::default(),
         }
     }
 }
@@ -0,0 +1,443 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use icalendar::{
+    Calendar, CalendarComponent, CalendarDateTime, Component, DatePerhapsTime, Event as iCalEvent,
+    EventLike, Property,
+};
+use matrix_sdk_base::ruma::events::room::message::TextMessageEventContent;
+use tracing::warn;
+
+use crate::{
+    events::{
+        calendar::{CalendarEventEventContent, EventLocation},
+        RecurrenceRule, UtcDateTime, ICAL_UTC_FORMAT,
+    },
+    Error, Result,
+};
+
+/// A calendar event parsed from an iCalendar file, ready to be sent
+#[derive(Clone, Debug)]
+pub struct ImportedCalendarEvent {
+    /// The `UID` of the VEVENT, also kept in the content
+    pub uid: Option<String>,
+    pub content: CalendarEventEventContent,
+    /// Parts of the VEVENT that couldn’t be taken over
+    pub warnings: Vec<String>,
+}
+
+/// The result of parsing an iCalendar file
+#[derive(Clone, Debug, Default)]
+pub struct ParsedCalendar {
+    pub events: Vec<ImportedCalendarEvent>,
+    /// VEVENTs that couldn’t be imported, with the reason
+    pub skipped: Vec<String>,
+}
+
+fn to_utc(value: DatePerhapsTime) -> (UtcDateTime, bool) {
+    match value {
+        DatePerhapsTime::Date(date) => (date.and_time(Default::default()).and_utc(), true),
+        DatePerhapsTime::DateTime(CalendarDateTime::Utc(dt)) => (dt, false),
+        DatePerhapsTime::DateTime(CalendarDateTime::Floating(dt)) => (dt.and_utc(), false),
+        DatePerhapsTime::DateTime(CalendarDateTime::WithTimezone { date_time, tzid }) => {
+            (local_to_utc(date_time, &tzid), false)
+        }
+    }
+}
+
+/// The timezone and local time of day of DTSTART, which EXDATEs default to
+fn start_zone(value: &DatePerhapsTime) -> (Option<String>, NaiveTime) {
+    match value {
+        DatePerhapsTime::Date(_) => (None, NaiveTime::default()),
+        DatePerhapsTime::DateTime(CalendarDateTime::Utc(dt)) => (None, dt.time()),
+        DatePerhapsTime::DateTime(CalendarDateTime::Floating(dt)) => (None, dt.time()),
+        DatePerhapsTime::DateTime(CalendarDateTime::WithTimezone { date_time, tzid }) => {
+            (Some(tzid.clone()), date_time.time())
+        }
+    }
+}
+
+/// Parse an EXDATE value. Local ones are in `tzid`, date-only ones take the
+/// time of day of DTSTART, so they match the occurrence they remove.
+fn parse_exdate(value: &str, tzid: Option<&str>, start_time: NaiveTime) -> Option<UtcDateTime> {
+    if let Ok(dt) = NaiveDateTime::parse_from_str(value, ICAL_UTC_FORMAT) {
+        return Some(dt.and_utc());
+    }
+    let local = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y%m%d")
+                .ok()
+                .map(|date| date.and_time(start_time))
+        })?;
+    Some(match tzid {
+        Some(tzid) => local_to_utc(local, tzid),
+        None => local.and_utc(),
+    })
+}
+
+fn local_to_utc(date_time: NaiveDateTime, tzid: &str) -> UtcDateTime {
+    match tzid.parse::<Tz>() {
+        Ok(tz) => tz
+            .from_local_datetime(&date_time)
+            .earliest()
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|| date_time.and_utc()),
+        Err(error) => {
+            warn!(?tzid, ?error, "unknown timezone, assuming UTC");
+            date_time.and_utc()
+        }
+    }
+}
+
+/// all instances of a possibly repeated property
+fn properties<'a>(event: &'a iCalEvent, key: &str) -> Vec<&'a Property> {
+    let mut props: Vec<&Property> = event
+        .multi_properties()
+        .get(key)
+        .map(|props| props.iter().collect())
+        .unwrap_or_default();
+    if let Some(single) = event.properties().get(key) {
+        props.push(single);
+    }
+    props
+}
+
+fn split_values(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(|v| v.trim().to_owned())
+        .filter(|v| !v.is_empty())
+}
+
+/// all values of a possibly repeated property, split at commas
+fn list_values(event: &iCalEvent, key: &str) -> Vec<String> {
+    let mut values: Vec<String> = properties(event, key)
+        .into_iter()
+        .flat_map(|p| split_values(p.value()))
+        .collect();
+    values.dedup();
+    values
+}
+
+fn convert_event(event: &iCalEvent) -> std::result::Result<ImportedCalendarEvent, String> {
+    let uid = event.get_uid().map(ToOwned::to_owned);
+    let label = uid
+        .clone()
+        .unwrap_or_else(|| "VEVENT without UID".to_owned());
+    if event.property_value("RECURRENCE-ID").is_some() {
+        return Err(format!(
+            "{label}: changes to single occurrences aren’t supported"
+        ));
+    }
+    let Some(title) = event.get_summary().map(str::trim).filter(|s| !s.is_empty()) else {
+        return Err(format!("{label}: SUMMARY is missing"));
+    };
+    let Some(start) = event.get_start() else {
+        return Err(format!("{label}: DTSTART is missing"));
+    };
+    let (start_tzid, start_time) = start_zone(&start);
+    let (utc_start, all_day) = to_utc(start);
+    let utc_end = match event.get_end().map(to_utc) {
+        Some((end, _)) if end >= utc_start => end,
+        Some(_) => return Err(format!("{label}: DTEND is before DTSTART")),
+        // RFC 5545: all-day events last one day, others have no duration
+        None if all_day => utc_start + Duration::days(1),
+        None => utc_start,
+    };
+
+    let mut warnings = vec![];
+    let mut locations = vec![];
+    if let Some(location) = event
+        .get_location()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        locations.push(EventLocation::Physical {
+            name: Some(location.to_owned()),
+            description: None,
+            icon: None,
+            coordinates: None,
+            uri: None,
+            address: None,
+            notes: None,
+        });
+    }
+    if let Some(url) = event.get_url().map(str::trim).filter(|s| !s.is_empty()) {
+        locations.push(EventLocation::Virtual {
+            name: None,
+            description: None,
+            icon: None,
+            uri: url.to_owned(),
+            notes: None,
+        });
+    }
+
+    let rrule = match event.property_value("RRULE") {
+        Some(raw) => match raw.parse::<RecurrenceRule>() {
+            Ok(rrule) => Some(rrule),
+            Err(error) => {
+                warnings.push(format!("{label}: recurrence dropped: {error}"));
+                None
+            }
+        },
+        None => None,
+    };
+    let mut exdates = vec![];
+    if rrule.is_some() {
+        for prop in properties(event, "EXDATE") {
+            let tzid = prop
+                .params()
+                .get("TZID")
+                .map(|p| p.value())
+                .or(start_tzid.as_deref());
+            for raw in split_values(prop.value()) {
+                match parse_exdate(&raw, tzid, start_time) {
+                    Some(exdate) if !exdates.contains(&exdate) => exdates.push(exdate),
+                    Some(_) => {}
+                    None => warnings.push(format!("{label}: invalid EXDATE {raw} ignored")),
+                }
+            }
+        }
+    }
+
+    // so the series keeps its local time across daylight saving changes
+    let time_zone = match (&rrule, start_tzid.as_deref()) {
+        (Some(_), Some(tzid)) => match tzid.parse::<Tz>() {
+            Ok(tz) => Some(tz),
+            Err(_) => {
+                warnings.push(format!(
+                    "{label}: unknown time zone {tzid}, repeating in UTC"
+                ));
+                None
+            }
+        },
+        _ => None,
+    };
+
+    let content = CalendarEventEventContent {
+        title: title.to_owned(),
+        description: event
+            .get_description()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(TextMessageEventContent::plain),
+        display: None,
+        utc_start,
+        utc_end,
+        show_without_time: all_day,
+        locations,
+        keywords: vec![],
+        categories: list_values(event, "CATEGORIES"),
+        rrule,
+        time_zone,
+        exdates,
+        ical_uid: uid.clone(),
+    };
+    Ok(ImportedCalendarEvent {
+        uid,
+        content,
+        warnings,
+    })
+}
+
+/// Parse the VEVENTs of an iCalendar (`.ics`) file into calendar event contents
+pub fn parse_ical(data: &str) -> Result<ParsedCalendar> {
+    let calendar = data
+        .parse::<Calendar>()
+        .map_err(|msg| Error::FailedToParse {
+            model_type: "iCalendar".to_owned(),
+            msg,
+        })?;
+    let mut parsed = ParsedCalendar::default();
+    for component in calendar.components.iter() {
+        let CalendarComponent::Event(event) = component else {
+            continue;
+        };
+        match convert_event(event) {
+            Ok(imported) => parsed.events.push(imported),
+            Err(reason) => parsed.skipped.push(reason),
+        }
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const ICS: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//test//test//EN\r
+BEGIN:VEVENT\r
+UID:team-weekly@example.org\r
+DTSTAMP:20240101T000000Z\r
+SUMMARY:Team weekly\r
+DESCRIPTION:Our weekly sync\r
+DTSTART:20240108T090000Z\r
+DTEND:20240108T100000Z\r
+RRULE:FREQ=WEEKLY;BYDAY=MO\r
+EXDATE:20240115T090000Z\r
+LOCATION:Room 101\r
+URL:https://meet.example.org/weekly\r
+CATEGORIES:work,sync\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:holiday@example.org\r
+DTSTAMP:20240101T000000Z\r
+SUMMARY:Holiday\r
+DTSTART;VALUE=DATE:20240301\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:broken@example.org\r
+DTSTAMP:20240101T000000Z\r
+SUMMARY:No start\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn parses_events() -> Result<()> {
+        let parsed = parse_ical(ICS)?;
+        assert_eq!(parsed.events.len(), 2);
+        assert_eq!(parsed.skipped.len(), 1);
+
+        let weekly = &parsed.events[0];
+        assert_eq!(weekly.uid.as_deref(), Some("team-weekly@example.org"));
+        assert!(weekly.warnings.is_empty());
+        let content = &weekly.content;
+        assert_eq!(content.title, "Team weekly");
+        assert_eq!(
+            content.description.as_ref().map(|d| d.body.as_str()),
+            Some("Our weekly sync")
+        );
+        assert_eq!(
+            content.utc_start,
+            Utc.with_ymd_and_hms(2024, 1, 8, 9, 0, 0).unwrap()
+        );
+        assert_eq!(
+            content.rrule.as_ref().map(ToString::to_string).as_deref(),
+            Some("FREQ=WEEKLY;BYDAY=MO")
+        );
+        assert_eq!(content.exdates.len(), 1);
+        assert_eq!(
+            content.categories,
+            vec!["work".to_owned(), "sync".to_owned()]
+        );
+        assert_eq!(content.locations.len(), 2);
+        assert!(matches!(
+            &content.locations[0],
+            EventLocation::Physical { name: Some(name), .. } if name == "Room 101"
+        ));
+        assert!(matches!(
+            &content.locations[1],
+            EventLocation::Virtual { uri, .. } if uri == "https://meet.example.org/weekly"
+        ));
+
+        let holiday = &parsed.events[1].content;
+        assert!(holiday.show_without_time);
+        assert_eq!(holiday.utc_end - holiday.utc_start, Duration::days(1));
+        Ok(())
+    }
+
+    const EXDATES_ICS: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//test//test//EN\r
+BEGIN:VEVENT\r
+UID:berlin-weekly@example.org\r
+DTSTAMP:20240101T000000Z\r
+SUMMARY:Berlin weekly\r
+DTSTART;TZID=Europe/Berlin:20240108T090000\r
+DTEND;TZID=Europe/Berlin:20240108T100000\r
+RRULE:FREQ=WEEKLY;COUNT=4\r
+EXDATE;TZID=Europe/Berlin:20240115T090000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:daily-standup@example.org\r
+DTSTAMP:20240101T000000Z\r
+SUMMARY:Standup\r
+DTSTART:20240108T093000Z\r
+DTEND:20240108T094500Z\r
+RRULE:FREQ=DAILY;COUNT=5\r
+EXDATE;VALUE=DATE:20240110,20240111\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn exdates_in_start_timezone() -> Result<()> {
+        let parsed = parse_ical(EXDATES_ICS)?;
+        let content = &parsed.events[0].content;
+        assert!(parsed.events[0].warnings.is_empty());
+        // 09:00 in Berlin is 08:00 UTC in winter
+        let second = Utc.with_ymd_and_hms(2024, 1, 15, 8, 0, 0).unwrap();
+        assert_eq!(content.exdates, vec![second]);
+
+        let starts = content.occurrence_starts(
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap(),
+        );
+        assert_eq!(starts.len(), 3);
+        assert!(!starts.contains(&second));
+        Ok(())
+    }
+
+    #[test]
+    fn series_keep_their_local_time() -> Result<()> {
+        let parsed = parse_ical(EXDATES_ICS)?;
+        let content = &parsed.events[0].content;
+        assert_eq!(content.time_zone, Some(chrono_tz::Europe::Berlin));
+        // the UTC one doesn't need any
+        assert_eq!(parsed.events[1].content.time_zone, None);
+
+        let mut content = content.clone();
+        content.rrule = Some("FREQ=WEEKLY".parse()?);
+        content.exdates.clear();
+        // crossing into summer time at the end of march
+        let starts = content.occurrence_starts(
+            Utc.with_ymd_and_hms(2024, 3, 20, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 4, 5, 0, 0, 0).unwrap(),
+        );
+        assert_eq!(
+            starts,
+            vec![
+                Utc.with_ymd_and_hms(2024, 3, 25, 8, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 4, 1, 7, 0, 0).unwrap(),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn date_only_exdates_take_start_time() -> Result<()> {
+        let parsed = parse_ical(EXDATES_ICS)?;
+        let content = &parsed.events[1].content;
+        assert!(parsed.events[1].warnings.is_empty());
+        assert_eq!(
+            content.exdates,
+            vec![
+                Utc.with_ymd_and_hms(2024, 1, 10, 9, 30, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 1, 11, 9, 30, 0).unwrap(),
+            ]
+        );
+
+        let starts = content.occurrence_starts(
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap(),
+        );
+        assert_eq!(
+            starts,
+            vec![
+                Utc.with_ymd_and_hms(2024, 1, 8, 9, 30, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 1, 9, 9, 30, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 1, 12, 9, 30, 0).unwrap(),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(parse_ical("this is not a calendar").is_err());
+    }
+}
@@ -0,0 +1,549 @@
+use matrix_sdk_base::ruma::{EventId, MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedUserId};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use super::EventMeta;
+use crate::{Error, Result};
+
+/// relations are not part of the content history
+static RELATION_FIELD: &str = "m.relates_to";
+/// update fields that say how to apply it rather than what to change
+static UPDATE_META_FIELDS: [&str; 3] = [RELATION_FIELD, "base_revision", "recurrence_id"];
+
+/// A single field changed by a revision, `None` if it was unset
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+/// Who changed what and when
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Revision {
+    pub event_id: OwnedEventId,
+    pub sender: OwnedUserId,
+    pub origin_server_ts: MilliSecondsSinceUnixEpoch,
+    /// the revision the update was based on, if it said so
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<OwnedEventId>,
+    pub changes: Vec<FieldChange>,
+}
+
+impl Revision {
+    fn changed(&self, field: &str) -> bool {
+        self.changes.iter().any(|c| c.field == field)
+    }
+}
+
+/// Two updates based on the same revision changed the same fields
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UpdateConflict {
+    /// the update that arrived later
+    pub event_id: OwnedEventId,
+    pub sender: OwnedUserId,
+    /// the revision it was based on
+    pub base: OwnedEventId,
+    /// the update applied since that revision it conflicts with
+    pub other_event_id: OwnedEventId,
+    pub other_sender: OwnedUserId,
+    /// the fields both changed
+    pub fields: Vec<String>,
+    /// the update whose values were kept for these fields
+    pub winner: OwnedEventId,
+}
+
+/// The field-level changes of all updates applied to an object, in the
+/// order they were applied. Only derived while applying updates, so stored
+/// models without it apply their updates again: see `store::migrations`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EditHistory {
+    revisions: Vec<Revision>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    conflicts: Vec<UpdateConflict>,
+}
+
+fn as_object<C: Serialize>(content: &C) -> Result<Map<String, Value>> {
+    match serde_json::to_value(content)? {
+        Value::Object(map) => Ok(map),
+        other => Err(Error::Custom(format!(
+            "content has no fields to track: {other}"
+        ))),
+    }
+}
+
+/// Undo the revisions on the content fields, latest first
+fn undo<'a>(
+    fields: &mut Map<String, Value>,
+    revisions: impl DoubleEndedIterator<Item = &'a Revision>,
+) {
+    for revision in revisions.rev() {
+        for change in revision.changes.iter() {
+            match &change.old {
+                Some(old) => fields.insert(change.field.clone(), old.clone()),
+                None => fields.remove(&change.field),
+            };
+        }
+    }
+}
+
+impl EditHistory {
+    pub fn is_empty(&self) -> bool {
+        self.revisions.is_empty() && self.conflicts.is_empty()
+    }
+
+    /// The revision the object is at: its last update or its creation
+    pub fn latest_revision(&self, object_id: &EventId) -> OwnedEventId {
+        self.revisions
+            .last()
+            .map(|r| r.event_id.clone())
+            .unwrap_or_else(|| object_id.to_owned())
+    }
+
+    /// Conflicts between concurrent updates and how they were resolved,
+    /// in the order they were detected
+    pub fn conflicts(&self) -> &[UpdateConflict] {
+        &self.conflicts
+    }
+
+    /// The revisions applied after `revision`, e.g. while someone was
+    /// editing based on it. `None` if the revision isn’t known.
+    pub fn changed_since(&self, object_id: &EventId, revision: &EventId) -> Option<&[Revision]> {
+        if object_id == revision {
+            return Some(&self.revisions);
+        }
+        let idx = self.revisions.iter().position(|r| r.event_id == revision)?;
+        Some(&self.revisions[idx + 1..])
+    }
+
+    /// Apply the `update` by `meta` to the `content` and record the revision.
+    ///
+    /// If the update is based on an older revision, fields also changed by
+    /// updates applied since are resolved deterministically: the causally
+    /// later update wins, otherwise the one with the later timestamp (and
+    /// event id as tie breaker). That gives the same result regardless of
+    /// the order both updates arrive in.
+    pub(crate) fn apply_update<C, U>(
+        &mut self,
+        object_id: &EventId,
+        meta: &EventMeta,
+        base: Option<&EventId>,
+        update: &U,
+        content: &mut C,
+        apply: impl FnOnce(&U, &mut C) -> Result<bool>,
+    ) -> Result<bool>
+    where
+        C: Serialize + Clone,
+        U: Serialize + DeserializeOwned,
+    {
+        let resolved = match base {
+            Some(base) => self.resolve(object_id, meta, base, update)?,
+            None => None,
+        };
+        let before = content.clone();
+        let updated = apply(resolved.as_ref().unwrap_or(update), content)?;
+        if updated {
+            self.record(meta, base, &before, content)?;
+        }
+        Ok(updated)
+    }
+
+    /// Record conflicts with updates concurrent to `base`. Returns the
+    /// update without the fields it lost, if it lost any.
+    fn resolve<U>(
+        &mut self,
+        object_id: &EventId,
+        meta: &EventMeta,
+        base: &EventId,
+        update: &U,
+    ) -> Result<Option<U>>
+    where
+        U: Serialize + DeserializeOwned,
+    {
+        let Some(concurrent) = self.changed_since(object_id, base) else {
+            // we don’t know what it was based on, nothing to compare with
+            return Ok(None);
+        };
+        let mut fields = as_object(update)?;
+        for key in UPDATE_META_FIELDS {
+            fields.remove(key);
+        }
+
+        let mut conflicts = Vec::new();
+        let mut lost = Vec::new();
+        for other in concurrent.iter() {
+            let overlapping: Vec<String> = fields
+                .keys()
+                .filter(|f| other.changed(f))
+                .cloned()
+                .collect();
+            if overlapping.is_empty() {
+                continue;
+            }
+            let incoming_wins = other.base.as_deref() != Some(&*meta.event_id)
+                && (meta.origin_server_ts, &meta.event_id)
+                    > (other.origin_server_ts, &other.event_id);
+            let winner = if incoming_wins {
+                meta.event_id.clone()
+            } else {
+                lost.extend(overlapping.iter().cloned());
+                other.event_id.clone()
+            };
+            conflicts.push(UpdateConflict {
+                event_id: meta.event_id.clone(),
+                sender: meta.sender.clone(),
+                base: base.to_owned(),
+                other_event_id: other.event_id.clone(),
+                other_sender: other.sender.clone(),
+                fields: overlapping,
+                winner,
+            });
+        }
+        self.conflicts.extend(conflicts);
+
+        if lost.is_empty() {
+            return Ok(None);
+        }
+        let mut trimmed = as_object(update)?;
+        for field in lost {
+            trimmed.remove(&field);
+        }
+        Ok(Some(serde_json::from_value(Value::Object(trimmed))?))
+    }
+
+    /// Record what the update by `meta` changed from `before` to `after`
+    pub(crate) fn record<C: Serialize>(
+        &mut self,
+        meta: &EventMeta,
+        base: Option<&EventId>,
+        before: &C,
+        after: &C,
+    ) -> Result<()> {
+        let before = as_object(before)?;
+        let mut after = as_object(after)?;
+        let mut changes = Vec::new();
+        for (field, old) in before.into_iter() {
+            let new = after.remove(&field);
+            if field != RELATION_FIELD && new.as_ref() != Some(&old) {
+                changes.push(FieldChange {
+                    field,
+                    old: Some(old),
+                    new,
+                });
+            }
+        }
+        // newly set fields
+        changes.extend(
+            after
+                .into_iter()
+                .filter(|(field, _)| field != RELATION_FIELD)
+                .map(|(field, new)| FieldChange {
+                    field,
+                    old: None,
+                    new: Some(new),
+                }),
+        );
+        changes.sort_by(|a, b| a.field.cmp(&b.field));
+
+        self.revisions.push(Revision {
+            event_id: meta.event_id.clone(),
+            sender: meta.sender.clone(),
+            origin_server_ts: meta.origin_server_ts,
+            base: base.map(ToOwned::to_owned),
+            changes,
+        });
+        Ok(())
+    }
+
+    /// All revisions, starting with the creation of the object by `meta`
+    /// holding the fields it was created with
+    pub fn revisions<C: Serialize>(&self, meta: &EventMeta, current: &C) -> Result<Vec<Revision>> {
+        let mut original = as_object(current)?;
+        undo(&mut original, self.revisions.iter());
+        original.remove(RELATION_FIELD);
+        let creation = Revision {
+            event_id: meta.event_id.clone(),
+            sender: meta.sender.clone(),
+            origin_server_ts: meta.origin_server_ts,
+            base: None,
+            changes: original
+                .into_iter()
+                .map(|(field, new)| FieldChange {
+                    field,
+                    old: None,
+                    new: Some(new),
+                })
+                .collect(),
+        };
+        Ok([creation]
+            .into_iter()
+            .chain(self.revisions.iter().cloned())
+            .collect())
+    }
+
+    /// The content as it was right after the given revision, the object
+    /// id itself refers to its creation
+    pub fn content_at<C: Serialize + DeserializeOwned>(
+        &self,
+        object_id: &EventId,
+        current: &C,
+        revision: &EventId,
+    ) -> Result<C> {
+        let applied = if object_id == revision {
+            0
+        } else {
+            self.revisions
+                .iter()
+                .position(|r| r.event_id == revision)
+                .ok_or_else(|| Error::ModelNotFound(revision.to_string()))?
+                + 1
+        };
+        let mut fields = as_object(current)?;
+        undo(&mut fields, self.revisions[applied..].iter());
+        Ok(serde_json::from_value(Value::Object(fields))?)
+    }
+
+    /// An update event content of type `U` for `object_id` that brings the
+    /// `current` content back to how it was right after `revision`
+    pub fn revert_update<C, U>(
+        &self,
+        object_id: &EventId,
+        current: &C,
+        revision: &EventId,
+    ) -> Result<U>
+    where
+        C: Serialize + DeserializeOwned,
+        U: DeserializeOwned,
+    {
+        let target = as_object(&self.content_at(object_id, current, revision)?)?;
+        let current = as_object(current)?;
+        let mut update = Map::new();
+        for (field, value) in current.iter() {
+            if field == RELATION_FIELD || target.get(field) == Some(value) {
+                continue;
+            }
+            // unset fields are skipped when serializing, send their empty value
+            let reverted = target.get(field).cloned().unwrap_or(match value {
+                Value::Array(_) => json!([]),
+                Value::Bool(_) => json!(false),
+                _ => Value::Null,
+            });
+            update.insert(field.clone(), reverted);
+        }
+        for (field, value) in target.iter() {
+            if field != RELATION_FIELD && !current.contains_key(field) {
+                update.insert(field.clone(), value.clone());
+            }
+        }
+        if update.is_empty() {
+            return Err(Error::Custom(format!(
+                "{object_id} is already at the state of {revision}"
+            )));
+        }
+        update.insert(
+            RELATION_FIELD.to_owned(),
+            json!({ "rel_type": "global.acter.dev.update", "event_id": object_id }),
+        );
+        update.insert(
+            "base_revision".to_owned(),
+            json!(self.latest_revision(object_id)),
+        );
+        Ok(serde_json::from_value(Value::Object(update))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::pins::{PinEventContent, PinUpdateEventContent};
+    use matrix_sdk_base::ruma::{event_id, owned_event_id, owned_room_id, owned_user_id};
+
+    fn meta(event_id: &str, ts: u32) -> EventMeta {
+        EventMeta {
+            event_id: OwnedEventId::try_from(event_id).unwrap(),
+            sender: owned_user_id!("@test:example.org"),
+            origin_server_ts: MilliSecondsSinceUnixEpoch(ts.into()),
+            room_id: owned_room_id!("!room:example.org"),
+            redacted: None,
+        }
+    }
+
+    fn pin(value: Value) -> PinEventContent {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn history() -> (EditHistory, PinEventContent) {
+        let created = pin(json!({ "title": "Rules" }));
+        let renamed = pin(json!({ "title": "Garden rules", "url": "https://acter.global" }));
+        let described = pin(json!({ "title": "Garden rules", "content": { "body": "be nice" } }));
+        let mut history = EditHistory::default();
+        history
+            .record(&meta("$rename", 2), None, &created, &renamed)
+            .unwrap();
+        history
+            .record(&meta("$describe", 3), None, &renamed, &described)
+            .unwrap();
+        (history, described)
+    }
+
+    #[test]
+    fn records_field_changes() -> Result<()> {
+        let (history, current) = history();
+        let revisions = history.revisions(&meta("$pin", 1), &current)?;
+        assert_eq!(revisions.len(), 3);
+        assert_eq!(revisions[0].event_id, owned_event_id!("$pin"));
+        assert_eq!(
+            revisions[0].changes,
+            vec![FieldChange {
+                field: "title".to_owned(),
+                old: None,
+                new: Some(json!("Rules")),
+            }]
+        );
+        assert_eq!(
+            revisions[1].changes,
+            vec![
+                FieldChange {
+                    field: "title".to_owned(),
+                    old: Some(json!("Rules")),
+                    new: Some(json!("Garden rules")),
+                },
+                FieldChange {
+                    field: "url".to_owned(),
+                    old: None,
+                    new: Some(json!("https://acter.global")),
+                },
+            ]
+        );
+        let fields: Vec<&str> = revisions[2]
+            .changes
+            .iter()
+            .map(|c| c.field.as_str())
+            .collect();
+        assert_eq!(fields, vec!["content", "url"]);
+        Ok(())
+    }
+
+    #[test]
+    fn reverts_to_revision() -> Result<()> {
+        let (history, current) = history();
+        let at_rename: PinEventContent =
+            history.content_at(event_id!("$pin"), &current, event_id!("$rename"))?;
+        assert_eq!(at_rename.url.as_deref(), Some("https://acter.global"));
+        assert!(at_rename.content.is_none());
+
+        let update: PinUpdateEventContent =
+            history.revert_update(event_id!("$pin"), &current, event_id!("$pin"))?;
+        assert_eq!(update.pin.event_id, owned_event_id!("$pin"));
+        assert_eq!(update.title.as_deref(), Some("Rules"));
+        assert!(matches!(update.content, Some(None))); // unset
+        assert!(update.url.is_none()); // unchanged
+        assert_eq!(update.base_revision, Some(owned_event_id!("$describe")));
+
+        let mut reverted = current.clone();
+        assert!(update.apply(&mut reverted)?);
+        assert_eq!(reverted.title, "Rules");
+        assert!(reverted.content.is_none());
+
+        assert!(history
+            .revert_update::<_, PinUpdateEventContent>(
+                event_id!("$pin"),
+                &current,
+                event_id!("$describe")
+            )
+            .is_err());
+        assert!(history
+            .content_at(event_id!("$pin"), &current, event_id!("$unknown"))
+            .is_err());
+        Ok(())
+    }
+
+    fn update(value: Value) -> PinUpdateEventContent {
+        let mut value = value;
+        value["m.relates_to"] =
+            json!({ "rel_type": "global.acter.dev.update", "event_id": "$pin" });
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// apply the updates in the given order, all based on the creation
+    fn apply_all(
+        updates: &[(&str, u32, &PinUpdateEventContent)],
+    ) -> (EditHistory, PinEventContent) {
+        let mut history = EditHistory::default();
+        let mut content = pin(json!({ "title": "Rules" }));
+        for (event_id, ts, update) in updates {
+            history
+                .apply_update(
+                    event_id!("$pin"),
+                    &meta(event_id, *ts),
+                    update.base_revision.as_deref(),
+                    *update,
+                    &mut content,
+                    PinUpdateEventContent::apply,
+                )
+                .unwrap();
+        }
+        (history, content)
+    }
+
+    #[test]
+    fn concurrent_updates_converge() {
+        let early = update(
+            json!({ "title": "Garden rules", "url": "https://acter.global", "base_revision": "$pin" }),
+        );
+        let late = update(json!({ "title": "House rules", "base_revision": "$pin" }));
+
+        let (in_order, content) = apply_all(&[("$early", 2, &early), ("$late", 3, &late)]);
+        let (reversed, reversed_content) = apply_all(&[("$late", 3, &late), ("$early", 2, &early)]);
+
+        for content in [&content, &reversed_content] {
+            assert_eq!(content.title, "House rules");
+            // not conflicting, so kept either way
+            assert_eq!(content.url.as_deref(), Some("https://acter.global"));
+        }
+        for history in [&in_order, &reversed] {
+            let [conflict] = history.conflicts() else {
+                panic!("expected one conflict, got {:?}", history.conflicts());
+            };
+            assert_eq!(conflict.fields, vec!["title".to_owned()]);
+            assert_eq!(conflict.winner, owned_event_id!("$late"));
+            assert_eq!(conflict.base, owned_event_id!("$pin"));
+        }
+        assert_eq!(in_order.conflicts()[0].event_id, owned_event_id!("$late"));
+        assert_eq!(reversed.conflicts()[0].event_id, owned_event_id!("$early"));
+        assert_eq!(
+            reversed
+                .changed_since(event_id!("$pin"), event_id!("$late"))
+                .map(|r| r.len()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn causally_later_update_wins() {
+        let first = update(json!({ "title": "Garden rules", "base_revision": "$pin" }));
+        // made knowing about $first, even if its clock is behind
+        let second = update(json!({ "title": "House rules", "base_revision": "$first" }));
+        let (history, content) = apply_all(&[("$first", 5, &first), ("$second", 4, &second)]);
+        assert_eq!(content.title, "House rules");
+        assert!(history.conflicts().is_empty());
+    }
+
+    #[test]
+    fn unknown_or_missing_base_never_conflicts() {
+        let first = update(json!({ "title": "Garden rules" }));
+        let second = update(json!({ "title": "House rules", "base_revision": "$unknown" }));
+        let (history, content) = apply_all(&[("$first", 5, &first), ("$second", 4, &second)]);
+        assert_eq!(content.title, "House rules");
+        assert!(history.conflicts().is_empty());
+        assert_eq!(
+            history.latest_revision(event_id!("$pin")),
+            owned_event_id!("$second")
+        );
+        assert!(history
+            .changed_since(event_id!("$pin"), event_id!("$unknown"))
+            .is_none());
+    }
+}
@@ -1,11 +1,14 @@
 use matrix_sdk_base::ruma::{
     events::{room::message::TextMessageEventContent, OriginalMessageLikeEvent},
-    OwnedEventId, RoomId, UserId,
+    EventId, OwnedEventId, RoomId, UserId,
 };
 use serde::{Deserialize, Serialize};
 use std::ops::Deref;
 
-use super::{default_model_execute, ActerModel, AnyActerModel, Capability, EventMeta};
+use super::{
+    default_model_execute, ActerModel, AnyActerModel, Capability, EditHistory, EventMeta, Revision,
+    UpdateConflict,
+};
 use crate::{
     events::pins::{PinEventContent, PinUpdateBuilder, PinUpdateEventContent},
     referencing::{ExecuteReference, IndexKey, SectionIndex},
@@ -17,6 +20,9 @@ use crate::{
 pub struct Pin {
     inner: PinEventContent,
     meta: EventMeta,
+
+    #[serde(default, skip_serializing_if = "EditHistory::is_empty")]
+    history: EditHistory,
 }
 impl Deref for Pin {
     type Target = PinEventContent;
@@ -49,8 +55,35 @@ impl Pin {
     pub fn updater(&self) -> PinUpdateBuilder {
         PinUpdateBuilder::default()
             .pin(self.meta.event_id.clone())
+            .base_revision(self.revision())
             .to_owned()
     }
+
+    /// The revision this pin is at, to base updates on
+    pub fn revision(&self) -> OwnedEventId {
+        self.history.latest_revision(&self.meta.event_id)
+    }
+
+    /// The updates applied since `revision`, `None` if it isn’t known
+    pub fn changed_since(&self, revision: &EventId) -> Option<&[Revision]> {
+        self.history.changed_since(&self.meta.event_id, revision)
+    }
+
+    /// Concurrent updates that changed the same fields and who won
+    pub fn conflicts(&self) -> &[UpdateConflict] {
+        self.history.conflicts()
+    }
+
+    /// The creation and all updates applied since, oldest first
+    pub fn revisions(&self) -> Result<Vec<Revision>> {
+        self.history.revisions(&self.meta, &self.inner)
+    }
+
+    /// An update bringing this pin back to how it was after `revision`
+    pub fn revert_update(&self, revision: &EventId) -> Result<PinUpdateEventContent> {
+        self.history
+            .revert_update(&self.meta.event_id, &self.inner, revision)
+    }
 }
 
 impl ActerModel for Pin {
@@ -85,7 +118,14 @@ impl ActerModel for Pin {
             return Ok(false);
         };
 
-        update.apply(&mut self.inner)
+        self.history.apply_update(
+            &self.meta.event_id,
+            update.event_meta(),
+            update.base_revision.as_deref(),
+            &update.inner,
+            &mut self.inner,
+            PinUpdateEventContent::apply,
+        )
     }
 }
 
@@ -108,6 +148,7 @@ impl From<OriginalMessageLikeEvent<PinEventContent>> for Pin {
                 origin_server_ts,
                 redacted: None,
             },
+            history: Default::default(),
         }
     }
 }
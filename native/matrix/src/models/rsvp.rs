@@ -8,7 +8,10 @@ use tracing::{error, trace};
 
 use super::{ActerModel, AnyActerModel, Capability, EventMeta};
 use crate::{
-    events::rsvp::{RsvpBuilder, RsvpEventContent},
+    events::{
+        rsvp::{RsvpBuilder, RsvpEventContent},
+        UtcDateTime,
+    },
     referencing::{ExecuteReference, IndexKey, ModelParam, ObjectListIndex},
     store::Store,
     Result,
@@ -49,7 +52,28 @@ impl RsvpManager {
         self.event_id.clone()
     }
 
+    /// The latest response per user for the whole series
     pub async fn rsvp_entries(&self) -> Result<HashMap<OwnedUserId, Rsvp>> {
+        self.rsvp_entries_filtered(|entry| entry.occurrence.is_none())
+            .await
+    }
+
+    /// The latest response per user for the given occurrence, falling back
+    /// to the response to the whole series
+    pub async fn rsvp_entries_for_occurrence(
+        &self,
+        occurrence: UtcDateTime,
+    ) -> Result<HashMap<OwnedUserId, Rsvp>> {
+        self.rsvp_entries_filtered(|entry| {
+            entry.occurrence.is_none() || entry.occurrence == Some(occurrence)
+        })
+        .await
+    }
+
+    async fn rsvp_entries_filtered<F>(&self, filter: F) -> Result<HashMap<OwnedUserId, Rsvp>>
+    where
+        F: Fn(&Rsvp) -> bool,
+    {
         let mut entries = HashMap::new();
         for mdl in self
             .store
@@ -57,6 +81,9 @@ impl RsvpManager {
             .await?
         {
             if let AnyActerModel::Rsvp(c) = mdl {
+                if !filter(&c) {
+                    continue;
+                }
                 let key = c.clone().meta.sender;
                 entries.entry(key).or_insert(c); // we ignore older entries
             }
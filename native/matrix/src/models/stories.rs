@@ -1,20 +1,41 @@
-use matrix_sdk::ruma::OwnedEventId;
+use chrono::{DateTime, Duration};
+use matrix_sdk::{room::Room, ruma::OwnedEventId};
 use matrix_sdk_base::ruma::{events::OriginalMessageLikeEvent, RoomId, UserId};
 use serde::{Deserialize, Serialize};
 use std::ops::Deref;
 
 use super::{default_model_execute, ActerModel, AnyActerModel, Capability, EventMeta};
 use crate::{
-    events::stories::{StoryEventContent, StoryUpdateBuilder, StoryUpdateEventContent},
+    events::{
+        settings::ActerAppSettingsContent,
+        stories::{StoryEventContent, StoryUpdateBuilder, StoryUpdateEventContent},
+        UtcDateTime,
+    },
     referencing::{ExecuteReference, IndexKey, SectionIndex},
     store::Store,
     Result,
 };
 
+/// How long stories are shown in that room before being archived, per
+/// its app settings. `None` if they are kept forever.
+pub async fn story_expiry(room: &Room) -> Result<Option<Duration>> {
+    let settings = match room
+        .get_state_event_static::<ActerAppSettingsContent>()
+        .await?
+    {
+        Some(raw) => raw.deserialize()?.original_content().cloned(),
+        None => None,
+    };
+    Ok(settings.unwrap_or_default().stories().expiry())
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Story {
     inner: StoryEventContent,
     pub meta: EventMeta,
+    /// expired and moved to [`SectionIndex::StoriesArchive`]
+    #[serde(default)]
+    archived: bool,
 }
 
 impl Deref for Story {
@@ -38,13 +59,41 @@ impl Story {
             .story_entry(self.meta.event_id.clone())
             .to_owned()
     }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived
+    }
+
+    pub(crate) fn archive(&mut self) {
+        self.archived = true;
+    }
+
+    pub(crate) fn unarchive(&mut self) {
+        self.archived = false;
+    }
+
+    /// When this story expires: as set by the sender or `default_expiry`
+    /// after it was sent. `None` if it doesn't.
+    pub fn expires_at(&self, default_expiry: Option<Duration>) -> Option<UtcDateTime> {
+        self.inner.expires_at.or_else(|| {
+            let sent_at = DateTime::from_timestamp_millis(u64::from(
+                self.meta.origin_server_ts.get(),
+            ) as i64)?;
+            Some(sent_at + default_expiry?)
+        })
+    }
 }
 
 impl ActerModel for Story {
     fn indizes(&self, _user_id: &UserId) -> Vec<IndexKey> {
+        let section = if self.archived {
+            SectionIndex::StoriesArchive
+        } else {
+            SectionIndex::Stories
+        };
         vec![
-            IndexKey::Section(SectionIndex::Stories),
-            IndexKey::RoomSection(self.meta.room_id.clone(), SectionIndex::Stories),
+            IndexKey::Section(section.clone()),
+            IndexKey::RoomSection(self.meta.room_id.clone(), section),
             IndexKey::ObjectHistory(self.meta.event_id.clone()),
             IndexKey::RoomHistory(self.meta.room_id.clone()),
             IndexKey::AllHistory,
@@ -99,6 +148,7 @@ impl From<OriginalMessageLikeEvent<StoryEventContent>> for Story {
                 origin_server_ts,
                 redacted: None,
             },
+            archived: false,
         }
     }
 }
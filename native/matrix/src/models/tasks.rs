@@ -1,5 +1,5 @@
 mod task;
 mod task_list;
 
-pub use task::{Task, TaskSelfAssign, TaskSelfUnassign, TaskUpdate};
+pub use task::{SubtasksStats, Task, TaskSelfAssign, TaskSelfUnassign, TaskUpdate};
 pub use task_list::{TaskList, TaskListUpdate, TaskStats};
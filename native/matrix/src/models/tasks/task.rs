@@ -1,23 +1,76 @@
+use derive_getters::Getters;
 use matrix_sdk_base::ruma::{
     events::{room::message::TextMessageEventContent, OriginalMessageLikeEvent},
-    OwnedEventId, OwnedUserId, RoomId, UserId,
+    EventId, OwnedEventId, OwnedUserId, RoomId, UserId,
 };
 use serde::{Deserialize, Serialize};
-use std::ops::Deref;
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::Deref,
+};
+use tracing::warn;
 
 use super::super::{
-    default_model_execute, ActerModel, AnyActerModel, Capability, EventMeta, Store,
+    default_model_execute, execution::transition_tree, ActerModel, AnyActerModel, Capability,
+    EditHistory, EventMeta, RedactedActerModel, Revision, Store, UpdateConflict,
 };
 use crate::{
-    events::tasks::{
-        TaskEventContent, TaskSelfAssignEventContent, TaskSelfUnassignEventContent,
-        TaskUpdateBuilder, TaskUpdateEventContent,
+    events::{
+        tasks::{
+            TaskEventContent, TaskSelfAssignEventContent, TaskSelfUnassignEventContent,
+            TaskUpdateBuilder, TaskUpdateEventContent,
+        },
+        Date,
     },
     models::InvitationsManager,
     referencing::{ExecuteReference, IndexKey, ObjectListIndex, SpecialListsIndex},
-    Result,
+    Error, Result,
 };
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Getters)]
+pub struct SubtasksStats {
+    /// the (rolled up) progress of each direct subtask
+    progress: BTreeMap<OwnedEventId, u8>,
+}
+
+impl SubtasksStats {
+    pub fn has_subtasks(&self) -> bool {
+        !self.progress.is_empty()
+    }
+
+    pub fn total_count(&self) -> u32 {
+        self.progress.len() as u32
+    }
+
+    pub fn done_count(&self) -> u32 {
+        self.progress.values().filter(|p| **p >= 100).count() as u32
+    }
+
+    /// average progress over all subtasks
+    pub fn percent(&self) -> Option<u8> {
+        if self.progress.is_empty() {
+            return None;
+        }
+        let sum: u32 = self.progress.values().map(|p| u32::from(*p).min(100)).sum();
+        Some((sum / self.progress.len() as u32) as u8)
+    }
+
+    async fn collect(store: &Store, task_id: &EventId) -> Result<Self> {
+        let key = IndexKey::ObjectList(task_id.to_owned(), ObjectListIndex::Subtasks);
+        let progress = store
+            .get_list(&key)
+            .await?
+            .filter_map(|m| match m {
+                AnyActerModel::Task(t) => {
+                    Some((t.meta.event_id.clone(), t.percent().unwrap_or_default()))
+                }
+                _ => None,
+            })
+            .collect();
+        Ok(SubtasksStats { progress })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Task {
     pub(crate) inner: TaskEventContent,
@@ -25,6 +78,12 @@ pub struct Task {
 
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     assignees: Vec<OwnedUserId>,
+
+    #[serde(default)]
+    subtasks: SubtasksStats,
+
+    #[serde(default, skip_serializing_if = "EditHistory::is_empty")]
+    history: EditHistory,
 }
 
 impl Deref for Task {
@@ -55,21 +114,106 @@ impl Task {
         &self.meta.sender
     }
 
+    /// Whether this task is complete, going by its progress as [`Self::percent`]
+    /// reports it: tasks with subtasks are done once all of those are
     pub fn is_done(&self) -> bool {
-        self.inner
-            .progress_percent
-            .map(|u| u >= 100)
-            .unwrap_or_default()
+        self.percent().map(|u| u >= 100).unwrap_or_default()
     }
 
     pub fn is_assigned(&self, user_id: &UserId) -> bool {
         self.assignees.iter().any(|o| o == user_id)
     }
 
+    /// The progress of this task, derived from its subtasks if it has any
     pub fn percent(&self) -> Option<u8> {
+        self.subtasks.percent().or(self.inner.progress_percent)
+    }
+
+    /// The progress set on this task itself, ignoring any subtasks
+    pub fn own_percent(&self) -> Option<u8> {
         self.inner.progress_percent
     }
 
+    pub fn subtasks_stats(&self) -> &SubtasksStats {
+        &self.subtasks
+    }
+
+    pub fn subtasks_key(&self) -> IndexKey {
+        IndexKey::ObjectList(self.meta.event_id.clone(), ObjectListIndex::Subtasks)
+    }
+
+    pub fn parent_task(&self) -> Option<OwnedEventId> {
+        self.inner.parent_task.clone()
+    }
+
+    pub fn blocked_by(&self) -> Vec<OwnedEventId> {
+        self.inner.blocked_by.clone()
+    }
+
+    pub fn is_recurring(&self) -> bool {
+        self.inner.recurrence.is_some()
+    }
+
+    /// The content of the instance to post after this recurring task has
+    /// been completed on `completed_on`, `None` if there is no further one
+    pub fn next_instance(&self, completed_on: Date) -> Option<TaskEventContent> {
+        let recurrence = self.inner.recurrence.as_ref()?;
+        let (due_date, recurrence) = recurrence.next(self.inner.due_date, completed_on)?;
+        let mut content = self.inner.clone();
+        content.due_date = Some(due_date);
+        content.recurrence = Some(recurrence);
+        content.previous_instance = Some(self.meta.event_id.clone());
+        content.progress_percent = None;
+        content.utc_start = None;
+        content.blocked_by.clear();
+        Some(content)
+    }
+
+    /// Ensure that making `parent_task` the parent of and `blocked_by` the
+    /// blockers of `task_id` doesn’t lead to a cycle with the tasks known
+    /// in the store.
+    pub async fn ensure_no_cycles(
+        store: &Store,
+        task_id: &EventId,
+        parent_task: Option<&EventId>,
+        blocked_by: &[OwnedEventId],
+    ) -> Result<()> {
+        // walk up the tree of parents
+        let mut visited = HashSet::new();
+        let mut next = parent_task.map(ToOwned::to_owned);
+        while let Some(current) = next.take() {
+            if current == task_id {
+                return Err(Error::RelationCycle(
+                    parent_task.map(ToString::to_string).unwrap_or_default(),
+                ));
+            }
+            if !visited.insert(current.clone()) {
+                break; // an existing cycle not involving us
+            }
+            if let Ok(AnyActerModel::Task(task)) = store.get(&current).await {
+                next = task.inner.parent_task;
+            }
+        }
+
+        // and through all the blockers of the blockers
+        let mut visited = HashSet::new();
+        for blocker in blocked_by {
+            let mut pending = vec![blocker.clone()];
+            while let Some(current) = pending.pop() {
+                if current == task_id {
+                    return Err(Error::RelationCycle(blocker.to_string()));
+                }
+                if !visited.insert(current.clone()) {
+                    continue;
+                }
+                if let Ok(AnyActerModel::Task(task)) = store.get(&current).await {
+                    pending.extend(task.inner.blocked_by);
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn due_date(&self) -> Option<String> {
         self.inner
             .due_date
@@ -90,9 +234,36 @@ impl Task {
     pub fn updater(&self) -> TaskUpdateBuilder {
         TaskUpdateBuilder::default()
             .task(self.meta.event_id.clone())
+            .base_revision(self.revision())
             .to_owned()
     }
 
+    /// The revision this task is at, to base updates on
+    pub fn revision(&self) -> OwnedEventId {
+        self.history.latest_revision(&self.meta.event_id)
+    }
+
+    /// The updates applied since `revision`, `None` if it isn’t known
+    pub fn changed_since(&self, revision: &EventId) -> Option<&[Revision]> {
+        self.history.changed_since(&self.meta.event_id, revision)
+    }
+
+    /// Concurrent updates that changed the same fields and who won
+    pub fn conflicts(&self) -> &[UpdateConflict] {
+        self.history.conflicts()
+    }
+
+    /// The creation and all updates applied since, oldest first
+    pub fn revisions(&self) -> Result<Vec<Revision>> {
+        self.history.revisions(&self.meta, &self.inner)
+    }
+
+    /// An update bringing this task back to how it was after `revision`
+    pub fn revert_update(&self, revision: &EventId) -> Result<TaskUpdateEventContent> {
+        self.history
+            .revert_update(&self.meta.event_id, &self.inner, revision)
+    }
+
     pub fn self_assign_event_content(&self) -> TaskSelfAssignEventContent {
         TaskSelfAssignEventContent {
             task: self.meta.event_id.clone().into(),
@@ -118,6 +289,12 @@ impl ActerModel for Task {
             IndexKey::ObjectHistory(self.inner.task_list_id.event_id.clone()),
             IndexKey::AllHistory,
         ];
+        if let Some(parent_task) = &self.inner.parent_task {
+            indizes.push(IndexKey::ObjectList(
+                parent_task.clone(),
+                ObjectListIndex::Subtasks,
+            ));
+        }
         if self.is_assigned(user_id) {
             indizes.push(if self.is_done() {
                 IndexKey::Special(SpecialListsIndex::MyDoneTasks)
@@ -141,8 +318,13 @@ impl ActerModel for Task {
         ]
     }
 
-    async fn execute(self, store: &Store) -> Result<Vec<ExecuteReference>> {
-        default_model_execute(store, self.into()).await
+    async fn execute(mut self, store: &Store) -> Result<Vec<ExecuteReference>> {
+        // subtasks might have been seen before their parent
+        self.subtasks = SubtasksStats::collect(store, &self.meta.event_id).await?;
+        let task = self.clone();
+        let mut updates = default_model_execute(store, self.into()).await?;
+        updates.extend(roll_up_progress(store, &task, None).await?);
+        Ok(updates)
     }
 
     fn belongs_to(&self) -> Option<Vec<OwnedEventId>> {
@@ -151,12 +333,36 @@ impl ActerModel for Task {
 
     fn transition(&mut self, model: &AnyActerModel) -> Result<bool> {
         match model {
-            AnyActerModel::TaskUpdate(update) => update.apply(&mut self.inner),
+            AnyActerModel::TaskUpdate(update) => self.history.apply_update(
+                &self.meta.event_id,
+                update.event_meta(),
+                update.base_revision.as_deref(),
+                &update.inner,
+                &mut self.inner,
+                TaskUpdateEventContent::apply,
+            ),
             AnyActerModel::TaskSelfAssign(update) => update.apply(self),
             AnyActerModel::TaskSelfUnassign(update) => update.apply(self),
             _ => Ok(false),
         }
     }
+
+    // a redacted subtask doesn't count towards its parent anymore
+    async fn redact(
+        &self,
+        store: &Store,
+        redaction_model: RedactedActerModel,
+    ) -> Result<Vec<ExecuteReference>> {
+        let model: AnyActerModel = redaction_model.into();
+        let mut models =
+            transition_tree(store, self.belongs_to().unwrap_or_default(), &model).await?;
+        models.push(model);
+        let mut updates = store.save_many(models).await?;
+        if let Some(parent) = self.inner.parent_task.clone() {
+            updates.extend(roll_up_into(store, parent, self.meta.event_id.clone(), None).await?);
+        }
+        Ok(updates)
+    }
 }
 
 impl From<OriginalMessageLikeEvent<TaskEventContent>> for Task {
@@ -172,6 +378,8 @@ impl From<OriginalMessageLikeEvent<TaskEventContent>> for Task {
         Task {
             inner: content,
             assignees: Vec::with_capacity(0),
+            subtasks: Default::default(),
+            history: Default::default(),
             meta: EventMeta {
                 room_id,
                 event_id,
@@ -202,8 +410,41 @@ impl ActerModel for TaskUpdate {
         &self.meta
     }
 
-    async fn execute(self, store: &Store) -> Result<Vec<ExecuteReference>> {
-        default_model_execute(store, self.into()).await
+    async fn execute(mut self, store: &Store) -> Result<Vec<ExecuteReference>> {
+        let task_id = self.inner.task.event_id.clone();
+        let previous_parent = match store.get(&task_id).await {
+            Ok(AnyActerModel::Task(task)) => {
+                if self.inner.changes_relations() {
+                    let parent_task = match &self.inner.parent_task {
+                        Some(p) => p.clone(),
+                        None => task.inner.parent_task.clone(),
+                    };
+                    let blocked_by = self
+                        .inner
+                        .blocked_by
+                        .as_ref()
+                        .unwrap_or(&task.inner.blocked_by);
+                    if let Err(error) =
+                        Task::ensure_no_cycles(store, &task_id, parent_task.as_deref(), blocked_by)
+                            .await
+                    {
+                        // judged by the updates this store has seen so far,
+                        // other clients may still end up with the relation
+                        warn!(?task_id, ?error, "ignoring relation changes of task update");
+                        self.inner.parent_task = None;
+                        self.inner.blocked_by = None;
+                    }
+                }
+                task.inner.parent_task
+            }
+            _ => None,
+        };
+
+        let mut updates = default_model_execute(store, self.into()).await?;
+        if let Ok(AnyActerModel::Task(task)) = store.get(&task_id).await {
+            updates.extend(roll_up_progress(store, &task, previous_parent).await?);
+        }
+        Ok(updates)
     }
 
     fn belongs_to(&self) -> Option<Vec<OwnedEventId>> {
@@ -211,6 +452,56 @@ impl ActerModel for TaskUpdate {
     }
 }
 
+/// Propagate the progress of `task` up through its parents, removing it from
+/// `previous_parent` if it has been moved away from that one
+async fn roll_up_progress(
+    store: &Store,
+    task: &Task,
+    previous_parent: Option<OwnedEventId>,
+) -> Result<Vec<ExecuteReference>> {
+    let mut updates = vec![];
+    let task_id = task.meta.event_id.clone();
+    if let Some(previous) = previous_parent.filter(|p| Some(p) != task.inner.parent_task.as_ref()) {
+        updates.extend(roll_up_into(store, previous, task_id.clone(), None).await?);
+    }
+    if let Some(parent) = task.inner.parent_task.clone() {
+        let percent = task.percent().unwrap_or_default();
+        updates.extend(roll_up_into(store, parent, task_id, Some(percent)).await?);
+    }
+    Ok(updates)
+}
+
+async fn roll_up_into(
+    store: &Store,
+    mut parent_id: OwnedEventId,
+    mut child_id: OwnedEventId,
+    mut child_percent: Option<u8>,
+) -> Result<Vec<ExecuteReference>> {
+    let mut updates = vec![];
+    let mut visited = HashSet::new();
+    while visited.insert(parent_id.clone()) {
+        let Ok(AnyActerModel::Task(mut parent)) = store.get(&parent_id).await else {
+            break; // parent not known (yet)
+        };
+        let changed = match child_percent {
+            Some(percent) => parent.subtasks.progress.insert(child_id, percent) != Some(percent),
+            None => parent.subtasks.progress.remove(&child_id).is_some(),
+        };
+        if !changed {
+            break;
+        }
+        let next = parent.inner.parent_task.clone();
+        child_id = parent.meta.event_id.clone();
+        child_percent = Some(parent.percent().unwrap_or_default());
+        updates.extend(store.save(parent.into()).await?);
+        match next {
+            Some(next) => parent_id = next,
+            None => break,
+        }
+    }
+    Ok(updates)
+}
+
 impl Deref for TaskUpdate {
     type Target = TaskUpdateEventContent;
     fn deref(&self) -> &Self::Target {
@@ -1,12 +1,13 @@
 use derive_getters::Getters;
 use matrix_sdk::ruma::OwnedEventId;
-use matrix_sdk_base::ruma::{events::OriginalMessageLikeEvent, RoomId, UserId};
+use matrix_sdk_base::ruma::{events::OriginalMessageLikeEvent, EventId, RoomId, UserId};
 use serde::{Deserialize, Serialize};
 use std::ops::Deref;
 use tracing::{trace, warn};
 
 use super::super::{
-    default_model_execute, ActerModel, AnyActerModel, Capability, EventMeta, Store,
+    default_model_execute, ActerModel, AnyActerModel, Capability, EditHistory, EventMeta, Revision,
+    Store,
 };
 use crate::{
     events::tasks::{TaskListEventContent, TaskListUpdateBuilder, TaskListUpdateEventContent},
@@ -25,6 +26,9 @@ pub struct TaskList {
     pub(crate) inner: TaskListEventContent,
     meta: EventMeta,
     task_stats: TaskStats,
+
+    #[serde(default, skip_serializing_if = "EditHistory::is_empty")]
+    history: EditHistory,
 }
 
 impl Deref for TaskList {
@@ -60,6 +64,17 @@ impl TaskList {
             .task_list(self.meta.event_id.clone())
             .to_owned()
     }
+
+    /// The creation and all updates applied since, oldest first
+    pub fn revisions(&self) -> Result<Vec<Revision>> {
+        self.history.revisions(&self.meta, &self.inner)
+    }
+
+    /// An update bringing this list back to how it was after `revision`
+    pub fn revert_update(&self, revision: &EventId) -> Result<TaskListUpdateEventContent> {
+        self.history
+            .revert_update(&self.meta.event_id, &self.inner, revision)
+    }
 }
 
 impl From<OriginalMessageLikeEvent<TaskListEventContent>> for TaskList {
@@ -82,6 +97,7 @@ impl From<OriginalMessageLikeEvent<TaskListEventContent>> for TaskList {
                 redacted: None,
             },
             task_stats: Default::default(),
+            history: Default::default(),
         }
     }
 }
@@ -115,7 +131,14 @@ impl ActerModel for TaskList {
 
     fn transition(&mut self, model: &AnyActerModel) -> Result<bool> {
         match model {
-            AnyActerModel::TaskListUpdate(update) => update.apply(&mut self.inner),
+            AnyActerModel::TaskListUpdate(update) => self.history.apply_update(
+                &self.meta.event_id,
+                update.event_meta(),
+                None,
+                &update.inner,
+                &mut self.inner,
+                TaskListUpdateEventContent::apply,
+            ),
             AnyActerModel::Task(task) => {
                 let key = self.event_id().to_owned();
                 trace!(?key, ?task, "adding task to list");
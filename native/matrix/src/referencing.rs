@@ -16,6 +16,8 @@ pub enum SectionIndex {
     Pins,
     Stories,
     Tasks,
+    /// expired stories, moved out of [`SectionIndex::Stories`]
+    StoriesArchive,
 }
 
 #[derive(
@@ -31,6 +33,7 @@ pub enum ObjectListIndex {
     Rsvp,
     Tasks,
     Invites,
+    Subtasks,
 }
 
 #[derive(
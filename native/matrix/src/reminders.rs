@@ -0,0 +1,333 @@
+use chrono::{Duration, NaiveTime};
+use matrix_sdk_base::ruma::OwnedRoomId;
+use std::collections::HashSet;
+use strum::Display;
+
+use crate::{
+    activities::object::ActivityObject,
+    client::CoreClient,
+    events::{rsvp::RsvpStatus, settings::AppReminderSettings, Date, UtcDateTime},
+    models::{ActerModel, AnyActerModel, RsvpManager},
+    referencing::{IndexKey, SectionIndex, SpecialListsIndex},
+    Result,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display)]
+#[strum(serialize_all = "camelCase")]
+pub enum ReminderKind {
+    TaskDue,
+    EventStart,
+}
+
+/// A reminder to show locally at `remind_at`, ahead of a task being due or
+/// an event the user is going to starting
+#[derive(Clone, Debug)]
+pub struct Reminder {
+    kind: ReminderKind,
+    object: ActivityObject,
+    room_id: OwnedRoomId,
+    /// when the task is due or the event (occurrence) starts
+    due_at: UtcDateTime,
+    minutes_before: u32,
+    remind_at: UtcDateTime,
+}
+
+impl Reminder {
+    fn new(
+        kind: ReminderKind,
+        object: ActivityObject,
+        room_id: OwnedRoomId,
+        due_at: UtcDateTime,
+        minutes_before: u32,
+    ) -> Self {
+        Reminder {
+            kind,
+            object,
+            room_id,
+            due_at,
+            minutes_before,
+            remind_at: due_at - Duration::minutes(minutes_before.into()),
+        }
+    }
+
+    /// Stable across recomputations, so platforms can replace or cancel
+    /// what they scheduled by it. Changes when the due time changes.
+    pub fn id(&self) -> String {
+        format!(
+            "{}:{}:{}:{}",
+            self.kind,
+            self.object.object_id_str(),
+            self.due_at.timestamp(),
+            self.minutes_before
+        )
+    }
+
+    pub fn kind(&self) -> ReminderKind {
+        self.kind
+    }
+
+    pub fn object(&self) -> &ActivityObject {
+        &self.object
+    }
+
+    pub fn room_id(&self) -> &OwnedRoomId {
+        &self.room_id
+    }
+
+    pub fn due_at(&self) -> UtcDateTime {
+        self.due_at
+    }
+
+    pub fn minutes_before(&self) -> u32 {
+        self.minutes_before
+    }
+
+    pub fn remind_at(&self) -> UtcDateTime {
+        self.remind_at
+    }
+}
+
+/// What changed between two computations of the upcoming reminders
+#[derive(Clone, Debug)]
+pub enum ReminderChange {
+    Schedule(Reminder),
+    Cancel(Reminder),
+}
+
+impl ReminderChange {
+    /// Cancel what is gone, schedule what is new, by [`Reminder::id`]
+    pub fn between(previous: &[Reminder], current: &[Reminder]) -> Vec<ReminderChange> {
+        let previous_ids: HashSet<String> = previous.iter().map(Reminder::id).collect();
+        let current_ids: HashSet<String> = current.iter().map(Reminder::id).collect();
+        previous
+            .iter()
+            .filter(|r| !current_ids.contains(&r.id()))
+            .cloned()
+            .map(ReminderChange::Cancel)
+            .chain(
+                current
+                    .iter()
+                    .filter(|r| !previous_ids.contains(&r.id()))
+                    .cloned()
+                    .map(ReminderChange::Schedule),
+            )
+            .collect()
+    }
+}
+
+/// When a task is due: at `utc_due_time_of_day` seconds after midnight UTC
+/// of its due date, or at that midnight if no time was given
+pub fn task_due_at(due_date: Date, utc_due_time_of_day: Option<i32>) -> UtcDateTime {
+    let midnight = due_date.and_time(NaiveTime::MIN).and_utc();
+    midnight + Duration::seconds(utc_due_time_of_day.unwrap_or_default().into())
+}
+
+/// The reminders for something due at `due_at` falling into `[from, until)`
+fn reminders_for(
+    kind: ReminderKind,
+    object: &ActivityObject,
+    room_id: &OwnedRoomId,
+    due_at: UtcDateTime,
+    offsets: &[u32],
+    from: UtcDateTime,
+    until: UtcDateTime,
+) -> Vec<Reminder> {
+    offsets
+        .iter()
+        .map(|minutes| Reminder::new(kind, object.clone(), room_id.clone(), due_at, *minutes))
+        .filter(|r| r.remind_at >= from && r.remind_at < until)
+        .collect()
+}
+
+impl CoreClient {
+    /// The reminders to show between `from` and `until`, soonest first: for
+    /// the open tasks assigned to the user and the events they are going or
+    /// maybe going to
+    pub async fn upcoming_reminders(
+        &self,
+        settings: &AppReminderSettings,
+        from: UtcDateTime,
+        until: UtcDateTime,
+    ) -> Result<Vec<Reminder>> {
+        let store = self.store();
+        let user_id = store.user_id().to_owned();
+        let mut reminders = Vec::new();
+
+        let task_offsets = settings.task_due_offsets();
+        if !task_offsets.is_empty() {
+            for model in store
+                .get_list(&IndexKey::Special(SpecialListsIndex::MyOpenTasks))
+                .await?
+            {
+                let AnyActerModel::Task(task) = &model else {
+                    continue;
+                };
+                let Some(due_date) = task.inner.due_date.filter(|_| !task.is_done()) else {
+                    continue;
+                };
+                let Ok(object) = ActivityObject::try_from(&model) else {
+                    continue;
+                };
+                let due_at = task_due_at(due_date, task.inner.utc_due_time_of_day);
+                reminders.extend(reminders_for(
+                    ReminderKind::TaskDue,
+                    &object,
+                    &task.meta.room_id,
+                    due_at,
+                    &task_offsets,
+                    from,
+                    until,
+                ));
+            }
+        }
+
+        let event_offsets = settings.event_start_offsets();
+        if let Some(max_offset) = event_offsets.iter().max() {
+            let starts_until = until + Duration::minutes((*max_offset).into());
+            for model in store
+                .get_list(&IndexKey::Section(SectionIndex::Calendar))
+                .await?
+            {
+                let AnyActerModel::CalendarEvent(event) = model else {
+                    continue;
+                };
+                let occurrences = event.occurrences(from, starts_until);
+                if occurrences.is_empty() {
+                    continue;
+                }
+                let event_id = event.event_id().to_owned();
+                let rsvps = RsvpManager::from_store_and_event_id(store, &event_id).await;
+                for occurrence in occurrences {
+                    let entries = rsvps
+                        .rsvp_entries_for_occurrence(*occurrence.recurrence_id())
+                        .await?;
+                    if !matches!(
+                        entries.get(&user_id).map(|r| &r.status),
+                        Some(RsvpStatus::Yes | RsvpStatus::Maybe)
+                    ) {
+                        continue;
+                    }
+                    let object = ActivityObject::CalendarEvent {
+                        object_id: event_id.clone(),
+                        title: occurrence.title(),
+                        description: occurrence.description.clone(),
+                        utc_start: occurrence.utc_start(),
+                        utc_end: occurrence.utc_end(),
+                    };
+                    reminders.extend(reminders_for(
+                        ReminderKind::EventStart,
+                        &object,
+                        &event.room_id().to_owned(),
+                        occurrence.utc_start(),
+                        &event_offsets,
+                        from,
+                        until,
+                    ));
+                }
+            }
+        }
+
+        reminders.sort_by_key(|r| r.remind_at);
+        Ok(reminders)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+    use matrix_sdk_base::ruma::{owned_event_id, owned_room_id};
+
+    fn at(rfc3339: &str) -> UtcDateTime {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn task(id: &str) -> ActivityObject {
+        ActivityObject::Task {
+            tl_id: owned_event_id!("$list"),
+            object_id: format!("${id}").parse().unwrap(),
+            title: format!("Task {id}"),
+            due_date: None,
+        }
+    }
+
+    #[test]
+    fn due_at_uses_time_of_day() {
+        let date = Date::from_ymd_opt(2026, 10, 20).unwrap();
+        assert_eq!(task_due_at(date, None), at("2026-10-20T00:00:00Z"));
+        assert_eq!(
+            task_due_at(date, Some(17 * 60 * 60 + 30 * 60)),
+            at("2026-10-20T17:30:00Z")
+        );
+        // before midnight of the due date
+        assert_eq!(task_due_at(date, Some(-3600)), at("2026-10-19T23:00:00Z"));
+    }
+
+    #[test]
+    fn only_reminders_in_window() {
+        let room_id = owned_room_id!("!space:example.org");
+        let due_at = at("2026-10-20T12:00:00Z");
+        let reminders = reminders_for(
+            ReminderKind::TaskDue,
+            &task("a"),
+            &room_id,
+            due_at,
+            &[0, 60, 24 * 60, 3 * 24 * 60],
+            at("2026-10-18T12:00:00Z"),
+            at("2026-10-20T12:00:00Z"),
+        );
+
+        // three days ahead is already past and at the due time is beyond `until`
+        let minutes: Vec<_> = reminders.iter().map(|r| r.minutes_before()).collect();
+        assert_eq!(minutes, [60, 24 * 60]);
+        assert_eq!(reminders[0].remind_at(), at("2026-10-20T11:00:00Z"));
+        assert_eq!(reminders[1].remind_at(), at("2026-10-19T12:00:00Z"));
+        assert_eq!(reminders[0].kind().to_string(), "taskDue");
+    }
+
+    #[test]
+    fn changes_by_id() {
+        let room_id = owned_room_id!("!space:example.org");
+        let first = Reminder::new(
+            ReminderKind::TaskDue,
+            task("a"),
+            room_id.clone(),
+            at("2026-10-20T12:00:00Z"),
+            60,
+        );
+        let kept = Reminder::new(
+            ReminderKind::EventStart,
+            task("b"),
+            room_id.clone(),
+            at("2026-10-21T12:00:00Z"),
+            15,
+        );
+        // the due time moved, so it is a different reminder now
+        let moved = Reminder::new(
+            ReminderKind::TaskDue,
+            task("a"),
+            room_id,
+            at("2026-10-22T12:00:00Z"),
+            60,
+        );
+        assert_ne!(first.id(), moved.id());
+
+        let changes = ReminderChange::between(&[first.clone(), kept.clone()], &[kept, moved]);
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(&changes[0], ReminderChange::Cancel(r) if r.id() == first.id()));
+        assert!(
+            matches!(&changes[1], ReminderChange::Schedule(r) if r.due_at() == at("2026-10-22T12:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn settings_default_and_off() {
+        let mut settings = AppReminderSettings::default();
+        assert_eq!(settings.task_due_offsets(), [24 * 60]);
+        assert_eq!(settings.event_start_offsets(), [15]);
+        settings.event_start = Some(vec![]);
+        assert!(settings.event_start_offsets().is_empty());
+    }
+}
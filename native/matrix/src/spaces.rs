@@ -17,7 +17,8 @@ use matrix_sdk_base::{
         },
         room::RoomType,
         serde::Raw,
-        MxcUri, OwnedRoomId, OwnedServerName, OwnedUserId, RoomId, ServerName, UserId,
+        MxcUri, OwnedEventId, OwnedRoomId, OwnedServerName, OwnedUserId, RoomId, ServerName,
+        UserId,
     },
     RoomState,
 };
@@ -147,6 +148,16 @@ impl CreateSpaceSettingsBuilder {
     }
 }
 
+impl CreateSpaceSettings {
+    pub fn parent(&self) -> Option<&OwnedRoomId> {
+        self.parent.as_ref()
+    }
+
+    pub fn set_parent(&mut self, parent: OwnedRoomId) {
+        self.parent = Some(parent);
+    }
+}
+
 #[derive(Clone, Debug, Default, Display)]
 pub enum RelationTargetType {
     #[default]
@@ -325,6 +336,29 @@ impl CoreClient {
         Ok(room.room_id().to_owned())
     }
 
+    /// List `child` as child of the `parent` space
+    pub async fn add_space_child(
+        &self,
+        parent: &RoomId,
+        child: &RoomId,
+        suggested: bool,
+    ) -> Result<OwnedEventId> {
+        let client = self.client();
+        let room = client
+            .get_room(parent)
+            .ok_or_else(|| Error::Custom(format!("Space {parent} not found")))?;
+        let Some(Ok(homeserver)) = client.homeserver().host_str().map(ServerName::parse) else {
+            return Err(Error::HomeserverMissesHostname);
+        };
+        let response = room
+            .send_state_event_for_key(
+                child,
+                assign!(SpaceChildEventContent::new(vec![homeserver]), { suggested }),
+            )
+            .await?;
+        Ok(response.event_id)
+    }
+
     // calculate the space relationships in accordance with:
     // https://spec.matrix.org/v1.6/client-server-api/#mspacechild-relationship
     pub async fn space_relations(&self, room: &Room) -> Result<SpaceRelations> {
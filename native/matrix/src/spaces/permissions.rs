@@ -15,6 +15,7 @@ use crate::events::{
     rsvp::RsvpEventContent,
     settings::{
         ActerAppSettingsContent, SimpleOnOffSettingBuilder, SimpleSettingWithTurnOffBuilder,
+        StoriesSettingsBuilder,
     },
     stories::StoryEventContent,
     tasks::{TaskEventContent, TaskListEventContent},
@@ -105,7 +106,7 @@ impl AppPermissionsBuilder {
 
     pub fn stories(&mut self, active: bool) {
         self.settings.stories = Some(
-            SimpleOnOffSettingBuilder::default()
+            StoriesSettingsBuilder::default()
                 .active(active)
                 .build()
                 .unwrap(),
@@ -1,13 +1,25 @@
-use matrix_sdk::ruma::OwnedEventId;
+use matrix_sdk::ruma::{EventId, OwnedEventId};
 use matrix_sdk::Client;
-use matrix_sdk_base::ruma::{OwnedRoomId, OwnedUserId, UserId};
+use matrix_sdk_base::ruma::{OwnedRoomId, OwnedUserId, RoomId, UserId};
 use scc::hash_map::{Entry, HashMap};
 use std::collections::HashSet;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use tracing::{debug, error, info, instrument, trace, warn};
 
 mod index;
+mod migrations;
+mod news_drafts;
+mod outbox;
+mod search;
 pub use index::{LifoIndex, RankedIndex, StoreIndex};
+use news_drafts::{load_news_drafts, NewsDrafts};
+pub use news_drafts::{NewsDraft, NEWS_DRAFTS_KEY};
+pub use outbox::{is_local_id, OutboxEntry, OutboxState, LOCAL_ID_PREFIX, OUTBOX_KEY};
+pub(crate) use outbox::{local_id_for, Outbox};
+pub use search::{
+    tokenize, SearchDocument, SearchField, SearchHit, SearchIndex, SearchQuery, SearchQueryBuilder,
+    SearchQueryBuilderError,
+};
 
 use crate::referencing::{ExecuteReference, IndexKey};
 use crate::{
@@ -22,11 +34,19 @@ pub struct Store {
     models: Arc<HashMap<OwnedEventId, AnyActerModel>>,
     indizes: Arc<HashMap<IndexKey, StoreIndex>>,
     dirty: Arc<Mutex<HashSet<OwnedEventId>>>, // our key mutex;
+    outbox: Arc<Mutex<Outbox>>,
+    news_drafts: Arc<Mutex<NewsDrafts>>,
+    search: Arc<RwLock<SearchIndex>>,
 }
 
 static ALL_MODELS_KEY: &str = "ACTER::ALL";
 static DB_VERSION_KEY: &str = "ACTER::DB_VERSION";
-static CURRENT_DB_VERSION: u32 = 1;
+static CURRENT_DB_VERSION: u32 = 2;
+
+/// Storage key of how far the history of the room has been loaded
+pub fn room_history_key(room_id: &RoomId) -> String {
+    format!("{room_id}::history")
+}
 
 async fn get_from_store<T: serde::de::DeserializeOwned>(client: Client, key: &str) -> Result<T> {
     let v = client
@@ -76,6 +96,53 @@ impl Store {
         Self::new_inner(client, user_id).await
     }
 
+    /// Start over with an empty store at the current version, all models
+    /// are derived from the room history again
+    async fn reset(client: Client, user_id: OwnedUserId) -> Result<Self> {
+        client
+            .state_store()
+            .set_custom_value_no_read(ALL_MODELS_KEY.as_bytes(), vec![])
+            .await
+            .map_err(|e| Error::Custom(format!("setting all models to [] failed: {e}")))?;
+
+        client
+            .state_store()
+            .set_custom_value_no_read(
+                DB_VERSION_KEY.as_bytes(),
+                CURRENT_DB_VERSION.to_le_bytes().to_vec(),
+            )
+            .await
+            .map_err(|e| Error::Custom(format!("setting db version failed: {e}")))?;
+
+        // load the history of all rooms from the start again
+        for room in client.rooms() {
+            let key = room_history_key(room.room_id());
+            client
+                .state_store()
+                .remove_custom_value(format!("acter:{key}").as_bytes())
+                .await
+                .map_err(|e| Error::Custom(format!("removing {key} failed: {e}")))?;
+        }
+
+        // drafts aren’t derived from the history, keep them
+        let news_drafts = load_news_drafts(client.clone()).await;
+
+        let store = Store {
+            client,
+            user_id,
+            indizes: Default::default(),
+            models: Default::default(),
+            dirty: Default::default(),
+            outbox: Default::default(),
+            news_drafts: Arc::new(Mutex::new(news_drafts)),
+            search: Default::default(),
+        };
+        if let Err(error) = store.reconcile_news_drafts().await {
+            warn!(?error, "Couldn’t reconcile news drafts at startup");
+        }
+        Ok(store)
+    }
+
     async fn new_inner(client: Client, user_id: OwnedUserId) -> Result<Self> {
         let ver = client
             .state_store()
@@ -85,29 +152,16 @@ impl Store {
             .map(|u| u32::from_le_bytes(u.as_chunks().0[0]))
             .unwrap_or_default();
         if ver < CURRENT_DB_VERSION {
-            // "upgrading" by resetting
-            client
-                .state_store()
-                .set_custom_value_no_read(ALL_MODELS_KEY.as_bytes(), vec![])
-                .await
-                .map_err(|e| Error::Custom(format!("setting all models to [] failed: {e}")))?;
-
-            client
-                .state_store()
-                .set_custom_value_no_read(
-                    DB_VERSION_KEY.as_bytes(),
-                    CURRENT_DB_VERSION.to_le_bytes().to_vec(),
-                )
-                .await
-                .map_err(|e| Error::Custom(format!("setting db version failed: {e}")))?;
-
-            return Ok(Store {
-                client,
-                user_id,
-                indizes: Default::default(),
-                models: Default::default(),
-                dirty: Default::default(),
-            });
+            if !migrations::can_migrate(ver) {
+                // nothing stored yet or too old, derive everything from the history
+                info!(ver, "no migration from this DB version. resetting");
+                return Self::reset(client, user_id).await;
+            }
+            if let Err(error) = migrations::migrate(&client, ver).await {
+                // no way to migrate, derive everything from the history again
+                warn!(ver, ?error, "migrating store failed. resetting");
+                return Self::reset(client, user_id).await;
+            }
         }
 
         // current DB version, attempt to load models
@@ -166,13 +220,48 @@ impl Store {
             let _ = models.insert(key, m);
         }
 
-        Ok(Store {
+        let mut search = SearchIndex::default();
+        let mut loaded = Vec::new();
+        models.scan(|_k, m| loaded.push(m.clone()));
+        let by_id: std::collections::HashMap<&EventId, &AnyActerModel> =
+            loaded.iter().map(|m| (m.event_id(), m)).collect();
+        for m in loaded.iter() {
+            let document =
+                SearchDocument::for_model(m, |parent| by_id.get(parent).map(|p| (*p).clone()));
+            search.update(m.event_id(), document);
+        }
+
+        let outbox =
+            match get_from_store::<Outbox>(client.clone(), &OUTBOX_KEY.as_storage_key()).await {
+                Ok(outbox) => outbox,
+                Err(Error::ModelNotFound(_)) => Default::default(),
+                Err(error) => {
+                    error!(
+                        ?error,
+                        "Couldn’t read outbox at startup. Dropping queued changes."
+                    );
+                    Default::default()
+                }
+            };
+        let news_drafts = load_news_drafts(client.clone()).await;
+
+        let store = Store {
             client,
             user_id,
             indizes: Arc::new(indizes),
             models: Arc::new(models),
             dirty: Default::default(),
-        })
+            outbox: Arc::new(Mutex::new(outbox)),
+            news_drafts: Arc::new(Mutex::new(news_drafts)),
+            search: Arc::new(RwLock::new(search)),
+        };
+        if let Err(error) = store.remove_orphaned_pending_models().await {
+            warn!(?error, "Couldn’t remove orphaned pending models at startup");
+        }
+        if let Err(error) = store.reconcile_news_drafts().await {
+            warn!(?error, "Couldn’t reconcile news drafts at startup");
+        }
+        Ok(store)
     }
 
     #[instrument(skip(self))]
@@ -207,6 +296,11 @@ impl Store {
         futures::future::join_all(models).await
     }
 
+    /// Full-text search over the locally known models, best matches first
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        Ok(self.search.read()?.search(query))
+    }
+
     #[instrument(skip(self))]
     async fn save_model_inner(
         &self,
@@ -230,6 +324,9 @@ impl Store {
         let mut new_indizes = mdl.indizes(user_id);
         let mut removed_indizes = Vec::new();
         let event_meta = mdl.event_meta().clone();
+        let search_document = SearchDocument::for_model(&mdl, |parent| {
+            self.models.get(parent).map(|p| p.get().clone())
+        });
         match self.models.entry(key.clone()) {
             Entry::Vacant(v) => {
                 v.insert_entry(mdl);
@@ -248,6 +345,8 @@ impl Store {
             }
         }
 
+        self.search.write()?.update(&key, search_document);
+
         for idz in removed_indizes.iter() {
             if let Some(mut v) = self.indizes.get(idz) {
                 v.get_mut().remove(&key);
@@ -330,6 +429,7 @@ impl Store {
                 }
                 // remove the model itself
                 self.models.remove(&model_id);
+                self.search.write()?.remove(&model_id);
                 dirty.insert(model_id.clone());
                 total_changed.push(ExecuteReference::Model(model_id));
             }
@@ -347,6 +447,35 @@ impl Store {
         Ok(total_changed)
     }
 
+    /// Remove a single model from the store and all its indizes
+    pub async fn remove_model(&self, model_id: &OwnedEventId) -> Result<Vec<ExecuteReference>> {
+        trace!(?model_id, "removing model");
+        let mut total_changed = {
+            let mut dirty = self.dirty.lock()?; // hold the lock
+            let mut total_changed = Vec::new();
+            let Some((_, model)) = self.models.remove(model_id) else {
+                return Ok(total_changed);
+            };
+            let room_idx = IndexKey::RoomModels(model.room_id().to_owned());
+            for index in model.indizes(&self.user_id).into_iter().chain([room_idx]) {
+                let _ = self
+                    .indizes
+                    .entry(index.clone())
+                    .and_modify(|l| l.remove(model_id));
+                total_changed.push(ExecuteReference::Index(index));
+            }
+            self.search.write()?.remove(model_id);
+            dirty.insert(model_id.clone());
+            total_changed.push(ExecuteReference::Model(model_id.clone()));
+            total_changed
+        };
+        self.sync().await?;
+
+        total_changed.sort();
+        total_changed.dedup();
+        Ok(total_changed)
+    }
+
     async fn sync(&self) -> Result<()> {
         trace!("sync start");
         let (models_to_write, to_remove, all_models) = {
@@ -960,6 +1089,104 @@ mod tests {
         Ok(())
     }
 
+    async fn client_at_version(version: u32, models: &[(&str, &[u8])]) -> anyhow::Result<Client> {
+        let (_store, client) = fresh_store_and_client().await?;
+        let state_store = client.state_store();
+        state_store
+            .set_custom_value_no_read(DB_VERSION_KEY.as_bytes(), version.to_le_bytes().to_vec())
+            .await?;
+        let keys: Vec<&str> = models.iter().map(|(k, _)| *k).collect();
+        state_store
+            .set_custom_value_no_read(ALL_MODELS_KEY.as_bytes(), serde_json::to_vec(&keys)?)
+            .await?;
+        for (key, raw) in models {
+            state_store
+                .set_custom_value_no_read(format!("acter:{key}").as_bytes(), raw.to_vec())
+                .await?;
+        }
+        Ok(client)
+    }
+
+    fn stored_task(event_id: &str, parent: Option<&str>, percent: u8) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "Task": {
+                "inner": {
+                    "title": event_id,
+                    "m.relates_to": {
+                        "rel_type": "global.acter.dev.belongs_to",
+                        "event_id": "$list",
+                    },
+                    "parent_task": parent,
+                    "progress_percent": percent,
+                },
+                "meta": {
+                    "event_id": event_id,
+                    "sender": "@test:example.org",
+                    "origin_server_ts": 1672407531453_u64,
+                    "room_id": "!room:example.org",
+                },
+            }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn migrates_from_v1() -> anyhow::Result<()> {
+        let _ = env_logger::try_init();
+        let client = client_at_version(
+            1,
+            &[
+                ("$parent", &stored_task("$parent", None, 0)),
+                ("$child", &stored_task("$child", Some("$parent"), 40)),
+            ],
+        )
+        .await?;
+
+        let store =
+            Store::new_with_auth(client.clone(), user_id!("@test:example.org").to_owned()).await?;
+        let AnyActerModel::Task(parent) = store.get(&OwnedEventId::try_from("$parent")?).await?
+        else {
+            panic!("not a task");
+        };
+        assert_eq!(parent.subtasks_stats().total_count(), 1);
+        assert_eq!(parent.percent(), Some(40));
+
+        let ver = client
+            .state_store()
+            .get_custom_value(DB_VERSION_KEY.as_bytes())
+            .await?
+            .unwrap();
+        assert_eq!(ver, CURRENT_DB_VERSION.to_le_bytes().to_vec());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resets_when_migration_fails() -> anyhow::Result<()> {
+        let _ = env_logger::try_init();
+        let client = client_at_version(
+            1,
+            &[
+                ("$parent", &stored_task("$parent", None, 0)),
+                ("$broken", b"not json"),
+            ],
+        )
+        .await?;
+
+        let store =
+            Store::new_with_auth(client.clone(), user_id!("@test:example.org").to_owned()).await?;
+        assert!(store
+            .get(&OwnedEventId::try_from("$parent")?)
+            .await
+            .is_err());
+        let ver = client
+            .state_store()
+            .get_custom_value(DB_VERSION_KEY.as_bytes())
+            .await?
+            .unwrap();
+        assert_eq!(ver, CURRENT_DB_VERSION.to_le_bytes().to_vec());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn save_and_get_raw_simple() -> anyhow::Result<()> {
         let _ = env_logger::try_init();
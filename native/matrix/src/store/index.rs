@@ -284,8 +284,10 @@ impl StoreIndex {
             ),
             IndexKey::Section(SectionIndex::Boosts)
             | IndexKey::Section(SectionIndex::Stories)
+            | IndexKey::Section(SectionIndex::StoriesArchive)
             | IndexKey::RoomSection(_, SectionIndex::Boosts)
-            | IndexKey::RoomSection(_, SectionIndex::Stories) => StoreIndex::Ranked(
+            | IndexKey::RoomSection(_, SectionIndex::Stories)
+            | IndexKey::RoomSection(_, SectionIndex::StoriesArchive) => StoreIndex::Ranked(
                 RankedIndex::new_with(meta.origin_server_ts, meta.event_id.clone()),
             ),
             IndexKey::ObjectList(_, ObjectListIndex::Tasks) => {
@@ -0,0 +1,560 @@
+use futures::future::join_all;
+use matrix_sdk::{config::RequestConfig, Client};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+use tracing::{info, trace, warn};
+
+use super::{ALL_MODELS_KEY, CURRENT_DB_VERSION, DB_VERSION_KEY};
+use crate::{
+    events::AnySyncActerEvent,
+    models::{ActerModel, AnyActerModel},
+    Error, Result,
+};
+
+/// The stored `AnyActerModel` JSON by storage key
+pub(crate) type RawModels = BTreeMap<String, Value>;
+
+/// The events that created the stored objects with updates, as far as
+/// they could be loaded, by storage key
+pub(crate) type Creations = BTreeMap<String, AnyActerModel>;
+
+/// Transforms the stored models of one DB version into those of the next
+///
+/// Steps must be idempotent: if writing the result back fails midway, they
+/// run again on partially migrated data at the next start.
+pub(crate) struct Migration {
+    /// the DB version this step migrates from, it yields `from + 1`
+    pub from: u32,
+    pub description: &'static str,
+    pub apply: fn(&mut RawModels, &Creations) -> Result<()>,
+}
+
+/// All migration steps, in order. Add a step here whenever bumping
+/// `CURRENT_DB_VERSION`. Anything older than the first step is reset.
+pub(crate) static MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "roll up the progress of subtasks, count the updates of calendar events \
+                  and record the edit history of tasks, task lists, pins and calendar events",
+    apply: v1_to_v2,
+}];
+
+/// Whether there is a migration from that DB version, rather than having
+/// to derive everything from the room history again
+pub(crate) fn can_migrate(from: u32) -> bool {
+    MIGRATIONS.first().is_some_and(|m| m.from <= from)
+}
+
+/// Run the steps needed to go from `from` to `to` over the models
+pub(crate) fn run(models: &mut RawModels, creations: &Creations, from: u32, to: u32) -> Result<()> {
+    for version in from..to {
+        let Some(migration) = MIGRATIONS.iter().find(|m| m.from == version) else {
+            return Err(Error::Custom(format!(
+                "no migration from DB version {version}"
+            )));
+        };
+        info!(
+            version,
+            description = migration.description,
+            "migrating stored models"
+        );
+        (migration.apply)(models, creations)?;
+    }
+    Ok(())
+}
+
+/// The stored objects that have updates we record the history of
+fn updated_objects(models: &RawModels) -> Vec<AnyActerModel> {
+    let mut targets = HashSet::new();
+    for value in models.values() {
+        let Ok(model) = serde_json::from_value::<AnyActerModel>(value.clone()) else {
+            continue;
+        };
+        if is_tracked_update(&model) {
+            targets.extend(model.belongs_to().unwrap_or_default());
+        }
+    }
+    targets
+        .into_iter()
+        .filter_map(|target| {
+            let value = models.get(target.as_str())?;
+            serde_json::from_value(value.clone()).ok()
+        })
+        .collect()
+}
+
+/// Load the events that created the objects, from the cache or the server
+async fn load_creations(client: &Client, objects: Vec<AnyActerModel>) -> Creations {
+    let loads = objects.into_iter().map(|object| async move {
+        let meta = object.event_meta();
+        let room = client.get_room(&meta.room_id)?;
+        let event = match room
+            .event(&meta.event_id, Some(RequestConfig::short_retry()))
+            .await
+        {
+            Ok(event) => event,
+            Err(error) => {
+                warn!(event_id = ?meta.event_id, ?error, "loading object creation failed");
+                return None;
+            }
+        };
+        let event = event
+            .kind
+            .raw()
+            .deserialize_as::<AnySyncActerEvent>()
+            .ok()?
+            .into_full_any_acter_event(meta.room_id.clone());
+        let creation = AnyActerModel::try_from(event).ok()?;
+        Some((meta.event_id.to_string(), creation))
+    });
+    join_all(loads).await.into_iter().flatten().collect()
+}
+
+/// Migrate the models in the client store to the current version in place
+pub(crate) async fn migrate(client: &Client, from: u32) -> Result<()> {
+    let store = client.state_store();
+    let keys: Vec<String> = match store.get_custom_value(ALL_MODELS_KEY.as_bytes()).await? {
+        Some(v) if !v.is_empty() => serde_json::from_slice(&v)?,
+        _ => vec![],
+    };
+
+    let mut models = RawModels::new();
+    for key in keys {
+        let Some(raw) = store
+            .get_custom_value(format!("acter:{key}").as_bytes())
+            .await?
+        else {
+            warn!(key, "Stored model missing, skipping migration");
+            continue;
+        };
+        models.insert(key, serde_json::from_slice(&raw)?);
+    }
+    let original = models.clone();
+    let creations = load_creations(client, updated_objects(&models)).await;
+
+    run(&mut models, &creations, from, CURRENT_DB_VERSION)?;
+
+    for (key, value) in models.iter() {
+        if original.get(key) == Some(value) {
+            continue;
+        }
+        trace!(key, "writing migrated model");
+        store
+            .set_custom_value_no_read(
+                format!("acter:{key}").as_bytes(),
+                serde_json::to_vec(value)?,
+            )
+            .await?;
+    }
+    let removed: Vec<&String> = original
+        .keys()
+        .filter(|k| !models.contains_key(*k))
+        .collect();
+    if !removed.is_empty() {
+        let remaining: Vec<&String> = models.keys().collect();
+        store
+            .set_custom_value_no_read(ALL_MODELS_KEY.as_bytes(), serde_json::to_vec(&remaining)?)
+            .await?;
+        for key in removed {
+            store
+                .remove_custom_value(format!("acter:{key}").as_bytes())
+                .await?;
+        }
+    }
+
+    store
+        .set_custom_value_no_read(
+            DB_VERSION_KEY.as_bytes(),
+            CURRENT_DB_VERSION.to_le_bytes().to_vec(),
+        )
+        .await?;
+    info!(from, to = CURRENT_DB_VERSION, "stored models migrated");
+    Ok(())
+}
+
+// --- Steps
+
+fn task_percent(
+    id: &str,
+    models: &RawModels,
+    children: &BTreeMap<String, Vec<String>>,
+    visiting: &mut HashSet<String>,
+) -> Option<u8> {
+    if let Some(subtasks) = children.get(id) {
+        if visiting.insert(id.to_owned()) {
+            let sum: u32 = subtasks
+                .iter()
+                .map(|c| u32::from(task_percent(c, models, children, visiting).unwrap_or_default()))
+                .map(|p| p.min(100))
+                .sum();
+            visiting.remove(id);
+            return Some((sum / subtasks.len() as u32) as u8);
+        }
+    }
+    models
+        .get(id)?
+        .pointer("/Task/inner/progress_percent")?
+        .as_u64()
+        .map(|p| p.min(100) as u8)
+}
+
+fn v1_to_v2(models: &mut RawModels, creations: &Creations) -> Result<()> {
+    subtasks_stats(models)?;
+    calendar_update_count(models)?;
+    edit_history(models, creations)
+}
+
+/// Tasks got `subtasks`, holding the rolled up progress of each direct
+/// subtask. It is only updated when subtasks change, so compute it for what
+/// is stored already.
+fn subtasks_stats(models: &mut RawModels) -> Result<()> {
+    let mut children: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (key, value) in models.iter() {
+        if let Some(parent) = value
+            .pointer("/Task/inner/parent_task")
+            .and_then(Value::as_str)
+        {
+            children
+                .entry(parent.to_owned())
+                .or_default()
+                .push(key.clone());
+        }
+    }
+
+    let mut stats = Vec::new();
+    for (parent, subtasks) in children.iter() {
+        if !models.get(parent).is_some_and(|m| m.get("Task").is_some()) {
+            continue; // parent not known (yet)
+        }
+        let progress: BTreeMap<&String, u8> = subtasks
+            .iter()
+            .map(|c| {
+                let percent = task_percent(c, models, &children, &mut HashSet::new());
+                (c, percent.unwrap_or_default())
+            })
+            .collect();
+        stats.push((parent.clone(), json!({ "progress": progress })));
+    }
+
+    for (parent, subtasks) in stats {
+        if let Some(Value::Object(task)) = models.get_mut(&parent).and_then(|m| m.get_mut("Task")) {
+            task.insert("subtasks".to_owned(), subtasks);
+        }
+    }
+    Ok(())
+}
+
+/// Calendar events got `update_count`, used as iCalendar `SEQUENCE`. Count
+/// the updates stored for each event.
+fn calendar_update_count(models: &mut RawModels) -> Result<()> {
+    let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+    for value in models.values() {
+        if let Some(target) = value
+            .pointer("/CalendarEventUpdate/inner/m.relates_to/event_id")
+            .and_then(Value::as_str)
+        {
+            *counts.entry(target.to_owned()).or_default() += 1;
+        }
+    }
+
+    for (target, count) in counts {
+        let Some(Value::Object(event)) = models
+            .get_mut(&target)
+            .and_then(|m| m.get_mut("CalendarEvent"))
+        else {
+            continue;
+        };
+        let current = event
+            .get("update_count")
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        event.insert("update_count".to_owned(), json!(current.max(count)));
+    }
+    Ok(())
+}
+
+fn is_tracked_update(model: &AnyActerModel) -> bool {
+    matches!(
+        model,
+        AnyActerModel::TaskUpdate(_)
+            | AnyActerModel::TaskListUpdate(_)
+            | AnyActerModel::PinUpdate(_)
+            | AnyActerModel::CalendarEventUpdate(_)
+    )
+}
+
+/// Tasks, task lists, pins and calendar events got `history`, recorded while
+/// applying updates. Apply the stored updates to the event that created the
+/// object again to record it, in the order they were sent. Objects whose
+/// creation couldn’t be loaded start their history now.
+fn edit_history(models: &mut RawModels, creations: &Creations) -> Result<()> {
+    let mut updates: BTreeMap<String, Vec<AnyActerModel>> = BTreeMap::new();
+    for value in models.values() {
+        let Ok(model) = serde_json::from_value::<AnyActerModel>(value.clone()) else {
+            continue;
+        };
+        if !is_tracked_update(&model) {
+            continue;
+        }
+        for target in model.belongs_to().unwrap_or_default() {
+            updates
+                .entry(target.to_string())
+                .or_default()
+                .push(model.clone());
+        }
+    }
+
+    for (target, mut updates) in updates {
+        let Some(mut replayed) = creations.get(&target).cloned() else {
+            continue;
+        };
+        updates.sort_by(|a, b| {
+            (a.event_meta().origin_server_ts, a.event_id())
+                .cmp(&(b.event_meta().origin_server_ts, b.event_id()))
+        });
+        for update in updates.iter() {
+            replayed.transition(update)?;
+        }
+        let replayed = serde_json::to_value(&replayed)?;
+        let Some((kind, Value::Object(replayed))) =
+            replayed.as_object().and_then(|m| m.iter().next())
+        else {
+            continue;
+        };
+        let Some(Value::Object(stored)) = models.get_mut(&target).and_then(|m| m.get_mut(kind))
+        else {
+            continue; // not stored as the same kind of object
+        };
+        match replayed.get("history") {
+            Some(history) => stored.insert("history".to_owned(), history.clone()),
+            None => stored.remove("history"),
+        };
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::AnyActerEvent;
+    use matrix_sdk_base::ruma::owned_event_id;
+
+    fn meta(event_id: &str) -> Value {
+        json!({
+            "event_id": event_id,
+            "sender": "@test:example.org",
+            "origin_server_ts": 1672407531453_u64,
+            "room_id": "!room:example.org",
+        })
+    }
+
+    fn task(event_id: &str, parent: Option<&str>, percent: Option<u8>) -> (String, Value) {
+        let mut inner = json!({
+            "title": event_id,
+            "m.relates_to": {
+                "rel_type": "global.acter.dev.belongs_to",
+                "event_id": "$list",
+            },
+        });
+        if let Some(parent) = parent {
+            inner["parent_task"] = json!(parent);
+        }
+        if let Some(percent) = percent {
+            inner["progress_percent"] = json!(percent);
+        }
+        (
+            event_id.to_owned(),
+            json!({ "Task": { "inner": inner, "meta": meta(event_id) } }),
+        )
+    }
+
+    fn parse(models: &RawModels, key: &str) -> AnyActerModel {
+        serde_json::from_value(models[key].clone()).expect("migrated model parses")
+    }
+
+    #[test]
+    fn steps_are_complete() {
+        // every version up to the current one can be reached from the first
+        let first = MIGRATIONS
+            .first()
+            .map(|m| m.from)
+            .unwrap_or(CURRENT_DB_VERSION);
+        for (idx, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.from, first + idx as u32, "steps out of order");
+        }
+        assert_eq!(first + MIGRATIONS.len() as u32, CURRENT_DB_VERSION);
+        let none = Creations::new();
+        assert!(run(&mut RawModels::new(), &none, 0, CURRENT_DB_VERSION).is_err());
+        assert!(!can_migrate(0));
+        assert!(can_migrate(first));
+        assert!(run(&mut RawModels::new(), &none, first, CURRENT_DB_VERSION).is_ok());
+    }
+
+    #[test]
+    fn v1_subtasks_stats() -> Result<()> {
+        let mut models: RawModels = [
+            task("$parent", None, Some(10)),
+            task("$child_a", Some("$parent"), Some(100)),
+            task("$child_b", Some("$parent"), None),
+            task("$grandchild", Some("$child_b"), Some(50)),
+            task("$orphan", Some("$unknown"), Some(20)),
+        ]
+        .into_iter()
+        .collect();
+
+        subtasks_stats(&mut models)?;
+        let again = models.clone();
+        subtasks_stats(&mut models)?;
+        assert_eq!(models, again, "step is idempotent");
+
+        let AnyActerModel::Task(parent) = parse(&models, "$parent") else {
+            panic!("not a task");
+        };
+        assert_eq!(parent.subtasks_stats().total_count(), 2);
+        assert_eq!(parent.subtasks_stats().done_count(), 1);
+        // ($child_a: 100 + $child_b: rolled up 50) / 2
+        assert_eq!(parent.percent(), Some(75));
+
+        let AnyActerModel::Task(child) = parse(&models, "$child_b") else {
+            panic!("not a task");
+        };
+        assert_eq!(child.percent(), Some(50));
+        assert!(models["$orphan"]["Task"].get("subtasks").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn v1_subtasks_cycles_terminate() -> Result<()> {
+        let mut models: RawModels = [
+            task("$a", Some("$b"), Some(40)),
+            task("$b", Some("$a"), Some(60)),
+        ]
+        .into_iter()
+        .collect();
+        subtasks_stats(&mut models)?;
+        assert!(models["$a"]["Task"].get("subtasks").is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn v1_calendar_update_count() -> Result<()> {
+        let event = json!({
+            "CalendarEvent": {
+                "inner": {
+                    "title": "Gathering",
+                    "utc_start": "2024-05-01T10:00:00Z",
+                    "utc_end": "2024-05-01T12:00:00Z",
+                },
+                "meta": meta("$event"),
+            }
+        });
+        let update = |event_id: &str| {
+            (
+                event_id.to_owned(),
+                json!({
+                    "CalendarEventUpdate": {
+                        "inner": {
+                            "m.relates_to": {
+                                "rel_type": "global.acter.dev.update",
+                                "event_id": "$event",
+                            },
+                            "title": event_id,
+                        },
+                        "meta": meta(event_id),
+                    }
+                }),
+            )
+        };
+        let mut models: RawModels = [("$event".to_owned(), event), update("$u1"), update("$u2")]
+            .into_iter()
+            .collect();
+
+        calendar_update_count(&mut models)?;
+        calendar_update_count(&mut models)?;
+
+        let AnyActerModel::CalendarEvent(event) = parse(&models, "$event") else {
+            panic!("not a calendar event");
+        };
+        assert_eq!(event.sequence(), 2);
+        assert!(matches!(
+            parse(&models, "$u1"),
+            AnyActerModel::CalendarEventUpdate(_)
+        ));
+        Ok(())
+    }
+
+    fn pin_event(event_id: &str, ts: u64, content: Value) -> Value {
+        json!({
+            "event_id": event_id,
+            "sender": "@test:example.org",
+            "origin_server_ts": ts,
+            "room_id": "!room:example.org",
+            "content": content,
+        })
+    }
+
+    fn pin_update(event_id: &str, ts: u64, mut content: Value) -> (String, Value) {
+        content["m.relates_to"] = json!({
+            "rel_type": "global.acter.dev.update",
+            "event_id": "$pin",
+        });
+        let mut event = pin_event(event_id, ts, content);
+        event["type"] = json!("global.acter.dev.pin.update");
+        let event: AnyActerEvent = serde_json::from_value(event).unwrap();
+        let model = AnyActerModel::try_from(event).unwrap();
+        (event_id.to_owned(), serde_json::to_value(model).unwrap())
+    }
+
+    #[test]
+    fn v1_edit_history() -> Result<()> {
+        let mut created = pin_event("$pin", 1, json!({ "title": "Rules" }));
+        created["type"] = json!("global.acter.dev.pin");
+        let creation =
+            AnyActerModel::try_from(serde_json::from_value::<AnyActerEvent>(created).unwrap())
+                .unwrap();
+        let mut stored = serde_json::to_value(&creation)?;
+        // as stored by v1: the updates applied, but nothing recorded
+        stored["Pin"]["inner"]["title"] = json!("House rules");
+        stored["Pin"]["inner"]["url"] = json!("https://acter.global");
+
+        let mut models: RawModels = [
+            ("$pin".to_owned(), stored),
+            // sent last, but stored first
+            pin_update("$rename", 3, json!({ "title": "House rules" })),
+            pin_update(
+                "$link",
+                2,
+                json!({ "title": "Garden rules", "url": "https://acter.global" }),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let creations: Creations = [("$pin".to_owned(), creation)].into_iter().collect();
+
+        run(&mut models, &creations, 1, CURRENT_DB_VERSION)?;
+        let again = models.clone();
+        edit_history(&mut models, &creations)?;
+        assert_eq!(models, again, "step is idempotent");
+
+        let AnyActerModel::Pin(pin) = parse(&models, "$pin") else {
+            panic!("not a pin");
+        };
+        assert_eq!(pin.title(), "House rules");
+        assert_eq!(pin.revision(), owned_event_id!("$rename"));
+        let revisions = pin.revisions()?;
+        let ids: Vec<_> = revisions.iter().map(|r| r.event_id.to_string()).collect();
+        assert_eq!(ids, ["$pin", "$link", "$rename"]);
+        let title = &revisions[2].changes[0];
+        assert_eq!(title.field, "title");
+        assert_eq!(title.old, Some(json!("Garden rules")));
+
+        // without the creation there is nothing to replay on
+        let mut models = again.clone();
+        models.get_mut("$pin").unwrap()["Pin"]
+            .as_object_mut()
+            .unwrap()
+            .remove("history");
+        edit_history(&mut models, &Creations::new())?;
+        assert!(models["$pin"]["Pin"].get("history").is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,364 @@
+use matrix_sdk::ruma::{
+    MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedRoomId, OwnedTransactionId, TransactionId,
+};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::BTreeSet, future::Future};
+use tracing::{info, trace, warn};
+
+use super::{get_from_store, outbox::local_id_for, Store};
+use crate::{
+    events::news::{NewsEntryEventContent, NewsSlide},
+    referencing::ExecuteReference,
+    Error, Result,
+};
+
+/// Storage key of the local news drafts, also used to notify about changes
+pub static NEWS_DRAFTS_KEY: ExecuteReference =
+    ExecuteReference::ModelType(Cow::Borrowed("ACTER::NEWS_DRAFTS"));
+
+/// A news entry prepared locally, with its media already uploaded. Published
+/// by the client once `publish_at` has come, if it is scheduled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NewsDraft {
+    draft_id: String,
+    room_id: OwnedRoomId,
+    slides: Vec<NewsSlide>,
+    created_at: MilliSecondsSinceUnixEpoch,
+    updated_at: MilliSecondsSinceUnixEpoch,
+    #[serde(default)]
+    publish_at: Option<MilliSecondsSinceUnixEpoch>,
+    /// why publishing it failed the last time
+    #[serde(default)]
+    last_error: Option<String>,
+    /// the local id of the outbox entry it is being published as
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    publishing_as: Option<OwnedEventId>,
+}
+
+impl NewsDraft {
+    pub fn new(room_id: OwnedRoomId, slides: Vec<NewsSlide>) -> Self {
+        let now = MilliSecondsSinceUnixEpoch::now();
+        NewsDraft {
+            draft_id: format!("draft-{}", TransactionId::new()),
+            room_id,
+            slides,
+            created_at: now,
+            updated_at: now,
+            publish_at: None,
+            last_error: None,
+            publishing_as: None,
+        }
+    }
+
+    pub fn draft_id(&self) -> &str {
+        &self.draft_id
+    }
+
+    pub fn room_id(&self) -> &OwnedRoomId {
+        &self.room_id
+    }
+
+    pub fn slides(&self) -> &[NewsSlide] {
+        &self.slides
+    }
+
+    pub fn created_at(&self) -> MilliSecondsSinceUnixEpoch {
+        self.created_at
+    }
+
+    pub fn updated_at(&self) -> MilliSecondsSinceUnixEpoch {
+        self.updated_at
+    }
+
+    /// When it is going to be published, if it is scheduled
+    pub fn publish_at(&self) -> Option<MilliSecondsSinceUnixEpoch> {
+        self.publish_at
+    }
+
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    pub fn is_due(&self, now: MilliSecondsSinceUnixEpoch) -> bool {
+        self.last_error.is_none()
+            && self.publishing_as.is_none()
+            && self.publish_at.is_some_and(|at| at <= now)
+    }
+
+    pub fn content(&self) -> NewsEntryEventContent {
+        NewsEntryEventContent {
+            slides: self.slides.clone(),
+        }
+    }
+
+    pub fn slides_mut(&mut self) -> &mut Vec<NewsSlide> {
+        &mut self.slides
+    }
+
+    /// Schedule, or reschedule, publishing; `None` keeps it as draft
+    pub fn set_publish_at(&mut self, publish_at: Option<MilliSecondsSinceUnixEpoch>) {
+        self.publish_at = publish_at;
+        // worth another try
+        self.last_error = None;
+    }
+
+    pub(crate) fn fail(&mut self, error: String) {
+        self.last_error = Some(error);
+    }
+
+    fn touch(&mut self) {
+        self.updated_at = MilliSecondsSinceUnixEpoch::now();
+    }
+}
+
+/// The persisted local news drafts, in the order they were created
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct NewsDrafts {
+    pub(crate) drafts: Vec<NewsDraft>,
+}
+
+impl NewsDrafts {
+    /// The draft with that id, unless it is being published
+    fn get_mut(&mut self, draft_id: &str) -> Option<&mut NewsDraft> {
+        self.drafts
+            .iter_mut()
+            .find(|d| d.draft_id == draft_id && d.publishing_as.is_none())
+    }
+
+    fn publishing_as_mut(&mut self, local_id: &OwnedEventId) -> Option<&mut NewsDraft> {
+        self.drafts
+            .iter_mut()
+            .find(|d| d.publishing_as.as_ref() == Some(local_id))
+    }
+
+    /// Settle the drafts we went down publishing: drop those whose outbox
+    /// entry made it and fail the others, they might have been posted or
+    /// not. Returns whether anything changed.
+    fn reconcile(&mut self, queued: &BTreeSet<OwnedEventId>) -> bool {
+        let before = self.drafts.len();
+        self.drafts.retain(|d| {
+            d.publishing_as
+                .as_ref()
+                .map_or(true, |local_id| !queued.contains(local_id))
+        });
+        let mut changed = self.drafts.len() != before;
+        for draft in self.drafts.iter_mut() {
+            if let Some(local_id) = draft.publishing_as.take() {
+                warn!(
+                    draft_id = draft.draft_id,
+                    ?local_id,
+                    "publishing news draft was interrupted"
+                );
+                draft.fail("Publishing was interrupted, it might have been posted".to_owned());
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+pub(crate) async fn load_news_drafts(client: matrix_sdk::Client) -> NewsDrafts {
+    match get_from_store::<NewsDrafts>(client, &NEWS_DRAFTS_KEY.as_storage_key()).await {
+        Ok(drafts) => drafts,
+        Err(Error::ModelNotFound(_)) => Default::default(),
+        Err(error) => {
+            warn!(
+                ?error,
+                "Couldn’t read news drafts at startup. Dropping them."
+            );
+            Default::default()
+        }
+    }
+}
+
+impl Store {
+    /// The local news drafts, scheduled or not
+    pub fn news_drafts(&self) -> Result<Vec<NewsDraft>> {
+        Ok(self
+            .news_drafts
+            .lock()?
+            .drafts
+            .iter()
+            .filter(|d| d.publishing_as.is_none())
+            .cloned()
+            .collect())
+    }
+
+    pub fn news_draft(&self, draft_id: &str) -> Result<NewsDraft> {
+        self.news_drafts
+            .lock()?
+            .get_mut(draft_id)
+            .cloned()
+            .ok_or_else(|| Error::ModelNotFound(draft_id.to_owned()))
+    }
+
+    /// Add a new draft, there mustn't be one with the same id yet
+    pub async fn add_news_draft(&self, mut draft: NewsDraft) -> Result<Vec<ExecuteReference>> {
+        draft.touch();
+        {
+            let mut drafts = self.news_drafts.lock()?;
+            if drafts.drafts.iter().any(|d| d.draft_id == draft.draft_id) {
+                return Err(Error::Custom(format!(
+                    "News draft {} exists already",
+                    draft.draft_id
+                )));
+            }
+            drafts.drafts.push(draft);
+        }
+        self.persist_news_drafts().await?;
+        Ok(vec![NEWS_DRAFTS_KEY.clone()])
+    }
+
+    /// Change the stored draft with `f`, failing if it is gone, e.g. as it
+    /// has been published or discarded meanwhile. Nothing changes if `f` fails.
+    pub async fn update_news_draft<E: From<Error>>(
+        &self,
+        draft_id: &str,
+        f: impl FnOnce(&mut NewsDraft) -> std::result::Result<(), E>,
+    ) -> std::result::Result<Vec<ExecuteReference>, E> {
+        {
+            let mut drafts = self.news_drafts.lock().map_err(Error::from)?;
+            let Some(draft) = drafts.get_mut(draft_id) else {
+                return Err(Error::ModelNotFound(draft_id.to_owned()).into());
+            };
+            let mut updated = draft.clone();
+            f(&mut updated)?;
+            updated.touch();
+            *draft = updated;
+        }
+        self.persist_news_drafts().await?;
+        Ok(vec![NEWS_DRAFTS_KEY.clone()])
+    }
+
+    /// Mark the draft as being published while `publish` queues it with the
+    /// given transaction id, so it can't be published or changed twice, and
+    /// drop it once that is done or unmark it if that fails. The mark is
+    /// persisted before, so [`Self::reconcile_news_drafts`] can find out what
+    /// happened if we went down in between.
+    pub async fn take_news_draft<R, Fut>(
+        &self,
+        draft_id: &str,
+        publish: impl FnOnce(NewsDraft, OwnedTransactionId) -> Fut,
+    ) -> Result<(R, Vec<ExecuteReference>)>
+    where
+        Fut: Future<Output = Result<R>>,
+    {
+        let txn_id = TransactionId::new();
+        let local_id = local_id_for(&txn_id)?;
+        let draft = {
+            let mut drafts = self.news_drafts.lock()?;
+            let Some(draft) = drafts.get_mut(draft_id) else {
+                return Err(Error::ModelNotFound(draft_id.to_owned()));
+            };
+            draft.publishing_as = Some(local_id.clone());
+            draft.clone()
+        };
+        if let Err(error) = self.persist_news_drafts().await {
+            if let Some(draft) = self.news_drafts.lock()?.publishing_as_mut(&local_id) {
+                draft.publishing_as = None;
+            }
+            return Err(error);
+        }
+
+        let published = publish(draft, txn_id).await;
+        {
+            let mut drafts = self.news_drafts.lock()?;
+            if published.is_ok() {
+                drafts
+                    .drafts
+                    .retain(|d| d.publishing_as.as_ref() != Some(&local_id));
+            } else if let Some(draft) = drafts.publishing_as_mut(&local_id) {
+                draft.publishing_as = None;
+            }
+        }
+        let persisted = self.persist_news_drafts().await;
+        let published = published?;
+        persisted?;
+        Ok((published, vec![NEWS_DRAFTS_KEY.clone()]))
+    }
+
+    /// Settle the drafts we went down publishing, once the outbox is loaded
+    pub(crate) async fn reconcile_news_drafts(&self) -> Result<()> {
+        let queued = self.with_outbox(|outbox| {
+            outbox
+                .entries
+                .iter()
+                .map(|e| e.local_id().to_owned())
+                .chain(outbox.resolved.keys().cloned())
+                .collect::<BTreeSet<_>>()
+        })?;
+        if !self.news_drafts.lock()?.reconcile(&queued) {
+            return Ok(());
+        }
+        info!("news drafts reconciled with the outbox");
+        self.persist_news_drafts().await
+    }
+
+    pub async fn remove_news_draft(&self, draft_id: &str) -> Result<Vec<ExecuteReference>> {
+        {
+            let mut drafts = self.news_drafts.lock()?;
+            let Some(pos) = drafts
+                .drafts
+                .iter()
+                .position(|d| d.draft_id == draft_id && d.publishing_as.is_none())
+            else {
+                return Err(Error::ModelNotFound(draft_id.to_owned()));
+            };
+            drafts.drafts.remove(pos);
+        }
+        self.persist_news_drafts().await?;
+        Ok(vec![NEWS_DRAFTS_KEY.clone()])
+    }
+
+    async fn persist_news_drafts(&self) -> Result<()> {
+        let drafts = self.news_drafts.lock()?.clone();
+        trace!(drafts = drafts.drafts.len(), "persisting news drafts");
+        self.set_raw(&NEWS_DRAFTS_KEY.as_storage_key(), &drafts)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix_sdk::ruma::{owned_room_id, UInt};
+
+    fn at(millis: u32) -> MilliSecondsSinceUnixEpoch {
+        MilliSecondsSinceUnixEpoch(UInt::from(millis))
+    }
+
+    #[test]
+    fn only_scheduled_without_error_is_due() {
+        let mut draft = NewsDraft::new(owned_room_id!("!room:example.org"), vec![]);
+        assert!(!draft.is_due(at(2000)), "drafts aren’t published");
+
+        draft.set_publish_at(Some(at(1000)));
+        assert!(!draft.is_due(at(999)));
+        assert!(draft.is_due(at(1000)));
+
+        draft.fail("no permission".to_owned());
+        assert!(
+            !draft.is_due(at(2000)),
+            "failed ones wait to be rescheduled"
+        );
+        draft.set_publish_at(Some(at(3000)));
+        assert_eq!(draft.last_error(), None);
+        assert!(draft.is_due(at(3000)));
+    }
+
+    #[test]
+    fn ids_are_unique_and_roundtrip() {
+        let room_id = owned_room_id!("!room:example.org");
+        let first = NewsDraft::new(room_id.clone(), vec![]);
+        let second = NewsDraft::new(room_id, vec![]);
+        assert_ne!(first.draft_id(), second.draft_id());
+
+        let drafts = NewsDrafts {
+            drafts: vec![first, second],
+        };
+        let restored: NewsDrafts =
+            serde_json::from_value(serde_json::to_value(&drafts).unwrap()).unwrap();
+        assert_eq!(restored.drafts.len(), 2);
+        assert_eq!(restored.drafts[0].draft_id(), drafts.drafts[0].draft_id());
+    }
+}
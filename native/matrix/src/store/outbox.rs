@@ -0,0 +1,396 @@
+use matrix_sdk::ruma::{
+    EventId, MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedRoomId, OwnedTransactionId,
+    TransactionId,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, SystemTime},
+};
+use strum::Display as StrumDisplay;
+use tracing::{trace, warn};
+
+use super::Store;
+use crate::{models::AnyActerModel, referencing::ExecuteReference, Result};
+
+/// Storage key of the outbox, also used to notify about changes to it
+pub static OUTBOX_KEY: ExecuteReference =
+    ExecuteReference::ModelType(Cow::Borrowed("ACTER::OUTBOX"));
+
+/// Event ids of pending models start with this
+pub static LOCAL_ID_PREFIX: &str = "$acter-outbox-";
+
+/// The longest wait between two attempts. Entries failing temporarily keep
+/// being retried at this pace until sent, failed for good or discarded.
+static MAX_RETRY_DELAY: Duration = Duration::from_secs(10 * 60);
+
+/// How long a sent entry waits for its event to come back via sync. After
+/// that we assume we missed it, the room history brings it back eventually.
+static MAX_UNRECONCILED_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub fn is_local_id(event_id: &EventId) -> bool {
+    event_id.as_str().starts_with(LOCAL_ID_PREFIX)
+}
+
+/// The id the pending model of the entry queued with `txn_id` is kept under
+pub(crate) fn local_id_for(txn_id: &TransactionId) -> Result<OwnedEventId> {
+    Ok(OwnedEventId::try_from(format!(
+        "{LOCAL_ID_PREFIX}{txn_id}"
+    ))?)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, StrumDisplay)]
+#[serde(tag = "state", rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum OutboxState {
+    /// Waiting to be sent
+    Queued,
+    /// The server accepted it, waiting for the event to come back via sync
+    Sent { event_id: OwnedEventId },
+    /// The server rejected it, won’t be retried unless asked to
+    Failed { error: String },
+    /// The target changed or was removed since this was queued, won’t be sent
+    /// unless asked to
+    Conflict { reason: String },
+}
+
+/// A queued creation or update, applied locally as pending model
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    local_id: OwnedEventId,
+    txn_id: OwnedTransactionId,
+    room_id: OwnedRoomId,
+    event_type: String,
+    content: serde_json::Value,
+    /// the model this update targets, if it is one
+    target: Option<OwnedEventId>,
+    /// the models the pending model was applied to
+    touches: Vec<OwnedEventId>,
+    queued_at: MilliSecondsSinceUnixEpoch,
+    attempts: u32,
+    retry_at: Option<MilliSecondsSinceUnixEpoch>,
+    last_error: Option<String>,
+    state: OutboxState,
+    /// when the server accepted it
+    #[serde(default)]
+    sent_at: Option<MilliSecondsSinceUnixEpoch>,
+}
+
+impl OutboxEntry {
+    pub(crate) fn new(
+        local_id: OwnedEventId,
+        txn_id: OwnedTransactionId,
+        room_id: OwnedRoomId,
+        event_type: String,
+        content: serde_json::Value,
+        target: Option<OwnedEventId>,
+        touches: Vec<OwnedEventId>,
+    ) -> Self {
+        OutboxEntry {
+            local_id,
+            txn_id,
+            room_id,
+            event_type,
+            content,
+            target,
+            touches,
+            queued_at: MilliSecondsSinceUnixEpoch::now(),
+            attempts: 0,
+            retry_at: None,
+            last_error: None,
+            state: OutboxState::Queued,
+            sent_at: None,
+        }
+    }
+
+    /// The event id of the pending model
+    pub fn local_id(&self) -> &EventId {
+        &self.local_id
+    }
+
+    pub fn txn_id(&self) -> &OwnedTransactionId {
+        &self.txn_id
+    }
+
+    pub fn room_id(&self) -> &OwnedRoomId {
+        &self.room_id
+    }
+
+    pub fn event_type(&self) -> &str {
+        &self.event_type
+    }
+
+    pub fn content(&self) -> &serde_json::Value {
+        &self.content
+    }
+
+    pub fn target(&self) -> Option<&EventId> {
+        self.target.as_deref()
+    }
+
+    pub fn touches(&self) -> &[OwnedEventId] {
+        &self.touches
+    }
+
+    pub fn queued_at(&self) -> MilliSecondsSinceUnixEpoch {
+        self.queued_at
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn retry_at(&self) -> Option<MilliSecondsSinceUnixEpoch> {
+        self.retry_at
+    }
+
+    /// The error of the last failed attempt
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    pub fn state(&self) -> &OutboxState {
+        &self.state
+    }
+
+    /// The event id the server gave this, once sent
+    pub fn event_id(&self) -> Option<&EventId> {
+        match &self.state {
+            OutboxState::Sent { event_id } => Some(event_id),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_due(&self, now: MilliSecondsSinceUnixEpoch) -> bool {
+        matches!(self.state, OutboxState::Queued) && self.retry_at.map(|t| t <= now).unwrap_or(true)
+    }
+
+    pub(crate) fn set_state(&mut self, state: OutboxState) {
+        if matches!(state, OutboxState::Sent { .. }) {
+            self.sent_at = Some(MilliSecondsSinceUnixEpoch::now());
+        }
+        self.state = state;
+    }
+
+    /// Whether it was sent but didn't come back via sync for too long
+    pub(crate) fn is_unreconciled(&self, now: MilliSecondsSinceUnixEpoch) -> bool {
+        if self.event_id().is_none() {
+            return false;
+        }
+        // entries sent before we kept track of it
+        let sent_at = self.sent_at.unwrap_or(self.queued_at);
+        let age = u64::from(now.get()).saturating_sub(u64::from(sent_at.get()));
+        age >= MAX_UNRECONCILED_AGE.as_millis() as u64
+    }
+
+    /// reset for another round of attempts
+    pub(crate) fn requeue(&mut self) {
+        self.state = OutboxState::Queued;
+        self.retry_at = None;
+    }
+
+    /// record a failed attempt and schedule the next one
+    pub(crate) fn backoff(&mut self, error: String) {
+        self.attempts = self.attempts.saturating_add(1);
+        self.last_error = Some(error);
+        let delay = Duration::from_secs(2u64.saturating_pow(self.attempts)).min(MAX_RETRY_DELAY);
+        self.retry_at = MilliSecondsSinceUnixEpoch::from_system_time(SystemTime::now() + delay);
+    }
+
+    pub(crate) fn fail(&mut self, error: String) {
+        self.attempts = self.attempts.saturating_add(1);
+        self.last_error = Some(error.clone());
+        self.state = OutboxState::Failed { error };
+    }
+}
+
+/// The persisted state of the outbox
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Outbox {
+    /// queued changes, in order
+    pub(crate) entries: Vec<OutboxEntry>,
+    /// the models as they were before any pending model was applied to them
+    pub(crate) bases: BTreeMap<OwnedEventId, AnyActerModel>,
+    /// local ids of entries already sent, with the event id they got
+    pub(crate) resolved: BTreeMap<OwnedEventId, OwnedEventId>,
+}
+
+impl Outbox {
+    pub(crate) fn entry_mut(&mut self, local_id: &EventId) -> Option<&mut OutboxEntry> {
+        self.entries.iter_mut().find(|e| e.local_id == local_id)
+    }
+
+    pub(crate) fn take_entry(&mut self, local_id: &EventId) -> Option<OutboxEntry> {
+        let pos = self.entries.iter().position(|e| e.local_id == local_id)?;
+        Some(self.entries.remove(pos))
+    }
+
+    /// The entry that was sent and got the given event id
+    pub(crate) fn take_sent(&mut self, event_id: &EventId) -> Option<OutboxEntry> {
+        let pos = self
+            .entries
+            .iter()
+            .position(|e| e.event_id() == Some(event_id))?;
+        Some(self.entries.remove(pos))
+    }
+
+    /// Replace the local ids of already sent entries with their real event id.
+    ///
+    /// Returns `None` if the content still refers to a model that hasn’t been
+    /// sent yet.
+    pub(crate) fn resolve_local_ids(
+        &self,
+        content: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        let mut raw = content.to_string();
+        for (local_id, event_id) in self.resolved.iter() {
+            raw = raw.replace(local_id.as_str(), event_id.as_str());
+        }
+        if raw.contains(LOCAL_ID_PREFIX) {
+            return None;
+        }
+        serde_json::from_str(&raw).ok()
+    }
+
+    /// drop bookkeeping no remaining entry needs anymore
+    pub(crate) fn prune(&mut self) {
+        let touched: BTreeSet<&OwnedEventId> =
+            self.entries.iter().flat_map(|e| e.touches.iter()).collect();
+        self.bases.retain(|k, _| touched.contains(k));
+        if self.entries.is_empty() {
+            self.resolved.clear();
+        }
+    }
+}
+
+impl Store {
+    /// Remove pending models without an outbox entry, e.g. as the outbox
+    /// couldn't be read at startup
+    pub(crate) async fn remove_orphaned_pending_models(&self) -> Result<()> {
+        let queued: BTreeSet<OwnedEventId> =
+            self.with_outbox(|o| o.entries.iter().map(|e| e.local_id.clone()).collect())?;
+        let mut orphaned = vec![];
+        self.models.scan(|event_id, _| {
+            if is_local_id(event_id) && !queued.contains(event_id) {
+                orphaned.push(event_id.clone());
+            }
+        });
+        for event_id in orphaned {
+            warn!(?event_id, "removing pending model without queued change");
+            self.remove_model(&event_id).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn with_outbox<R>(&self, f: impl FnOnce(&mut Outbox) -> R) -> Result<R> {
+        let mut outbox = self.outbox.lock()?;
+        Ok(f(&mut outbox))
+    }
+
+    pub(crate) async fn persist_outbox(&self) -> Result<()> {
+        let outbox = self.outbox.lock()?.clone();
+        trace!(entries = outbox.entries.len(), "persisting outbox");
+        self.set_raw(&OUTBOX_KEY.as_storage_key(), &outbox).await
+    }
+
+    /// The changes waiting to be sent or reconciled, in order
+    pub fn outbox_entries(&self) -> Result<Vec<OutboxEntry>> {
+        self.with_outbox(|o| o.entries.clone())
+    }
+
+    /// Whether the model at this key is a local model not yet confirmed by the server
+    pub fn is_pending(&self, event_id: &EventId) -> bool {
+        is_local_id(event_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix_sdk::ruma::{owned_event_id, owned_room_id, TransactionId};
+    use serde_json::json;
+
+    fn entry(local_id: OwnedEventId, content: serde_json::Value) -> OutboxEntry {
+        OutboxEntry::new(
+            local_id,
+            TransactionId::new(),
+            owned_room_id!("!room:example.org"),
+            "global.acter.dev.task".to_owned(),
+            content,
+            None,
+            vec![],
+        )
+    }
+
+    #[test]
+    fn resolves_local_ids() {
+        let list_id = OwnedEventId::try_from(format!("{LOCAL_ID_PREFIX}list")).unwrap();
+        let task_id = OwnedEventId::try_from(format!("{LOCAL_ID_PREFIX}task")).unwrap();
+        let content = json!({
+            "title": "task",
+            "m.relates_to": { "rel_type": "global.acter.dev.belongs_to", "event_id": list_id },
+        });
+        let mut outbox = Outbox {
+            entries: vec![entry(task_id, content.clone())],
+            ..Default::default()
+        };
+        // the list hasn’t been sent yet
+        assert!(outbox.resolve_local_ids(&content).is_none());
+
+        outbox
+            .resolved
+            .insert(list_id, owned_event_id!("$real-list"));
+        let resolved = outbox.resolve_local_ids(&content).unwrap();
+        assert_eq!(resolved["m.relates_to"]["event_id"], "$real-list");
+        assert_eq!(resolved["title"], "task");
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let local_id = OwnedEventId::try_from(format!("{LOCAL_ID_PREFIX}a")).unwrap();
+        let mut entry = entry(local_id.clone(), json!({}));
+        assert!(is_local_id(&local_id));
+        assert!(entry.is_due(MilliSecondsSinceUnixEpoch::now()));
+
+        entry.backoff("offline".to_owned());
+        assert_eq!(entry.attempts(), 1);
+        assert_eq!(entry.last_error(), Some("offline"));
+        assert!(!entry.is_due(MilliSecondsSinceUnixEpoch::now()));
+        let first = entry.retry_at().unwrap();
+
+        entry.backoff("still offline".to_owned());
+        assert!(entry.retry_at().unwrap() > first);
+
+        for _ in 0..20 {
+            entry.backoff("offline".to_owned());
+        }
+        let max = MilliSecondsSinceUnixEpoch::from_system_time(
+            SystemTime::now() + MAX_RETRY_DELAY + Duration::from_secs(1),
+        )
+        .unwrap();
+        assert!(entry.retry_at().unwrap() <= max);
+
+        entry.fail("forbidden".to_owned());
+        assert!(!entry.is_due(MilliSecondsSinceUnixEpoch::now()));
+        entry.requeue();
+        assert!(entry.is_due(MilliSecondsSinceUnixEpoch::now()));
+    }
+
+    #[test]
+    fn sent_entries_wait_for_sync_only_so_long() {
+        let local_id = OwnedEventId::try_from(format!("{LOCAL_ID_PREFIX}a")).unwrap();
+        let mut entry = entry(local_id, json!({}));
+        let later = MilliSecondsSinceUnixEpoch::from_system_time(
+            SystemTime::now() + MAX_UNRECONCILED_AGE + Duration::from_secs(1),
+        )
+        .unwrap();
+        assert!(!entry.is_unreconciled(later), "never sent");
+
+        entry.set_state(OutboxState::Sent {
+            event_id: owned_event_id!("$sent"),
+        });
+        assert!(!entry.is_unreconciled(MilliSecondsSinceUnixEpoch::now()));
+        assert!(entry.is_unreconciled(later));
+    }
+}
@@ -0,0 +1,643 @@
+use derive_builder::Builder;
+use matrix_sdk::ruma::{
+    EventId, MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedRoomId, OwnedUserId,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use strum::{Display as StrumDisplay, EnumString};
+
+use crate::{
+    events::{attachments::AttachmentContent, calendar::EventLocation},
+    models::{ActerModel, AnyActerModel},
+    referencing::SectionIndex,
+};
+
+/// How much a match in the given field counts
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, StrumDisplay, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum SearchField {
+    Title,
+    Name,
+    Body,
+}
+
+impl SearchField {
+    fn weight(&self) -> f32 {
+        match self {
+            SearchField::Title => 3.0,
+            SearchField::Name => 2.0,
+            SearchField::Body => 1.0,
+        }
+    }
+}
+
+/// Lowercased words of the text, split at anything not alphanumeric
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// The searchable text of a model and what it can be filtered by
+#[derive(Clone, Debug)]
+pub struct SearchDocument {
+    event_id: OwnedEventId,
+    room_id: OwnedRoomId,
+    sender: OwnedUserId,
+    origin_server_ts: MilliSecondsSinceUnixEpoch,
+    model_type: String,
+    section: Option<SectionIndex>,
+    /// the object a comment or attachment is on
+    parent: Option<OwnedEventId>,
+    fields: Vec<(SearchField, String)>,
+}
+
+fn section_of(model: &AnyActerModel) -> Option<SectionIndex> {
+    match model {
+        AnyActerModel::TaskList(_) | AnyActerModel::Task(_) => Some(SectionIndex::Tasks),
+        AnyActerModel::Pin(_) => Some(SectionIndex::Pins),
+        AnyActerModel::CalendarEvent(_) => Some(SectionIndex::Calendar),
+        AnyActerModel::NewsEntry(_) => Some(SectionIndex::Boosts),
+        AnyActerModel::Story(_) => Some(SectionIndex::Stories),
+        _ => None,
+    }
+}
+
+fn push_text(fields: &mut Vec<(SearchField, String)>, field: SearchField, text: &str) {
+    if !text.trim().is_empty() {
+        fields.push((field, text.to_owned()));
+    }
+}
+
+impl SearchDocument {
+    /// The document for the model, if it has anything to search for.
+    ///
+    /// `parent_of` is used to find the object comments and attachments are on.
+    pub fn for_model(
+        model: &AnyActerModel,
+        parent_of: impl Fn(&EventId) -> Option<AnyActerModel>,
+    ) -> Option<Self> {
+        let mut fields = vec![];
+        let mut parent = None;
+        match model {
+            AnyActerModel::TaskList(list) => {
+                push_text(&mut fields, SearchField::Title, &list.name);
+                if let Some(desc) = &list.description {
+                    push_text(&mut fields, SearchField::Body, &desc.body);
+                }
+                for keyword in list.keywords.iter().chain(list.categories.iter()) {
+                    push_text(&mut fields, SearchField::Name, keyword);
+                }
+            }
+            AnyActerModel::Task(task) => {
+                push_text(&mut fields, SearchField::Title, &task.title);
+                if let Some(desc) = &task.description {
+                    push_text(&mut fields, SearchField::Body, &desc.body);
+                }
+                for keyword in task.keywords.iter().chain(task.categories.iter()) {
+                    push_text(&mut fields, SearchField::Name, keyword);
+                }
+            }
+            AnyActerModel::Pin(pin) => {
+                push_text(&mut fields, SearchField::Title, &pin.title);
+                if let Some(content) = &pin.content {
+                    push_text(&mut fields, SearchField::Body, &content.body);
+                }
+                if let Some(url) = &pin.url {
+                    push_text(&mut fields, SearchField::Name, url);
+                }
+            }
+            AnyActerModel::CalendarEvent(event) => {
+                push_text(&mut fields, SearchField::Title, &event.title);
+                if let Some(desc) = &event.description {
+                    push_text(&mut fields, SearchField::Body, &desc.body);
+                }
+                for location in event.locations.iter() {
+                    if let EventLocation::Physical {
+                        name: Some(name), ..
+                    }
+                    | EventLocation::Virtual {
+                        name: Some(name), ..
+                    } = location
+                    {
+                        push_text(&mut fields, SearchField::Name, name);
+                    }
+                }
+                for category in event.categories.iter() {
+                    push_text(&mut fields, SearchField::Name, category);
+                }
+            }
+            AnyActerModel::NewsEntry(news) => {
+                for slide in news.slides.iter() {
+                    push_text(&mut fields, SearchField::Body, &slide.content.text_str());
+                }
+            }
+            AnyActerModel::Story(story) => {
+                for slide in story.slides.iter() {
+                    push_text(&mut fields, SearchField::Body, &slide.content.text_str());
+                }
+            }
+            AnyActerModel::Comment(comment) => {
+                push_text(&mut fields, SearchField::Body, &comment.content.body);
+                parent = Some(comment.on.event_id.clone());
+            }
+            AnyActerModel::Attachment(attachment) => {
+                if let Some(name) = attachment.content.name() {
+                    push_text(&mut fields, SearchField::Name, &name);
+                }
+                if let AttachmentContent::Link(link) = &attachment.content {
+                    push_text(&mut fields, SearchField::Name, &link.link);
+                }
+                parent = Some(attachment.on.event_id.clone());
+            }
+            _ => return None,
+        }
+        if fields.is_empty() {
+            return None;
+        }
+        let section = match &parent {
+            Some(parent_id) => parent_of(parent_id).as_ref().and_then(section_of),
+            None => section_of(model),
+        };
+        let meta = model.event_meta();
+        Some(SearchDocument {
+            event_id: meta.event_id.clone(),
+            room_id: meta.room_id.clone(),
+            sender: meta.sender.clone(),
+            origin_server_ts: meta.origin_server_ts,
+            model_type: model.model_type().to_owned(),
+            section,
+            parent,
+            fields,
+        })
+    }
+
+    fn matches(&self, query: &SearchQuery) -> bool {
+        if !query.sections.is_empty()
+            && !self
+                .section
+                .as_ref()
+                .map(|s| query.sections.contains(s))
+                .unwrap_or_default()
+        {
+            return false;
+        }
+        if !query.room_ids.is_empty() && !query.room_ids.contains(&self.room_id) {
+            return false;
+        }
+        if !query.senders.is_empty() && !query.senders.contains(&self.sender) {
+            return false;
+        }
+        if !query.model_types.is_empty() && !query.model_types.contains(&self.model_type) {
+            return false;
+        }
+        if query
+            .since
+            .map(|s| self.origin_server_ts < s)
+            .unwrap_or_default()
+        {
+            return false;
+        }
+        if query
+            .until
+            .map(|u| self.origin_server_ts > u)
+            .unwrap_or_default()
+        {
+            return false;
+        }
+        true
+    }
+
+    /// the text to show for a hit, preferring the field that matched best
+    fn snippet(&self, terms: &[String]) -> String {
+        let best = self
+            .fields
+            .iter()
+            .max_by_key(|(field, text)| {
+                let hits = tokenize(text)
+                    .filter(|t| terms.iter().any(|q| t.starts_with(q.as_str())))
+                    .count();
+                (hits, field.weight() as u32)
+            })
+            .map(|(_, text)| text.as_str())
+            .unwrap_or_default();
+        let mut snippet: String = best.chars().take(SNIPPET_LENGTH).collect();
+        if snippet.len() < best.len() {
+            snippet.push('…');
+        }
+        snippet
+    }
+}
+
+const SNIPPET_LENGTH: usize = 140;
+
+/// What to search for and how to narrow it down
+#[derive(Clone, Debug, Default, Builder)]
+#[builder(name = "SearchQueryBuilder", derive(Debug), default)]
+pub struct SearchQuery {
+    /// free text, all words must match, the last one also as prefix
+    #[builder(setter(into))]
+    pub text: String,
+    /// only in these sections, comments and attachments count for the
+    /// section of the object they are on
+    pub sections: Vec<SectionIndex>,
+    pub room_ids: Vec<OwnedRoomId>,
+    pub senders: Vec<OwnedUserId>,
+    pub model_types: Vec<String>,
+    pub since: Option<MilliSecondsSinceUnixEpoch>,
+    pub until: Option<MilliSecondsSinceUnixEpoch>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchHit {
+    pub event_id: OwnedEventId,
+    pub room_id: OwnedRoomId,
+    pub sender: OwnedUserId,
+    pub origin_server_ts: MilliSecondsSinceUnixEpoch,
+    pub model_type: String,
+    pub section: Option<SectionIndex>,
+    pub parent: Option<OwnedEventId>,
+    pub score: f32,
+    pub snippet: String,
+}
+
+/// An inverted index over the searchable text of all models
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    documents: HashMap<OwnedEventId, SearchDocument>,
+    /// term to documents containing it, with their weighted term frequency
+    postings: BTreeMap<String, HashMap<OwnedEventId, f32>>,
+    /// object to the comments and attachments on it
+    children: HashMap<OwnedEventId, HashSet<OwnedEventId>>,
+}
+
+impl SearchIndex {
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Add the document, replacing the previous version
+    pub fn upsert(&mut self, document: SearchDocument) {
+        let event_id = document.event_id.clone();
+        self.remove(&event_id);
+        let mut frequencies: HashMap<String, f32> = HashMap::new();
+        for (field, text) in document.fields.iter() {
+            for term in tokenize(text) {
+                *frequencies.entry(term).or_default() += field.weight();
+            }
+        }
+        for (term, frequency) in frequencies {
+            self.postings
+                .entry(term)
+                .or_default()
+                .insert(event_id.clone(), frequency);
+        }
+        if let Some(parent) = document.parent.clone() {
+            self.children
+                .entry(parent)
+                .or_default()
+                .insert(event_id.clone());
+        }
+        self.documents.insert(event_id, document);
+    }
+
+    /// Index the new version of the model or drop it if nothing is searchable
+    /// anymore, e.g. after it was redacted
+    pub fn update(&mut self, event_id: &EventId, document: Option<SearchDocument>) {
+        let Some(document) = document else {
+            self.remove(event_id);
+            return;
+        };
+        if let Some(section) = document.section.clone() {
+            // comments and attachments we have seen before the object they are on
+            for child_id in self.children.get(event_id).into_iter().flatten() {
+                if let Some(child) = self.documents.get_mut(child_id) {
+                    child.section.get_or_insert_with(|| section.clone());
+                }
+            }
+        }
+        self.upsert(document);
+    }
+
+    pub fn remove(&mut self, event_id: &EventId) {
+        let Some(previous) = self.documents.remove(event_id) else {
+            return;
+        };
+        if let Some(parent) = previous.parent.as_ref() {
+            if let Some(children) = self.children.get_mut(parent) {
+                children.remove(event_id);
+                if children.is_empty() {
+                    self.children.remove(parent);
+                }
+            }
+        }
+        let terms: HashSet<String> = previous
+            .fields
+            .iter()
+            .flat_map(|(_, text)| tokenize(text).collect::<Vec<_>>())
+            .collect();
+        for term in terms {
+            if let Some(docs) = self.postings.get_mut(&term) {
+                docs.remove(event_id);
+                if docs.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    /// documents containing the term, the last query term also matches as prefix
+    fn term_scores(&self, term: &str, prefix: bool) -> HashMap<OwnedEventId, f32> {
+        let total = self.documents.len() as f32;
+        let mut scores: HashMap<OwnedEventId, f32> = HashMap::new();
+        let mut add = |docs: &HashMap<OwnedEventId, f32>, factor: f32| {
+            let idf = (1.0 + total / docs.len() as f32).ln();
+            for (event_id, frequency) in docs {
+                let score = factor * idf * frequency / (frequency + 1.0);
+                let entry = scores.entry(event_id.clone()).or_default();
+                *entry = entry.max(score);
+            }
+        };
+        if prefix {
+            for (candidate, docs) in self
+                .postings
+                .range(term.to_owned()..)
+                .take_while(|(k, _)| k.starts_with(term))
+            {
+                // full word matches rank above prefix matches
+                add(docs, if candidate == term { 1.0 } else { 0.5 });
+            }
+        } else if let Some(docs) = self.postings.get(term) {
+            add(docs, 1.0);
+        }
+        scores
+    }
+
+    /// Ranked hits for the query, best first, newer first on equal score
+    pub fn search(&self, query: &SearchQuery) -> Vec<SearchHit> {
+        let terms: Vec<String> = tokenize(&query.text).collect();
+        let Some(last) = terms.len().checked_sub(1) else {
+            return vec![];
+        };
+        let mut scores: Option<HashMap<OwnedEventId, f32>> = None;
+        for (idx, term) in terms.iter().enumerate() {
+            let found = self.term_scores(term, idx == last);
+            scores = Some(match scores {
+                None => found,
+                // all terms have to match
+                Some(previous) => previous
+                    .into_iter()
+                    .filter_map(|(event_id, score)| {
+                        found.get(&event_id).map(|s| (event_id, score + s))
+                    })
+                    .collect(),
+            });
+        }
+
+        let mut hits: Vec<SearchHit> = scores
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(event_id, score)| {
+                let doc = self.documents.get(&event_id)?;
+                if !doc.matches(query) {
+                    return None;
+                }
+                Some(SearchHit {
+                    event_id,
+                    room_id: doc.room_id.clone(),
+                    sender: doc.sender.clone(),
+                    origin_server_ts: doc.origin_server_ts,
+                    model_type: doc.model_type.clone(),
+                    section: doc.section.clone(),
+                    parent: doc.parent.clone(),
+                    score,
+                    snippet: doc.snippet(&terms),
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.origin_server_ts.cmp(&a.origin_server_ts))
+        });
+        if let Some(limit) = query.limit {
+            hits.truncate(limit);
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::AnyActerEvent;
+    use serde_json::{from_value, json};
+
+    fn model(event_id: &str, sender: &str, ts: u64, content: serde_json::Value) -> AnyActerModel {
+        let event: AnyActerEvent = from_value(json!({
+            "type": content["type"],
+            "room_id": "!room:example.org",
+            "sender": sender,
+            "origin_server_ts": ts,
+            "event_id": event_id,
+            "content": content["content"],
+        }))
+        .unwrap();
+        AnyActerModel::try_from(event).unwrap()
+    }
+
+    fn index(models: &[AnyActerModel]) -> SearchIndex {
+        let mut index = SearchIndex::default();
+        for m in models {
+            let doc = SearchDocument::for_model(m, |id| {
+                models.iter().find(|m| m.event_id() == id).cloned()
+            });
+            if let Some(doc) = doc {
+                index.upsert(doc);
+            }
+        }
+        index
+    }
+
+    fn fixtures() -> Vec<AnyActerModel> {
+        vec![
+            model(
+                "$pin",
+                "@alice:example.org",
+                100,
+                json!({
+                    "type": "global.acter.dev.pin",
+                    "content": {
+                        "title": "Garden rules",
+                        "content": { "body": "Please close the gate of the garden" },
+                    },
+                }),
+            ),
+            model(
+                "$event",
+                "@bob:example.org",
+                200,
+                json!({
+                    "type": "global.acter.dev.calendar_event",
+                    "content": {
+                        "title": "Community gathering",
+                        "description": { "body": "bring snacks for the garden party" },
+                        "utc_start": "2024-05-01T10:00:00Z",
+                        "utc_end": "2024-05-01T12:00:00Z",
+                    },
+                }),
+            ),
+            model(
+                "$comment",
+                "@bob:example.org",
+                300,
+                json!({
+                    "type": "global.acter.dev.comment",
+                    "content": {
+                        "content": { "body": "The gardening tools are in the shed" },
+                        "m.relates_to": {
+                            "rel_type": "global.acter.dev.belongs_to",
+                            "event_id": "$pin",
+                        },
+                    },
+                }),
+            ),
+        ]
+    }
+
+    #[test]
+    fn tokenizes_words() {
+        let tokens: Vec<String> = tokenize("Hello, Wörld! it's 2024").collect();
+        assert_eq!(tokens, vec!["hello", "wörld", "it", "s", "2024"]);
+    }
+
+    #[test]
+    fn ranks_titles_first() {
+        let index = index(&fixtures());
+        assert_eq!(index.len(), 3);
+        let hits = index.search(&SearchQuery {
+            text: "garden".to_owned(),
+            ..Default::default()
+        });
+        let ids: Vec<&str> = hits.iter().map(|h| h.event_id.as_str()).collect();
+        // title match, then body match, then the prefix match of "gardening"
+        assert_eq!(ids, vec!["$pin", "$event", "$comment"]);
+        assert_eq!(hits[0].snippet, "Garden rules");
+        assert_eq!(hits[2].parent.as_deref().map(|p| p.as_str()), Some("$pin"));
+    }
+
+    #[test]
+    fn all_words_must_match() {
+        let index = index(&fixtures());
+        let hits = index.search(&SearchQuery {
+            text: "garden snac".to_owned(),
+            ..Default::default()
+        });
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].event_id.as_str(), "$event");
+        assert!(index
+            .search(&SearchQuery {
+                text: "snac garden".to_owned(),
+                ..Default::default()
+            })
+            .is_empty());
+    }
+
+    #[test]
+    fn filters() {
+        let index = index(&fixtures());
+        let query = |query: SearchQuery| -> Vec<String> {
+            index
+                .search(&query)
+                .into_iter()
+                .map(|h| h.event_id.to_string())
+                .collect()
+        };
+        assert_eq!(
+            query(SearchQuery {
+                text: "garden".to_owned(),
+                sections: vec![SectionIndex::Pins],
+                ..Default::default()
+            }),
+            vec!["$pin", "$comment"] // the comment is on a pin
+        );
+        assert_eq!(
+            query(SearchQuery {
+                text: "garden".to_owned(),
+                senders: vec!["@bob:example.org".try_into().unwrap()],
+                since: Some(MilliSecondsSinceUnixEpoch(250u32.into())),
+                ..Default::default()
+            }),
+            vec!["$comment"]
+        );
+        assert_eq!(
+            query(SearchQuery {
+                text: "garden".to_owned(),
+                limit: Some(1),
+                ..Default::default()
+            }),
+            vec!["$pin"]
+        );
+    }
+
+    #[test]
+    fn updates_replace_and_remove() {
+        let mut fixtures = fixtures();
+        let mut index = index(&fixtures);
+        fixtures[0] = model(
+            "$pin",
+            "@alice:example.org",
+            100,
+            json!({
+                "type": "global.acter.dev.pin",
+                "content": { "title": "Parking" },
+            }),
+        );
+        index.upsert(SearchDocument::for_model(&fixtures[0], |_| None).unwrap());
+        let garden = SearchQuery {
+            text: "garden".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(index.search(&garden).len(), 2);
+        assert_eq!(
+            index
+                .search(&SearchQuery {
+                    text: "parking".to_owned(),
+                    ..Default::default()
+                })
+                .len(),
+            1
+        );
+
+        index.remove(&OwnedEventId::try_from("$event").unwrap());
+        assert_eq!(index.search(&garden).len(), 1);
+        assert!(!index.postings.contains_key("snacks"));
+    }
+
+    #[test]
+    fn comments_seen_first_get_the_section_later() {
+        let fixtures = fixtures();
+        let mut index = SearchIndex::default();
+        let comment = SearchDocument::for_model(&fixtures[2], |_| None).unwrap();
+        index.update(fixtures[2].event_id(), Some(comment));
+        let in_pins = SearchQuery {
+            text: "shed".to_owned(),
+            sections: vec![SectionIndex::Pins],
+            ..Default::default()
+        };
+        assert!(index.search(&in_pins).is_empty());
+
+        let pin = SearchDocument::for_model(&fixtures[0], |_| None);
+        index.update(fixtures[0].event_id(), pin);
+        let hits = index.search(&in_pins);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(&*hits[0].event_id, fixtures[2].event_id());
+
+        index.remove(fixtures[2].event_id());
+        assert!(index.children.is_empty());
+    }
+}
@@ -1,15 +1,22 @@
 use async_stream::try_stream;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use core::pin::Pin;
 use futures::{
     task::{Context as FuturesContext, Poll},
     Stream,
 };
 use indexmap::IndexMap;
-use matrix_sdk_base::{ruma::RoomId, RoomState};
+use matrix_sdk_base::{
+    ruma::{EventId, OwnedRoomId, RoomId},
+    RoomState,
+};
 pub use minijinja::value::Value;
 use minijinja::Environment;
 use serde::Deserialize;
-use std::{collections::BTreeMap, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, PoisonError},
+};
 use tokio_retry::{
     strategy::{jitter, FibonacciBackoff},
     Retry,
@@ -17,20 +24,30 @@ use tokio_retry::{
 use toml::{Table, Value as TomlValue};
 use tracing::trace;
 
+pub mod export;
 pub mod filters;
 pub mod functions;
+mod record;
+mod validate;
 pub mod values;
 
-use values::{ObjRef, UserValue};
+pub use record::{CreatedObject, ExecutionRecord, SpaceChildLink};
+pub use validate::Problem;
+use values::{ObjRef, UserValue, UtcDateTimeValue};
 
 use crate::{
     client::CoreClient,
+    convos::CreateConvoSettings,
     events::{
+        attachments::AttachmentEventContent,
         calendar::CalendarEventEventContent,
+        comments::CommentEventContent,
         news::NewsEntryEventContent,
         pins::PinEventContent,
+        rsvp::RsvpEventContent,
         stories::StoryEventContent,
         tasks::{TaskEventContent, TaskListEventContent},
+        BelongsTo, CategoriesStateEventContent, UtcDateTime,
     },
     spaces::CreateSpaceSettings,
 };
@@ -69,6 +86,23 @@ pub enum Error {
 
     #[error("{0} already found in context.")]
     ContextClash(String),
+
+    #[error("Invalid value for input {0}: {1}")]
+    InvalidInput(String, String),
+
+    #[error("'{0}' is not a known field and would be ignored")]
+    UnknownField(String),
+
+    #[error("{} problem(s) found:\n{}", .0.len(), list_problems(.0))]
+    Problems(Vec<Problem>),
+}
+
+fn list_problems(problems: &[Problem]) -> String {
+    problems
+        .iter()
+        .map(|p| format!(" - {p}"))
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[derive(Deserialize)]
@@ -79,6 +113,8 @@ pub enum Input {
         required: bool,
 
         description: Option<String>,
+
+        default: Option<String>,
     },
     User {
         #[serde(default)]
@@ -98,6 +134,39 @@ pub enum Input {
 
         description: Option<String>,
     },
+    /// A point in time, given as RFC 3339 or plain `YYYY-MM-DD` (midnight UTC)
+    Date {
+        #[serde(default)]
+        required: bool,
+
+        description: Option<String>,
+
+        default: Option<String>,
+    },
+    /// One of the listed `options`
+    Choice {
+        #[serde(default)]
+        required: bool,
+
+        description: Option<String>,
+
+        options: Vec<String>,
+
+        default: Option<String>,
+    },
+    /// A whole number, optionally within `min` and `max` (inclusive)
+    Number {
+        #[serde(default)]
+        required: bool,
+
+        description: Option<String>,
+
+        min: Option<i64>,
+
+        max: Option<i64>,
+
+        default: Option<i64>,
+    },
 }
 
 impl Input {
@@ -105,8 +174,10 @@ impl Input {
         match self {
             Input::Text { required, .. }
             | Input::User { required, .. }
-            | Input::Space { required, .. } => *required,
-            // _ => false,
+            | Input::Space { required, .. }
+            | Input::Date { required, .. }
+            | Input::Choice { required, .. }
+            | Input::Number { required, .. } => *required,
         }
     }
 
@@ -124,18 +195,162 @@ impl Input {
     pub fn is_space(&self) -> bool {
         matches!(self, Input::Space { .. })
     }
+
+    /// The `type` as written in the template
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Input::Text { .. } => "text",
+            Input::User { .. } => "user",
+            Input::Space { .. } => "space",
+            Input::Date { .. } => "date",
+            Input::Choice { .. } => "choice",
+            Input::Number { .. } => "number",
+        }
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            Input::Text { description, .. }
+            | Input::User { description, .. }
+            | Input::Space { description, .. }
+            | Input::Date { description, .. }
+            | Input::Choice { description, .. }
+            | Input::Number { description, .. } => description.as_deref(),
+        }
+    }
+
+    /// The options to pick from for choices, empty otherwise
+    pub fn options(&self) -> &[String] {
+        match self {
+            Input::Choice { options, .. } => options,
+            _ => &[],
+        }
+    }
+
+    pub fn has_default_value(&self) -> bool {
+        match self {
+            Input::Text { default, .. }
+            | Input::Date { default, .. }
+            | Input::Choice { default, .. } => default.is_some(),
+            Input::Number { default, .. } => default.is_some(),
+            Input::User { .. } | Input::Space { .. } => false,
+        }
+    }
+
+    /// The context value for the default of input `name`, if it has one
+    pub fn default_value(&self, name: &str) -> Result<Option<Value>, Error> {
+        match self {
+            Input::Text { default, .. }
+            | Input::Date { default, .. }
+            | Input::Choice { default, .. } => default
+                .as_deref()
+                .map(|d| self.parse_value(name, d))
+                .transpose(),
+            Input::Number { default, .. } => {
+                default.map(|d| self.number_value(name, d)).transpose()
+            }
+            Input::User { .. } | Input::Space { .. } => Ok(None),
+        }
+    }
+
+    /// Parse the textual `raw` value given for input `name` into its context
+    /// value, validating it. Spaces take the room id, users can’t be given
+    /// as text as they need a logged in client.
+    pub fn parse_value(&self, name: &str, raw: &str) -> Result<Value, Error> {
+        match self {
+            Input::Text { .. } => Ok(Value::from(raw.to_owned())),
+            Input::Space { .. } => {
+                RoomId::parse(raw)
+                    .map_err(|e| Error::InvalidInput(name.to_owned(), e.to_string()))?;
+                Ok(Value::from_object(ObjRef::new(
+                    raw.to_owned(),
+                    "space".to_owned(),
+                )))
+            }
+            Input::User { .. } => Err(Error::InvalidInput(
+                name.to_owned(),
+                "users must be added with their client".to_owned(),
+            )),
+            Input::Date { .. } => Ok(Value::from_object(UtcDateTimeValue::new(parse_date(
+                name, raw,
+            )?))),
+            Input::Choice { .. } => self.choice_value(name, raw),
+            Input::Number { .. } => {
+                let number = raw.trim().parse::<i64>().map_err(|e| {
+                    Error::InvalidInput(name.to_owned(), format!("'{raw}' is not a number: {e}"))
+                })?;
+                self.number_value(name, number)
+            }
+        }
+    }
+
+    fn choice_value(&self, name: &str, choice: &str) -> Result<Value, Error> {
+        let options = self.options();
+        if !options.iter().any(|o| o == choice) {
+            return Err(Error::InvalidInput(
+                name.to_owned(),
+                format!("'{choice}' is not one of {options:?}"),
+            ));
+        }
+        Ok(Value::from(choice.to_owned()))
+    }
+
+    fn number_value(&self, name: &str, number: i64) -> Result<Value, Error> {
+        let Input::Number { min, max, .. } = self else {
+            return Err(Error::InvalidInput(
+                name.to_owned(),
+                format!("expected {}, got a number", self.kind()),
+            ));
+        };
+        if min.is_some_and(|min| number < min) || max.is_some_and(|max| number > max) {
+            return Err(Error::InvalidInput(
+                name.to_owned(),
+                format!("{number} is out of range {min:?}..={max:?}"),
+            ));
+        }
+        Ok(Value::from(number))
+    }
+}
+
+fn parse_date(name: &str, raw: &str) -> Result<UtcDateTime, Error> {
+    let raw = raw.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(date.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .map(|d| d.and_time(NaiveTime::MIN).and_utc())
+        .map_err(|_| {
+            Error::InvalidInput(
+                name.to_owned(),
+                format!("'{raw}' is neither an RFC 3339 date time nor YYYY-MM-DD"),
+            )
+        })
 }
 
 #[derive(Deserialize)]
 #[serde(tag = "type", rename_all = "kebab-case")]
 pub enum ObjectInner {
+    /// A space, the sub-space of the one it is created `in` if given
     Space {
         #[serde(default, rename = "is-default")]
         is_default: bool,
 
+        /// whether it is suggested in its parent space
+        #[serde(default)]
+        suggested: bool,
+
         #[serde(flatten)]
         fields: CreateSpaceSettings,
     },
+    /// A chat inside the space it is created `in`
+    Chat {
+        /// whether it is suggested in its parent space
+        #[serde(default)]
+        suggested: bool,
+
+        #[serde(flatten)]
+        fields: CreateConvoSettings,
+    },
     TaskList {
         #[serde(flatten)]
         fields: TaskListEventContent,
@@ -160,8 +375,54 @@ pub enum ObjectInner {
         #[serde(flatten)]
         fields: StoryEventContent,
     },
+    Comment {
+        #[serde(flatten)]
+        fields: CommentEventContent,
+    },
+    Attachment {
+        #[serde(flatten)]
+        fields: AttachmentEventContent,
+    },
+    Rsvp {
+        #[serde(flatten)]
+        fields: RsvpEventContent,
+    },
+    /// The categories of the given type, e.g. `spaces` or `chats`, of the
+    /// space
+    Categories {
+        #[serde(rename = "category-type")]
+        cat_type: String,
+
+        #[serde(flatten)]
+        fields: CategoriesStateEventContent,
+    },
 }
 
+impl ObjectInner {
+    /// The `type` as written in the template
+    pub fn obj_type(&self) -> &'static str {
+        match self {
+            ObjectInner::Space { .. } => "space",
+            ObjectInner::Chat { .. } => "chat",
+            ObjectInner::TaskList { .. } => "task-list",
+            ObjectInner::Task { .. } => "task",
+            ObjectInner::Pin { .. } => "pin",
+            ObjectInner::CalendarEvent { .. } => "calendar-event",
+            ObjectInner::NewsEntry { .. } => "news-entry",
+            ObjectInner::Story { .. } => "story",
+            ObjectInner::Comment { .. } => "comment",
+            ObjectInner::Attachment { .. } => "attachment",
+            ObjectInner::Rsvp { .. } => "rsvp",
+            ObjectInner::Categories { .. } => "categories",
+        }
+    }
+}
+
+/// The field naming the object another one is `on`, e.g. the pin a comment
+/// is made on. Resolves to its `m.relates_to`.
+static ON_FIELD: &str = "on";
+static RELATES_TO_FIELD: &str = "m.relates_to";
+
 #[derive(Deserialize)]
 pub struct Object {
     #[serde(alias = "in")]
@@ -193,9 +454,23 @@ type Context = BTreeMap<String, Value>;
 pub struct ExecutionStream {
     total: u32,
     done: u32,
+    record: Arc<Mutex<ExecutionRecord>>,
     stream: Pin<Box<dyn Stream<Item = Result<(), Error>> + Unpin + Send>>,
 }
 
+/// The environment templates are rendered in
+fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+
+    // functions
+    env.add_function("future", functions::future);
+    env.add_function("now", functions::now);
+
+    // filters
+
+    env
+}
+
 fn execute_value_template(
     value: TomlValue,
     env: &Environment,
@@ -226,18 +501,84 @@ fn execute_value_template(
     }
 }
 
+/// The room id of the space or chat `room_name` refers to
+fn ref_room_id(context: &Context, room_name: &str, key: &str) -> Result<OwnedRoomId, Error> {
+    let room_id_str = context
+        .get(room_name)
+        .ok_or_else(|| {
+            Error::UnknownReference("room".to_owned(), room_name.to_owned(), key.to_owned())
+        })?
+        .get_attr("id")
+        .map_err(|e| Error::Remap(format!("{key} room={room_name} attr=id"), e.to_string()))?
+        .to_string();
+
+    RoomId::parse(room_id_str.clone()).map_err(|e| {
+        Error::Remap(
+            format!("{key}.room({room_name}).id({room_id_str}) parse failed"),
+            e.to_string(),
+        )
+    })
+}
+
+/// Replace the `on` of the rendered object `key` by the `m.relates_to`
+/// pointing at the object it references
+fn resolve_on(key: &str, table: &mut Table, context: &Context) -> Result<(), Error> {
+    let Some(on) = table.remove(ON_FIELD) else {
+        return Ok(());
+    };
+    let Some(on) = on.as_str() else {
+        return Err(Error::Remap(
+            format!("{key}.{ON_FIELD}"),
+            "must be the key of an object".to_owned(),
+        ));
+    };
+    if table.contains_key(RELATES_TO_FIELD) {
+        return Err(Error::Remap(
+            format!("{key}.{ON_FIELD}"),
+            format!("can’t be given together with `{RELATES_TO_FIELD}`"),
+        ));
+    }
+    let event_id_str = context
+        .get(on)
+        .ok_or_else(|| Error::UnknownReference("object".to_owned(), on.to_owned(), key.to_owned()))?
+        .get_attr("id")
+        .map_err(|e| Error::Remap(format!("{key} on={on} attr=id"), e.to_string()))?
+        .to_string();
+    let event_id = EventId::parse(&event_id_str).map_err(|e| {
+        Error::Remap(
+            format!("{key}.on({on}).id({event_id_str}) is not an event"),
+            e.to_string(),
+        )
+    })?;
+    table.insert(
+        RELATES_TO_FIELD.to_owned(),
+        TomlValue::try_from(BelongsTo::from(event_id))?,
+    );
+    Ok(())
+}
+
 impl ExecutionStream {
     pub fn new(
         total: u32,
+        record: Arc<Mutex<ExecutionRecord>>,
         stream: Box<dyn Stream<Item = Result<(), Error>> + Unpin + Send>,
     ) -> Self {
         ExecutionStream {
             done: 0,
             total,
+            record,
             stream: Pin::new(stream),
         }
     }
 
+    /// What has been created so far, to resume or roll back a failed run
+    pub fn record(&self) -> ExecutionRecord {
+        self.record
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
     pub fn total(&self) -> u32 {
         self.total
     }
@@ -292,6 +633,55 @@ impl Engine {
         }
     }
 
+    /// Check `name` is not requested as an input of another kind
+    fn check_kind(&self, name: &str, kind: &str) -> Result<Option<&Input>, Error> {
+        match self.root.inputs.get(name) {
+            Some(input) if input.kind() != kind => Err(Error::InvalidInput(
+                name.to_owned(),
+                format!("expected {}, got {kind}", input.kind()),
+            )),
+            input => Ok(input),
+        }
+    }
+
+    pub fn add_text(&mut self, name: String, value: String) -> Result<(), Error> {
+        self.check_kind(&name, "text")?;
+        self.add_context(name, Value::from(value))
+    }
+
+    pub fn add_date(&mut self, name: String, value: UtcDateTime) -> Result<(), Error> {
+        self.check_kind(&name, "date")?;
+        self.add_context(name, Value::from_object(UtcDateTimeValue::new(value)))
+    }
+
+    pub fn add_number(&mut self, name: String, value: i64) -> Result<(), Error> {
+        let value = match self.check_kind(&name, "number")? {
+            Some(input) => input.number_value(&name, value)?,
+            None => Value::from(value),
+        };
+        self.add_context(name, value)
+    }
+
+    pub fn add_choice(&mut self, name: String, value: String) -> Result<(), Error> {
+        let value = match self.check_kind(&name, "choice")? {
+            Some(input) => input.choice_value(&name, &value)?,
+            None => Value::from(value),
+        };
+        self.add_context(name, value)
+    }
+
+    /// Add the textual `value` for the requested input `name`, parsed and
+    /// validated according to its kind
+    pub fn add_input(&mut self, name: String, value: &str) -> Result<(), Error> {
+        let input = self
+            .root
+            .inputs
+            .get(&name)
+            .ok_or_else(|| Error::InvalidInput(name.clone(), "not a known input".to_owned()))?;
+        let value = input.parse_value(&name, value)?;
+        self.add_context(name, value)
+    }
+
     pub fn add_ref(&mut self, name: String, obj_type: String, id: String) -> Result<(), Error> {
         if self
             .context
@@ -319,20 +709,33 @@ impl Engine {
         }
     }
 
-    pub fn execute(&self) -> Result<ExecutionStream, Error> {
-        trace!(name = ?self.root.name, "executing");
-
-        let env = {
-            let mut env = Environment::new();
+    /// Make the already added user `existing` available as `name` as well
+    pub fn alias_user(&mut self, name: String, existing: &str) -> Result<(), Error> {
+        let (Some(client), Some(value)) = (self.users.get(existing), self.context.get(existing))
+        else {
+            return Err(Error::UnknownReference(
+                "user".to_owned(),
+                existing.to_owned(),
+                name,
+            ));
+        };
+        let (client, value) = (client.clone(), value.clone());
+        self.users.insert(name.clone(), client);
+        self.add_context(name, value)
+    }
 
-            // functions
-            env.add_function("future", functions::future);
-            env.add_function("now", functions::now);
+    pub fn execute(&self) -> Result<ExecutionStream, Error> {
+        self.resume(ExecutionRecord::default())
+    }
 
-            // filters
+    /// Execute the template, skipping the objects the `record` of an
+    /// earlier run says have been created already
+    pub fn resume(&self, previous: ExecutionRecord) -> Result<ExecutionStream, Error> {
+        trace!(name = ?self.root.name, resumed = previous.len(), "executing");
 
-            env
-        };
+        let env = environment();
+        let record = Arc::new(Mutex::new(previous.clone()));
+        let recorder = record.clone();
 
         let users = self.users.clone();
         let mut context = self.context.clone();
@@ -342,6 +745,7 @@ impl Engine {
             let mut default_user = None;
             let mut default_user_name = None;
             let mut default_space = None;
+            let mut missing = Vec::new();
 
             for (name, input) in self.requested_inputs() {
                 trace!(
@@ -350,8 +754,13 @@ impl Engine {
                     is_user = input.is_user(),
                     "parsing input",
                 );
-                if input.is_required() && !context.contains_key(name) {
-                    return Err(Error::MissingInputs(vec![name.clone()]));
+                if !context.contains_key(name) {
+                    if let Some(value) = input.default_value(name)? {
+                        context.insert(name.clone(), value);
+                    } else if input.is_required() {
+                        missing.push(name.clone());
+                        continue;
+                    }
                 }
                 if input.is_default() {
                     if input.is_user() {
@@ -375,6 +784,9 @@ impl Engine {
                     }
                 }
             }
+            if !missing.is_empty() {
+                return Err(Error::MissingInputs(missing));
+            }
             (default_user, default_user_name, default_space)
         };
 
@@ -383,56 +795,143 @@ impl Engine {
         let stream = try_stream! {
             trace!(total = objects.len(), "starting execution");
             for (count, (key, fields)) in objects.into_iter().enumerate() {
+                if let Some(created) = previous.get(&key) {
+                    trace!(count, ?key, "already created");
+                    context.insert(
+                        key.clone(),
+                        Value::from_object(ObjRef::new(created.id.clone(), created.obj_type.clone())),
+                    );
+                    if created.is_default {
+                        default_space = Some(key.clone());
+                    }
+                    yield;
+                    continue
+                }
                 trace!(count, ?key, "executing");
                 let reformatted = execute_value_template(TomlValue::Table(fields), &env, &context)
                     .map_err(|e| Error::RenderingObject(key.clone(), e.to_string()))?;
-                let TomlValue::Table(t) = reformatted else {
+                let TomlValue::Table(mut t) = reformatted else {
                     unreachable!("We always get back a table after sending in a table.");
                 };
+                resolve_on(&key, &mut t, &context)?;
                 let Object { room, user, obj } = Table::try_into::<Object>(t)?;
 
-                let client = if user.is_none() || user == default_user_key  {
-                    default_user.clone().ok_or_else(|| Error::NoDefaultSet("user".to_owned(), key.clone()))?
+                let (client, user_key) = if user.is_none() || user == default_user_key  {
+                    (
+                        default_user.clone().ok_or_else(|| Error::NoDefaultSet("user".to_owned(), key.clone()))?,
+                        default_user_key.clone().unwrap_or_default(),
+                    )
                 } else { // must be the case
                     let Some(username) = user else {
                         unimplemented!("never reached");
                     };
-                    users.get(&username).ok_or_else(|| Error::UnknownReference("user".to_owned(), key.clone(), username))?.clone()
+                    (
+                        users.get(&username).ok_or_else(|| Error::UnknownReference("user".to_owned(), key.clone(), username.clone()))?.clone(),
+                        username,
+                    )
                 };
 
-                if let ObjectInner::Space { is_default, fields } = obj {
-                    if is_default && default_space.is_some() {
-                        Err(Error::TooManyDefaults("Space".to_owned()))?;
+                let obj = match obj {
+                    ObjectInner::Space { is_default, suggested, mut fields } => {
+                        if is_default && default_space.is_some() {
+                            Err(Error::TooManyDefaults("Space".to_owned()))?;
+                        }
+                        // a space created `in` another one becomes its sub-space
+                        if fields.parent().is_none() {
+                            if let Some(room_name) = &room {
+                                fields.set_parent(ref_room_id(&context, room_name, &key)?);
+                            }
+                        }
+                        let parent = fields.parent().cloned();
+                        let new_room_id = client
+                            .create_acter_space(fields)
+                            .await
+                            .map_err(|e| Error::Remap(format!("Creating space '{key}' failed"), e.to_string()))?;
+                        context.insert(
+                            key.clone(),
+                            Value::from_object(ObjRef::new(new_room_id.to_string() , "space".to_owned())),
+                        );
+                        if is_default {
+                            default_space = Some(key.clone());
+                        }
+                        recorder.lock().unwrap_or_else(PoisonError::into_inner).push(CreatedObject {
+                            key: key.clone(),
+                            obj_type: "space".to_owned(),
+                            id: new_room_id.to_string(),
+                            room_id: new_room_id.to_string(),
+                            user: user_key.clone(),
+                            is_default,
+                            child_of: None,
+                        });
+                        if let Some(parent) = parent {
+                            let event_id = client
+                                .add_space_child(&parent, &new_room_id, suggested)
+                                .await
+                                .map_err(|e| Error::Remap(format!("Adding space '{key}' to {parent} failed"), e.to_string()))?;
+                            recorder.lock().unwrap_or_else(PoisonError::into_inner).set_child_of(&key, SpaceChildLink {
+                                parent_id: parent.to_string(),
+                                event_id: event_id.to_string(),
+                            });
+                        }
+                        let retry_strategy = FibonacciBackoff::from_millis(100)
+                            .map(jitter)
+                            .take(10);
+                        let fetcher_client = client.client().clone();
+                        Retry::spawn(retry_strategy, move || {
+                            std::future::ready(if fetcher_client
+                                .get_room(&new_room_id)
+                                .is_some_and(|x| matches!(x.state(), RoomState::Joined)) {
+                                    Ok(())
+                                } else {
+                                    Err(Error::Remap(
+                                        format!("created space '{key}' ({new_room_id}) could not be found"),
+                                        "Do you have a sync running?".to_owned()
+                                    ))
+                                })
+                        }).await?;
+
+                        continue
                     }
-                    let new_room_id = client
-                        .create_acter_space(fields)
-                        .await
-                        .map_err(|e| Error::Remap(format!("Creating space '{key}' failed"), e.to_string()))?;
-                    context.insert(
-                        key.clone(),
-                        Value::from_object(ObjRef::new(new_room_id.to_string() , "space".to_owned())),
-                    );
-                    if is_default {
-                        default_space = Some(key.clone());
+                    ObjectInner::Chat { suggested, mut fields } => {
+                        // chats live in the given or default space, if there is one
+                        if fields.parent().is_none() {
+                            if let Some(room_name) = room.clone().or_else(|| default_space.clone()) {
+                                fields.set_parent(ref_room_id(&context, &room_name, &key)?);
+                            }
+                        }
+                        let parent = fields.parent().cloned();
+                        let new_room_id = client
+                            .create_acter_convo(fields)
+                            .await
+                            .map_err(|e| Error::Remap(format!("Creating chat '{key}' failed"), e.to_string()))?;
+                        trace!(?new_room_id, "chat created");
+                        context.insert(
+                            key.clone(),
+                            Value::from_object(ObjRef::new(new_room_id.to_string(), "chat".to_owned())),
+                        );
+                        recorder.lock().unwrap_or_else(PoisonError::into_inner).push(CreatedObject {
+                            key: key.clone(),
+                            obj_type: "chat".to_owned(),
+                            id: new_room_id.to_string(),
+                            room_id: new_room_id.to_string(),
+                            user: user_key.clone(),
+                            is_default: false,
+                            child_of: None,
+                        });
+                        if let Some(parent) = parent {
+                            let event_id = client
+                                .add_space_child(&parent, &new_room_id, suggested)
+                                .await
+                                .map_err(|e| Error::Remap(format!("Adding chat '{key}' to {parent} failed"), e.to_string()))?;
+                            recorder.lock().unwrap_or_else(PoisonError::into_inner).set_child_of(&key, SpaceChildLink {
+                                parent_id: parent.to_string(),
+                                event_id: event_id.to_string(),
+                            });
+                        }
+                        yield;
+                        continue
                     }
-                    let retry_strategy = FibonacciBackoff::from_millis(100)
-                        .map(jitter)
-                        .take(10);
-                    let fetcher_client = client.client().clone();
-                    Retry::spawn(retry_strategy, move || {
-                        std::future::ready(if fetcher_client
-                            .get_room(&new_room_id)
-                            .is_some_and(|x| matches!(x.state(), RoomState::Joined)) {
-                                Ok(())
-                            } else {
-                                Err(Error::Remap(
-                                    format!("created space '{key}' ({new_room_id}) could not be found"),
-                                    "Do you have a sync running?".to_owned()
-                                ))
-                            })
-                    }).await?;
-
-                    continue
+                    obj => obj,
                 };
 
                 let room_name = match room {
@@ -440,115 +939,82 @@ impl Engine {
                     None => default_space.clone().ok_or_else(|| Error::NoDefaultSet("room".to_owned(), key.clone()))?
                 };
 
-                let room_id_str = context
-                    .get(&room_name)
-                    .ok_or_else(|| Error::UnknownReference("room".to_owned(), room_name.clone(), key.clone()))?
-                    .get_attr("id")
-                    .map_err(|e| Error::Remap(format!("{key} room={room_name} attr=id"), e.to_string()))?
-                    .to_string();
-
-                let room_id = RoomId::parse(room_id_str.clone())
-                    .map_err(|e| Error::Remap(format!("{key}.room({room_name}).id({room_id_str}) parse failed"), e.to_string()))?;
+                let room_id = ref_room_id(&context, &room_name, &key)?;
 
                 let room = client
                     .client()
                     .get_room(&room_id)
                     .ok_or_else(|| Error::UnknownReference(format!("{key}.room"), room_name.clone(), key.clone()))?;
 
-                match obj {
+                let obj_type = obj.obj_type();
+                let response = match obj {
                     ObjectInner::TaskList { fields } => {
                         trace!(?fields, "submitting task list");
-                        let id = room
-                            .send(fields)
-                            .await
-                            .map_err(|e| Error::Remap(format!("{key} submission failed"), e.to_string()))?
-                            .event_id;
-                        trace!(?id, "task list created");
-                        context.insert(
-                            key.clone(),
-                            Value::from_object(ObjRef::new(id.to_string(), "task-list".to_owned())),
-                        );
-                        yield
+                        room.send(fields).await.map(|r| r.event_id)
                     }
                     ObjectInner::Task { fields } => {
                         trace!(?fields, "submitting task");
-                        let id = room
-                            .send(fields)
-                            .await
-                            .map_err(|e| Error::Remap(format!("{key} submission failed"), e.to_string()))?
-                            .event_id;
-                        trace!(?id, "task created");
-                        context.insert(
-                            key.clone(),
-                            Value::from_object(ObjRef::new(id.to_string(), "task".to_owned())),
-                        );
-                        yield
+                        room.send(fields).await.map(|r| r.event_id)
                     }
                     ObjectInner::CalendarEvent { fields } => {
                         trace!(?fields, "submitting calendar event");
-                        let id = room
-                            .send(fields)
-                            .await
-                            .map_err(|e| Error::Remap(format!("{key} submission failed"), e.to_string()))?
-                            .event_id;
-                        trace!(?id, "calendar event created");
-                        context.insert(
-                            key.clone(),
-                            Value::from_object(ObjRef::new(id.to_string(), "calendar-event".to_owned())),
-                        );
-                        yield
+                        room.send(fields).await.map(|r| r.event_id)
                     }
                     ObjectInner::Pin { fields } => {
                         trace!(?fields, "submitting pin");
-                        let id = room
-                            .send(fields)
-                            .await
-                            .map_err(|e| Error::Remap(format!("{key} submission failed"), e.to_string()))?
-                            .event_id;
-                        trace!(?id, "pin created");
-                        context.insert(
-                            key.clone(),
-                            Value::from_object(ObjRef::new(id.to_string(), "pin".to_owned())),
-                        );
-                        yield
+                        room.send(fields).await.map(|r| r.event_id)
                     }
                     ObjectInner::NewsEntry { fields } => {
                         trace!(?fields, "submitting news entry");
-                        let id = room
-                            .send(fields)
-                            .await
-                            .map_err(|e| Error::Remap(format!("{key} submission failed"), e.to_string()))?
-                            .event_id;
-                        trace!(?id, "news created");
-                        context.insert(
-                            key.clone(),
-                            Value::from_object(ObjRef::new(id.to_string(), "news-entry".to_owned())),
-                        );
-                        yield
+                        room.send(fields).await.map(|r| r.event_id)
                     }
                     ObjectInner::Story { fields } => {
                         trace!(?fields, "submitting story");
-                        let id = room
-                            .send(fields)
-                            .await
-                            .map_err(|e| Error::Remap(format!("{key} submission failed"), e.to_string()))?
-                            .event_id;
-                        trace!(?id, "story created");
-                        context.insert(
-                            key.clone(),
-                            Value::from_object(ObjRef::new(id.to_string(), "story".to_owned())),
-                        );
-                        yield
+                        room.send(fields).await.map(|r| r.event_id)
+                    }
+                    ObjectInner::Comment { fields } => {
+                        trace!(?fields, "submitting comment");
+                        room.send(fields).await.map(|r| r.event_id)
+                    }
+                    ObjectInner::Attachment { fields } => {
+                        trace!(?fields, "submitting attachment");
+                        room.send(fields).await.map(|r| r.event_id)
+                    }
+                    ObjectInner::Rsvp { fields } => {
+                        trace!(?fields, "submitting rsvp");
+                        room.send(fields).await.map(|r| r.event_id)
+                    }
+                    ObjectInner::Categories { cat_type, fields } => {
+                        trace!(?fields, cat_type, "submitting categories");
+                        room.send_state_event_for_key(&cat_type, fields).await.map(|r| r.event_id)
                     }
-                    ObjectInner::Space { .. } => {
+                    ObjectInner::Space { .. } | ObjectInner::Chat { .. } => {
                         unreachable!("we already handled that above");
                     }
-                }
+                };
+                let id = response
+                    .map_err(|e| Error::Remap(format!("{key} submission failed"), e.to_string()))?;
+                trace!(?id, obj_type, "object created");
+                context.insert(
+                    key.clone(),
+                    Value::from_object(ObjRef::new(id.to_string(), obj_type.to_owned())),
+                );
+                recorder.lock().unwrap_or_else(PoisonError::into_inner).push(CreatedObject {
+                    key: key.clone(),
+                    obj_type: obj_type.to_owned(),
+                    id: id.to_string(),
+                    room_id: room_id.to_string(),
+                    user: user_key.clone(),
+                    is_default: false,
+                    child_of: None,
+                });
+                yield
             }
         };
 
         Ok(ExecutionStream::new(
             total as u32,
+            record,
             Box::new(Box::pin(stream)),
         ))
     }
@@ -609,4 +1075,121 @@ slides = [
 
         Ok(())
     }
+
+    const INPUTS_TMPL: &str = r#"
+version = "0.1"
+
+[inputs]
+team = { type = "text", required = true, default = "Core team" }
+kickoff = { type = "date", required = true }
+review = { type = "date", default = "2024-06-01" }
+cadence = { type = "choice", options = ["weekly", "monthly"], default = "weekly" }
+seats = { type = "number", min = 1, max = 20, required = true }
+space = { type = "space" }
+
+[objects]
+       "#;
+
+    #[test]
+    fn parsing_typed_inputs() -> Result<()> {
+        let engine = Engine::with_template(INPUTS_TMPL)?;
+        let inputs = engine.requested_inputs();
+        let kinds: Vec<&str> = inputs.values().map(Input::kind).collect();
+        assert_eq!(
+            kinds,
+            vec!["text", "date", "date", "choice", "number", "space"]
+        );
+        assert_eq!(inputs["cadence"].options(), ["weekly", "monthly"]);
+
+        let review = inputs["review"]
+            .default_value("review")?
+            .expect("has default");
+        assert_eq!(review.to_string(), "2024-06-01T00:00:00+00:00");
+        assert!(inputs["kickoff"].default_value("kickoff")?.is_none());
+        assert!(inputs["seats"].parse_value("seats", "21").is_err());
+        assert!(inputs["seats"].parse_value("seats", "many").is_err());
+        assert!(inputs["cadence"].parse_value("cadence", "daily").is_err());
+        assert!(inputs["kickoff"]
+            .parse_value("kickoff", "tomorrow")
+            .is_err());
+        assert!(inputs["space"].parse_value("space", "not-a-room").is_err());
+        assert_eq!(
+            inputs["kickoff"]
+                .parse_value("kickoff", "2024-05-01T10:00:00+02:00")?
+                .get_attr("as_timestamp")?
+                .to_string(),
+            "1714550400"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn setting_typed_inputs() -> Result<()> {
+        let mut engine = Engine::with_template(INPUTS_TMPL)?;
+        let Err(Error::MissingInputs(missing)) = engine.execute() else {
+            panic!("inputs are missing");
+        };
+        // `team` falls back to its default
+        assert_eq!(missing, vec!["kickoff", "seats"]);
+
+        assert!(matches!(
+            engine.add_number("seats".to_owned(), 0),
+            Err(Error::InvalidInput(..))
+        ));
+        assert!(matches!(
+            engine.add_text("seats".to_owned(), "5".to_owned()),
+            Err(Error::InvalidInput(..))
+        ));
+        engine.add_number("seats".to_owned(), 5)?;
+        engine.add_input("kickoff".to_owned(), "2024-05-01")?;
+        engine.add_choice("cadence".to_owned(), "monthly".to_owned())?;
+        assert!(matches!(
+            engine.add_input("unknown".to_owned(), "value"),
+            Err(Error::InvalidInput(..))
+        ));
+        assert!(matches!(
+            engine.add_text("cadence".to_owned(), "weekly".to_owned()),
+            Err(Error::InvalidInput(..))
+        ));
+        engine.execute()?;
+        Ok(())
+    }
+
+    #[test]
+    fn resolving_on() -> Result<()> {
+        let mut context = Context::new();
+        context.insert(
+            "pin".to_owned(),
+            Value::from_object(ObjRef::new("$pin".to_owned(), "pin".to_owned())),
+        );
+        context.insert(
+            "space".to_owned(),
+            Value::from_object(ObjRef::new(
+                "!space:example.org".to_owned(),
+                "space".to_owned(),
+            )),
+        );
+
+        let mut comment: Table = toml::from_str(
+            r#"
+type = "comment"
+on = "pin"
+content = { body = "First!" }
+"#,
+        )?;
+        resolve_on("comment", &mut comment, &context)?;
+        assert!(comment.get(ON_FIELD).is_none());
+        let Object { obj, .. } = comment.try_into::<Object>()?;
+        let ObjectInner::Comment { fields } = obj else {
+            panic!("comment expected");
+        };
+        assert_eq!(fields.on.event_id, "$pin");
+
+        for on in ["space", "unknown"] {
+            let mut table = Table::new();
+            table.insert(ON_FIELD.to_owned(), TomlValue::String(on.to_owned()));
+            assert!(resolve_on("comment", &mut table, &context).is_err());
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,358 @@
+use chrono::{NaiveDate, Utc};
+use matrix_sdk_base::ruma::RoomId;
+use serde_json::{Map, Value as JsonValue};
+use std::{collections::BTreeMap, time::SystemTime};
+use toml::{Table, Value as TomlValue};
+
+use super::Error;
+use crate::{
+    client::CoreClient,
+    events::UtcDateTime,
+    models::{ActerModel, AnyActerModel},
+    referencing::{IndexKey, ObjectListIndex, SectionIndex},
+    store::Store,
+};
+
+/// The input the exported objects are created in
+static SPACE_INPUT: &str = "space";
+/// The input creating the exported objects
+static USER_INPUT: &str = "main";
+
+/// Fields only meaningful for the exported instance, not for a blueprint
+static INSTANCE_FIELDS: [&str; 3] = ["progress_percent", "previous_instance", "ical_uid"];
+
+/// The object `type` in the template and the prefix of its key
+fn object_type(model: &AnyActerModel) -> Option<(&'static str, &'static str)> {
+    Some(match model {
+        AnyActerModel::TaskList(_) => ("task-list", "task_list"),
+        AnyActerModel::Task(_) => ("task", "task"),
+        AnyActerModel::Pin(_) => ("pin", "pin"),
+        AnyActerModel::CalendarEvent(_) => ("calendar-event", "event"),
+        AnyActerModel::NewsEntry(_) => ("news-entry", "news"),
+        AnyActerModel::Story(_) => ("story", "story"),
+        _ => return None,
+    })
+}
+
+/// Keep minijinja from interpreting template syntax in the exported content
+fn escape(text: &str) -> String {
+    if ["{{", "{%", "{#"].iter().any(|m| text.contains(m)) {
+        format!("{{% raw %}}{text}{{% endraw %}}")
+    } else {
+        text.to_owned()
+    }
+}
+
+/// `future()` arguments for the `seconds` from now, largest units first
+fn future_args(seconds: i64) -> String {
+    let mut rest = seconds.abs();
+    let sign = if seconds < 0 { "-" } else { "" };
+    let mut args = Vec::new();
+    for (unit, size) in [
+        ("days", 86_400),
+        ("hours", 3_600),
+        ("mins", 60),
+        ("secs", 1),
+    ] {
+        if rest >= size {
+            args.push(format!("{unit}={sign}{}", rest / size));
+            rest %= size;
+        }
+    }
+    args.join(", ")
+}
+
+/// Dates relative to when the template is executed, as they were relative
+/// to `now` when exporting
+fn relative_date(text: &str, now: &UtcDateTime) -> Option<String> {
+    if let Ok(date) = chrono::DateTime::parse_from_rfc3339(text) {
+        let seconds = date.with_timezone(&Utc).timestamp() - now.timestamp();
+        if seconds == 0 {
+            return Some("{{ now().as_rfc3339 }}".to_owned());
+        }
+        return Some(format!(
+            "{{{{ future({}).as_rfc3339 }}}}",
+            future_args(seconds)
+        ));
+    }
+    if text.len() == 10 {
+        if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+            let days = (date - now.date_naive()).num_days();
+            return Some(format!("{{{{ future(days={days}).as_rfc3339[:10] }}}}"));
+        }
+    }
+    None
+}
+
+/// Turns the fields of stored objects into template objects
+struct Exporter {
+    now: UtcDateTime,
+    /// the template key of each exported object by its event id
+    keys: BTreeMap<String, String>,
+}
+
+impl Exporter {
+    fn value(&self, value: JsonValue) -> Option<TomlValue> {
+        Some(match value {
+            JsonValue::Null => return None,
+            JsonValue::Bool(b) => TomlValue::Boolean(b),
+            JsonValue::Number(n) => match n.as_i64() {
+                Some(i) => TomlValue::Integer(i),
+                None => TomlValue::Float(n.as_f64()?),
+            },
+            JsonValue::String(s) => TomlValue::String(match self.keys.get(&s) {
+                Some(key) => format!("{{{{ {key}.id }}}}"),
+                None => relative_date(&s, &self.now).unwrap_or_else(|| escape(&s)),
+            }),
+            JsonValue::Array(items) => {
+                TomlValue::Array(items.into_iter().filter_map(|v| self.value(v)).collect())
+            }
+            JsonValue::Object(fields) => TomlValue::Table(self.table(fields)),
+        })
+    }
+
+    fn table(&self, fields: Map<String, JsonValue>) -> Table {
+        fields
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, self.value(value)?)))
+            .collect()
+    }
+
+    fn object(&self, obj_type: &str, model: &AnyActerModel) -> Result<Table, Error> {
+        let mut content = serde_json::to_value(model)
+            .ok()
+            .and_then(|v| match v {
+                // externally tagged: { "Task": { "inner": .., "meta": .. } }
+                JsonValue::Object(variant) => variant.into_iter().next(),
+                _ => None,
+            })
+            .and_then(|(_, mut v)| match v.get_mut("inner").map(JsonValue::take) {
+                Some(JsonValue::Object(inner)) => Some(inner),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                Error::Remap(
+                    model.event_id().to_string(),
+                    "content couldn’t be exported".to_owned(),
+                )
+            })?;
+        for field in INSTANCE_FIELDS {
+            content.remove(field);
+        }
+        let mut table = Table::new();
+        table.insert("type".to_owned(), TomlValue::String(obj_type.to_owned()));
+        table.extend(self.table(content));
+        Ok(table)
+    }
+}
+
+/// Render a `version = "0.1"` template recreating the `models` in a space
+/// given as input. Objects referencing each other are linked through their
+/// keys, dates are made relative to `now`. Anything but task lists, tasks,
+/// pins, calendar events, news and stories is skipped.
+pub fn render_template(
+    name: Option<String>,
+    models: Vec<AnyActerModel>,
+    now: UtcDateTime,
+) -> Result<String, Error> {
+    let mut models: Vec<_> = models
+        .into_iter()
+        .filter_map(|m| Some((object_type(&m)?, m)))
+        .collect();
+    // objects can only reference what was created before them
+    models.sort_by_key(|(_, m)| m.event_meta().origin_server_ts);
+
+    let mut counts: BTreeMap<&str, u32> = BTreeMap::new();
+    let keys: Vec<String> = models
+        .iter()
+        .map(|((_, prefix), _)| {
+            let count = counts.entry(prefix).or_default();
+            *count += 1;
+            format!("{prefix}_{count}")
+        })
+        .collect();
+    let exporter = Exporter {
+        now,
+        keys: models
+            .iter()
+            .zip(keys.iter())
+            .map(|((_, m), key)| (m.event_id().to_string(), key.clone()))
+            .collect(),
+    };
+
+    let mut objects = Table::new();
+    for (((obj_type, _), model), key) in models.iter().zip(keys) {
+        objects.insert(key, TomlValue::Table(exporter.object(obj_type, model)?));
+    }
+
+    let mut inputs = Table::new();
+    inputs.insert(
+        USER_INPUT.to_owned(),
+        toml::toml! {
+            type = "user"
+            is-default = true
+            required = true
+            description = "The user creating the objects"
+        }
+        .into(),
+    );
+    inputs.insert(
+        SPACE_INPUT.to_owned(),
+        toml::toml! {
+            type = "space"
+            is-default = true
+            required = true
+            description = "The space to create the objects in"
+        }
+        .into(),
+    );
+
+    let mut root = Table::new();
+    root.insert("version".to_owned(), TomlValue::String("0.1".to_owned()));
+    if let Some(name) = name {
+        root.insert("name".to_owned(), TomlValue::String(name));
+    }
+    root.insert("inputs".to_owned(), TomlValue::Table(inputs));
+    root.insert("objects".to_owned(), TomlValue::Table(objects));
+    Ok(toml::to_string_pretty(&root)?)
+}
+
+/// The task lists with their tasks, pins, calendar events, news and stories
+/// of the space
+pub async fn space_objects(store: &Store, room_id: &RoomId) -> Result<Vec<AnyActerModel>, Error> {
+    let list = |key: IndexKey| {
+        store
+            .get_list_inner(&key)
+            .map(Iterator::collect::<Vec<_>>)
+            .map_err(|e| Error::Remap(format!("{key:?}"), e.to_string()))
+    };
+    let mut models = Vec::new();
+    for section in [
+        SectionIndex::Tasks,
+        SectionIndex::Pins,
+        SectionIndex::Calendar,
+        SectionIndex::Boosts,
+        SectionIndex::Stories,
+    ] {
+        for model in list(IndexKey::RoomSection(room_id.to_owned(), section))? {
+            if let AnyActerModel::TaskList(task_list) = &model {
+                models.extend(list(IndexKey::ObjectList(
+                    task_list.event_id().to_owned(),
+                    ObjectListIndex::Tasks,
+                ))?);
+            }
+            models.push(model);
+        }
+    }
+    Ok(models)
+}
+
+impl CoreClient {
+    /// Export the objects of the space as template, to recreate them in
+    /// another space
+    pub async fn export_template(&self, room_id: &RoomId) -> Result<String, Error> {
+        let name = self
+            .client
+            .get_room(room_id)
+            .and_then(|room| room.cached_display_name())
+            .map(|name| name.to_string());
+        let models = space_objects(&self.store, room_id).await?;
+        render_template(name, models, UtcDateTime::from(SystemTime::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::Engine;
+    use anyhow::Result;
+    use serde_json::json;
+
+    fn model(variant: &str, event_id: &str, ts: u64, inner: JsonValue) -> AnyActerModel {
+        serde_json::from_value(json!({
+            variant: {
+                "inner": inner,
+                "meta": {
+                    "event_id": event_id,
+                    "sender": "@test:example.org",
+                    "origin_server_ts": ts,
+                    "room_id": "!room:example.org",
+                },
+            }
+        }))
+        .expect("model parses")
+    }
+
+    #[test]
+    fn exports_references_and_relative_dates() -> Result<()> {
+        let now = UtcDateTime::from_timestamp(1_714_550_400, 0).expect("valid"); // 2024-05-01T08:00Z
+        let models = vec![
+            model(
+                "Task",
+                "$task",
+                2,
+                json!({
+                    "title": "Water {{ plants }}",
+                    "m.relates_to": { "rel_type": "global.acter.dev.belongs_to", "event_id": "$list" },
+                    "due_date": "2024-05-04",
+                    "progress_percent": 50,
+                }),
+            ),
+            model("TaskList", "$list", 1, json!({ "name": "Garden" })),
+            model(
+                "CalendarEvent",
+                "$event",
+                3,
+                json!({
+                    "title": "Planting",
+                    "utc_start": "2024-05-02T10:30:00Z",
+                    "utc_end": "2024-04-30T08:00:00Z",
+                    "ical_uid": "abc",
+                }),
+            ),
+        ];
+        let template = render_template(Some("Garden".to_owned()), models, now)?;
+        let parsed: Table = toml::from_str(&template)?;
+        let objects = parsed["objects"].as_table().expect("objects");
+
+        let keys: Vec<&str> = objects.keys().map(String::as_str).collect();
+        assert_eq!(
+            keys,
+            vec!["task_list_1", "task_1", "event_1"],
+            "in creation order"
+        );
+
+        let task = objects["task_1"].as_table().expect("task");
+        assert_eq!(task["type"].as_str(), Some("task"));
+        assert_eq!(
+            task["m.relates_to"]["event_id"].as_str(),
+            Some("{{ task_list_1.id }}")
+        );
+        assert_eq!(
+            task["title"].as_str(),
+            Some("{% raw %}Water {{ plants }}{% endraw %}")
+        );
+        assert_eq!(
+            task["due_date"].as_str(),
+            Some("{{ future(days=3).as_rfc3339[:10] }}")
+        );
+        assert!(task.get("progress_percent").is_none());
+
+        let event = objects["event_1"].as_table().expect("event");
+        assert_eq!(
+            event["utc_start"].as_str(),
+            Some("{{ future(days=1, hours=2, mins=30).as_rfc3339 }}")
+        );
+        assert_eq!(
+            event["utc_end"].as_str(),
+            Some("{{ future(days=-1).as_rfc3339 }}")
+        );
+        assert!(event.get("ical_uid").is_none());
+
+        let engine = Engine::with_template(&template)?;
+        let inputs = engine.requested_inputs();
+        assert!(inputs["main"].is_user() && inputs["main"].is_default());
+        assert!(inputs["space"].is_space() && inputs["space"].is_default());
+        Ok(())
+    }
+}
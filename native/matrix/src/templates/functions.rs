@@ -10,67 +10,50 @@ pub fn now() -> Value {
     Value::from_object(UtcDateTimeValue::new(UtcDateTime::from(SystemTime::now())))
 }
 
+/// the integer given for `name`, either as number or as string
+fn int_arg(kwargs: &Value, name: &str) -> Result<Option<i64>, Error> {
+    let Ok(value) = kwargs.get_attr(name) else {
+        return Ok(None);
+    };
+    if value.is_undefined() || value.is_none() {
+        return Ok(None);
+    }
+    let parsed = match value.as_str() {
+        Some(s) => s.parse::<i64>().ok(),
+        None => value.as_i64(),
+    };
+    parsed.map(Some).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidOperation,
+            format!("{name} must be a whole number, got {value}"),
+        )
+    })
+}
+
 /// create a date in the future add `days`, `weeks`, `hours`, `mins`, `secs` (or any combinations of them) to create
-/// a date in the future. Example:
+/// a date in the future. Negative values go into the past. Example:
 /// ```no_compile
 ///     {{ future(weeks=3, days=4, hours=20, mins=10)}}
 /// ```
 pub fn future(kwargs: Value) -> Result<Value, Error> {
     let date = UtcDateTime::from(SystemTime::now());
     let mut duration = Duration::zero();
-    if let Some(Ok(days)) = kwargs
-        .get_attr("days")
-        .ok()
-        .filter(|x| !x.is_undefined())
-        .and_then(|f| f.as_str().map(str::parse::<i64>))
-    {
-        duration = duration
-            .checked_add(&Duration::days(days))
-            .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, "days couldn’t be added"))?;
-    }
-
-    if let Some(Ok(weeks)) = kwargs
-        .get_attr("weeks")
-        .ok()
-        .filter(|x| !x.is_undefined())
-        .and_then(|f| f.as_str().map(str::parse::<i64>))
-    {
-        duration = duration
-            .checked_add(&Duration::weeks(weeks))
-            .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, "weeks couldn’t be added"))?;
-    }
-
-    if let Some(Ok(hours)) = kwargs
-        .get_attr("hours")
-        .ok()
-        .filter(|x| !x.is_undefined())
-        .and_then(|f| f.as_str().map(str::parse::<i64>))
-    {
-        duration = duration
-            .checked_add(&Duration::hours(hours))
-            .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, "hours couldn’t be added"))?;
-    }
-
-    if let Some(Ok(minutes)) = kwargs
-        .get_attr("mins")
-        .ok()
-        .filter(|x| !x.is_undefined())
-        .and_then(|f| f.as_str().map(str::parse::<i64>))
-    {
-        duration = duration
-            .checked_add(&Duration::minutes(minutes))
-            .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, "minutes couldn’t be added"))?;
-    }
-
-    if let Some(Ok(seconds)) = kwargs
-        .get_attr("secs")
-        .ok()
-        .filter(|x| !x.is_undefined())
-        .and_then(|f| f.as_str().map(str::parse::<i64>))
-    {
-        duration = duration
-            .checked_add(&Duration::seconds(seconds))
-            .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, "seconds couldn’t be added"))?;
+    let units: [(&str, fn(i64) -> Duration); 5] = [
+        ("days", Duration::days),
+        ("weeks", Duration::weeks),
+        ("hours", Duration::hours),
+        ("mins", Duration::minutes),
+        ("secs", Duration::seconds),
+    ];
+    for (name, unit) in units {
+        if let Some(amount) = int_arg(&kwargs, name)? {
+            duration = duration.checked_add(&unit(amount)).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("{name} couldn’t be added"),
+                )
+            })?;
+        }
     }
 
     let val = Value::from_object(UtcDateTimeValue::new(date + duration));
@@ -0,0 +1,124 @@
+use matrix_sdk_base::ruma::{EventId, RoomId};
+use serde::{Deserialize, Serialize};
+
+use super::{Engine, Error, Problem};
+
+/// An object created by executing a template
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreatedObject {
+    /// the key of the object in the template
+    pub key: String,
+    pub obj_type: String,
+    /// the room id for spaces and chats, the event id otherwise
+    pub id: String,
+    pub room_id: String,
+    /// the key of the user who created it
+    pub user: String,
+    /// whether this space became the default space
+    #[serde(default)]
+    pub is_default: bool,
+    /// the `m.space.child` event adding this space or chat to its parent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub child_of: Option<SpaceChildLink>,
+}
+
+/// A space or chat having been added as child to a space
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpaceChildLink {
+    pub parent_id: String,
+    pub event_id: String,
+}
+
+/// What an execution created, in order. Persist it to resume a partially
+/// failed run or to roll it back.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionRecord {
+    created: Vec<CreatedObject>,
+}
+
+impl ExecutionRecord {
+    pub fn created(&self) -> &[CreatedObject] {
+        &self.created
+    }
+
+    pub fn get(&self, key: &str) -> Option<&CreatedObject> {
+        self.created.iter().find(|c| c.key == key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.created.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, created: CreatedObject) {
+        self.created.push(created);
+    }
+
+    pub(crate) fn set_child_of(&mut self, key: &str, link: SpaceChildLink) {
+        if let Some(created) = self.created.iter_mut().find(|c| c.key == key) {
+            created.child_of = Some(link);
+        }
+    }
+}
+
+impl Engine {
+    /// Undo what the `record` says was created, latest first: redact the
+    /// objects, unlink the spaces and chats from their parents and leave them. Keeps going on failures and reports
+    /// all of them at the end.
+    pub async fn rollback(&self, record: &ExecutionRecord) -> Result<(), Error> {
+        let mut problems = Vec::new();
+        for created in record.created().iter().rev() {
+            if let Err(error) = self.undo(created).await {
+                problems.push(Problem::new(format!("objects.{}", created.key), error));
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Problems(problems))
+        }
+    }
+
+    async fn undo(&self, created: &CreatedObject) -> Result<(), Error> {
+        let client = self.users.get(&created.user).ok_or_else(|| {
+            Error::UnknownReference("user".to_owned(), created.user.clone(), created.key.clone())
+        })?;
+        let remap =
+            |e: &dyn ToString| Error::Remap(format!("{} rollback", created.key), e.to_string());
+        let room_id = RoomId::parse(&created.room_id).map_err(|e| remap(&e))?;
+        let room = client.client().get_room(&room_id).ok_or_else(|| {
+            Error::UnknownReference(
+                "room".to_owned(),
+                created.room_id.clone(),
+                created.key.clone(),
+            )
+        })?;
+        if matches!(created.obj_type.as_str(), "space" | "chat") {
+            if let Some(link) = &created.child_of {
+                let parent_id = RoomId::parse(&link.parent_id).map_err(|e| remap(&e))?;
+                let parent = client.client().get_room(&parent_id).ok_or_else(|| {
+                    Error::UnknownReference(
+                        "room".to_owned(),
+                        link.parent_id.clone(),
+                        created.key.clone(),
+                    )
+                })?;
+                let event_id = EventId::parse(&link.event_id).map_err(|e| remap(&e))?;
+                parent
+                    .redact(&event_id, Some("template execution rolled back"), None)
+                    .await
+                    .map_err(|e| remap(&e))?;
+            }
+            room.leave().await.map_err(|e| remap(&e))?;
+        } else {
+            let event_id = EventId::parse(&created.id).map_err(|e| remap(&e))?;
+            room.redact(&event_id, Some("template execution rolled back"), None)
+                .await
+                .map_err(|e| remap(&e))?;
+        }
+        Ok(())
+    }
+}
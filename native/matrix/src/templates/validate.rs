@@ -0,0 +1,598 @@
+use minijinja::{context, Environment, UndefinedBehavior};
+use std::{collections::BTreeSet, fmt, time::SystemTime};
+use toml::{Table, Value as TomlValue};
+
+use super::{
+    environment, execute_value_template, resolve_on, values::ObjRef, values::UtcDateTimeValue,
+    Context, Engine, Error, Input, Object, ObjectInner, Value,
+};
+use crate::events::UtcDateTime;
+
+/// Names available to all templates besides the context
+static GLOBALS: [&str; 6] = ["now", "future", "range", "dict", "namespace", "debug"];
+
+/// Fields of an object that aren’t part of its content
+static OBJECT_FIELDS: [&str; 6] = ["type", "in", "room", "as", "user", "on"];
+
+/// The fields a space can be created with
+static SPACE_FIELDS: [&str; 11] = [
+    "is-default",
+    "suggested",
+    "name",
+    "visibility",
+    "join_rule",
+    "invites",
+    "alias",
+    "topic",
+    "avatar_uri",
+    "parent",
+    "permissions",
+];
+
+/// The fields a chat can be created with
+static CHAT_FIELDS: [&str; 6] = ["suggested", "name", "invites", "alias", "topic", "parent"];
+
+/// A problem found in a template, at its location in the TOML
+#[derive(Debug)]
+pub struct Problem {
+    /// dotted path, e.g. `objects.task_1.title`
+    pub location: String,
+    pub error: Error,
+}
+
+impl Problem {
+    pub(crate) fn new(location: String, error: Error) -> Self {
+        Problem { location, error }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.error)
+    }
+}
+
+/// What objects will resolve to once executed
+fn placeholder_ref(key: &str, obj_type: &str) -> Value {
+    let id = if matches!(obj_type, "space" | "chat") {
+        format!("!dry-run-{key}:localhost")
+    } else {
+        format!("$dry-run-{key}")
+    };
+    Value::from_object(ObjRef::new(id, obj_type.to_owned()))
+}
+
+/// A value of the right kind for inputs not given
+fn placeholder_input(name: &str, input: &Input) -> Value {
+    match input {
+        Input::Text { .. } => Value::from(String::new()),
+        Input::User { .. } => context! {
+            user_id => format!("@dry-run-{name}:localhost"),
+            display_name => name,
+        },
+        Input::Space { .. } => placeholder_ref(name, "space"),
+        Input::Date { .. } => {
+            Value::from_object(UtcDateTimeValue::new(UtcDateTime::from(SystemTime::now())))
+        }
+        Input::Choice { options, .. } => Value::from(options.first().cloned().unwrap_or_default()),
+        Input::Number { min, .. } => Value::from(min.unwrap_or_default()),
+    }
+}
+
+/// The field name of a serde "missing field `name`" error
+fn missing_field(error: &toml::de::Error) -> Option<String> {
+    let message = error.message();
+    let (_, rest) = message.split_once("missing field `")?;
+    rest.split_once('`').map(|(field, _)| field.to_owned())
+}
+
+fn is_empty(value: &TomlValue) -> bool {
+    match value {
+        TomlValue::String(s) => s.is_empty(),
+        TomlValue::Array(a) => a.is_empty(),
+        TomlValue::Table(t) => t.is_empty(),
+        TomlValue::Boolean(b) => !b,
+        _ => false,
+    }
+}
+
+/// The fields the content of the object was read from
+fn known_fields(obj: &ObjectInner) -> Option<BTreeSet<String>> {
+    let content = match obj {
+        ObjectInner::Space { .. } => {
+            return Some(SPACE_FIELDS.iter().map(|f| f.to_string()).collect())
+        }
+        ObjectInner::Chat { .. } => {
+            return Some(CHAT_FIELDS.iter().map(|f| f.to_string()).collect())
+        }
+        ObjectInner::Categories { fields, .. } => {
+            let mut known = BTreeSet::from(["category-type".to_owned()]);
+            if let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(fields) {
+                known.extend(fields.into_iter().map(|(k, _)| k));
+            }
+            return Some(known);
+        }
+        ObjectInner::TaskList { fields } => serde_json::to_value(fields),
+        ObjectInner::Task { fields } => serde_json::to_value(fields),
+        ObjectInner::Pin { fields } => serde_json::to_value(fields),
+        ObjectInner::CalendarEvent { fields } => serde_json::to_value(fields),
+        ObjectInner::NewsEntry { fields } => serde_json::to_value(fields),
+        ObjectInner::Story { fields } => serde_json::to_value(fields),
+        ObjectInner::Comment { fields } => serde_json::to_value(fields),
+        ObjectInner::Attachment { fields } => serde_json::to_value(fields),
+        ObjectInner::Rsvp { fields } => serde_json::to_value(fields),
+    };
+    match content.ok()? {
+        serde_json::Value::Object(fields) => Some(fields.into_iter().map(|(k, _)| k).collect()),
+        _ => None,
+    }
+}
+
+/// Walks the template like an execution would, without sending anything
+struct Validator<'a> {
+    env: Environment<'static>,
+    context: Context,
+    users: BTreeSet<String>,
+    spaces: BTreeSet<String>,
+    default_user: Option<String>,
+    default_space: Option<String>,
+    problems: Vec<Problem>,
+    engine: &'a Engine,
+}
+
+impl Validator<'_> {
+    fn problem(&mut self, location: String, error: Error) {
+        self.problems.push(Problem::new(location, error));
+    }
+
+    fn check_inputs(&mut self) {
+        let mut missing = Vec::new();
+        for (name, input) in self.engine.requested_inputs() {
+            let location = format!("inputs.{name}");
+            if !self.context.contains_key(name) {
+                let value = match input.default_value(name) {
+                    Ok(Some(value)) => value,
+                    Ok(None) => {
+                        if input.is_required() {
+                            missing.push(name.clone());
+                        }
+                        placeholder_input(name, input)
+                    }
+                    Err(error) => {
+                        self.problem(location.clone(), error);
+                        placeholder_input(name, input)
+                    }
+                };
+                self.context.insert(name.clone(), value);
+            }
+            if input.is_user() {
+                self.users.insert(name.clone());
+                if input.is_default() && self.default_user.replace(name.clone()).is_some() {
+                    self.problem(location, Error::TooManyDefaults("User".to_owned()));
+                }
+            } else if input.is_space() {
+                self.spaces.insert(name.clone());
+                if input.is_default() && self.default_space.replace(name.clone()).is_some() {
+                    self.problem(location, Error::TooManyDefaults("Space".to_owned()));
+                }
+            }
+        }
+        if !missing.is_empty() {
+            self.problem("inputs".to_owned(), Error::MissingInputs(missing));
+        }
+    }
+
+    /// Check every template string only uses what is known by then
+    fn check_references(&mut self, key: &str, location: &str, value: &TomlValue) -> bool {
+        match value {
+            TomlValue::String(s) if s.contains("{{") || s.contains("{%") => {
+                let variables = self
+                    .env
+                    .template_from_str(s)
+                    .map(|tmpl| tmpl.undeclared_variables(false));
+                let unknown: Vec<String> = match variables {
+                    Ok(variables) => variables
+                        .into_iter()
+                        .filter(|var| {
+                            !self.context.contains_key(var) && !GLOBALS.contains(&var.as_str())
+                        })
+                        .collect(),
+                    Err(error) => {
+                        self.problem(location.to_owned(), error.into());
+                        return false;
+                    }
+                };
+                let found = unknown.is_empty();
+                for var in unknown {
+                    self.problem(
+                        location.to_owned(),
+                        Error::UnknownReference("variable".to_owned(), var, key.to_owned()),
+                    );
+                }
+                found
+            }
+            TomlValue::Array(items) => {
+                let mut found = true;
+                for (idx, v) in items.iter().enumerate() {
+                    found &= self.check_references(key, &format!("{location}[{idx}]"), v);
+                }
+                found
+            }
+            TomlValue::Table(fields) => {
+                let mut found = true;
+                for (field, v) in fields.iter() {
+                    found &= self.check_references(key, &format!("{location}.{field}"), v);
+                }
+                found
+            }
+            _ => true,
+        }
+    }
+
+    /// Parse the rendered object, collecting all missing fields at once
+    fn parse(&mut self, key: &str, location: &str, mut table: Table) -> Option<Object> {
+        let mut missing = Vec::new();
+        let parsed = loop {
+            match table.clone().try_into::<Object>() {
+                Ok(obj) => break Some(obj),
+                Err(error) => match missing_field(&error) {
+                    Some(field) if !missing.contains(&field) => {
+                        // fill in to find the next one
+                        table.insert(field.clone(), TomlValue::String(String::new()));
+                        missing.push(field);
+                    }
+                    _ => {
+                        if missing.is_empty() {
+                            self.problem(location.to_owned(), error.into());
+                        }
+                        break None;
+                    }
+                },
+            }
+        };
+        if !missing.is_empty() {
+            self.problem(
+                location.to_owned(),
+                Error::MissingFields(key.to_owned(), missing),
+            );
+        }
+        // keep checking with the missing fields filled in
+        let obj = parsed?;
+        if let Some(known) = known_fields(&obj.obj) {
+            for (field, value) in table.iter() {
+                if !known.contains(field)
+                    && !OBJECT_FIELDS.contains(&field.as_str())
+                    && !is_empty(value)
+                {
+                    self.problem(
+                        format!("{location}.{field}"),
+                        Error::UnknownField(field.clone()),
+                    );
+                }
+            }
+        }
+        Some(obj)
+    }
+
+    fn check_object(&mut self, key: &str, fields: &Table) {
+        let location = format!("objects.{key}");
+        let obj_type = fields
+            .get("type")
+            .and_then(TomlValue::as_str)
+            .unwrap_or_default()
+            .to_owned();
+        // known to later objects, even if broken, to not report them again
+        let placeholder = placeholder_ref(key, &obj_type);
+
+        if !self.check_references(key, &location, &TomlValue::Table(fields.clone())) {
+            self.context.insert(key.to_owned(), placeholder);
+            return;
+        }
+        let mut rendered = match execute_value_template(
+            TomlValue::Table(fields.clone()),
+            &self.env,
+            &self.context,
+        ) {
+            Ok(TomlValue::Table(t)) => t,
+            Ok(_) => unreachable!("We always get back a table after sending in a table."),
+            Err(error) => {
+                self.problem(location, error);
+                self.context.insert(key.to_owned(), placeholder);
+                return;
+            }
+        };
+        self.context.insert(key.to_owned(), placeholder);
+        if let Err(error) = resolve_on(key, &mut rendered, &self.context) {
+            self.problem(format!("{location}.on"), error);
+            return;
+        }
+        let Some(Object { room, user, obj }) = self.parse(key, &location, rendered) else {
+            return;
+        };
+
+        match user.or_else(|| self.default_user.clone()) {
+            Some(user) if !self.users.contains(&user) => self.problem(
+                format!("{location}.as"),
+                Error::UnknownReference("user".to_owned(), user, key.to_owned()),
+            ),
+            None => self.problem(
+                location.clone(),
+                Error::NoDefaultSet("user".to_owned(), key.to_owned()),
+            ),
+            _ => {}
+        }
+
+        // spaces and chats don’t need to be in another space
+        let room = match obj {
+            ObjectInner::Space { is_default, .. } => {
+                self.spaces.insert(key.to_owned());
+                if is_default && self.default_space.replace(key.to_owned()).is_some() {
+                    self.problem(location.clone(), Error::TooManyDefaults("Space".to_owned()));
+                }
+                match room {
+                    Some(room) => Some(room),
+                    None => return,
+                }
+            }
+            ObjectInner::Chat { .. } => match room.or_else(|| self.default_space.clone()) {
+                Some(room) => Some(room),
+                None => return,
+            },
+            _ => room.or_else(|| self.default_space.clone()),
+        };
+        match room {
+            Some(room) if !self.spaces.contains(&room) => self.problem(
+                format!("{location}.in"),
+                Error::UnknownReference("room".to_owned(), room, key.to_owned()),
+            ),
+            None => self.problem(
+                location,
+                Error::NoDefaultSet("room".to_owned(), key.to_owned()),
+            ),
+            _ => {}
+        }
+    }
+}
+
+impl Engine {
+    /// Check the template without sending anything: inputs, references
+    /// between objects and the fields of each object. Reports all problems
+    /// found at once, with their location in the TOML.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut env = environment();
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        let mut validator = Validator {
+            env,
+            context: self.context.clone(),
+            users: self.users.keys().cloned().collect(),
+            spaces: BTreeSet::new(),
+            default_user: None,
+            default_space: None,
+            problems: Vec::new(),
+            engine: self,
+        };
+        validator.check_inputs();
+        for (key, fields) in self.root.objects.iter() {
+            validator.check_object(key, fields);
+        }
+
+        if validator.problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Problems(validator.problems))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    const BROKEN_TMPL: &str = r#"
+version = "0.1"
+
+[inputs]
+main = { type = "user", is-default = true, required = true }
+space = { type = "space", is-default = true, required = true }
+
+[objects.list]
+type = "task-list"
+name = "Chores"
+
+[objects.typo]
+type = "task"
+titel = "Water the plants"
+"m.relates_to" = { rel_type = "global.acter.dev.belongs_to", event_id = "{{ list.id }}" }
+
+[objects.early]
+type = "pin"
+title = "{{ late.id }}"
+
+[objects.late]
+type = "pin"
+title = "Late"
+in = "nowhere"
+"#;
+
+    #[test]
+    fn reports_all_problems() -> Result<()> {
+        let engine = Engine::with_template(BROKEN_TMPL)?;
+        let Err(Error::Problems(problems)) = engine.validate() else {
+            panic!("template is broken");
+        };
+        let found: Vec<(&str, String)> = problems
+            .iter()
+            .map(|p| (p.location.as_str(), p.error.to_string()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "inputs",
+                    Error::MissingInputs(vec!["main".to_owned(), "space".to_owned()]).to_string()
+                ),
+                (
+                    "objects.typo",
+                    Error::MissingFields("typo".to_owned(), vec!["title".to_owned()]).to_string()
+                ),
+                (
+                    "objects.typo.titel",
+                    Error::UnknownField("titel".to_owned()).to_string()
+                ),
+                (
+                    "objects.early.title",
+                    Error::UnknownReference(
+                        "variable".to_owned(),
+                        "late".to_owned(),
+                        "early".to_owned()
+                    )
+                    .to_string()
+                ),
+                (
+                    "objects.late.in",
+                    Error::UnknownReference(
+                        "room".to_owned(),
+                        "nowhere".to_owned(),
+                        "late".to_owned()
+                    )
+                    .to_string()
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn valid_without_objects() -> Result<()> {
+        let mut engine = Engine::with_template(
+            r#"
+version = "0.1"
+
+[inputs]
+space = { type = "space", required = true }
+seats = { type = "number", default = 3 }
+
+[objects]
+"#,
+        )?;
+        assert!(engine.validate().is_err());
+        engine.add_ref(
+            "space".to_owned(),
+            "space".to_owned(),
+            "!space:example.org".to_owned(),
+        )?;
+        engine.validate()?;
+        Ok(())
+    }
+
+    const CHILDREN_TMPL: &str = r#"
+version = "0.1"
+
+[inputs]
+main = { type = "user", is-default = true, required = true }
+space = { type = "space", is-default = true, required = true }
+
+[objects.team]
+type = "space"
+name = "Team"
+in = "space"
+suggested = true
+
+[objects.lobby]
+type = "chat"
+name = "Lobby"
+in = "team"
+
+[objects.welcome]
+type = "pin"
+title = "Welcome"
+
+[objects.hello]
+type = "comment"
+on = "welcome"
+content = { body = "Say hi!" }
+
+[objects.website]
+type = "attachment"
+on = "welcome"
+content = { type = "Link", link = "https://acter.global", name = "Website" }
+
+[objects.kickoff]
+type = "calendar-event"
+title = "Kickoff"
+utc_start = "{{ future(days=1).as_rfc3339 }}"
+utc_end = "{{ future(days=1, hours=1).as_rfc3339 }}"
+
+[objects.going]
+type = "rsvp"
+on = "kickoff"
+status = { type = "yes" }
+
+[objects.chat_categories]
+type = "categories"
+category-type = "chats"
+categories = [{ title = "General", entries = ["{{ lobby.id }}"] }]
+"#;
+
+    fn problems(engine: &Engine) -> Vec<(String, String)> {
+        match engine.validate() {
+            Ok(()) => vec![],
+            Err(Error::Problems(problems)) => problems
+                .into_iter()
+                .map(|p| (p.location, p.error.to_string()))
+                .collect(),
+            Err(error) => panic!("unexpected error: {error}"),
+        }
+    }
+
+    #[test]
+    fn children_and_cross_references() -> Result<()> {
+        let mut engine = Engine::with_template(CHILDREN_TMPL)?;
+        engine.add_ref(
+            "space".to_owned(),
+            "space".to_owned(),
+            "!space:example.org".to_owned(),
+        )?;
+        // only the user, which needs a client, is missing
+        assert_eq!(
+            problems(&engine),
+            vec![(
+                "inputs".to_owned(),
+                Error::MissingInputs(vec!["main".to_owned()]).to_string()
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn broken_cross_references() -> Result<()> {
+        let tmpl = CHILDREN_TMPL
+            .replace(r#"on = "kickoff""#, r#"on = "kick-off""#)
+            .replace(
+                r#"content = { body = "Say hi!" }"#,
+                r#"content = { body = "Say hi!" }
+"m.relates_to" = { rel_type = "global.acter.dev.belongs_to", event_id = "$pin" }"#,
+            )
+            .replace(r#"in = "team""#, r#"in = "welcome""#)
+            .replace(r#"on = "welcome""#, r#"on = "lobby""#);
+        let mut engine = Engine::with_template(&tmpl)?;
+        engine.add_ref(
+            "space".to_owned(),
+            "space".to_owned(),
+            "!space:example.org".to_owned(),
+        )?;
+        let locations: Vec<String> = problems(&engine).into_iter().map(|(l, _)| l).collect();
+        assert_eq!(
+            locations,
+            vec![
+                "inputs",
+                "objects.lobby.in",
+                "objects.hello.on",
+                "objects.website.on",
+                "objects.going.on",
+            ]
+        );
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use minijinja::value::{Enumerator, Object, Value};
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
 use super::Error;
 use crate::{client::CoreClient, events::UtcDateTime};
@@ -98,6 +98,11 @@ impl Object for UtcDateTimeValue {
     }
 
     fn enumerate(self: &Arc<Self>) -> Enumerator {
-        Enumerator::Str(&["as_timestamp"])
+        Enumerator::Str(&["as_timestamp", "as_rfc3339"])
+    }
+
+    /// rendered as is, the date can be used for date fields directly
+    fn render(self: &Arc<Self>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.date.to_rfc3339())
     }
 }
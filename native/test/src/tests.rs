@@ -23,6 +23,7 @@ mod reply;
 mod room;
 mod room_updates;
 mod rsvp;
+mod search;
 mod spaces;
 mod stories;
 mod super_invites;
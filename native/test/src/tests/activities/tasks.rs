@@ -425,3 +425,73 @@ async fn task_update_due_date() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn task_completion_in_digest() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (user, sync_state, _engine) =
+        random_user_with_template("tasks_completion_digest", TMPL).await?;
+    sync_state.await_has_synced_history().await?;
+
+    // wait for sync to catch up
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(30);
+    let fetcher_client = user.clone();
+    let task_lists = Retry::spawn(retry_strategy.clone(), move || {
+        let client = fetcher_client.clone();
+        async move {
+            let task_lists = client.task_lists().await?;
+            if task_lists.len() != 1 {
+                bail!("not all task_lists found");
+            }
+            Ok(task_lists)
+        }
+    })
+    .await?;
+    let task_list = task_lists.first().unwrap();
+
+    let target_list = task_list.clone();
+    let tasks = Retry::spawn(retry_strategy.clone(), move || {
+        let task_list = target_list.clone();
+        async move {
+            let tasks = task_list.tasks().await?;
+            if tasks.len() != 1 {
+                bail!("not all tasks found");
+            }
+            Ok(tasks)
+        }
+    })
+    .await?;
+    let task = tasks.first().unwrap();
+
+    let digest = user.activity_digest(0, 7, None).await?;
+    assert_eq!(digest.tasks_completed_count(), 0);
+    assert!(digest.activities_count() > 0);
+
+    task.update_builder()?.mark_done().send().await?;
+
+    let fetcher_client = user.clone();
+    let digest = Retry::spawn(retry_strategy, move || {
+        let client = fetcher_client.clone();
+        async move {
+            let digest = client
+                .activity_digest(0, 7, Some("https://app.example.org/#".to_owned()))
+                .await?;
+            if digest.tasks_completed_count() != 1 {
+                bail!("task completion not in digest yet");
+            }
+            Ok(digest)
+        }
+    })
+    .await?;
+
+    assert!(!digest.is_empty());
+    assert!(digest
+        .text()
+        .contains("Tasks completed\n  - Check the weather"));
+    assert!(digest
+        .markdown()
+        .contains("- [Check the weather](<https://app.example.org/#/tasks/"));
+    assert!(digest.html().contains("<h2>Tasks completed</h2>"));
+
+    Ok(())
+}
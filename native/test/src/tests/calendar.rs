@@ -1,5 +1,8 @@
+use acter_matrix::events::rsvp::RsvpStatus;
 use anyhow::{bail, Result};
 use chrono::{Duration, Utc};
+use std::io::Write;
+use tempfile::NamedTempFile;
 use tokio_retry::{
     strategy::{jitter, FibonacciBackoff},
     Retry,
@@ -545,3 +548,253 @@ async fn calendar_event_format() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn calendar_event_recurring() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (users, _sync_states, space_id, _engine) =
+        random_users_with_random_space_under_template("calendar_recurring", 1, TMPL).await?;
+
+    let user = users[0].clone();
+
+    // wait for sync to catch up
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(30);
+    let space = Retry::spawn(retry_strategy, || async {
+        user.space(space_id.to_string()).await
+    })
+    .await?;
+
+    let now = Utc::now();
+    let start = now + Duration::days(1);
+    let end = start + Duration::hours(1);
+
+    space
+        .calendar_event_draft()?
+        .title("Weekly sync".to_owned())
+        .utc_start_from_rfc3339(start.to_rfc3339())?
+        .utc_end_from_rfc3339(end.to_rfc3339())?
+        .rrule("FREQ=WEEKLY;COUNT=4".to_owned())?
+        .send()
+        .await?;
+
+    // wait for sync to catch up
+    let retry_strategy = FibonacciBackoff::from_millis(500).map(jitter).take(10);
+    let cal_events = Retry::spawn(retry_strategy.clone(), || async {
+        let cal_events = space.calendar_events().await?;
+        if cal_events.len() != 1 {
+            bail!("not all calendar_events found");
+        }
+        Ok(cal_events)
+    })
+    .await?;
+    let main_event = cal_events.first().expect("main event should be available");
+    assert!(main_event.is_recurring());
+    assert_eq!(main_event.rrule().as_deref(), Some("FREQ=WEEKLY;COUNT=4"));
+
+    let window_end = (now + Duration::days(60)).to_rfc3339();
+    let occurrences = main_event.occurrences(now.to_rfc3339(), window_end.clone())?;
+    assert_eq!(occurrences.len(), 4);
+    assert!(occurrences.iter().all(|o| !o.is_overridden()));
+    let second = occurrences[1].recurrence_id_rfc3339();
+
+    // change only the second occurrence
+    let subscriber = main_event.subscribe();
+    main_event
+        .update_builder()?
+        .occurrence_from_rfc3339(second.clone())?
+        .title("Weekly sync - special".to_owned())
+        .send()
+        .await?;
+
+    Retry::spawn(retry_strategy.clone(), || async {
+        if subscriber.is_empty() {
+            bail!("not been alerted to reload");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let edited_event = Retry::spawn(retry_strategy.clone(), || async {
+        main_event.refresh().await
+    })
+    .await?;
+    assert_eq!(edited_event.title(), "Weekly sync");
+    let occurrences = edited_event.occurrences(now.to_rfc3339(), window_end.clone())?;
+    assert_eq!(occurrences.len(), 4);
+    assert_eq!(occurrences[1].title(), "Weekly sync - special");
+    assert!(occurrences[1].is_overridden());
+    assert_eq!(occurrences[2].title(), "Weekly sync");
+
+    // only existing occurrences can be changed or removed
+    let between = (start + Duration::hours(2)).to_rfc3339();
+    let mut updater = edited_event.update_builder()?;
+    assert!(updater.occurrence_from_rfc3339(between.clone()).is_err());
+    assert!(updater.add_exdate_from_rfc3339(between).is_err());
+    let fourth = occurrences[3].recurrence_id_rfc3339();
+    updater.add_exdate_from_rfc3339(fourth.clone())?;
+    assert!(updater.add_exdate_from_rfc3339(fourth.clone()).is_err());
+    assert!(updater.occurrence_from_rfc3339(fourth).is_err());
+
+    // RSVP to the second occurrence only
+    let rsvp_manager = edited_event.rsvps().await?;
+    let rsvp_listener = rsvp_manager.subscribe();
+    rsvp_manager
+        .rsvp_draft()?
+        .status("yes".to_owned())
+        .occurrence_from_rfc3339(second.clone())?
+        .send()
+        .await?;
+
+    Retry::spawn(retry_strategy.clone(), || async {
+        if rsvp_listener.is_empty() {
+            bail!("rsvp not received");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let rsvp_manager = edited_event.rsvps().await?;
+    let for_occurrence = rsvp_manager.responded_by_me_for_occurrence(second).await?;
+    assert_eq!(for_occurrence.status(), Some(RsvpStatus::Yes));
+    let for_series = rsvp_manager.responded_by_me().await?;
+    assert_eq!(for_series.status(), None);
+
+    Ok(())
+}
+
+const ICS: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//acter//test//EN\r
+BEGIN:VEVENT\r
+UID:planning@example.org\r
+DTSTAMP:20240101T000000Z\r
+SUMMARY:Planning\r
+DTSTART:20300108T090000Z\r
+DTEND:20300108T100000Z\r
+LOCATION:Town hall\r
+CATEGORIES:work\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:holiday@example.org\r
+DTSTAMP:20240101T000000Z\r
+SUMMARY:Holiday\r
+DTSTART;VALUE=DATE:20300301\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+#[tokio::test]
+async fn calendar_event_ical_import() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (users, _sync_states, space_id, _engine) =
+        random_users_with_random_space_under_template("calendar_ical_import", 1, TMPL).await?;
+
+    let user = users[0].clone();
+
+    // wait for sync to catch up
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(30);
+    let space = Retry::spawn(retry_strategy, || async {
+        user.space(space_id.to_string()).await
+    })
+    .await?;
+
+    let mut tmp_file = NamedTempFile::new()?;
+    tmp_file.as_file_mut().write_all(ICS.as_bytes())?;
+    let file_path = tmp_file.path().to_string_lossy().to_string();
+
+    // a dry run doesn't create anything
+    let report = space.import_ical(file_path.clone(), true).await?;
+    assert!(report.is_dry_run());
+    assert_eq!(report.new_titles(), vec!["Planning", "Holiday"]);
+    assert!(report.existing_uids().is_empty());
+    assert!(report.created_event_ids().is_empty());
+
+    let report = space.import_ical(file_path.clone(), false).await?;
+    assert_eq!(report.created_event_ids().len(), 2);
+
+    let retry_strategy = FibonacciBackoff::from_millis(500).map(jitter).take(10);
+    let cal_events = Retry::spawn(retry_strategy, || async {
+        let cal_events = space.calendar_events().await?;
+        if cal_events.len() != 2 {
+            bail!("not all calendar_events found");
+        }
+        Ok(cal_events)
+    })
+    .await?;
+    let holiday = cal_events
+        .iter()
+        .find(|e| e.title() == "Holiday")
+        .expect("holiday was imported");
+    assert!(holiday.show_without_time());
+    assert_eq!(holiday.ical_uid(), "holiday@example.org");
+
+    // importing again finds all events to exist already
+    let report = space.import_ical(file_path, true).await?;
+    assert!(report.new_uids().is_empty());
+    assert_eq!(report.existing_uids().len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn calendar_space_ical_feed() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (user, sync_state, _engine) =
+        random_user_with_template("calendar_ical_feed", THREE_EVENTS_TMPL).await?;
+    sync_state.await_has_synced_history().await?;
+
+    // wait for sync to catch up
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(30);
+    Retry::spawn(retry_strategy, || async {
+        if user.calendar_events().await?.len() != 3 {
+            bail!("not all calendar_events found");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let spaces = user.spaces().await?;
+    let main_space = spaces.first().expect("main space should be available");
+    let cal_events = main_space.calendar_events().await?;
+    let declined = cal_events.first().expect("event should be available");
+    assert_eq!(declined.title(), "Onboarding on Acter3");
+
+    let rsvp_manager = declined.rsvps().await?;
+    let rsvp_listener = rsvp_manager.subscribe();
+    rsvp_manager
+        .rsvp_draft()?
+        .status("no".to_owned())
+        .send()
+        .await?;
+    let retry_strategy = FibonacciBackoff::from_millis(500).map(jitter).take(10);
+    Retry::spawn(retry_strategy, || async {
+        if rsvp_listener.is_empty() {
+            bail!("rsvp not received");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let now = Utc::now();
+    let from = now.to_rfc3339();
+    let to = (now + Duration::days(30)).to_rfc3339();
+
+    let feed = main_space
+        .ical_feed(from.clone(), to.clone(), false)
+        .await?;
+    assert_eq!(feed.matches("BEGIN:VEVENT").count(), 3);
+    assert!(feed.contains("PARTSTAT=DECLINED"));
+    assert!(feed.contains(&declined.ical_uid()));
+
+    let feed = main_space.ical_feed(from.clone(), to.clone(), true).await?;
+    assert_eq!(feed.matches("BEGIN:VEVENT").count(), 2);
+    assert!(!feed.contains(&declined.ical_uid()));
+
+    // only the first event lies within the next hour
+    let in_an_hour = (now + Duration::minutes(59)).to_rfc3339();
+    let feed = user.ical_feed(from, in_an_hour, true).await?;
+    assert_eq!(feed.matches("BEGIN:VEVENT").count(), 1);
+    assert!(feed.contains("Onboarding on Acter1"));
+
+    Ok(())
+}
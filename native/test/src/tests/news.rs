@@ -1,5 +1,6 @@
 use acter::{new_colorize_builder, new_obj_ref_builder, NewsSlideDraft};
 use anyhow::{bail, Result};
+use chrono::Utc;
 use core::time::Duration;
 use std::io::Write;
 use tempfile::NamedTempFile;
@@ -102,6 +103,81 @@ async fn news_smoketest() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn news_draft_scheduled_publishing() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (mut user, room_id) = random_user_with_random_space("news_schedule").await?;
+    let state_sync = user.start_sync();
+    state_sync.await_has_synced_history().await?;
+
+    // wait for sync to catch up
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    Retry::spawn(retry_strategy.clone(), || async {
+        user.space(room_id.to_string()).await
+    })
+    .await?;
+
+    let space = user.space(room_id.to_string()).await?;
+    let first_draft = user.text_plain_draft("first slide".to_owned());
+    let draft_id = space
+        .news_draft()?
+        .add_slide(Box::new(first_draft.into()))
+        .save_draft()
+        .await?;
+
+    let drafts = space.news_drafts().await?;
+    assert_eq!(drafts.len(), 1);
+    let draft = &drafts[0];
+    assert_eq!(draft.draft_id(), draft_id);
+    assert!(!draft.is_scheduled());
+    assert!(space.scheduled_news().await?.is_empty());
+
+    // editing the slides
+    let second_draft = user.text_plain_draft("second slide".to_owned());
+    draft.add_slide(Box::new(second_draft.into())).await?;
+    draft.swap_slides(0, 1).await?;
+    let draft = draft.refresh().await?;
+    assert_eq!(draft.slides_count(), 2);
+    let first = draft.get_slide(0).expect("we have a slide");
+    assert_eq!(first.msg_content().body(), "second slide");
+
+    // far out first, then moved to right away
+    let now = Utc::now().timestamp_millis() as u64;
+    draft.schedule(now + 60 * 60 * 1000).await?;
+    let scheduled = space.scheduled_news().await?;
+    assert_eq!(scheduled.len(), 1);
+    assert_eq!(scheduled[0].publish_at(), Some(now + 60 * 60 * 1000));
+    draft.schedule(now + 1000).await?;
+
+    let news_entry = Retry::spawn(retry_strategy.clone(), || async {
+        let news_entries = space.latest_news_entries(1).await?;
+        if news_entries.len() != 1 {
+            bail!("scheduled news not published");
+        }
+        Ok(news_entries[0].clone())
+    })
+    .await?;
+    assert_eq!(news_entry.slides_count(), 2);
+    assert!(space.news_drafts().await?.is_empty());
+
+    // cancelled ones stay drafts
+    let text_draft = user.text_plain_draft("not yet".to_owned());
+    let draft_id = space
+        .news_draft()?
+        .add_slide(Box::new(text_draft.into()))
+        .schedule(now + 60 * 60 * 1000)
+        .await?;
+    let draft = user.news_draft(draft_id).await?;
+    assert!(draft.is_scheduled());
+    draft.unschedule().await?;
+    assert!(space.scheduled_news().await?.is_empty());
+    assert_eq!(space.news_drafts().await?.len(), 1);
+    draft.discard().await?;
+    assert!(user.news_drafts().await?.is_empty());
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn news_plain_text_test() -> Result<()> {
     let _ = env_logger::try_init();
@@ -458,3 +458,137 @@ async fn pin_self_ref_attachments() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn pin_revisions_and_revert() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (user, sync_state, _engine) = random_user_with_template("pin_revisions", TMPL).await?;
+    sync_state.await_has_synced_history().await?;
+
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    Retry::spawn(retry_strategy.clone(), || async {
+        if user.pins().await?.len() != 3 {
+            bail!("not all pins found");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let pin = user
+        .pins()
+        .await?
+        .into_iter()
+        .find(|p| p.title() == "Acter Website")
+        .expect("website pin exists");
+    assert_eq!(pin.revisions()?.len(), 1, "only the creation");
+
+    let subscriber = pin.subscribe();
+    pin.update_builder()?
+        .title("Acter Homepage".to_owned())
+        .unset_url()
+        .send()
+        .await?;
+    Retry::spawn(retry_strategy.clone(), || async {
+        if subscriber.is_empty() {
+            bail!("not been alerted to reload");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let pin = pin.refresh().await?;
+    assert_eq!(pin.title(), "Acter Homepage");
+    let revisions = pin.revisions()?;
+    assert_eq!(revisions.len(), 2);
+    let changes = revisions[1].changes();
+    let fields: Vec<String> = changes.iter().map(|c| c.field()).collect();
+    assert_eq!(fields, vec!["title", "url"]);
+    assert_eq!(changes[0].old_text().as_deref(), Some("Acter Website"));
+    assert_eq!(changes[0].new_text().as_deref(), Some("Acter Homepage"));
+    assert!(changes[1].new_json().is_none());
+
+    // back to how it was created
+    let subscriber = pin.subscribe();
+    pin.revert_to(revisions[0].event_id_str()).await?;
+    Retry::spawn(retry_strategy, || async {
+        if subscriber.is_empty() {
+            bail!("not been alerted to reload");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let pin = pin.refresh().await?;
+    assert_eq!(pin.title(), "Acter Website");
+    assert_eq!(pin.url().as_deref(), Some("https://acter.global"));
+    assert_eq!(pin.revisions()?.len(), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn pin_concurrent_updates_conflict() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (user, sync_state, _engine) = random_user_with_template("pin_conflicts", TMPL).await?;
+    sync_state.await_has_synced_history().await?;
+
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    Retry::spawn(retry_strategy.clone(), || async {
+        if user.pins().await?.len() != 3 {
+            bail!("not all pins found");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let pin = user
+        .pins()
+        .await?
+        .into_iter()
+        .find(|p| p.title() == "Acter Website")
+        .expect("website pin exists");
+    let base = pin.revision_str();
+    assert_eq!(base, pin.event_id_str(), "not updated yet");
+
+    // both edits start from the same revision
+    let mut first = pin.update_builder()?;
+    first.title("Acter Homepage".to_owned());
+    let mut second = pin.update_builder()?;
+    second.title("Acter Blog".to_owned());
+
+    let subscriber = pin.subscribe();
+    let first_id = first.send().await?;
+    Retry::spawn(retry_strategy.clone(), || async {
+        if subscriber.is_empty() {
+            bail!("not been alerted to reload");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let subscriber = pin.subscribe();
+    let second_id = second.send().await?;
+    Retry::spawn(retry_strategy, || async {
+        if subscriber.is_empty() {
+            bail!("not been alerted to reload");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let pin = pin.refresh().await?;
+    let changed = pin.changed_since(base)?;
+    assert_eq!(changed.len(), 2);
+    assert_eq!(changed[0].event_id_str(), first_id.to_string());
+
+    let conflicts = pin.conflicts();
+    assert_eq!(conflicts.len(), 1);
+    let conflict = &conflicts[0];
+    assert_eq!(conflict.event_id_str(), second_id.to_string());
+    assert_eq!(conflict.other_event_id_str(), first_id.to_string());
+    assert_eq!(conflict.other_sender(), user.user_id()?.to_string());
+    assert_eq!(conflict.fields(), vec!["title"]);
+    // the later one wins
+    assert_eq!(conflict.winner_str(), second_id.to_string());
+    assert_eq!(pin.title(), "Acter Blog");
+    Ok(())
+}
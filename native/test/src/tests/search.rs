@@ -0,0 +1,81 @@
+use anyhow::{bail, Result};
+use tokio_retry::{
+    strategy::{jitter, FibonacciBackoff},
+    Retry,
+};
+
+use crate::utils::random_user_with_template;
+
+const TMPL: &str = r#"
+version = "0.1"
+name = "Local Search Template"
+
+[inputs]
+main = { type = "user", is-default = true, required = true, description = "The starting user" }
+
+[objects]
+main_space = { type = "space", is-default = true, name = "{{ main.display_name }}’s search test space" }
+
+[objects.garden-pin]
+type = "pin"
+title = "Garden rules"
+content = { body = "Please close the gate" }
+
+[objects.garden-event]
+type = "calendar-event"
+title = "Community gathering"
+description = { body = "bring snacks for the garden party" }
+utc_start = "{{ future(add_days=1).as_rfc3339 }}"
+utc_end = "{{ future(add_days=2).as_rfc3339 }}"
+
+[objects.garden-list]
+type = "task-list"
+name = "Chores"
+
+[objects.garden-task]
+type = "task"
+title = "Water the plants"
+"m.relates_to" = { event_id = "{{ garden-list.id }}" }
+"#;
+
+#[tokio::test]
+async fn local_search_across_sections() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (user, sync_state, _engine) = random_user_with_template("local_search", TMPL).await?;
+    sync_state.await_has_synced_history().await?;
+
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    Retry::spawn(retry_strategy, || async {
+        if user.local_search("garden".to_owned()).search().await?.len() != 2 {
+            bail!("not all objects found");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let results = user.local_search("garden".to_owned()).search().await?;
+    // the title match ranks above the one in the description
+    assert_eq!(results[0].snippet(), "Garden rules");
+    assert_eq!(results[0].section().as_deref(), Some("pins"));
+    assert_eq!(results[1].section().as_deref(), Some("calendar"));
+
+    let mut search = user.local_search("garden".to_owned());
+    search.section("calendar".to_owned())?;
+    let results = search.search().await?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].model_type(), "global.acter.dev.calendar_event");
+
+    let space = user
+        .spaces()
+        .await?
+        .pop()
+        .expect("space should be available");
+    let results = space.local_search("plan".to_owned()).search().await?;
+    assert_eq!(results.len(), 1, "prefix matches the task");
+    assert_eq!(results[0].snippet(), "Water the plants");
+
+    let mut search = user.local_search("garden".to_owned());
+    search.sender("@nobody:example.org".to_owned())?;
+    assert!(search.search().await?.is_empty());
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use acter::{new_colorize_builder, StorySlideDraft};
 use anyhow::{bail, Result};
+use chrono::Utc;
 use core::time::Duration;
 use std::io::Write;
 use tempfile::NamedTempFile;
@@ -102,6 +103,77 @@ async fn story_smoketest() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn story_expiry_archives() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (mut user, room_id) = random_user_with_random_space("story_expiry").await?;
+    let state_sync = user.start_sync();
+    state_sync.await_has_synced_history().await?;
+
+    // wait for sync to catch up
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    Retry::spawn(retry_strategy.clone(), || async {
+        user.space(room_id.to_string()).await
+    })
+    .await?;
+
+    let space = user.space(room_id.to_string()).await?;
+    let expires_at = Utc::now() + chrono::Duration::seconds(3);
+    let text_draft = user.text_plain_draft("gone soon".to_owned());
+    let short_lived = space
+        .story_draft()?
+        .add_slide(Box::new(text_draft.into()))
+        .expires_at_from_rfc3339(expires_at.to_rfc3339())?
+        .send()
+        .await?;
+    let text_draft = user.text_plain_draft("here for the day".to_owned());
+    space
+        .story_draft()?
+        .add_slide(Box::new(text_draft.into()))
+        .send()
+        .await?;
+
+    Retry::spawn(retry_strategy.clone(), || async {
+        if space.latest_stories(10).await?.len() != 2 {
+            bail!("stories not found");
+        }
+        Ok(())
+    })
+    .await?;
+
+    // give it time to expire
+    let retry_strategy = FibonacciBackoff::from_millis(500).map(jitter).take(10);
+    let archived = Retry::spawn(retry_strategy, || async {
+        let archived = space.archived_stories(10).await?;
+        if archived.len() != 1 {
+            bail!("story not archived yet");
+        }
+        Ok(archived)
+    })
+    .await?;
+
+    let story = &archived[0];
+    assert_eq!(story.event_id(), short_lived);
+    assert!(story.is_archived());
+    assert_eq!(
+        story.expires_at().await?,
+        Some(expires_at.timestamp_millis() as u64)
+    );
+
+    let current = space.latest_stories(10).await?;
+    assert_eq!(current.len(), 1);
+    assert!(!current[0].is_archived());
+    // the default of a day
+    let sent_at = current[0].origin_server_ts();
+    assert_eq!(
+        current[0].expires_at().await?,
+        Some(sent_at + 24 * 60 * 60 * 1000)
+    );
+    assert_eq!(user.archived_stories(10).await?.len(), 1);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn story_plain_text_test() -> Result<()> {
     let _ = env_logger::try_init();
@@ -1,5 +1,5 @@
 use acter::api::SuperInvitesTokenUpdateBuilder;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use tokio_retry::{
     strategy::{jitter, FibonacciBackoff},
     Retry,
@@ -67,6 +67,70 @@ async fn super_invites_flow_with_registration_and_rooms() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn super_invites_redeem_link() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (mut user, room_id) = random_user_with_random_space("super_invites_link").await?;
+
+    let state_sync = user.start_sync();
+    state_sync.await_has_synced_history().await?;
+
+    // wait for sync to catch up
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    Retry::spawn(retry_strategy, || async {
+        user.space(room_id.to_string()).await
+    })
+    .await?;
+
+    let mut token_builder = SuperInvitesTokenUpdateBuilder::new();
+    token_builder.add_room(room_id.to_string());
+    let token = user
+        .super_invites()
+        .create_or_update_token(Box::new(token_builder))
+        .await?;
+
+    // the link names our server rather than a fixed one
+    let server_name = user.user_id()?.server_name().to_string();
+    let link = token.ref_details().generate_internal_link(false)?;
+    assert!(link.starts_with(&format!("acter:i/{server_name}/{}?", token.token())));
+    assert_eq!(user.super_invite_server_name().await?, server_name);
+
+    let other = random_user_under_token("super_invites_link_other", &token.token()).await?;
+    let other_invites = other.super_invites();
+    assert_eq!(
+        other_invites
+            .info_for_link(link.clone())
+            .await?
+            .rooms_count(),
+        1
+    );
+
+    let new_rooms = other_invites.redeem_link(link).await?;
+    assert_eq!(new_rooms, [room_id.to_string()]);
+
+    // configuring the server changes the links, once the setting synced
+    user.set_super_invite_server_name(Some("invites.example.org".to_owned()))
+        .await?;
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    Retry::spawn(retry_strategy, || async {
+        if user.super_invite_server_name().await? != "invites.example.org" {
+            bail!("super invite server not yet updated");
+        }
+        Ok(())
+    })
+    .await?;
+    let token = user.super_invites().tokens().await?.remove(0);
+    let link = token.ref_details().generate_internal_link(false)?;
+    assert!(link.starts_with("acter:i/invites.example.org/"));
+
+    // and those are still redeemed on the homeserver of whoever follows them
+    let third = random_user_under_token("super_invites_link_third", &token.token()).await?;
+    let new_rooms = third.super_invites().redeem_link(link).await?;
+    assert_eq!(new_rooms, [room_id.to_string()]);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn super_invites_manage() -> Result<()> {
     let _ = env_logger::try_init();
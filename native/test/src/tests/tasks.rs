@@ -1,4 +1,8 @@
 mod invitations;
+mod outbox;
+mod recurring;
+mod reminders;
+mod subtasks;
 
 use acter::testing::wait_for;
 use acter_matrix::models::ActerModel;
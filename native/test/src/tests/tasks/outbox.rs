@@ -0,0 +1,71 @@
+use acter_matrix::store::is_local_id;
+use anyhow::{bail, Result};
+use matrix_sdk_base::ruma::EventId;
+use tokio_retry::{
+    strategy::{jitter, FibonacciBackoff},
+    Retry,
+};
+
+use crate::utils::random_users_with_random_space_under_template;
+
+const TMPL: &str = r#"
+version = "0.1"
+name = "Outbox Template"
+
+[inputs]
+main = { type = "user", is-default = true, required = true, description = "The starting user" }
+space = { type = "space", is-default = true, required = true, description = "The main user" }
+
+[objects.tasklist]
+type = "task-list"
+name = "Errands"
+"#;
+
+#[tokio::test]
+async fn queued_task_is_reconciled() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (users, _sync_states, _space_id, _engine) =
+        random_users_with_random_space_under_template("outbox", 1, TMPL).await?;
+    let user = users.first().expect("exists");
+
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    let task_list = Retry::spawn(retry_strategy.clone(), || async {
+        let entries = user.task_lists().await?;
+        let Some(task_list) = entries.first() else {
+            bail!("no task lists found");
+        };
+        Ok(task_list.clone())
+    })
+    .await?;
+
+    let local_id = task_list
+        .task_builder()?
+        .title("Buy milk".to_owned())
+        .queue()
+        .await?;
+    assert!(is_local_id(&local_id));
+
+    // shown right away
+    let pending = task_list.task(local_id.to_string()).await?;
+    assert_eq!(pending.title(), "Buy milk");
+    assert_eq!(user.outbox()?.len(), 1);
+
+    // sent with the next sync and replaced by the real one
+    let retry_strategy = FibonacciBackoff::from_millis(500).map(jitter).take(10);
+    let real = Retry::spawn(retry_strategy, || async {
+        user.flush_outbox().await?;
+        if !user.outbox()?.is_empty() {
+            bail!("still queued");
+        }
+        let tasks = task_list.tasks().await?;
+        let Some(real) = tasks.into_iter().find(|t| t.title() == "Buy milk") else {
+            bail!("task not found");
+        };
+        Ok(real)
+    })
+    .await?;
+    assert!(!is_local_id(&EventId::parse(real.event_id_str())?));
+    assert_eq!(task_list.tasks().await?.len(), 1);
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use anyhow::{bail, Result};
+use chrono::{Datelike, Duration, Utc};
+use tokio_retry::{
+    strategy::{jitter, FibonacciBackoff},
+    Retry,
+};
+
+use crate::utils::random_users_with_random_space_under_template;
+
+const TMPL: &str = r#"
+version = "0.1"
+name = "Recurring Tasks Template"
+
+[inputs]
+main = { type = "user", is-default = true, required = true, description = "The starting user" }
+space = { type = "space", is-default = true, required = true, description = "The main user" }
+
+[objects.tasklist]
+type = "task-list"
+name = "Chores"
+"#;
+
+#[tokio::test]
+async fn recurring_task_respawns() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (users, _sync_states, _space_id, _engine) =
+        random_users_with_random_space_under_template("recurring", 1, TMPL).await?;
+    let user = users.first().expect("exists");
+
+    // wait for sync to catch up
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    let task_list = Retry::spawn(retry_strategy.clone(), || async {
+        let entries = user.task_lists().await?;
+        let Some(task_list) = entries.first() else {
+            bail!("no task lists found");
+        };
+        Ok(task_list.clone())
+    })
+    .await?;
+
+    let due = Utc::now().date_naive();
+    let first_id = task_list
+        .task_builder()?
+        .title("Take out the trash".to_owned())
+        .due_date(due.year(), due.month(), due.day())
+        .recurrence("weekly".to_owned(), 1, "due_date".to_owned())?
+        .send()
+        .await?;
+
+    let retry_strategy = FibonacciBackoff::from_millis(500).map(jitter).take(10);
+    let first = Retry::spawn(retry_strategy.clone(), || async {
+        task_list.task(first_id.to_string()).await
+    })
+    .await?;
+    assert!(first.is_recurring());
+    assert_eq!(first.recurrence_rrule().as_deref(), Some("FREQ=WEEKLY"));
+    assert_eq!(first.recurrence_relative_to().as_deref(), Some("due_date"));
+
+    first.update_builder()?.mark_done().send().await?;
+
+    let next = Retry::spawn(retry_strategy, || async {
+        let tasks = task_list.tasks().await?;
+        let Some(next) = tasks
+            .into_iter()
+            .find(|t| t.previous_instance_id_str() == Some(first_id.to_string()))
+        else {
+            bail!("next instance not found");
+        };
+        Ok(next)
+    })
+    .await?;
+    assert_eq!(next.title(), "Take out the trash");
+    assert!(!next.is_done());
+    assert!(next.is_recurring());
+    assert_eq!(
+        next.due_date(),
+        Some((due + Duration::days(7)).format("%Y-%m-%d").to_string())
+    );
+
+    Ok(())
+}
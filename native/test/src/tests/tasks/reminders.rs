@@ -0,0 +1,102 @@
+use anyhow::{bail, Result};
+use chrono::{Datelike, Duration, Utc};
+use tokio_retry::{
+    strategy::{jitter, FibonacciBackoff},
+    Retry,
+};
+
+use crate::utils::random_users_with_random_space_under_template;
+
+const TMPL: &str = r#"
+version = "0.1"
+name = "Task Reminders Template"
+
+[inputs]
+main = { type = "user", is-default = true, required = true, description = "The starting user" }
+space = { type = "space", is-default = true, required = true, description = "The main user" }
+
+[objects.tasklist]
+type = "task-list"
+name = "Errands"
+"#;
+
+const WEEK_SECS: u32 = 7 * 24 * 60 * 60;
+
+#[tokio::test]
+async fn due_task_reminder() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (users, _sync_states, _space_id, _engine) =
+        random_users_with_random_space_under_template("task_reminders", 1, TMPL).await?;
+    let user = users.first().expect("exists");
+
+    // wait for sync to catch up
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    let task_list = Retry::spawn(retry_strategy.clone(), || async {
+        let entries = user.task_lists().await?;
+        let Some(task_list) = entries.first() else {
+            bail!("no task lists found");
+        };
+        Ok(task_list.clone())
+    })
+    .await?;
+
+    let due = Utc::now().date_naive() + Duration::days(3);
+    let task_id = task_list
+        .task_builder()?
+        .title("Renew the passport".to_owned())
+        .due_date(due.year(), due.month(), due.day())
+        .utc_due_time_of_day(12 * 60 * 60)
+        .send()
+        .await?;
+
+    let retry_strategy = FibonacciBackoff::from_millis(500).map(jitter).take(10);
+    let task = Retry::spawn(retry_strategy.clone(), || async {
+        task_list.task(task_id.to_string()).await
+    })
+    .await?;
+
+    // not assigned to us, so nothing to remind of
+    assert!(user.upcoming_reminders(WEEK_SECS).await?.is_empty());
+
+    task.assign_self().await?;
+
+    let reminders = Retry::spawn(retry_strategy.clone(), || async {
+        let reminders = user.upcoming_reminders(WEEK_SECS).await?;
+        if reminders.is_empty() {
+            bail!("no reminder yet");
+        }
+        Ok(reminders)
+    })
+    .await?;
+    assert_eq!(reminders.len(), 1);
+    let reminder = &reminders[0];
+    assert_eq!(reminder.push_style(), "taskDueReminder");
+    assert_eq!(reminder.parent_id_str(), Some(task_id.to_string()));
+    assert_eq!(
+        reminder.due_date(),
+        Some(due.format("%Y-%m-%d").to_string())
+    );
+    // a day ahead by default
+    let expected_at = (due - Duration::days(1))
+        .and_hms_opt(12, 0, 0)
+        .expect("valid time")
+        .and_utc();
+    assert_eq!(
+        reminder.reminder_at(),
+        Some(expected_at.timestamp_millis() as u64)
+    );
+    assert!(reminder.reminder_id().is_some());
+    assert!(!reminder.is_reminder_cancelled());
+
+    task.update_builder()?.mark_done().send().await?;
+
+    Retry::spawn(retry_strategy, || async {
+        if !user.upcoming_reminders(WEEK_SECS).await?.is_empty() {
+            bail!("reminder still there");
+        }
+        Ok(())
+    })
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use anyhow::{bail, Result};
+use tokio_retry::{
+    strategy::{jitter, FibonacciBackoff},
+    Retry,
+};
+
+use crate::utils::random_users_with_random_space_under_template;
+
+const TMPL: &str = r#"
+version = "0.1"
+name = "Subtasks Template"
+
+[inputs]
+main = { type = "user", is-default = true, required = true, description = "The starting user" }
+space = { type = "space", is-default = true, required = true, description = "The main user" }
+
+[objects.tasklist]
+type = "task-list"
+name = "Release"
+
+[objects.parent]
+type = "task"
+title = "Ship the release"
+"m.relates_to" = { event_id = "{{ tasklist.id }}" }
+"#;
+
+#[tokio::test]
+async fn subtasks_and_blockers() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (users, _sync_states, _space_id, _engine) =
+        random_users_with_random_space_under_template("subtasks", 1, TMPL).await?;
+    let user = users.first().expect("exists");
+
+    // wait for sync to catch up
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    let (task_list, parent) = Retry::spawn(retry_strategy.clone(), || async {
+        let entries = user.task_lists().await?;
+        let Some(task_list) = entries.first() else {
+            bail!("no task lists found");
+        };
+        let tasks = task_list.tasks().await?;
+        let Some(task) = tasks.first() else {
+            bail!("no tasks found")
+        };
+        Ok((task_list.clone(), task.clone()))
+    })
+    .await?;
+    let parent_id = parent.event_id_str();
+
+    let write_id = task_list
+        .task_builder()?
+        .title("Write changelog".to_owned())
+        .parent_task(parent_id.clone())?
+        .send()
+        .await?;
+    let tag_id = task_list
+        .task_builder()?
+        .title("Tag the release".to_owned())
+        .parent_task(parent_id.clone())?
+        .blocked_by(&mut vec![write_id.to_string()])?
+        .send()
+        .await?;
+
+    let retry_strategy = FibonacciBackoff::from_millis(500).map(jitter).take(10);
+    let subtasks = Retry::spawn(retry_strategy.clone(), || async {
+        let subtasks = parent.subtasks().await?;
+        if subtasks.len() != 2 {
+            bail!("not all subtasks found");
+        }
+        Ok(subtasks)
+    })
+    .await?;
+    let tag = subtasks
+        .iter()
+        .find(|t| t.event_id() == tag_id)
+        .expect("tag task exists");
+    assert_eq!(tag.parent_task_id_str(), Some(parent_id.clone()));
+    assert_eq!(tag.blocked_by_str(), vec![write_id.to_string()]);
+    assert!(tag.is_blocked().await?);
+
+    // the changelog blocking on tagging would be a cycle
+    let write = subtasks
+        .iter()
+        .find(|t| t.event_id() == write_id)
+        .expect("write task exists");
+    assert!(write
+        .update_builder()?
+        .blocked_by(&mut vec![tag_id.to_string()])?
+        .send()
+        .await
+        .is_err());
+
+    write.update_builder()?.mark_done().send().await?;
+
+    let parent = Retry::spawn(retry_strategy, || async {
+        let parent = parent.refresh().await?;
+        if parent.subtasks_done_count() != 1 {
+            bail!("progress not rolled up yet");
+        }
+        Ok(parent)
+    })
+    .await?;
+    assert_eq!(parent.subtasks_count(), 2);
+    assert_eq!(parent.percent(), Some(50));
+    assert_eq!(parent.progress_percent(), None);
+    assert!(!tag.refresh().await?.is_blocked().await?);
+
+    Ok(())
+}
@@ -1,10 +1,12 @@
+use acter::api::new_space_settings_builder;
 use anyhow::{bail, Result};
+use futures::{pin_mut, StreamExt};
 use tokio_retry::{
     strategy::{jitter, FibonacciBackoff},
     Retry,
 };
 
-use crate::utils::random_user_with_template;
+use crate::utils::{random_user, random_user_with_template};
 
 const TMPL: &str = r#"
 version = "0.1"
@@ -64,3 +66,307 @@ async fn template_creates_space() -> Result<()> {
     assert_eq!(main_space.task_lists().await?.len(), 1);
     Ok(())
 }
+
+#[tokio::test]
+async fn template_export_recreates_space() -> Result<()> {
+    let _ = env_logger::try_init();
+    let (user, sync_state, _engine) = random_user_with_template("template_export", TMPL).await?;
+    sync_state.await_has_synced_history().await?;
+
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    Retry::spawn(retry_strategy.clone(), || async {
+        let task_lists = user.task_lists().await?;
+        if user.pins().await?.len() != 2 || task_lists.len() != 1 {
+            bail!("not all pins and task lists found");
+        }
+        if task_lists[0].tasks().await?.is_empty() {
+            bail!("task not found");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let source = user
+        .spaces()
+        .await?
+        .into_iter()
+        .next()
+        .expect("main space should be available");
+    let exported = source.export_template().await?;
+    assert!(exported.contains("version = \"0.1\""));
+    assert!(exported.contains("{{ task_list_1.id }}"));
+
+    let settings = {
+        let mut builder = new_space_settings_builder();
+        builder.set_name("blueprint copy".to_owned());
+        builder.build()?
+    };
+    let copy_id = user.create_acter_space(Box::new(settings)).await?;
+    let copy = Retry::spawn(retry_strategy.clone(), || async {
+        user.space(copy_id.to_string()).await
+    })
+    .await?;
+
+    let mut engine = user.template_engine(&exported).await?;
+    engine.add_ref("space".to_owned(), "space".to_owned(), copy_id.to_string())?;
+    let exec_stream = engine.execute()?;
+    assert_eq!(exec_stream.total(), 4);
+    pin_mut!(exec_stream);
+    while let Some(i) = exec_stream.next().await {
+        i?
+    }
+
+    Retry::spawn(retry_strategy, || async {
+        if copy.pins().await?.len() != 2 || copy.task_lists().await?.len() != 1 {
+            bail!("not all pins and task lists copied");
+        }
+        Ok(())
+    })
+    .await?;
+    let task_lists = copy.task_lists().await?;
+    let tasks = task_lists[0].tasks().await?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].title(), "Scroll through the news");
+    Ok(())
+}
+
+const RESUMABLE_TMPL: &str = r#"
+version = "0.1"
+
+[inputs]
+main = { type = "user", is-default = true, required = true }
+
+[objects]
+main_space = { type = "space", is-default = true, name = "resumable space" }
+
+[objects.first-pin]
+type = "pin"
+title = "First"
+
+[objects.second-pin]
+type = "pin"
+title = "Second"
+in = "{{ broken }}"
+"#;
+
+#[tokio::test]
+async fn template_resume_and_rollback() -> Result<()> {
+    let _ = env_logger::try_init();
+    let mut user = random_user("template_resume").await?;
+    let sync_state = user.start_sync();
+    sync_state.await_has_synced_history().await?;
+
+    let mut engine = user.template_engine(RESUMABLE_TMPL).await?;
+    assert!(engine.validate().is_err(), "`broken` isn’t known");
+
+    // fails at the last object, after creating the first two
+    engine.add_text("broken".to_owned(), "nowhere".to_owned())?;
+    engine.validate().expect_err("`nowhere` isn’t a space");
+    let exec_stream = engine.execute()?;
+    pin_mut!(exec_stream);
+    let mut failed = false;
+    while let Some(i) = exec_stream.next().await {
+        if i.is_err() {
+            failed = true;
+            break;
+        }
+    }
+    assert!(failed);
+    let record = exec_stream.record();
+    let created: Vec<&str> = record.created().iter().map(|c| c.key.as_str()).collect();
+    assert_eq!(created, vec!["main_space", "first-pin"]);
+
+    // resume with the fixed template, not creating anything twice
+    let mut engine = user
+        .template_engine(&RESUMABLE_TMPL.replace("{{ broken }}", "main_space"))
+        .await?;
+    engine.validate()?;
+    let exec_stream = engine.resume(record)?;
+    pin_mut!(exec_stream);
+    while let Some(i) = exec_stream.next().await {
+        i?
+    }
+    let record = exec_stream.record();
+    assert_eq!(record.len(), 3);
+
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    Retry::spawn(retry_strategy.clone(), || async {
+        if user.pins().await?.len() != 2 {
+            bail!("pins not found");
+        }
+        Ok(())
+    })
+    .await?;
+    assert_eq!(user.spaces().await?.len(), 1);
+
+    engine.rollback(&record).await?;
+    Retry::spawn(retry_strategy, || async {
+        if !user.spaces().await?.is_empty() {
+            bail!("space not left yet");
+        }
+        Ok(())
+    })
+    .await?;
+    Ok(())
+}
+
+const CHILDREN_TMPL: &str = r#"
+version = "0.1"
+
+[inputs]
+main = { type = "user", is-default = true, required = true }
+
+[objects.main_space]
+type = "space"
+is-default = true
+name = "{{ main.display_name }}’s onboarding space"
+
+[objects.team_space]
+type = "space"
+name = "Team"
+in = "main_space"
+suggested = true
+
+[objects.lobby]
+type = "chat"
+name = "Lobby"
+suggested = true
+
+[objects.welcome]
+type = "pin"
+title = "Welcome"
+
+[objects.welcome_comment]
+type = "comment"
+on = "welcome"
+content = { body = "Introduce yourself below!" }
+
+[objects.welcome_link]
+type = "attachment"
+on = "welcome"
+content = { type = "Link", link = "https://acter.global", name = "Acter" }
+
+[objects.kickoff]
+type = "calendar-event"
+title = "Kickoff"
+utc_start = "{{ future(days=1).as_rfc3339 }}"
+utc_end = "{{ future(days=1, hours=1).as_rfc3339 }}"
+
+[objects.kickoff_rsvp]
+type = "rsvp"
+on = "kickoff"
+status = { type = "yes" }
+
+[objects.space_categories]
+type = "categories"
+category-type = "spaces"
+categories = [{ title = "Teams", entries = ["{{ team_space.id }}"] }]
+"#;
+
+#[tokio::test]
+async fn template_creates_children_and_references() -> Result<()> {
+    let _ = env_logger::try_init();
+    let mut user = random_user("template_children").await?;
+    let sync_state = user.start_sync();
+    sync_state.await_has_synced_history().await?;
+
+    let engine = user.template_engine(CHILDREN_TMPL).await?;
+    engine.validate()?;
+    let exec_stream = engine.execute()?;
+    pin_mut!(exec_stream);
+    while let Some(i) = exec_stream.next().await {
+        i?
+    }
+    let record = exec_stream.record();
+    let created_id = |key: &str| {
+        record
+            .get(key)
+            .map(|c| c.id.clone())
+            .expect("object was created")
+    };
+
+    let retry_strategy = FibonacciBackoff::from_millis(100).map(jitter).take(10);
+    let main_space = Retry::spawn(retry_strategy.clone(), || async {
+        user.space(created_id("main_space")).await
+    })
+    .await?;
+
+    // the sub-space and the chat are listed as its children
+    Retry::spawn(retry_strategy.clone(), || async {
+        let children: Vec<String> = main_space
+            .space_relations()
+            .await?
+            .children()
+            .iter()
+            .map(|c| c.room_id().to_string())
+            .collect();
+        if !children.contains(&created_id("team_space")) || !children.contains(&created_id("lobby"))
+        {
+            bail!("children not found yet: {children:?}");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let pin = Retry::spawn(retry_strategy.clone(), || async {
+        user.wait_for_pin(created_id("welcome"), None).await
+    })
+    .await?;
+    Retry::spawn(retry_strategy.clone(), || async {
+        let comments = pin.comments().await?.comments().await?;
+        let attachments = pin.attachments().await?.attachments().await?;
+        if comments.len() != 1 || attachments.len() != 1 {
+            bail!("comment and attachment not found yet");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let event = user
+        .wait_for_calendar_event(created_id("kickoff"), None)
+        .await?;
+    Retry::spawn(retry_strategy.clone(), || async {
+        if event
+            .rsvps()
+            .await?
+            .count_at_status("yes".to_owned())
+            .await?
+            != 1
+        {
+            bail!("rsvp not found yet");
+        }
+        Ok(())
+    })
+    .await?;
+
+    let categories = Retry::spawn(retry_strategy.clone(), || async {
+        let categories = main_space
+            .categories("spaces".to_owned())
+            .await?
+            .categories();
+        if categories.is_empty() {
+            bail!("categories not found yet");
+        }
+        Ok(categories)
+    })
+    .await?;
+    assert_eq!(categories[0].title(), "Teams");
+    assert_eq!(categories[0].entries(), vec![created_id("team_space")]);
+
+    // rolling back unlinks the children from their parent
+    let team_link = record
+        .get("team_space")
+        .and_then(|c| c.child_of.clone())
+        .expect("sub-space was linked");
+    assert_eq!(team_link.parent_id, created_id("main_space"));
+    assert!(record.get("lobby").is_some_and(|c| c.child_of.is_some()));
+    engine.rollback(&record).await?;
+    Retry::spawn(retry_strategy, || async {
+        if !user.spaces().await?.is_empty() {
+            bail!("spaces not left yet");
+        }
+        Ok(())
+    })
+    .await?;
+    Ok(())
+}